@@ -2,8 +2,11 @@
 
 use crate::errors::Error;
 use crate::vba::VbaProject;
+#[cfg(feature = "picture")]
+use crate::Picture;
 use crate::{
-    open_workbook, open_workbook_from_rs, Data, Metadata, Ods, Range, Reader, Xls, Xlsb, Xlsx,
+    open_workbook, open_workbook_from_rs, Data, DataValidation, Metadata, Ods, Range, Reader,
+    SheetLayout, SheetProtection, SheetView, WorkbookProtection, Xls, Xlsb, Xlsx,
 };
 use std::borrow::Cow;
 use std::fs::File;
@@ -124,6 +127,54 @@ where
         }
     }
 
+    fn worksheet_layout(&mut self, name: &str) -> Result<SheetLayout, Self::Error> {
+        match *self {
+            Sheets::Xls(ref mut e) => e.worksheet_layout(name).map_err(Error::Xls),
+            Sheets::Xlsx(ref mut e) => e.worksheet_layout(name).map_err(Error::Xlsx),
+            Sheets::Xlsb(ref mut e) => e.worksheet_layout(name).map_err(Error::Xlsb),
+            Sheets::Ods(ref mut e) => e.worksheet_layout(name).map_err(Error::Ods),
+        }
+    }
+
+    fn worksheet_view(&mut self, name: &str) -> Result<SheetView, Self::Error> {
+        match *self {
+            Sheets::Xls(ref mut e) => e.worksheet_view(name).map_err(Error::Xls),
+            Sheets::Xlsx(ref mut e) => e.worksheet_view(name).map_err(Error::Xlsx),
+            Sheets::Xlsb(ref mut e) => e.worksheet_view(name).map_err(Error::Xlsb),
+            Sheets::Ods(ref mut e) => e.worksheet_view(name).map_err(Error::Ods),
+        }
+    }
+
+    fn worksheet_protection(&mut self, name: &str) -> Result<Option<SheetProtection>, Self::Error> {
+        match *self {
+            Sheets::Xls(ref mut e) => e.worksheet_protection(name).map_err(Error::Xls),
+            Sheets::Xlsx(ref mut e) => e.worksheet_protection(name).map_err(Error::Xlsx),
+            Sheets::Xlsb(ref mut e) => e.worksheet_protection(name).map_err(Error::Xlsb),
+            Sheets::Ods(ref mut e) => e.worksheet_protection(name).map_err(Error::Ods),
+        }
+    }
+
+    fn workbook_protection(&mut self) -> Result<Option<WorkbookProtection>, Self::Error> {
+        match *self {
+            Sheets::Xls(ref mut e) => e.workbook_protection().map_err(Error::Xls),
+            Sheets::Xlsx(ref mut e) => e.workbook_protection().map_err(Error::Xlsx),
+            Sheets::Xlsb(ref mut e) => e.workbook_protection().map_err(Error::Xlsb),
+            Sheets::Ods(ref mut e) => e.workbook_protection().map_err(Error::Ods),
+        }
+    }
+
+    fn worksheet_data_validations(
+        &mut self,
+        name: &str,
+    ) -> Result<Vec<DataValidation>, Self::Error> {
+        match *self {
+            Sheets::Xls(ref mut e) => e.worksheet_data_validations(name).map_err(Error::Xls),
+            Sheets::Xlsx(ref mut e) => e.worksheet_data_validations(name).map_err(Error::Xlsx),
+            Sheets::Xlsb(ref mut e) => e.worksheet_data_validations(name).map_err(Error::Xlsb),
+            Sheets::Ods(ref mut e) => e.worksheet_data_validations(name).map_err(Error::Ods),
+        }
+    }
+
     fn worksheets(&mut self) -> Vec<(String, Range<Data>)> {
         match *self {
             Sheets::Xls(ref mut e) => e.worksheets(),
@@ -142,4 +193,14 @@ where
             Sheets::Ods(ref e) => e.pictures(),
         }
     }
+
+    #[cfg(feature = "picture")]
+    fn sheet_pictures(&self) -> Option<Vec<Picture>> {
+        match *self {
+            Sheets::Xls(ref e) => e.sheet_pictures(),
+            Sheets::Xlsx(ref e) => e.sheet_pictures(),
+            Sheets::Xlsb(ref e) => e.sheet_pictures(),
+            Sheets::Ods(ref e) => e.sheet_pictures(),
+        }
+    }
 }
@@ -0,0 +1,360 @@
+//! AutoFilter and sort state of worksheets and tables
+
+use std::cmp::Ordering;
+
+use crate::{Data, Dimensions, Range};
+
+/// An autofilter: the filter buttons of a header row and the filters applied to the rows below
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AutoFilter {
+    /// Filtered range, including the header row
+    pub range: Dimensions,
+    /// Filters, only for the columns which are filtered
+    pub columns: Vec<FilterColumn>,
+    /// How the range is sorted, if it is
+    pub sort_state: Option<SortState>,
+}
+
+impl AutoFilter {
+    /// Get the rows of `range` kept by the filters, as absolute row indexes
+    ///
+    /// The header row is not included. Filters which cannot be evaluated
+    /// (colors, icons, date groups, dynamic filters other than above/below average)
+    /// keep every row.
+    ///
+    /// # Examples
+    /// ```
+    /// use calamine::{AutoFilter, CustomFilter, Data, Dimensions, Filter, FilterColumn, FilterOperator, Range};
+    ///
+    /// let mut range = Range::new((0, 0), (3, 0));
+    /// range.set_value((0, 0), Data::String("qty".to_string()));
+    /// range.set_value((1, 0), Data::Float(5.));
+    /// range.set_value((2, 0), Data::Float(15.));
+    /// range.set_value((3, 0), Data::Float(25.));
+    /// let filter = AutoFilter {
+    ///     range: Dimensions { start: (0, 0), end: (3, 0) },
+    ///     columns: vec![FilterColumn {
+    ///         column: 0,
+    ///         filter: Filter::Custom {
+    ///             and: false,
+    ///             conditions: vec![CustomFilter {
+    ///                 operator: FilterOperator::GreaterThan,
+    ///                 value: "10".to_string(),
+    ///             }],
+    ///         },
+    ///     }],
+    ///     sort_state: None,
+    /// };
+    /// assert_eq!(filter.visible_rows(&range), vec![2, 3]);
+    /// ```
+    pub fn visible_rows(&self, range: &Range<Data>) -> Vec<u32> {
+        let (first, last) = (self.range.start.0 + 1, self.range.end.0);
+        (first..=last)
+            .filter(|&row| {
+                self.columns.iter().all(|c| {
+                    let col = self.range.start.1 + c.column;
+                    c.filter.matches(range.get_value((row, col)))
+                })
+            })
+            .collect()
+    }
+}
+
+/// The filter of a column of an `AutoFilter`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterColumn {
+    /// Column, relative to the first column of the filtered range
+    pub column: u32,
+    /// Filter applied to the column
+    pub filter: Filter,
+}
+
+/// A column filter
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// Keep the cells displaying one of the values
+    Values {
+        /// Displayed values to keep
+        values: Vec<String>,
+        /// Also keep empty cells
+        blank: bool,
+    },
+    /// Keep the cells matching one or both conditions
+    Custom {
+        /// Must both conditions match, instead of any of them
+        and: bool,
+        /// Conditions (at most 2)
+        conditions: Vec<CustomFilter>,
+    },
+    /// Keep the top or bottom values
+    Top10 {
+        /// Top instead of bottom values
+        top: bool,
+        /// Is `value` a percentage
+        percent: bool,
+        /// Number, or percentage, of values to keep
+        value: f64,
+        /// Threshold computed when the filter was applied
+        filter_value: Option<f64>,
+    },
+    /// Filter depending on the data or the current date (e.g. "aboveAverage", "today")
+    Dynamic {
+        /// Kind of dynamic filter
+        dynamic_type: String,
+        /// Value computed when the filter was applied (e.g. the average)
+        value: Option<f64>,
+        /// Upper bound computed when the filter was applied, for date ranges
+        max_value: Option<f64>,
+    },
+    /// Filter not modeled here (colors, icons, date groups)
+    Other(String),
+}
+
+impl Filter {
+    /// Is a cell value kept by the filter
+    ///
+    /// Filters which cannot be evaluated keep every value.
+    /// Numbers are compared with their plain representation, not their displayed format.
+    pub fn matches(&self, value: Option<&Data>) -> bool {
+        let value = value.unwrap_or(&Data::Empty);
+        match self {
+            Filter::Values { values, blank } => match value {
+                Data::Empty => *blank,
+                Data::String(s) if s.is_empty() => *blank,
+                v => {
+                    let v = v.to_string();
+                    values.iter().any(|f| f.eq_ignore_ascii_case(&v))
+                }
+            },
+            Filter::Custom { and, conditions } => {
+                if *and {
+                    conditions.iter().all(|c| c.matches(value))
+                } else {
+                    conditions.iter().any(|c| c.matches(value))
+                }
+            }
+            Filter::Top10 {
+                top,
+                filter_value: Some(threshold),
+                ..
+            } => match as_number(value) {
+                Some(v) if *top => v >= *threshold,
+                Some(v) => v <= *threshold,
+                None => false,
+            },
+            Filter::Dynamic {
+                dynamic_type,
+                value: Some(average),
+                ..
+            } if dynamic_type == "aboveAverage" || dynamic_type == "belowAverage" => {
+                match as_number(value) {
+                    Some(v) if dynamic_type == "aboveAverage" => v > *average,
+                    Some(v) => v < *average,
+                    None => false,
+                }
+            }
+            _ => true,
+        }
+    }
+}
+
+/// A condition of a `Filter::Custom`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomFilter {
+    /// Comparison operator
+    pub operator: FilterOperator,
+    /// Value compared to, with `*` and `?` wildcards for text
+    pub value: String,
+}
+
+impl CustomFilter {
+    /// Is a cell value matching the condition
+    pub fn matches(&self, value: &Data) -> bool {
+        let ordering = match (as_number(value), self.value.parse::<f64>()) {
+            (Some(v), Ok(f)) => v.partial_cmp(&f),
+            (None, Ok(_)) => None,
+            _ => {
+                let v = value.to_string().to_lowercase();
+                let f = self.value.to_lowercase();
+                match self.operator {
+                    FilterOperator::Equal => return wildcard_match(&f, &v),
+                    FilterOperator::NotEqual => return !wildcard_match(&f, &v),
+                    _ => Some(v.cmp(&f)),
+                }
+            }
+        };
+        match (self.operator, ordering) {
+            (FilterOperator::NotEqual, o) => o != Some(Ordering::Equal),
+            (_, None) => false,
+            (FilterOperator::Equal, Some(o)) => o == Ordering::Equal,
+            (FilterOperator::GreaterThan, Some(o)) => o == Ordering::Greater,
+            (FilterOperator::GreaterThanOrEqual, Some(o)) => o != Ordering::Less,
+            (FilterOperator::LessThan, Some(o)) => o == Ordering::Less,
+            (FilterOperator::LessThanOrEqual, Some(o)) => o != Ordering::Greater,
+        }
+    }
+}
+
+/// Comparison operator of a `CustomFilter`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FilterOperator {
+    /// Equal
+    #[default]
+    Equal,
+    /// Not equal
+    NotEqual,
+    /// Greater than
+    GreaterThan,
+    /// Greater than or equal
+    GreaterThanOrEqual,
+    /// Less than
+    LessThan,
+    /// Less than or equal
+    LessThanOrEqual,
+}
+
+impl FilterOperator {
+    /// Convert the xlsx `operator` attribute
+    pub(crate) fn from_name(name: &[u8]) -> FilterOperator {
+        match name {
+            b"notEqual" => FilterOperator::NotEqual,
+            b"greaterThan" => FilterOperator::GreaterThan,
+            b"greaterThanOrEqual" => FilterOperator::GreaterThanOrEqual,
+            b"lessThan" => FilterOperator::LessThan,
+            b"lessThanOrEqual" => FilterOperator::LessThanOrEqual,
+            _ => FilterOperator::Equal,
+        }
+    }
+}
+
+/// Sort state of a range
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SortState {
+    /// Sorted range, without the header row
+    pub range: Dimensions,
+    /// Is the sort case sensitive
+    pub case_sensitive: bool,
+    /// Sort keys, the first one being the primary key
+    pub conditions: Vec<SortCondition>,
+}
+
+/// A sort key of a `SortState`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SortCondition {
+    /// Column (or row) the range is sorted by
+    pub range: Dimensions,
+    /// Descending instead of ascending order
+    pub descending: bool,
+    /// What the cells are sorted by
+    pub sort_by: SortBy,
+    /// Custom order of the values (e.g. "Low,Medium,High")
+    pub custom_list: Option<String>,
+}
+
+/// What the cells are sorted by in a `SortCondition`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortBy {
+    /// Cell values
+    #[default]
+    Value,
+    /// Cell background color
+    CellColor,
+    /// Font color
+    FontColor,
+    /// Conditional formatting icon
+    Icon,
+}
+
+impl SortBy {
+    /// Convert the xlsx `sortBy` attribute
+    pub(crate) fn from_name(name: &[u8]) -> SortBy {
+        match name {
+            b"cellColor" => SortBy::CellColor,
+            b"fontColor" => SortBy::FontColor,
+            b"icon" => SortBy::Icon,
+            _ => SortBy::Value,
+        }
+    }
+}
+
+fn as_number(value: &Data) -> Option<f64> {
+    match value {
+        Data::Int(i) => Some(*i as f64),
+        Data::Float(f) => Some(*f),
+        Data::DateTime(d) => Some(d.as_f64()),
+        _ => None,
+    }
+}
+
+/// Matches `text` against a pattern with `*` (any characters) and `?` (one character)
+/// wildcards, `~` escaping the next character
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+            Some('~') if pattern.get(p + 1) == Some(&text[t]) => {
+                p += 2;
+                t += 1;
+                continue;
+            }
+            Some(c) if *c == text[t] => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+            _ => (),
+        }
+        match backtrack {
+            Some((bp, bt)) => {
+                p = bp + 1;
+                t = bt + 1;
+                backtrack = Some((bp, bt + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("a*c", "abbc"));
+        assert!(wildcard_match("a?c", "abc"));
+        assert!(!wildcard_match("a?c", "abbc"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("~*", "*"));
+        assert!(!wildcard_match("~*", "a"));
+    }
+
+    #[test]
+    fn custom_filter() {
+        let filter = |operator, value: &str| CustomFilter {
+            operator,
+            value: value.to_string(),
+        };
+        let s = |s: &str| Data::String(s.to_string());
+        assert!(filter(FilterOperator::Equal, "no*").matches(&s("North")));
+        assert!(filter(FilterOperator::NotEqual, "no*").matches(&s("South")));
+        assert!(filter(FilterOperator::GreaterThan, "10").matches(&Data::Float(10.5)));
+        assert!(!filter(FilterOperator::GreaterThan, "10").matches(&s("abc")));
+        assert!(filter(FilterOperator::LessThanOrEqual, "b").matches(&s("B")));
+        assert!(!filter(FilterOperator::Equal, "1").matches(&Data::Empty));
+    }
+}
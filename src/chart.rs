@@ -0,0 +1,505 @@
+//! Charts placed on worksheets and chartsheets, with the references and cached values of their series
+
+use std::io::{BufRead, Read, Seek};
+
+use quick_xml::events::Event;
+use quick_xml::Reader as XmlReader;
+use zip::read::ZipArchive;
+use zip::result::ZipError;
+
+use crate::relationships::{attribute, read_relationships, resolve_target, xml_reader};
+use crate::Data;
+
+/// Maximum number of cached points of a series, guarding against corrupted `ptCount` and `idx`
+const MAX_POINTS: usize = 1_000_000;
+
+/// A chart placed on a worksheet or a chartsheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chart {
+    /// Name of the sheet containing the chart
+    pub sheet_name: String,
+    /// Name of the chart shape, if any
+    pub name: Option<String>,
+    /// Type of the first plot of the chart, combined charts having series of other types
+    pub chart_type: Option<ChartType>,
+    /// Title, `None` if the chart has no title or an automatic one
+    pub title: Option<String>,
+    /// Axes, in file order
+    pub axes: Vec<ChartAxis>,
+    /// Series of all the plots of the chart
+    pub series: Vec<ChartSeries>,
+}
+
+/// Type of a chart plot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartType {
+    /// Area
+    Area,
+    /// 3D area
+    Area3D,
+    /// Horizontal bars
+    Bar,
+    /// 3D horizontal bars
+    Bar3D,
+    /// Vertical bars
+    Column,
+    /// 3D vertical bars
+    Column3D,
+    /// Line
+    Line,
+    /// 3D line
+    Line3D,
+    /// Pie
+    Pie,
+    /// 3D pie
+    Pie3D,
+    /// Pie of pie or bar of pie
+    OfPie,
+    /// Doughnut
+    Doughnut,
+    /// Scatter (XY)
+    Scatter,
+    /// Bubble
+    Bubble,
+    /// Radar
+    Radar,
+    /// Stock (high-low-close)
+    Stock,
+    /// Surface
+    Surface,
+    /// 3D surface
+    Surface3D,
+}
+
+impl ChartType {
+    /// Convert the name of a plot element (e.g. `barChart`), bars being vertical by default
+    fn from_name(name: &[u8]) -> Option<ChartType> {
+        let chart_type = match name {
+            b"areaChart" => ChartType::Area,
+            b"area3DChart" => ChartType::Area3D,
+            b"barChart" => ChartType::Column,
+            b"bar3DChart" => ChartType::Column3D,
+            b"lineChart" => ChartType::Line,
+            b"line3DChart" => ChartType::Line3D,
+            b"pieChart" => ChartType::Pie,
+            b"pie3DChart" => ChartType::Pie3D,
+            b"ofPieChart" => ChartType::OfPie,
+            b"doughnutChart" => ChartType::Doughnut,
+            b"scatterChart" => ChartType::Scatter,
+            b"bubbleChart" => ChartType::Bubble,
+            b"radarChart" => ChartType::Radar,
+            b"stockChart" => ChartType::Stock,
+            b"surfaceChart" => ChartType::Surface,
+            b"surface3DChart" => ChartType::Surface3D,
+            _ => return None,
+        };
+        Some(chart_type)
+    }
+}
+
+/// An axis of a chart
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartAxis {
+    /// Kind of axis
+    pub kind: ChartAxisKind,
+    /// Position of the axis in the plot area
+    pub position: ChartAxisPosition,
+    /// Title, if any
+    pub title: Option<String>,
+    /// Is the axis displayed
+    pub visible: bool,
+}
+
+/// Kind of a chart axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartAxisKind {
+    /// Categories
+    Category,
+    /// Values, also used for both axes of scatter and bubble charts
+    Value,
+    /// Dates
+    Date,
+    /// Series, the depth axis of 3D charts
+    Series,
+}
+
+/// Position of a chart axis
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChartAxisPosition {
+    /// Bottom
+    #[default]
+    Bottom,
+    /// Left
+    Left,
+    /// Right
+    Right,
+    /// Top
+    Top,
+}
+
+/// A series of a chart
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartSeries {
+    /// Type of the plot containing the series
+    pub chart_type: ChartType,
+    /// Name of the series, as displayed in the legend
+    pub name: Option<String>,
+    /// Formula of the cell containing the name (e.g. "Sheet1!$B$1"), if any
+    pub name_formula: Option<String>,
+    /// Categories, or x values of scatter and bubble charts
+    pub categories: Option<ChartSeriesData>,
+    /// Values, or y values of scatter and bubble charts
+    pub values: Option<ChartSeriesData>,
+}
+
+/// Data of a chart series: a reference to cells and the values cached when the file was saved
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChartSeriesData {
+    /// Formula of the referenced cells (e.g. "Sheet1!$B$2:$B$5"), `None` for literal values
+    pub formula: Option<String>,
+    /// Cached values, missing points being `Data::Empty`
+    ///
+    /// Only the first level of multi-level categories is read.
+    pub values: Vec<Data>,
+}
+
+/// Reads the charts of the drawings of each sheet (xlsx and xlsb files)
+///
+/// `sheets` are the (name, path) of the sheets, chartsheets included
+pub(crate) fn read_sheet_charts<RS, E>(
+    zip: &mut ZipArchive<RS>,
+    sheets: &[(String, String)],
+) -> Result<Vec<Chart>, E>
+where
+    RS: Read + Seek,
+    E: From<ZipError> + From<quick_xml::Error>,
+{
+    let mut charts = Vec::new();
+    for (sheet_name, sheet_path) in sheets {
+        for (_, typ, target) in read_relationships::<_, E>(zip, sheet_path)? {
+            if !typ.ends_with("/drawing") {
+                continue;
+            }
+            let drawing_path = resolve_target(sheet_path, &target);
+            let frames = match zip.by_name(&drawing_path) {
+                Ok(f) => parse_chart_frames(&mut xml_reader(f))?,
+                Err(ZipError::FileNotFound) => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if frames.is_empty() {
+                continue;
+            }
+            let relationships = read_relationships::<_, E>(zip, &drawing_path)?;
+            for (name, id) in frames {
+                // new chart types (`chartEx` parts) have another schema and are skipped
+                let Some((_, _, target)) = relationships
+                    .iter()
+                    .find(|(i, typ, _)| *i == id && typ.ends_with("/chart"))
+                else {
+                    continue;
+                };
+                let chart_path = resolve_target(&drawing_path, target);
+                let mut chart = match zip.by_name(&chart_path) {
+                    Ok(f) => parse_chart(&mut xml_reader(f))?,
+                    Err(ZipError::FileNotFound) => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                chart.sheet_name = sheet_name.clone();
+                chart.name = name;
+                charts.push(chart);
+            }
+        }
+    }
+    Ok(charts)
+}
+
+/// Parses the graphic frames of a drawing part, returns the (name, relationship id) of the charts
+fn parse_chart_frames<B: BufRead>(
+    xml: &mut XmlReader<B>,
+) -> Result<Vec<(Option<String>, String)>, quick_xml::Error> {
+    let mut frames = Vec::new();
+    let mut buf = Vec::new();
+    let mut name = None;
+    let mut in_frame = false;
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf)? {
+            Event::Start(ref e) | Event::Empty(ref e) => match e.local_name().as_ref() {
+                b"graphicFrame" => {
+                    in_frame = true;
+                    name = None;
+                }
+                b"cNvPr" if in_frame => name = attribute(xml, e, b"name")?,
+                b"chart" if in_frame => {
+                    if let Some(id) = attribute(xml, e, b"id")? {
+                        frames.push((name.clone(), id));
+                    }
+                }
+                _ => (),
+            },
+            Event::End(ref e) if e.local_name().as_ref() == b"graphicFrame" => in_frame = false,
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    Ok(frames)
+}
+
+/// Is the innermost open `ser` element directly followed by a `child` element
+fn in_series_child(path: &[Vec<u8>], child: &[u8]) -> bool {
+    match path.iter().rposition(|n| n == b"ser") {
+        Some(i) => path.get(i + 1).map_or(false, |n| n == child),
+        None => false,
+    }
+}
+
+/// Parses a chart part (`xl/charts/chartN.xml`) [ECMA-376 21.2]
+fn parse_chart<B: BufRead>(xml: &mut XmlReader<B>) -> Result<Chart, quick_xml::Error> {
+    let mut chart = Chart {
+        sheet_name: String::new(),
+        name: None,
+        chart_type: None,
+        title: None,
+        axes: Vec::new(),
+        series: Vec::new(),
+    };
+    let mut buf = Vec::new();
+    // local names of the open elements
+    let mut path: Vec<Vec<u8>> = Vec::new();
+    let mut plot: Option<ChartType> = None;
+    let mut series: Option<ChartSeries> = None;
+    let mut data: Option<ChartSeriesData> = None;
+    let mut numeric = false;
+    // level of multi-level categories
+    let mut level = 0;
+    let mut point = 0;
+    let mut axis: Option<ChartAxis> = None;
+    let mut title: Option<String> = None;
+    let mut paragraphs = 0;
+
+    loop {
+        buf.clear();
+        let (e, is_empty) = match xml.read_event_into(&mut buf)? {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::Text(t) => {
+                if path.iter().any(|n| n == b"extLst") {
+                    continue;
+                }
+                let text = t.unescape()?;
+                match path.last().map(Vec::as_slice) {
+                    Some(b"t") => {
+                        if let Some(ref mut title) = title {
+                            title.push_str(&text);
+                        }
+                    }
+                    Some(b"f") => {
+                        if let Some(ref mut data) = data {
+                            data.formula = Some(text.into_owned());
+                        } else if let Some(ref mut series) = series {
+                            if in_series_child(&path, b"tx") {
+                                series.name_formula = Some(text.into_owned());
+                            }
+                        }
+                    }
+                    Some(b"v") => {
+                        if let Some(ref mut data) = data {
+                            if level == 0 && point < MAX_POINTS && path.iter().any(|n| n == b"pt") {
+                                if data.values.len() <= point {
+                                    data.values.resize(point + 1, Data::Empty);
+                                }
+                                data.values[point] = match text.trim().parse() {
+                                    Ok(v) if numeric => Data::Float(v),
+                                    _ => Data::String(text.into_owned()),
+                                };
+                            }
+                        } else if let Some(ref mut title) = title {
+                            title.push_str(&text);
+                        } else if let Some(ref mut series) = series {
+                            if in_series_child(&path, b"tx") {
+                                series.name = Some(text.into_owned());
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+                continue;
+            }
+            Event::End(_) => {
+                let Some(name) = path.pop() else {
+                    continue;
+                };
+                if path.iter().any(|n| n == b"extLst") {
+                    continue;
+                }
+                match name.as_slice() {
+                    b"title" => {
+                        let text = title.take().filter(|t| !t.is_empty());
+                        match axis {
+                            Some(ref mut axis) => axis.title = text,
+                            None if path.last().map_or(false, |n| n == b"chart") => {
+                                chart.title = text
+                            }
+                            None => (),
+                        }
+                    }
+                    b"cat" | b"xVal" if path.last().map_or(false, |n| n == b"ser") => {
+                        if let Some(ref mut series) = series {
+                            series.categories = data.take();
+                        }
+                    }
+                    b"val" | b"yVal" if path.last().map_or(false, |n| n == b"ser") => {
+                        if let Some(ref mut series) = series {
+                            series.values = data.take();
+                        }
+                    }
+                    b"lvl" => level += 1,
+                    b"ser" => chart.series.extend(series.take()),
+                    b"catAx" | b"valAx" | b"dateAx" | b"serAx" => chart.axes.extend(axis.take()),
+                    _ => {
+                        if let Some(p) = plot.filter(|_| ChartType::from_name(&name).is_some()) {
+                            chart.chart_type.get_or_insert(p);
+                            plot = None;
+                        }
+                    }
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        let name = e.local_name();
+        let name = name.as_ref();
+        if !path.iter().any(|n| n == b"extLst") {
+            let parent = path.last().map(Vec::as_slice);
+            match name {
+                b"catAx" | b"valAx" | b"dateAx" | b"serAx" if !is_empty => {
+                    let kind = match name {
+                        b"catAx" => ChartAxisKind::Category,
+                        b"valAx" => ChartAxisKind::Value,
+                        b"dateAx" => ChartAxisKind::Date,
+                        _ => ChartAxisKind::Series,
+                    };
+                    axis = Some(ChartAxis {
+                        kind,
+                        position: ChartAxisPosition::default(),
+                        title: None,
+                        visible: true,
+                    });
+                }
+                b"axPos" => {
+                    if let Some(ref mut axis) = axis {
+                        axis.position = match attribute(xml, &e, b"val")?.as_deref() {
+                            Some("l") => ChartAxisPosition::Left,
+                            Some("r") => ChartAxisPosition::Right,
+                            Some("t") => ChartAxisPosition::Top,
+                            _ => ChartAxisPosition::Bottom,
+                        };
+                    }
+                }
+                b"delete" if parent.map_or(false, |p| p.ends_with(b"Ax")) => {
+                    if let Some(ref mut axis) = axis {
+                        let val = attribute(xml, &e, b"val")?;
+                        axis.visible = matches!(val.as_deref(), Some("0" | "false"));
+                    }
+                }
+                b"barDir" if attribute(xml, &e, b"val")?.as_deref() == Some("bar") => {
+                    plot = match plot {
+                        Some(ChartType::Column) => Some(ChartType::Bar),
+                        Some(ChartType::Column3D) => Some(ChartType::Bar3D),
+                        p => p,
+                    };
+                }
+                b"ser" if !is_empty => {
+                    series = plot.map(|chart_type| ChartSeries {
+                        chart_type,
+                        name: None,
+                        name_formula: None,
+                        categories: None,
+                        values: None,
+                    });
+                }
+                b"cat" | b"val" | b"xVal" | b"yVal" if parent == Some(b"ser") => {
+                    data = Some(ChartSeriesData::default());
+                    numeric = false;
+                    level = 0;
+                }
+                b"numRef" | b"numLit" => numeric = true,
+                b"ptCount" if level == 0 => {
+                    if let (Some(data), Some(count)) = (&mut data, attribute(xml, &e, b"val")?) {
+                        let count = count.parse().unwrap_or(0);
+                        data.values.resize(count.min(MAX_POINTS), Data::Empty);
+                    }
+                }
+                b"pt" => {
+                    point = attribute(xml, &e, b"idx")?.map_or(0, |v| v.parse().unwrap_or(0));
+                }
+                b"title" if !is_empty => {
+                    title = Some(String::new());
+                    paragraphs = 0;
+                }
+                b"p" if title.is_some() && path.iter().any(|n| n == b"rich") => {
+                    if let Some(ref mut title) = title {
+                        if paragraphs > 0 {
+                            title.push('\n');
+                        }
+                    }
+                    paragraphs += 1;
+                }
+                _ if parent == Some(b"plotArea") => {
+                    if let Some(chart_type) = ChartType::from_name(name) {
+                        plot = Some(chart_type);
+                    }
+                }
+                _ => (),
+            }
+        }
+        if !is_empty {
+            path.push(name.to_vec());
+        }
+    }
+    Ok(chart)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chart_titles() {
+        let xml = r#"<c:chartSpace xmlns:c="http://schemas.openxmlformats.org/drawingml/2006/chart" xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main">
+<c:chart><c:title><c:tx><c:rich><a:p><a:r><a:t>Line 1</a:t></a:r></a:p><a:p><a:r><a:t>Line </a:t></a:r><a:r><a:t>2</a:t></a:r></a:p></c:rich></c:tx></c:title>
+<c:plotArea><c:bar3DChart><c:barDir val="bar"/><c:ser><c:tx><c:v>Literal</c:v></c:tx>
+<c:val><c:numLit><c:ptCount val="3"/><c:pt idx="0"><c:v>1</c:v></c:pt><c:pt idx="2"><c:v>3</c:v></c:pt></c:numLit></c:val>
+<c:extLst><c:ext><c:f>Ignored!$A$1</c:f></c:ext></c:extLst></c:ser></c:bar3DChart>
+<c:catAx><c:delete val="1"/><c:axPos val="l"/></c:catAx></c:plotArea></c:chart></c:chartSpace>"#;
+        let chart = parse_chart(&mut XmlReader::from_str(xml)).unwrap();
+        assert_eq!(chart.chart_type, Some(ChartType::Bar3D));
+        assert_eq!(chart.title.as_deref(), Some("Line 1\nLine 2"));
+        assert_eq!(chart.series.len(), 1);
+        let series = &chart.series[0];
+        assert_eq!(series.name.as_deref(), Some("Literal"));
+        assert_eq!(series.name_formula, None);
+        let values = series.values.as_ref().unwrap();
+        assert_eq!(values.formula, None);
+        assert_eq!(
+            values.values,
+            vec![Data::Float(1.), Data::Empty, Data::Float(3.)]
+        );
+        assert_eq!(chart.axes.len(), 1);
+        assert_eq!(chart.axes[0].position, ChartAxisPosition::Left);
+        assert!(!chart.axes[0].visible);
+    }
+
+    #[test]
+    fn chart_point_counts() {
+        let xml = r#"<c:chartSpace xmlns:c="http://schemas.openxmlformats.org/drawingml/2006/chart">
+<c:chart><c:plotArea><c:lineChart><c:ser>
+<c:val><c:numLit><c:ptCount val="4294967295"/><c:pt idx="4294967294"><c:v>1</c:v></c:pt></c:numLit></c:val>
+</c:ser></c:lineChart></c:plotArea></c:chart></c:chartSpace>"#;
+        let chart = parse_chart(&mut XmlReader::from_str(xml)).unwrap();
+        let values = chart.series[0].values.as_ref().unwrap();
+        assert_eq!(values.values.len(), MAX_POINTS);
+        assert!(values.values.iter().all(|v| *v == Data::Empty));
+    }
+}
@@ -0,0 +1,178 @@
+//! Conditional formatting rules
+
+use crate::{Color, DifferentialFormat, Dimensions, ValidationOperator};
+
+/// Conditional formatting rules applying to some cell ranges
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConditionalFormatting {
+    /// Cell ranges the rules apply to
+    pub ranges: Vec<Dimensions>,
+    /// Rules, in file order
+    pub rules: Vec<ConditionalFormatRule>,
+}
+
+/// A conditional formatting rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalFormatRule {
+    /// Kind of rule, with its parameters
+    pub rule_type: ConditionalFormatType,
+    /// Priority, the lowest value being evaluated first
+    pub priority: i32,
+    /// Are the rules with a lower priority skipped when this one applies
+    pub stop_if_true: bool,
+    /// Formulas of the rule (e.g. the values compared in `CellIs` rules)
+    pub formulas: Vec<String>,
+    /// Format applied to the cells, resolved from the workbook differential formats
+    pub format: Option<DifferentialFormat>,
+}
+
+/// Kind of a `ConditionalFormatRule`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionalFormatType {
+    /// Compare the cell value with the formulas, using the same operators as data validations
+    CellIs(ValidationOperator),
+    /// Cells for which the first formula is true
+    Expression,
+    /// Color gradient
+    ColorScale(ColorScale),
+    /// Bar proportional to the value
+    DataBar(DataBar),
+    /// Icon depending on the value
+    IconSet(IconSet),
+    /// Top or bottom values
+    Top10 {
+        /// Number, or percentage, of values
+        rank: u32,
+        /// Is `rank` a percentage
+        percent: bool,
+        /// Bottom instead of top values
+        bottom: bool,
+    },
+    /// Values above or below the average
+    AboveAverage {
+        /// Above instead of below
+        above: bool,
+        /// Also include values equal to the average
+        equal: bool,
+        /// Number of standard deviations from the average
+        std_dev: Option<u32>,
+    },
+    /// Duplicate values
+    DuplicateValues,
+    /// Unique values
+    UniqueValues,
+    /// Text containing the value
+    ContainsText(String),
+    /// Text not containing the value
+    NotContainsText(String),
+    /// Text beginning with the value
+    BeginsWith(String),
+    /// Text ending with the value
+    EndsWith(String),
+    /// Empty cells
+    ContainsBlanks,
+    /// Non empty cells
+    NotContainsBlanks,
+    /// Cells with an error
+    ContainsErrors,
+    /// Cells without error
+    NotContainsErrors,
+    /// Dates in a time period (e.g. "today", "last7Days")
+    TimePeriod(String),
+    /// Unknown kind of rule
+    Other(String),
+}
+
+/// Color scale rule parameters
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColorScale {
+    /// Thresholds, from the lowest to the highest
+    pub values: Vec<ConditionalValue>,
+    /// Colors, one per threshold
+    pub colors: Vec<Color>,
+}
+
+/// Data bar rule parameters
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DataBar {
+    /// Value of the shortest bar
+    pub min: ConditionalValue,
+    /// Value of the longest bar
+    pub max: ConditionalValue,
+    /// Color of the bars
+    pub color: Option<Color>,
+    /// Is the cell value shown next to the bar
+    pub show_value: bool,
+}
+
+/// Icon set rule parameters
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IconSet {
+    /// Name of the icon set (e.g. "3TrafficLights1")
+    pub icon_set: String,
+    /// Thresholds, one per icon
+    pub values: Vec<ConditionalValue>,
+    /// Is the cell value shown next to the icon
+    pub show_value: bool,
+    /// Are the icons in reverse order
+    pub reverse: bool,
+}
+
+/// A threshold of color scale, data bar and icon set rules
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConditionalValue {
+    /// How the threshold is computed
+    pub value_type: ConditionalValueType,
+    /// The value or formula, if any
+    pub value: Option<String>,
+}
+
+/// How a `ConditionalValue` is computed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConditionalValueType {
+    /// Minimum value of the range
+    #[default]
+    Min,
+    /// Maximum value of the range
+    Max,
+    /// A number
+    Number,
+    /// A percentage of the range
+    Percent,
+    /// A percentile of the range
+    Percentile,
+    /// The result of a formula
+    Formula,
+    /// Minimum value, or 0 if all values are positive
+    AutoMin,
+    /// Maximum value, or 0 if all values are negative
+    AutoMax,
+}
+
+impl ConditionalValueType {
+    /// Convert the `iType` of xlsb BrtCFVO records
+    pub(crate) fn from_code(code: u32) -> ConditionalValueType {
+        match code {
+            1 => ConditionalValueType::Number,
+            3 => ConditionalValueType::Max,
+            4 => ConditionalValueType::Percent,
+            5 => ConditionalValueType::Percentile,
+            7 => ConditionalValueType::Formula,
+            _ => ConditionalValueType::Min,
+        }
+    }
+
+    /// Convert the xlsx `cfvo` `type` attribute
+    pub(crate) fn from_name(name: &[u8]) -> ConditionalValueType {
+        match name {
+            b"max" => ConditionalValueType::Max,
+            b"num" => ConditionalValueType::Number,
+            b"percent" => ConditionalValueType::Percent,
+            b"percentile" => ConditionalValueType::Percentile,
+            b"formula" => ConditionalValueType::Formula,
+            b"autoMin" => ConditionalValueType::AutoMin,
+            b"autoMax" => ConditionalValueType::AutoMax,
+            _ => ConditionalValueType::Min,
+        }
+    }
+}
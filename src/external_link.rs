@@ -0,0 +1,141 @@
+//! Links to other workbooks, with the values cached from them
+
+use crate::{Data, Range};
+
+/// A workbook referenced by formulas, e.g. `[1]Sheet1!A1` in xlsx files
+#[derive(Debug, Clone, Default)]
+pub struct ExternalLink {
+    /// Path or URL of the linked workbook, as saved in the file
+    ///
+    /// DDE links are saved as "service|topic".
+    pub target: String,
+    /// Sheets of the linked workbook
+    pub sheets: Vec<ExternalSheet>,
+    /// Defined names of the linked workbook used by formulas
+    pub names: Vec<String>,
+}
+
+impl ExternalLink {
+    /// Get the file name of the linked workbook, i.e. the last segment of its target
+    ///
+    /// # Examples
+    /// ```
+    /// use calamine::ExternalLink;
+    ///
+    /// let link = ExternalLink {
+    ///     target: "file:///C:\\Reports\\Budget.xlsx".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(link.file_name(), "Budget.xlsx");
+    /// ```
+    pub fn file_name(&self) -> &str {
+        self.target
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or(&self.target)
+    }
+}
+
+/// A sheet of a linked workbook
+#[derive(Debug, Clone, Default)]
+pub struct ExternalSheet {
+    /// Sheet name
+    pub name: String,
+    /// Values of the cells referenced by formulas, cached when the file was saved
+    pub cells: Range<Data>,
+}
+
+/// Replaces the indexes of external workbooks (`[1]`, 1-based) by their file names
+///
+/// Structured references (`Table1[[#This Row],[1]]`) and string literals are left unchanged.
+/// The sheet name is quoted if the file name requires it.
+pub(crate) fn replace_external_indexes(formula: &str, links: &[ExternalLink]) -> String {
+    let chars: Vec<char> = formula.chars().collect();
+    let mut replaced = String::with_capacity(formula.len());
+    let mut in_string = false;
+    // depth of the brackets of structured references
+    let mut depth = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        match c {
+            '"' => in_string = !in_string,
+            _ if in_string => (),
+            '[' if depth == 0 && (i < 2 || !is_name_char(chars[i - 2])) => {
+                let digits = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+                let link = match chars[i..i + digits]
+                    .iter()
+                    .collect::<String>()
+                    .parse::<usize>()
+                {
+                    Ok(n) if n > 0 && chars.get(i + digits) == Some(&']') => links.get(n - 1),
+                    _ => None,
+                };
+                let Some(link) = link else {
+                    depth += 1;
+                    replaced.push(c);
+                    continue;
+                };
+                i += digits + 1;
+                let file_name = link.file_name();
+                let quoted = replaced.ends_with('\'');
+                if quoted || file_name.chars().all(is_name_char) {
+                    replaced.push('[');
+                    replaced.push_str(&file_name.replace('\'', "''"));
+                    replaced.push(']');
+                } else {
+                    // quote the whole `[file]Sheet` prefix
+                    let sheet_len = chars[i..].iter().take_while(|c| **c != '!').count();
+                    replaced.push_str("'[");
+                    replaced.push_str(&file_name.replace('\'', "''"));
+                    replaced.push(']');
+                    replaced.extend(&chars[i..i + sheet_len]);
+                    replaced.push('\'');
+                    i += sheet_len;
+                }
+                continue;
+            }
+            '[' => depth += 1,
+            ']' if depth > 0 => depth -= 1,
+            _ => (),
+        }
+        replaced.push(c);
+    }
+    replaced
+}
+
+/// Can the character be part of a name, or of an unquoted sheet name
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn external_indexes() {
+        let link = |target: &str| ExternalLink {
+            target: target.to_string(),
+            ..Default::default()
+        };
+        let links = [link("Book2.xlsx"), link("C:\\data\\My Book.xlsx")];
+        assert_eq!(
+            replace_external_indexes("SUM([1]Sheet1!A1:B2)+'[1]Sheet 2'!A1", &links),
+            "SUM([Book2.xlsx]Sheet1!A1:B2)+'[Book2.xlsx]Sheet 2'!A1"
+        );
+        assert_eq!(
+            replace_external_indexes("[2]Data!A1&[2]!Total", &links),
+            "'[My Book.xlsx]Data'!A1&'[My Book.xlsx]'!Total"
+        );
+        // unknown indexes, structured references and strings are left unchanged
+        assert_eq!(
+            replace_external_indexes(
+                "[3]Sheet1!A1+Table1[[#This Row],[1]]+Table1[1]&\"[1]\"",
+                &links
+            ),
+            "[3]Sheet1!A1+Table1[[#This Row],[1]]+Table1[1]&\"[1]\""
+        );
+    }
+}
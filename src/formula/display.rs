@@ -0,0 +1,318 @@
+//! Rendering of formulas as displayed by Excel
+
+use super::{lex, Token};
+use crate::utils::FTAB_LOCALIZED;
+
+/// Prefixes written in files before the names of the functions added after Excel 2007
+/// (`_xlfn.`), of some of their worksheet functions (`_xlws.`) and of `LAMBDA` parameters
+/// (`_xlpm.`)
+const FUTURE_PREFIXES: [&str; 3] = ["_xlfn.", "_xlws.", "_xlpm."];
+
+/// Language of the function names and separators of formulas
+///
+/// Only about a hundred common functions (e.g. `SUM`, `IF`, `VLOOKUP`, `XLOOKUP`) are
+/// translated. The others, such as most statistical, engineering and financial functions,
+/// keep their english name, which Excel does not recognize in other languages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// English, as stored in files
+    #[default]
+    English,
+    /// German, e.g. `SUMME(A1;1,5)`
+    German,
+    /// French, e.g. `SOMME(A1;1,5)`
+    French,
+    /// Spanish, e.g. `SUMA(A1;1,5)`
+    Spanish,
+    /// Italian, e.g. `SOMMA(A1;1,5)`
+    Italian,
+    /// Brazilian Portuguese, e.g. `SOMA(A1;1,5)`
+    Portuguese,
+    /// Dutch, e.g. `SOM(A1;1,5)`
+    Dutch,
+}
+
+impl Locale {
+    /// Get the locale of a language code, e.g. `de` or `fr-FR`
+    pub fn from_code(code: &str) -> Option<Locale> {
+        let language = code.split(['-', '_']).next().unwrap_or_default();
+        match &*language.to_ascii_lowercase() {
+            "en" => Some(Locale::English),
+            "de" => Some(Locale::German),
+            "fr" => Some(Locale::French),
+            "es" => Some(Locale::Spanish),
+            "it" => Some(Locale::Italian),
+            "pt" => Some(Locale::Portuguese),
+            "nl" => Some(Locale::Dutch),
+            _ => None,
+        }
+    }
+
+    /// Separator of the arguments of functions, also used by the union operator
+    pub fn argument_separator(self) -> char {
+        match self {
+            Locale::English => ',',
+            _ => ';',
+        }
+    }
+
+    /// Decimal separator of numbers
+    pub fn decimal_separator(self) -> char {
+        match self {
+            Locale::English => '.',
+            _ => ',',
+        }
+    }
+
+    /// Separators of the columns and rows of array constants
+    pub fn array_separators(self) -> (char, char) {
+        match self {
+            Locale::English => (',', ';'),
+            Locale::German | Locale::French | Locale::Italian => ('.', ';'),
+            Locale::Spanish | Locale::Portuguese | Locale::Dutch => ('\\', ';'),
+        }
+    }
+
+    /// Name of a function, or of a boolean, in this language
+    fn translate(self, name: &str) -> &str {
+        let column = match self {
+            Locale::English => return name,
+            Locale::German => 0,
+            Locale::French => 1,
+            Locale::Spanish => 2,
+            Locale::Italian => 3,
+            Locale::Portuguese => 4,
+            Locale::Dutch => 5,
+        };
+        let upper = name.to_ascii_uppercase();
+        match FTAB_LOCALIZED.binary_search_by(|(english, _)| (*english).cmp(&upper)) {
+            Ok(i) => FTAB_LOCALIZED[i].1[column],
+            Err(_) => name,
+        }
+    }
+}
+
+/// Render a formula as Excel displays it, from its form stored in files
+///
+/// The `_xlfn.`, `_xlws.` and `_xlpm.` prefixes are removed, and the `_xlfn.SINGLE` and
+/// `_xlfn.ANCHORARRAY` functions are written with the `@` and `#` operators. Formulas which
+/// cannot be tokenized are returned unchanged.
+///
+/// # Examples
+/// ```
+/// use calamine::formula::display_formula;
+///
+/// assert_eq!(
+///     display_formula("=_xlfn._xlws.FILTER(A:A,_xlfn.ANCHORARRAY(B1)>0)"),
+///     "=FILTER(A:A,B1#>0)"
+/// );
+/// assert_eq!(
+///     display_formula("_xlfn.LAMBDA(_xlpm.x,_xlpm.x*2)(_xlfn.SINGLE(A1:A3))"),
+///     "LAMBDA(x,x*2)(@A1:A3)"
+/// );
+/// ```
+pub fn display_formula(formula: &str) -> String {
+    localize_formula(formula, Locale::English)
+}
+
+/// Render a formula as Excel displays it in another language
+///
+/// Like [`display_formula`], with the function names, booleans and separators of the `locale`.
+/// Error literals, defined names and sheet names are not translated, nor are the functions
+/// missing from the translation table, see [`Locale`].
+///
+/// # Examples
+/// ```
+/// use calamine::formula::{localize_formula, Locale};
+///
+/// assert_eq!(
+///     localize_formula("=IF(A1>0.5,SUM(A1:B2),{1,2;3,4})", Locale::German),
+///     "=WENN(A1>0,5;SUMME(A1:B2);{1.2;3.4})"
+/// );
+/// assert_eq!(
+///     localize_formula("_xlfn.XLOOKUP(A1,B:B,C:C,FALSE)", Locale::French),
+///     "RECHERCHEX(A1;B:B;C:C;FAUX)"
+/// );
+/// // not translated
+/// assert_eq!(localize_formula("BESSELJ(1,2)", Locale::German), "BESSELJ(1;2)");
+/// ```
+pub fn localize_formula(formula: &str, locale: Locale) -> String {
+    let (prefix, body) = match formula.strip_prefix('=') {
+        Some(body) => ("=", body),
+        None => ("", formula),
+    };
+    let Ok(tokens) = lex(body, None) else {
+        return formula.to_string();
+    };
+    let chars: Vec<char> = body.chars().collect();
+    let text = |start: usize, end: usize| chars[start..end].iter().collect::<String>();
+
+    // index of the closing parenthesis of each function and opening parenthesis
+    let mut closing = vec![None; tokens.len()];
+    let mut open = Vec::new();
+    for (i, (token, ..)) in tokens.iter().enumerate() {
+        match token {
+            Token::Function(_) | Token::OpenParen => open.push(i),
+            Token::CloseParen => {
+                if let Some(o) = open.pop() {
+                    closing[o] = Some(i);
+                }
+            }
+            _ => (),
+        }
+    }
+    // is the argument between two parentheses a single operand, which needs no parentheses
+    let is_operand = |open: usize, close: usize| {
+        let inner: Vec<usize> = (open + 1..close)
+            .filter(|&i| !matches!(tokens[i].0, Token::Whitespace(_)))
+            .collect();
+        match &*inner {
+            [i] => matches!(
+                tokens[*i].0,
+                Token::Reference(_) | Token::Name { .. } | Token::StructuredRef(_)
+            ),
+            [first, .., last] => {
+                matches!(tokens[*first].0, Token::Function(_) | Token::OpenParen)
+                    && closing[*first] == Some(*last)
+            }
+            [] => false,
+        }
+    };
+
+    let (column_separator, row_separator) = locale.array_separators();
+    let mut displayed = prefix.to_string();
+    // replacement of the closing parentheses of the `@` and `#` operators
+    let mut closers = vec![None; tokens.len()];
+    let mut skipped = vec![false; tokens.len()];
+    let is_whitespace = |i: &usize| matches!(tokens[*i].0, Token::Whitespace(_));
+    // is the current comma in an array constant
+    let mut in_array = false;
+    for (i, (token, start, end)) in tokens.iter().enumerate() {
+        if skipped[i] {
+            continue;
+        }
+        match token {
+            Token::Function(name) => {
+                let operator = match (name.strip_prefix("_xlfn."), closing[i]) {
+                    (Some(f), Some(close)) if f.eq_ignore_ascii_case("SINGLE") => {
+                        Some(("@", "", close))
+                    }
+                    (Some(f), Some(close)) if f.eq_ignore_ascii_case("ANCHORARRAY") => {
+                        Some(("", "#", close))
+                    }
+                    _ => None,
+                };
+                match operator {
+                    Some((before, after, close)) if is_operand(i, close) => {
+                        // without the parentheses, the spaces around the operand are removed
+                        for j in (i + 1..close).take_while(is_whitespace) {
+                            skipped[j] = true;
+                        }
+                        for j in (i + 1..close).rev().take_while(is_whitespace) {
+                            skipped[j] = true;
+                        }
+                        displayed.push_str(before);
+                        closers[close] = Some(after.to_string());
+                    }
+                    Some((before, after, close)) => {
+                        displayed.push_str(before);
+                        displayed.push('(');
+                        closers[close] = Some(format!("){after}"));
+                    }
+                    None => {
+                        displayed.push_str(locale.translate(strip_future_prefixes(name)));
+                        displayed.push('(');
+                    }
+                }
+            }
+            Token::Name { .. } => displayed.push_str(strip_future_prefixes(&text(*start, *end))),
+            Token::Bool(b) => {
+                displayed.push_str(locale.translate(if *b { "TRUE" } else { "FALSE" }))
+            }
+            Token::Number(_) => displayed.extend(text(*start, *end).chars().map(|c| match c {
+                '.' => locale.decimal_separator(),
+                c => c,
+            })),
+            Token::OpenBrace => {
+                in_array = true;
+                displayed.push('{');
+            }
+            Token::CloseBrace => {
+                in_array = false;
+                displayed.push('}');
+            }
+            Token::Comma if in_array => displayed.push(column_separator),
+            Token::Comma => displayed.push(locale.argument_separator()),
+            Token::Semicolon => displayed.push(row_separator),
+            Token::CloseParen => match closers[i].take() {
+                Some(closer) => displayed.push_str(&closer),
+                None => displayed.push(')'),
+            },
+            _ => displayed.push_str(&text(*start, *end)),
+        }
+    }
+    displayed
+}
+
+/// Remove the prefixes of newer functions and `LAMBDA` parameters
+fn strip_future_prefixes(mut name: &str) -> &str {
+    while let Some(stripped) = FUTURE_PREFIXES.iter().find_map(|p| name.strip_prefix(p)) {
+        name = stripped;
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        for (stored, displayed) in [
+            ("=_xlfn.STDEV.S(A1:A3)", "=STDEV.S(A1:A3)"),
+            ("_xlfn.SINGLE(Table1[Amount])", "@Table1[Amount]"),
+            ("_xlfn.SINGLE(INDEX(A:A,2))", "@INDEX(A:A,2)"),
+            (
+                "_xlfn.ANCHORARRAY((A1))+_xlfn.ANCHORARRAY(A1,)",
+                "(A1)#+(A1,)#",
+            ),
+            ("SUM( _xlfn.ANCHORARRAY( A1 ) )", "SUM( A1# )"),
+            (
+                "\"_xlfn.X\"&'_xlfn.Sheet'!A1",
+                "\"_xlfn.X\"&'_xlfn.Sheet'!A1",
+            ),
+            ("_xlfn.XLOOKUP(\"", "_xlfn.XLOOKUP(\""),
+        ] {
+            assert_eq!(display_formula(stored), displayed);
+        }
+    }
+
+    #[test]
+    fn localize() {
+        assert_eq!(Locale::from_code("pt-BR"), Some(Locale::Portuguese));
+        assert_eq!(Locale::from_code("NL_nl"), Some(Locale::Dutch));
+        assert_eq!(Locale::from_code("ja"), None);
+        for (locale, localized) in [
+            (Locale::English, "SUM((A1,B1),1.5,\"a,b\")&TRUE"),
+            (Locale::German, "SUMME((A1;B1);1,5;\"a,b\")&WAHR"),
+            (Locale::French, "SOMME((A1;B1);1,5;\"a,b\")&VRAI"),
+            (Locale::Spanish, "SUMA((A1;B1);1,5;\"a,b\")&VERDADERO"),
+            (Locale::Italian, "SOMMA((A1;B1);1,5;\"a,b\")&VERO"),
+            (Locale::Portuguese, "SOMA((A1;B1);1,5;\"a,b\")&VERDADEIRO"),
+            (Locale::Dutch, "SOM((A1;B1);1,5;\"a,b\")&WAAR"),
+        ] {
+            let formula = "SUM((A1,B1),1.5,\"a,b\")&TRUE";
+            assert_eq!(localize_formula(formula, locale), localized);
+        }
+        // functions without a translation keep their name
+        assert_eq!(
+            localize_formula("_xlfn.STDEV.S({1.5,2;3,4})+MyFunc(A1)", Locale::Dutch),
+            "STDEV.S({1,5\\2;3\\4})+MyFunc(A1)"
+        );
+    }
+
+    #[test]
+    fn sorted_translations() {
+        assert!(FTAB_LOCALIZED.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+}
@@ -0,0 +1,827 @@
+//! Evaluation of the formulas of a workbook
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::io::{Read, Seek};
+use std::rc::Rc;
+
+use super::functions;
+use super::graph::{self, Node};
+use super::{parse, BinaryOp, Expr, FormulaError, UnaryOp};
+use crate::reference::{CellRef, RefTarget, Reference};
+use crate::{Cell, CellErrorType, Data, Range, Reader};
+
+/// An error while evaluating formulas
+#[derive(Debug)]
+pub enum EvalError {
+    /// There is no sheet with this name
+    SheetNotFound(String),
+    /// The formula cannot be parsed
+    Formula(FormulaError),
+    /// A formula depends on its own value
+    CircularReference {
+        /// Sheet of the cell
+        sheet: String,
+        /// Row of the cell, 0-based
+        row: u32,
+        /// Column of the cell, 0-based
+        col: u32,
+    },
+}
+
+from_err!(FormulaError, EvalError, Formula);
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::SheetNotFound(name) => write!(f, "Sheet '{name}' not found"),
+            EvalError::Formula(e) => write!(f, "Formula error: {e}"),
+            EvalError::CircularReference { sheet, row, col } => {
+                let cell = Reference {
+                    sheet: Some(sheet.clone()),
+                    target: RefTarget::Cell(CellRef {
+                        row: Some(*row),
+                        col: Some(*col),
+                        ..CellRef::default()
+                    }),
+                };
+                write!(f, "Circular reference at {cell}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EvalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EvalError::Formula(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// An intermediate value: a single value, or the values of an area or an array constant
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Value {
+    Scalar(Data),
+    Array(Vec<Vec<Data>>),
+}
+
+impl Value {
+    /// The value where a single value is expected: the first value of an array
+    pub(super) fn into_scalar(self) -> Data {
+        match self {
+            Value::Scalar(v) => v,
+            Value::Array(rows) => rows
+                .into_iter()
+                .next()
+                .and_then(|r| r.into_iter().next())
+                .unwrap_or(Data::Error(CellErrorType::Value)),
+        }
+    }
+
+    /// The values by rows, a single value being a 1x1 array
+    pub(super) fn into_rows(self) -> Vec<Vec<Data>> {
+        match self {
+            Value::Scalar(v) => vec![vec![v]],
+            Value::Array(rows) => rows,
+        }
+    }
+}
+
+impl From<Data> for Value {
+    fn from(v: Data) -> Value {
+        Value::Scalar(v)
+    }
+}
+
+/// The cell whose formula is being evaluated
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Context {
+    pub sheet: usize,
+    pub row: u32,
+    pub col: u32,
+}
+
+/// Values and parsed formulas of a sheet
+pub(super) struct Sheet {
+    pub name: String,
+    pub values: Range<Data>,
+    pub formulas: HashMap<(u32, u32), Rc<Expr>>,
+    computed: HashMap<(u32, u32), Data>,
+    /// Last used row and column, bounding whole rows and columns
+    pub end: (u32, u32),
+}
+
+/// Defined names, by uppercase name
+pub(super) type Names = HashMap<String, Rc<Expr>>;
+
+/// Loads the sheets and defined names of a workbook
+///
+/// Formulas which cannot be parsed, e.g. the OpenFormula of ods files, are skipped.
+pub(super) fn load<RS, R>(workbook: &mut R) -> Result<(Vec<Sheet>, Names), R::Error>
+where
+    RS: Read + Seek,
+    R: Reader<RS>,
+{
+    let mut sheets = Vec::new();
+    for name in workbook.sheet_names() {
+        let values = workbook.worksheet_range(&name)?;
+        let mut end = values.end().unwrap_or((0, 0));
+        let mut formulas = HashMap::new();
+        let formula_range = workbook.worksheet_formula(&name)?;
+        // formula ranges start at their first formula, not at A1
+        let start = formula_range.start().unwrap_or((0, 0));
+        for (row, col, formula) in formula_range.used_cells() {
+            if formula.starts_with("of:") {
+                continue;
+            }
+            if let Ok(expr) = parse(formula) {
+                let pos = (start.0 + row as u32, start.1 + col as u32);
+                formulas.insert(pos, Rc::new(expr));
+            }
+        }
+        for &(row, col) in formulas.keys() {
+            end = (end.0.max(row), end.1.max(col));
+        }
+        sheets.push(Sheet {
+            name,
+            values,
+            formulas,
+            computed: HashMap::new(),
+            end,
+        });
+    }
+    let names = workbook
+        .defined_names()
+        .iter()
+        .filter_map(|(name, formula)| {
+            let expr = parse(formula).ok()?;
+            Some((name.to_uppercase(), Rc::new(expr)))
+        })
+        .collect();
+    Ok((sheets, names))
+}
+
+/// Calculates the values of the formulas of a workbook
+///
+/// The evaluator loads the values and formulas of all the sheets, then recomputes formulas on
+/// demand, e.g. for workbooks written by generators which do not calculate their results.
+/// Values of formulas are cached, cells without formulas keep their stored value.
+///
+/// Supported are the operators and the usual arithmetic, comparison, text, logical, lookup
+/// (`VLOOKUP`, `HLOOKUP`, `INDEX`, `MATCH`, `XLOOKUP`), aggregate and date functions. Unknown
+/// functions, structured references and external references evaluate to errors, and formulas
+/// which cannot be parsed (e.g. the OpenFormula of ods files) keep their cached value.
+/// Dates are serial numbers of the 1900 date system and arrays are reduced to their first value
+/// where a single value is expected. Areas are read up to the last used row and column of their
+/// sheet.
+///
+/// # Examples
+/// ```
+/// use calamine::{open_workbook, Data, Xlsx};
+/// use calamine::formula::Evaluator;
+///
+/// # let path = format!("{}/tests/evaluate.xlsx", env!("CARGO_MANIFEST_DIR"));
+/// let mut workbook: Xlsx<_> = open_workbook(path).unwrap();
+/// let mut evaluator = Evaluator::new(&mut workbook).unwrap();
+/// assert_eq!(evaluator.evaluate("Sheet1", "SUM(B1:B3)*2").unwrap(), Data::Float(12.));
+/// let range = evaluator.worksheet_range("Sheet1").unwrap();
+/// assert_eq!(range.get_value((0, 2)), Some(&Data::Float(10.)));
+/// ```
+pub struct Evaluator {
+    sheets: Vec<Sheet>,
+    sheet_names: Vec<String>,
+    /// Formula cells, by sheet, to find the formulas in an area
+    formula_cells: Vec<BTreeSet<(u32, u32)>>,
+    names: Names,
+    visiting: HashSet<(usize, u32, u32)>,
+    visiting_names: HashSet<String>,
+}
+
+impl Evaluator {
+    /// Loads the values and formulas of all the sheets of a workbook
+    pub fn new<RS, R>(workbook: &mut R) -> Result<Evaluator, R::Error>
+    where
+        RS: Read + Seek,
+        R: Reader<RS>,
+    {
+        let (sheets, names) = load(workbook)?;
+        Ok(Evaluator::from_sheets(sheets, names))
+    }
+
+    fn from_sheets(sheets: Vec<Sheet>, names: Names) -> Evaluator {
+        Evaluator {
+            sheet_names: sheets.iter().map(|s| s.name.clone()).collect(),
+            formula_cells: sheets
+                .iter()
+                .map(|s| s.formulas.keys().copied().collect())
+                .collect(),
+            sheets,
+            names,
+            visiting: HashSet::new(),
+            visiting_names: HashSet::new(),
+        }
+    }
+
+    /// Value of a cell, at its absolute position, calculating its formula if any
+    pub fn evaluate_cell(&mut self, sheet: &str, position: (u32, u32)) -> Result<Data, EvalError> {
+        let sheet = self.sheet_index(sheet)?;
+        self.cell_value(sheet, position.0, position.1)
+    }
+
+    /// Calculates a formula, whose relative references are relative to the first cell of `sheet`
+    pub fn evaluate(&mut self, sheet: &str, formula: &str) -> Result<Data, EvalError> {
+        let sheet = self.sheet_index(sheet)?;
+        let expr = parse(formula)?;
+        let ctx = Context {
+            sheet,
+            row: 0,
+            col: 0,
+        };
+        Ok(self.eval(&expr, &ctx)?.into_scalar())
+    }
+
+    /// Values of a sheet, with all its formulas calculated
+    pub fn worksheet_range(&mut self, sheet: &str) -> Result<Range<Data>, EvalError> {
+        let index = self.sheet_index(sheet)?;
+        let mut positions: Vec<_> = self.sheets[index].formulas.keys().copied().collect();
+        // evaluate from the top left, as formulas usually depend on the cells above them
+        positions.sort_unstable();
+        let sheet = &self.sheets[index];
+        let start = sheet.values.start().unwrap_or((0, 0));
+        let mut cells: Vec<_> = sheet
+            .values
+            .used_cells()
+            .map(|(row, col, v)| Cell::new((start.0 + row as u32, start.1 + col as u32), v.clone()))
+            .filter(|c| !sheet.formulas.contains_key(&c.pos))
+            .collect();
+        for (row, col) in positions {
+            let value = self.cell_value(index, row, col)?;
+            cells.push(Cell::new((row, col), value));
+        }
+        cells.sort_unstable_by_key(|c| c.pos);
+        Ok(Range::from_sparse(cells))
+    }
+
+    fn sheet_index(&self, name: &str) -> Result<usize, EvalError> {
+        self.sheets
+            .iter()
+            .position(|s| s.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| EvalError::SheetNotFound(name.to_string()))
+    }
+
+    fn cell_value(&mut self, sheet: usize, row: u32, col: u32) -> Result<Data, EvalError> {
+        let s = &self.sheets[sheet];
+        if let Some(value) = s.computed.get(&(row, col)) {
+            return Ok(value.clone());
+        }
+        if !s.formulas.contains_key(&(row, col)) {
+            return Ok(s.values.get_value((row, col)).cloned().unwrap_or_default());
+        }
+        // calculate the formulas read by this one first, so that a long chain of formulas does
+        // not recurse once per cell; their errors are only reported if their value is needed
+        for (sheet, row, col) in self.pending_precedents((sheet, row, col)) {
+            let _ = self.formula_value(sheet, row, col);
+        }
+        self.formula_value(sheet, row, col)
+    }
+
+    /// Formulas read by a formula, directly or through other formulas, which are not calculated
+    /// yet, each after the formulas it reads
+    fn pending_precedents(&self, node: Node) -> Vec<Node> {
+        let mut order = Vec::new();
+        let mut started = HashSet::new();
+        // iterative depth first search, with the precedents of each formula and the next one
+        let mut calls = vec![(node, self.direct_precedents(node), 0)];
+        started.insert(node);
+        while let Some((node, precedents, next)) = calls.last_mut() {
+            match precedents.get(*next) {
+                Some(&precedent) => {
+                    *next += 1;
+                    // formulas being visited are circular references, reported when evaluated
+                    if started.insert(precedent) {
+                        calls.push((precedent, self.direct_precedents(precedent), 0));
+                    }
+                }
+                None => {
+                    order.push(*node);
+                    calls.pop();
+                }
+            }
+        }
+        // the formula itself comes last
+        order.pop();
+        order
+    }
+
+    /// Formulas, not calculated yet, in the areas read by a formula
+    fn direct_precedents(&self, (sheet, row, col): Node) -> Vec<Node> {
+        let Some(expr) = self.sheets[sheet].formulas.get(&(row, col)) else {
+            return Vec::new();
+        };
+        let mut spans = Vec::new();
+        graph::collect(
+            expr,
+            sheet,
+            &self.sheet_names,
+            &self.names,
+            &mut Vec::new(),
+            &mut spans,
+        );
+        spans
+            .iter()
+            .flat_map(|span| graph::cells_in(&self.formula_cells[span.sheet], span))
+            .filter(|(sheet, row, col)| !self.sheets[*sheet].computed.contains_key(&(*row, *col)))
+            .collect()
+    }
+
+    /// Value of a formula cell, calculating it if needed
+    fn formula_value(&mut self, sheet: usize, row: u32, col: u32) -> Result<Data, EvalError> {
+        let s = &self.sheets[sheet];
+        if let Some(value) = s.computed.get(&(row, col)) {
+            return Ok(value.clone());
+        }
+        let expr = Rc::clone(&s.formulas[&(row, col)]);
+        if !self.visiting.insert((sheet, row, col)) {
+            return Err(EvalError::CircularReference {
+                sheet: s.name.clone(),
+                row,
+                col,
+            });
+        }
+        let ctx = Context { sheet, row, col };
+        let value = self.eval(&expr, &ctx);
+        self.visiting.remove(&(sheet, row, col));
+        let value = match value?.into_scalar() {
+            // a formula referencing an empty cell returns 0
+            Data::Empty => Data::Float(0.),
+            v => v,
+        };
+        self.sheets[sheet]
+            .computed
+            .insert((row, col), value.clone());
+        Ok(value)
+    }
+
+    /// Evaluates an expression where a single value is expected
+    pub(super) fn scalar(&mut self, expr: &Expr, ctx: &Context) -> Result<Data, EvalError> {
+        Ok(self.eval(expr, ctx)?.into_scalar())
+    }
+
+    pub(super) fn eval(&mut self, expr: &Expr, ctx: &Context) -> Result<Value, EvalError> {
+        let value = match expr {
+            Expr::Number(n) => Data::Float(*n).into(),
+            Expr::Text(s) => Data::String(s.clone()).into(),
+            Expr::Bool(b) => Data::Bool(*b).into(),
+            Expr::Error(e) => Data::Error(e.clone()).into(),
+            Expr::Reference(r) => match self.resolve(r, ctx) {
+                Ok((sheet, target)) => self.area_values(sheet, target)?,
+                Err(e) => Data::Error(e).into(),
+            },
+            Expr::Name { name, .. } => self.name_value(name, ctx)?,
+            Expr::StructuredRef(_) => Data::Error(CellErrorType::Name).into(),
+            Expr::Array(rows) if rows.is_empty() => Data::Error(CellErrorType::Value).into(),
+            Expr::Array(rows) => {
+                let mut values = Vec::with_capacity(rows.len());
+                for row in rows {
+                    let mut r = Vec::with_capacity(row.len());
+                    for e in row {
+                        r.push(self.scalar(e, ctx)?);
+                    }
+                    values.push(r);
+                }
+                Value::Array(values)
+            }
+            Expr::Function { name, args } => functions::call(self, ctx, name, args)?,
+            // LAMBDA functions are not supported
+            Expr::Call { .. } => Data::Error(CellErrorType::Name).into(),
+            Expr::Paren(e) => self.eval(e, ctx)?,
+            Expr::Missing => Data::Empty.into(),
+            Expr::Unary(UnaryOp::Plus | UnaryOp::Spill, e) => self.eval(e, ctx)?,
+            Expr::Unary(UnaryOp::ImplicitIntersection, e) => self.scalar(e, ctx)?.into(),
+            Expr::Unary(UnaryOp::Minus, e) => map(self.eval(e, ctx)?, |v| {
+                number_result(to_number(&v).map(|n| -n))
+            }),
+            Expr::Unary(UnaryOp::Percent, e) => map(self.eval(e, ctx)?, |v| {
+                number_result(to_number(&v).map(|n| n / 100.))
+            }),
+            Expr::Binary(op @ (BinaryOp::Range | BinaryOp::Intersect), left, right) => {
+                match (left.as_ref(), right.as_ref()) {
+                    (Expr::Reference(l), Expr::Reference(r)) => {
+                        match self.combine(*op, l, r, ctx) {
+                            Ok((sheet, target)) => self.area_values(sheet, target)?,
+                            Err(e) => Data::Error(e).into(),
+                        }
+                    }
+                    _ => Data::Error(CellErrorType::Value).into(),
+                }
+            }
+            Expr::Binary(BinaryOp::Union, ..) => Data::Error(CellErrorType::Value).into(),
+            Expr::Binary(op, left, right) => {
+                let left = self.eval(left, ctx)?;
+                let right = self.eval(right, ctx)?;
+                map2(left, right, |l, r| binary(*op, l, r))
+            }
+        };
+        Ok(value)
+    }
+
+    fn name_value(&mut self, name: &str, ctx: &Context) -> Result<Value, EvalError> {
+        let key = name.to_uppercase();
+        let Some(expr) = self.names.get(&key).cloned() else {
+            return Ok(Data::Error(CellErrorType::Name).into());
+        };
+        if !self.visiting_names.insert(key.clone()) {
+            return Ok(Data::Error(CellErrorType::Name).into());
+        }
+        let value = self.eval(&expr, ctx);
+        self.visiting_names.remove(&key);
+        value
+    }
+
+    /// Sheet index and cells of a reference
+    pub(super) fn resolve(
+        &self,
+        r: &Reference,
+        ctx: &Context,
+    ) -> Result<(usize, RefTarget), CellErrorType> {
+        let sheet = match &r.sheet {
+            None => ctx.sheet,
+            Some(name) => self
+                .sheets
+                .iter()
+                .position(|s| s.name.eq_ignore_ascii_case(name))
+                .ok_or(CellErrorType::Ref)?,
+        };
+        match r.target {
+            RefTarget::Invalid => Err(CellErrorType::Ref),
+            target => Ok((sheet, target)),
+        }
+    }
+
+    /// The area of `A1:B2` or `A1 B2` when its operands are references
+    fn combine(
+        &self,
+        op: BinaryOp,
+        left: &Reference,
+        right: &Reference,
+        ctx: &Context,
+    ) -> Result<(usize, RefTarget), CellErrorType> {
+        let (sheet, left) = self.resolve(left, ctx)?;
+        let (right_sheet, right) = self.resolve(right, ctx)?;
+        if sheet != right_sheet {
+            return Err(CellErrorType::Value);
+        }
+        let ((r0, c0), (r1, c1)) = self.bounds(sheet, left);
+        let ((s0, d0), (s1, d1)) = self.bounds(sheet, right);
+        let (first, last) = if op == BinaryOp::Range {
+            ((r0.min(s0), c0.min(d0)), (r1.max(s1), c1.max(d1)))
+        } else {
+            ((r0.max(s0), c0.max(d0)), (r1.min(s1), c1.min(d1)))
+        };
+        if first.0 > last.0 || first.1 > last.1 {
+            return Err(CellErrorType::Null);
+        }
+        let cell = |(row, col)| CellRef {
+            row: Some(row),
+            col: Some(col),
+            ..CellRef::default()
+        };
+        Ok((sheet, RefTarget::Area(cell(first), cell(last))))
+    }
+
+    /// First and last cells of a target, whole rows and columns ending at the last used cell
+    pub(super) fn bounds(&self, sheet: usize, target: RefTarget) -> ((u32, u32), (u32, u32)) {
+        let end = self.sheets[sheet].end;
+        let (first, last) = match target {
+            RefTarget::Cell(c) => (c, c),
+            RefTarget::Area(first, last) => (first, last),
+            RefTarget::Invalid => unreachable!("invalid references are not resolved"),
+        };
+        let (r0, r1) = match (first.row, last.row) {
+            (Some(a), Some(b)) => (a.min(b), a.max(b)),
+            _ => (0, end.0),
+        };
+        let (c0, c1) = match (first.col, last.col) {
+            (Some(a), Some(b)) => (a.min(b), a.max(b)),
+            _ => (0, end.1),
+        };
+        ((r0, c0), (r1, c1))
+    }
+
+    fn area_values(&mut self, sheet: usize, target: RefTarget) -> Result<Value, EvalError> {
+        let ((r0, c0), (r1, c1)) = self.bounds(sheet, target);
+        if let RefTarget::Cell(_) = target {
+            return Ok(self.cell_value(sheet, r0, c0)?.into());
+        }
+        // the cells after the last used ones are empty, keep at least the first cell
+        let end = self.sheets[sheet].end;
+        let (r1, c1) = (r1.min(end.0).max(r0), c1.min(end.1).max(c0));
+        let mut rows = Vec::with_capacity((r1 - r0 + 1) as usize);
+        for row in r0..=r1 {
+            let mut values = Vec::with_capacity((c1 - c0 + 1) as usize);
+            for col in c0..=c1 {
+                values.push(self.cell_value(sheet, row, col)?);
+            }
+            rows.push(values);
+        }
+        Ok(Value::Array(rows))
+    }
+}
+
+/// Applies `f` to a value or to every value of an array
+pub(super) fn map<F: Fn(Data) -> Data>(value: Value, f: F) -> Value {
+    match value {
+        Value::Scalar(v) => Value::Scalar(f(v)),
+        Value::Array(rows) => Value::Array(
+            rows.into_iter()
+                .map(|r| r.into_iter().map(&f).collect())
+                .collect(),
+        ),
+    }
+}
+
+/// Applies `f` to two values, element-wise for arrays
+///
+/// Single values, rows and columns are repeated to the size of the other array, other values
+/// out of the smaller array are `#N/A`.
+pub(super) fn map2<F: Fn(Data, Data) -> Data>(left: Value, right: Value, f: F) -> Value {
+    let (left, right) = match (left, right) {
+        (Value::Scalar(l), Value::Scalar(r)) => return Value::Scalar(f(l, r)),
+        (l, r) => (l.into_rows(), r.into_rows()),
+    };
+    let height = left.len().max(right.len());
+    let width = left
+        .iter()
+        .chain(right.iter())
+        .map(|r| r.len())
+        .max()
+        .unwrap_or(0);
+    let get = |rows: &[Vec<Data>], i: usize, j: usize| {
+        let i = if rows.len() == 1 { 0 } else { i };
+        rows.get(i)
+            .and_then(|r| r.get(if r.len() == 1 { 0 } else { j }))
+            .cloned()
+            .unwrap_or(Data::Error(CellErrorType::NA))
+    };
+    Value::Array(
+        (0..height)
+            .map(|i| {
+                (0..width)
+                    .map(|j| f(get(&left, i, j), get(&right, i, j)))
+                    .collect()
+            })
+            .collect(),
+    )
+}
+
+fn binary(op: BinaryOp, left: Data, right: Data) -> Data {
+    if let Data::Error(e) = left {
+        return Data::Error(e);
+    }
+    if let Data::Error(e) = right {
+        return Data::Error(e);
+    }
+    let arithmetic = |f: fn(f64, f64) -> f64| {
+        let l = to_number(&left)?;
+        let r = to_number(&right)?;
+        Ok(f(l, r))
+    };
+    match op {
+        BinaryOp::Add => number_result(arithmetic(|l, r| l + r)),
+        BinaryOp::Sub => number_result(arithmetic(|l, r| l - r)),
+        BinaryOp::Mul => number_result(arithmetic(|l, r| l * r)),
+        BinaryOp::Div => match arithmetic(|l, r| l / r) {
+            Ok(_) if to_number(&right) == Ok(0.) => Data::Error(CellErrorType::Div0),
+            n => number_result(n),
+        },
+        BinaryOp::Pow => match arithmetic(f64::powf) {
+            Ok(_) if to_number(&left) == Ok(0.) && to_number(&right) == Ok(0.) => {
+                Data::Error(CellErrorType::Num)
+            }
+            n => number_result(n),
+        },
+        BinaryOp::Concat => match (to_text(&left), to_text(&right)) {
+            (Ok(l), Ok(r)) => Data::String(l + &r),
+            (Err(e), _) | (_, Err(e)) => Data::Error(e),
+        },
+        BinaryOp::Eq => Data::Bool(compare(&left, &right) == Ordering::Equal),
+        BinaryOp::Ne => Data::Bool(compare(&left, &right) != Ordering::Equal),
+        BinaryOp::Lt => Data::Bool(compare(&left, &right) == Ordering::Less),
+        BinaryOp::Le => Data::Bool(compare(&left, &right) != Ordering::Greater),
+        BinaryOp::Gt => Data::Bool(compare(&left, &right) == Ordering::Greater),
+        BinaryOp::Ge => Data::Bool(compare(&left, &right) != Ordering::Less),
+        BinaryOp::Range | BinaryOp::Union | BinaryOp::Intersect => {
+            Data::Error(CellErrorType::Value)
+        }
+    }
+}
+
+/// A number as a cell value, `#NUM!` if it is not finite
+pub(super) fn number_result(n: Result<f64, CellErrorType>) -> Data {
+    match n {
+        Ok(n) if n.is_finite() => Data::Float(n),
+        Ok(_) => Data::Error(CellErrorType::Num),
+        Err(e) => Data::Error(e),
+    }
+}
+
+/// Converts a value to a number, parsing strings and with booleans as 0 and 1
+pub(super) fn to_number(value: &Data) -> Result<f64, CellErrorType> {
+    match value {
+        Data::Int(i) => Ok(*i as f64),
+        Data::Float(f) => Ok(*f),
+        Data::Bool(b) => Ok(if *b { 1. } else { 0. }),
+        Data::DateTime(d) => Ok(d.as_f64()),
+        Data::Empty => Ok(0.),
+        Data::Error(e) => Err(e.clone()),
+        Data::String(s) | Data::DateTimeIso(s) | Data::DurationIso(s) => {
+            let s = s.trim();
+            if let Some(p) = s.strip_suffix('%') {
+                p.trim().parse::<f64>().map(|n| n / 100.)
+            } else {
+                s.parse::<f64>()
+            }
+            .map_err(|_| CellErrorType::Value)
+        }
+    }
+}
+
+/// Converts a value to text, numbers being written as in the general format
+pub(super) fn to_text(value: &Data) -> Result<String, CellErrorType> {
+    match value {
+        Data::Int(i) => Ok(i.to_string()),
+        Data::Float(f) => Ok(format_number(*f)),
+        Data::DateTime(d) => Ok(format_number(d.as_f64())),
+        Data::Bool(true) => Ok("TRUE".to_string()),
+        Data::Bool(false) => Ok("FALSE".to_string()),
+        Data::Empty => Ok(String::new()),
+        Data::Error(e) => Err(e.clone()),
+        Data::String(s) | Data::DateTimeIso(s) | Data::DurationIso(s) => Ok(s.clone()),
+    }
+}
+
+/// Converts a value to a boolean, numbers being true when they are not 0
+pub(super) fn to_bool(value: &Data) -> Result<bool, CellErrorType> {
+    match value {
+        Data::Bool(b) => Ok(*b),
+        Data::Empty => Ok(false),
+        Data::Error(e) => Err(e.clone()),
+        Data::String(s) if s.eq_ignore_ascii_case("TRUE") => Ok(true),
+        Data::String(s) if s.eq_ignore_ascii_case("FALSE") => Ok(false),
+        Data::String(_) | Data::DateTimeIso(_) | Data::DurationIso(_) => Err(CellErrorType::Value),
+        v => to_number(v).map(|n| n != 0.),
+    }
+}
+
+/// Writes a number with at most 15 significant digits, as Excel does
+pub(super) fn format_number(n: f64) -> String {
+    if n == n.trunc() && n.abs() < 1e15 {
+        return format!("{}", n as i64);
+    }
+    let s = format!("{:.14e}", n);
+    let (mantissa, exponent) = s.split_once('e').unwrap_or((&s, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+    if (-5..15).contains(&exponent) {
+        // back to a positional notation, rounded to 15 digits
+        let rounded: f64 = format!("{mantissa}e{exponent}").parse().unwrap_or(n);
+        rounded.to_string()
+    } else {
+        format!(
+            "{mantissa}E{}{:02}",
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        )
+    }
+}
+
+/// Orders two values as Excel comparison operators do
+///
+/// Numbers are lower than strings, which are lower than booleans. Strings are compared ignoring
+/// their case and an empty cell is equal to 0, `""` or `FALSE`.
+pub(super) fn compare(left: &Data, right: &Data) -> Ordering {
+    fn rank(v: &Data) -> u8 {
+        match v {
+            Data::String(_) | Data::DateTimeIso(_) | Data::DurationIso(_) => 1,
+            Data::Bool(_) => 2,
+            Data::Error(_) => 3,
+            _ => 0,
+        }
+    }
+    let (left, right) = match (left, right) {
+        (Data::Empty, Data::Empty) => return Ordering::Equal,
+        (Data::Empty, r) => (empty_like(r), r.clone()),
+        (l, Data::Empty) => (l.clone(), empty_like(l)),
+        (l, r) => (l.clone(), r.clone()),
+    };
+    match rank(&left).cmp(&rank(&right)) {
+        Ordering::Equal => {}
+        o => return o,
+    }
+    match rank(&left) {
+        0 => {
+            let l = to_number(&left).unwrap_or(0.);
+            let r = to_number(&right).unwrap_or(0.);
+            l.partial_cmp(&r).unwrap_or(Ordering::Equal)
+        }
+        1 => {
+            let l = to_text(&left).unwrap_or_default().to_lowercase();
+            let r = to_text(&right).unwrap_or_default().to_lowercase();
+            l.cmp(&r)
+        }
+        2 => to_bool(&left).ok().cmp(&to_bool(&right).ok()),
+        _ => Ordering::Equal,
+    }
+}
+
+/// The value of an empty cell compared to `other`
+fn empty_like(other: &Data) -> Data {
+    match other {
+        Data::String(_) | Data::DateTimeIso(_) | Data::DurationIso(_) => {
+            Data::String(String::new())
+        }
+        Data::Bool(_) => Data::Bool(false),
+        _ => Data::Float(0.),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_as_text() {
+        assert_eq!(format_number(3.), "3");
+        assert_eq!(format_number(-0.5), "-0.5");
+        assert_eq!(format_number(0.1 + 0.2), "0.3");
+        assert_eq!(format_number(1e20), "1E+20");
+        assert_eq!(format_number(1.5e-7), "1.5E-07");
+    }
+
+    #[test]
+    fn comparisons() {
+        let s = |s: &str| Data::String(s.to_string());
+        assert_eq!(compare(&s("abc"), &s("ABC")), Ordering::Equal);
+        assert_eq!(compare(&Data::Float(1e10), &s("a")), Ordering::Less);
+        assert_eq!(compare(&s("z"), &Data::Bool(false)), Ordering::Less);
+        assert_eq!(compare(&Data::Empty, &Data::Int(0)), Ordering::Equal);
+        assert_eq!(compare(&Data::Empty, &s("")), Ordering::Equal);
+        assert_eq!(compare(&Data::Int(2), &Data::Float(1.5)), Ordering::Greater);
+    }
+
+    /// A sheet whose first column is a chain of formulas, each reading the cell above (or below)
+    fn chain(len: u32, upwards: bool) -> Evaluator {
+        let first = if upwards { 0 } else { len - 1 };
+        let values = Range::from_sparse(vec![Cell::new((first, 0), Data::Float(1.))]);
+        let formulas = (0..len)
+            .filter(|&row| row != first)
+            .map(|row| {
+                // rows of the formula are 1-based
+                let read = if upwards { row } else { row + 2 };
+                let expr = parse(&format!("A{read}+1")).unwrap();
+                ((row, 0), Rc::new(expr))
+            })
+            .collect();
+        let sheet = Sheet {
+            name: "Sheet1".to_string(),
+            values,
+            formulas,
+            computed: HashMap::new(),
+            end: (len - 1, 0),
+        };
+        Evaluator::from_sheets(vec![sheet], Names::new())
+    }
+
+    #[test]
+    fn long_chains() {
+        let mut evaluator = chain(20_000, true);
+        assert_eq!(
+            evaluator.evaluate_cell("Sheet1", (19_999, 0)).unwrap(),
+            Data::Float(20_000.)
+        );
+        let mut evaluator = chain(20_000, false);
+        let range = evaluator.worksheet_range("Sheet1").unwrap();
+        assert_eq!(range.get_value((0, 0)), Some(&Data::Float(20_000.)));
+        assert_eq!(
+            evaluator.evaluate("Sheet1", "SUM(A1:XFD1048576)").unwrap(),
+            Data::Float(200_010_000.)
+        );
+    }
+
+    #[test]
+    fn broadcasting() {
+        let column = Value::Array(vec![vec![Data::Float(1.)], vec![Data::Float(2.)]]);
+        let row = Value::Array(vec![vec![Data::Float(10.), Data::Float(20.)]]);
+        let sum = map2(column, row, |l, r| binary(BinaryOp::Add, l, r));
+        assert_eq!(
+            sum,
+            Value::Array(vec![
+                vec![Data::Float(11.), Data::Float(21.)],
+                vec![Data::Float(12.), Data::Float(22.)],
+            ])
+        );
+    }
+}
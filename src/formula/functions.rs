@@ -0,0 +1,1187 @@
+//! Worksheet functions of the formula [`Evaluator`]
+
+use std::cmp::Ordering;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::eval::{
+    compare, map, to_bool, to_number, to_text, Context, EvalError, Evaluator, Value,
+};
+use super::Expr;
+use crate::{CellErrorType, Data, DataType};
+
+type Res<T> = Result<T, CellErrorType>;
+
+/// Calls a function, `#NAME?` for unknown functions
+pub(super) fn call(
+    ev: &mut Evaluator,
+    ctx: &Context,
+    name: &str,
+    args: &[Expr],
+) -> Result<Value, EvalError> {
+    let upper = name.to_ascii_uppercase();
+    let name = upper
+        .strip_prefix("_XLFN.")
+        .or_else(|| upper.strip_prefix("_XLWS."))
+        .unwrap_or(&upper);
+
+    // functions evaluating only some of their arguments, or their references
+    match name {
+        "IF" => return if_(ev, ctx, args),
+        "IFERROR" | "IFNA" => {
+            let value = ev.eval(first_arg(args), ctx)?;
+            let is_caught = |v: &Data| match v {
+                Data::Error(CellErrorType::NA) => true,
+                Data::Error(_) => name == "IFERROR",
+                _ => false,
+            };
+            let caught = match &value {
+                Value::Scalar(v) => is_caught(v),
+                Value::Array(rows) => rows.iter().flatten().any(is_caught),
+            };
+            if !caught {
+                return Ok(value);
+            }
+            let alternative = ev.scalar(args.get(1).unwrap_or(&Expr::Missing), ctx)?;
+            return Ok(map(value, |v| {
+                if is_caught(&v) {
+                    alternative.clone()
+                } else {
+                    v
+                }
+            }));
+        }
+        "IFS" => {
+            for pair in args.chunks(2) {
+                let [condition, value] = pair else {
+                    break;
+                };
+                match to_bool(&ev.scalar(condition, ctx)?) {
+                    Ok(true) => return ev.eval(value, ctx),
+                    Ok(false) => {}
+                    Err(e) => return Ok(Data::Error(e).into()),
+                }
+            }
+            return Ok(Data::Error(CellErrorType::NA).into());
+        }
+        "SWITCH" => {
+            let value = ev.scalar(first_arg(args), ctx)?;
+            if let Data::Error(e) = value {
+                return Ok(Data::Error(e).into());
+            }
+            let cases = args.get(1..).unwrap_or_default();
+            for pair in cases.chunks(2) {
+                match pair {
+                    [case, result] => {
+                        let case = ev.scalar(case, ctx)?;
+                        if compare(&value, &case) == Ordering::Equal {
+                            return ev.eval(result, ctx);
+                        }
+                    }
+                    [default] => return ev.eval(default, ctx),
+                    _ => {}
+                }
+            }
+            return Ok(Data::Error(CellErrorType::NA).into());
+        }
+        "CHOOSE" => {
+            let index = ev.scalar(first_arg(args), ctx)?;
+            return match to_number(&index).map(|n| n.trunc() as usize) {
+                Ok(i) if i >= 1 && i < args.len() => ev.eval(&args[i], ctx),
+                Ok(_) => Ok(Data::Error(CellErrorType::Value).into()),
+                Err(e) => Ok(Data::Error(e).into()),
+            };
+        }
+        "ROW" | "COLUMN" => {
+            let position = match args.first() {
+                None | Some(Expr::Missing) => Ok((ctx.row, ctx.col)),
+                Some(Expr::Reference(r)) => ev
+                    .resolve(r, ctx)
+                    .map(|(sheet, target)| ev.bounds(sheet, target).0),
+                Some(_) => Err(CellErrorType::Value),
+            };
+            let value = position.map(|(row, col)| {
+                let n = if name == "ROW" { row } else { col };
+                Data::Float(n as f64 + 1.)
+            });
+            return Ok(scalar(value).into());
+        }
+        "ROWS" | "COLUMNS" => {
+            // the size of a reference, without reading its cells which are clipped to the used
+            // range
+            if let [Expr::Reference(r)] = args {
+                let size = ev.resolve(r, ctx).map(|(sheet, target)| {
+                    let ((r0, c0), (r1, c1)) = ev.bounds(sheet, target);
+                    let n = if name == "ROWS" { r1 - r0 } else { c1 - c0 };
+                    Data::Float(n as f64 + 1.)
+                });
+                return Ok(scalar(size).into());
+            }
+        }
+        _ => {}
+    }
+
+    let mut values = Vec::with_capacity(args.len());
+    for arg in args {
+        values.push(ev.eval(arg, ctx)?);
+    }
+    let given = |i: usize| args.get(i).map_or(false, |a| *a != Expr::Missing);
+    match apply(name, &values, given) {
+        Ok(value) => Ok(value),
+        Err(e) => Ok(Data::Error(e).into()),
+    }
+}
+
+fn first_arg(args: &[Expr]) -> &Expr {
+    args.first().unwrap_or(&Expr::Missing)
+}
+
+fn if_(ev: &mut Evaluator, ctx: &Context, args: &[Expr]) -> Result<Value, EvalError> {
+    let branch = |ev: &mut Evaluator, i: usize| match args.get(i) {
+        Some(e) => ev.eval(e, ctx),
+        // a missing else branch is FALSE, a missing then branch is 0
+        None => Ok(Data::Bool(false).into()),
+    };
+    match ev.eval(first_arg(args), ctx)? {
+        Value::Scalar(condition) => match to_bool(&condition) {
+            Ok(true) => branch(ev, 1),
+            Ok(false) => branch(ev, 2),
+            Err(e) => Ok(Data::Error(e).into()),
+        },
+        // array conditions choose between the branches element-wise
+        conditions => {
+            let conditions = conditions.into_rows();
+            let then = branch(ev, 1)?.into_rows();
+            let otherwise = branch(ev, 2)?.into_rows();
+            let get = |rows: &[Vec<Data>], i: usize, j: usize| {
+                let i = if rows.len() == 1 { 0 } else { i };
+                rows.get(i)
+                    .and_then(|r| r.get(if r.len() == 1 { 0 } else { j }))
+                    .cloned()
+                    .unwrap_or(Data::Error(CellErrorType::NA))
+            };
+            let rows = conditions
+                .iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    row.iter()
+                        .enumerate()
+                        .map(|(j, c)| match to_bool(c) {
+                            Ok(true) => get(&then, i, j),
+                            Ok(false) => get(&otherwise, i, j),
+                            Err(e) => Data::Error(e),
+                        })
+                        .collect()
+                })
+                .collect();
+            Ok(Value::Array(rows))
+        }
+    }
+}
+
+fn scalar(value: Res<Data>) -> Data {
+    value.unwrap_or_else(Data::Error)
+}
+
+/// Argument `i`, reduced to a single value
+fn arg(args: &[Value], i: usize) -> Res<Data> {
+    match args.get(i) {
+        Some(Value::Scalar(v)) => Ok(v.clone()),
+        Some(Value::Array(rows)) => Ok(rows
+            .first()
+            .and_then(|r| r.first())
+            .cloned()
+            .unwrap_or(Data::Error(CellErrorType::Value))),
+        None => Err(CellErrorType::Value),
+    }
+}
+
+fn num(args: &[Value], i: usize) -> Res<f64> {
+    to_number(&arg(args, i)?)
+}
+
+fn opt_num(args: &[Value], i: usize, default: f64) -> Res<f64> {
+    if i < args.len() {
+        num(args, i)
+    } else {
+        Ok(default)
+    }
+}
+
+fn text(args: &[Value], i: usize) -> Res<String> {
+    to_text(&arg(args, i)?)
+}
+
+fn number(n: f64) -> Res<Value> {
+    if n.is_finite() {
+        Ok(Data::Float(n).into())
+    } else {
+        Err(CellErrorType::Num)
+    }
+}
+
+fn boolean(b: bool) -> Res<Value> {
+    Ok(Data::Bool(b).into())
+}
+
+fn string(s: String) -> Res<Value> {
+    Ok(Data::String(s).into())
+}
+
+fn is_number(v: &Data) -> bool {
+    matches!(v, Data::Int(_) | Data::Float(_) | Data::DateTime(_))
+}
+
+fn is_text(v: &Data) -> bool {
+    matches!(
+        v,
+        Data::String(_) | Data::DateTimeIso(_) | Data::DurationIso(_)
+    )
+}
+
+/// Numbers of aggregate functions: arguments are converted, only numbers of arrays are kept
+fn numbers(args: &[Value]) -> Res<Vec<f64>> {
+    let mut numbers = Vec::new();
+    for arg in args {
+        match arg {
+            Value::Scalar(v) => numbers.push(to_number(v)?),
+            Value::Array(rows) => {
+                for v in rows.iter().flatten() {
+                    match v {
+                        Data::Error(e) => return Err(e.clone()),
+                        v if is_number(v) => numbers.push(to_number(v)?),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    Ok(numbers)
+}
+
+/// All values of the arguments, arrays being flattened by rows
+fn flatten(args: &[Value]) -> impl Iterator<Item = &Data> {
+    args.iter()
+        .flat_map(|a| -> Box<dyn Iterator<Item = &Data>> {
+            match a {
+                Value::Scalar(v) => Box::new(std::iter::once(v)),
+                Value::Array(rows) => Box::new(rows.iter().flatten()),
+            }
+        })
+}
+
+/// Booleans of logical functions, `#VALUE!` when there are none
+fn booleans(args: &[Value]) -> Res<Vec<bool>> {
+    let mut booleans = Vec::new();
+    for arg in args {
+        match arg {
+            Value::Scalar(v) => booleans.push(to_bool(v)?),
+            Value::Array(rows) => {
+                for v in rows.iter().flatten() {
+                    match v {
+                        Data::Error(e) => return Err(e.clone()),
+                        v if is_number(v) || matches!(v, Data::Bool(_)) => {
+                            booleans.push(to_bool(v)?)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    if booleans.is_empty() {
+        Err(CellErrorType::Value)
+    } else {
+        Ok(booleans)
+    }
+}
+
+/// Values of a row or a column, `None` for 2D arrays
+fn vector(value: &Value) -> Option<Vec<Data>> {
+    match value {
+        Value::Scalar(v) => Some(vec![v.clone()]),
+        Value::Array(rows) if rows.len() == 1 => Some(rows[0].clone()),
+        Value::Array(rows) if rows.iter().all(|r| r.len() == 1) => {
+            Some(rows.iter().map(|r| r[0].clone()).collect())
+        }
+        Value::Array(_) => None,
+    }
+}
+
+fn transpose(rows: Vec<Vec<Data>>) -> Vec<Vec<Data>> {
+    let width = rows.first().map_or(0, |r| r.len());
+    (0..width)
+        .map(|j| {
+            rows.iter()
+                .map(|r| r.get(j).cloned().unwrap_or_default())
+                .collect()
+        })
+        .collect()
+}
+
+fn apply<F: Fn(usize) -> bool>(name: &str, args: &[Value], given: F) -> Res<Value> {
+    match name {
+        // math and aggregates
+        "SUM" => number(numbers(args)?.iter().sum()),
+        "PRODUCT" => number(numbers(args)?.iter().product()),
+        "AVERAGE" => {
+            let numbers = numbers(args)?;
+            if numbers.is_empty() {
+                return Err(CellErrorType::Div0);
+            }
+            number(numbers.iter().sum::<f64>() / numbers.len() as f64)
+        }
+        "MIN" => number(numbers(args)?.into_iter().reduce(f64::min).unwrap_or(0.)),
+        "MAX" => number(numbers(args)?.into_iter().reduce(f64::max).unwrap_or(0.)),
+        "MEDIAN" => {
+            let mut numbers = numbers(args)?;
+            if numbers.is_empty() {
+                return Err(CellErrorType::Num);
+            }
+            numbers.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+            let mid = numbers.len() / 2;
+            if numbers.len() % 2 == 0 {
+                number((numbers[mid - 1] + numbers[mid]) / 2.)
+            } else {
+                number(numbers[mid])
+            }
+        }
+        "COUNT" => {
+            let count = args
+                .iter()
+                .map(|a| match a {
+                    Value::Scalar(v) => (!v.is_empty() && to_number(v).is_ok()) as usize,
+                    Value::Array(rows) => rows.iter().flatten().filter(|v| is_number(v)).count(),
+                })
+                .sum::<usize>();
+            number(count as f64)
+        }
+        "COUNTA" => number(flatten(args).filter(|v| !v.is_empty()).count() as f64),
+        "COUNTBLANK" => {
+            let blank = |v: &&Data| v.is_empty() || matches!(v, Data::String(s) if s.is_empty());
+            number(flatten(args).filter(blank).count() as f64)
+        }
+        "SUMPRODUCT" => {
+            let arrays: Vec<_> = args.iter().map(|a| a.clone().into_rows()).collect();
+            let Some(first) = arrays.first() else {
+                return Err(CellErrorType::Value);
+            };
+            let size = |rows: &Vec<Vec<Data>>| (rows.len(), rows.first().map_or(0, |r| r.len()));
+            if arrays.iter().any(|a| size(a) != size(first)) {
+                return Err(CellErrorType::Value);
+            }
+            let mut sum = 0.;
+            for (i, row) in first.iter().enumerate() {
+                for j in 0..row.len() {
+                    let mut product = 1.;
+                    for array in &arrays {
+                        match &array[i][j] {
+                            Data::Error(e) => return Err(e.clone()),
+                            v if is_number(v) => product *= to_number(v)?,
+                            _ => product = 0.,
+                        }
+                    }
+                    sum += product;
+                }
+            }
+            number(sum)
+        }
+        "SUMIF" | "AVERAGEIF" | "COUNTIF" => {
+            let range = args
+                .first()
+                .ok_or(CellErrorType::Value)?
+                .clone()
+                .into_rows();
+            let criteria = Criteria::new(&arg(args, 1)?);
+            let values = match args.get(2) {
+                Some(v) if name != "COUNTIF" && given(2) => v.clone().into_rows(),
+                _ => range.clone(),
+            };
+            let matched = range.iter().enumerate().flat_map(|(i, row)| {
+                let values = &values;
+                let criteria = &criteria;
+                row.iter()
+                    .enumerate()
+                    .filter(move |(_, v)| criteria.matches(v))
+                    .map(move |(j, _)| values.get(i).and_then(|r| r.get(j)))
+            });
+            aggregate_if(name, matched)
+        }
+        "SUMIFS" | "AVERAGEIFS" | "COUNTIFS" | "MINIFS" | "MAXIFS" => {
+            let (values, conditions) = if name == "COUNTIFS" {
+                (None, args)
+            } else {
+                let values = args
+                    .first()
+                    .ok_or(CellErrorType::Value)?
+                    .clone()
+                    .into_rows();
+                (Some(values), args.get(1..).unwrap_or_default())
+            };
+            if conditions.is_empty() || conditions.len() % 2 != 0 {
+                return Err(CellErrorType::Value);
+            }
+            let mut pairs = Vec::new();
+            for (i, pair) in conditions.chunks(2).enumerate() {
+                let range = pair[0].clone().into_rows();
+                let criteria = Criteria::new(&arg(conditions, 2 * i + 1)?);
+                pairs.push((range, criteria));
+            }
+            let (first, _) = &pairs[0];
+            let height = values.as_ref().unwrap_or(first).len();
+            let width = values
+                .as_ref()
+                .unwrap_or(first)
+                .first()
+                .map_or(0, |r| r.len());
+            let matched = (0..height)
+                .flat_map(|i| (0..width).map(move |j| (i, j)))
+                .filter_map(|(i, j)| {
+                    let all = pairs.iter().all(|(range, criteria)| {
+                        range
+                            .get(i)
+                            .and_then(|r| r.get(j))
+                            .map_or(false, |v| criteria.matches(v))
+                    });
+                    let value = match &values {
+                        Some(values) => values.get(i).and_then(|r| r.get(j)),
+                        None => Some(&Data::Empty),
+                    };
+                    all.then_some(value)
+                });
+            aggregate_if(name, matched)
+        }
+        "ABS" => number(num(args, 0)?.abs()),
+        "INT" => number(num(args, 0)?.floor()),
+        "SIGN" => {
+            let n = num(args, 0)?;
+            number(if n == 0. { 0. } else { n.signum() })
+        }
+        "SQRT" => {
+            let n = num(args, 0)?;
+            if n < 0. {
+                return Err(CellErrorType::Num);
+            }
+            number(n.sqrt())
+        }
+        "EXP" => number(num(args, 0)?.exp()),
+        "LN" | "LOG10" | "LOG" => {
+            let n = num(args, 0)?;
+            let base = match name {
+                "LN" => std::f64::consts::E,
+                "LOG" => opt_num(args, 1, 10.)?,
+                _ => 10.,
+            };
+            if n <= 0. || base <= 0. || base == 1. {
+                return Err(if base == 1. {
+                    CellErrorType::Div0
+                } else {
+                    CellErrorType::Num
+                });
+            }
+            number(n.log(base))
+        }
+        "PI" => number(std::f64::consts::PI),
+        "POWER" => {
+            let (base, exponent) = (num(args, 0)?, num(args, 1)?);
+            if base == 0. && exponent == 0. {
+                return Err(CellErrorType::Num);
+            }
+            number(base.powf(exponent))
+        }
+        "MOD" => {
+            let (n, d) = (num(args, 0)?, num(args, 1)?);
+            if d == 0. {
+                return Err(CellErrorType::Div0);
+            }
+            number(n - d * (n / d).floor())
+        }
+        "ROUND" | "ROUNDUP" | "ROUNDDOWN" | "TRUNC" => {
+            let n = num(args, 0)?;
+            let digits = opt_num(args, 1, 0.)?.trunc() as i32;
+            number(round(n, digits, name))
+        }
+
+        // logical
+        "AND" => boolean(booleans(args)?.iter().all(|b| *b)),
+        "OR" => boolean(booleans(args)?.iter().any(|b| *b)),
+        "XOR" => boolean(booleans(args)?.iter().filter(|b| **b).count() % 2 == 1),
+        "NOT" => boolean(!to_bool(&arg(args, 0)?)?),
+        "TRUE" => boolean(true),
+        "FALSE" => boolean(false),
+
+        // information
+        "ISBLANK" => boolean(arg(args, 0)?.is_empty()),
+        "ISNUMBER" => boolean(is_number(&arg(args, 0)?)),
+        "ISTEXT" => boolean(is_text(&arg(args, 0)?)),
+        "ISNONTEXT" => boolean(!is_text(&arg(args, 0)?)),
+        "ISLOGICAL" => boolean(matches!(arg(args, 0)?, Data::Bool(_))),
+        "ISERROR" => boolean(matches!(arg(args, 0)?, Data::Error(_))),
+        "ISERR" => boolean(matches!(arg(args, 0)?, Data::Error(e) if e != CellErrorType::NA)),
+        "ISNA" => boolean(matches!(arg(args, 0)?, Data::Error(CellErrorType::NA))),
+        "NA" => Err(CellErrorType::NA),
+
+        // text
+        "LEN" => number(text(args, 0)?.chars().count() as f64),
+        "LEFT" | "RIGHT" => {
+            let s = text(args, 0)?;
+            let n = opt_num(args, 1, 1.)?;
+            if n < 0. {
+                return Err(CellErrorType::Value);
+            }
+            let chars: Vec<char> = s.chars().collect();
+            let n = (n as usize).min(chars.len());
+            let chars = if name == "LEFT" {
+                &chars[..n]
+            } else {
+                &chars[chars.len() - n..]
+            };
+            string(chars.iter().collect())
+        }
+        "MID" => {
+            let s = text(args, 0)?;
+            let (start, n) = (num(args, 1)?, num(args, 2)?);
+            if start < 1. || n < 0. {
+                return Err(CellErrorType::Value);
+            }
+            string(
+                s.chars()
+                    .skip(start as usize - 1)
+                    .take(n as usize)
+                    .collect(),
+            )
+        }
+        "UPPER" => string(text(args, 0)?.to_uppercase()),
+        "LOWER" => string(text(args, 0)?.to_lowercase()),
+        "TRIM" => string(
+            text(args, 0)?
+                .split(' ')
+                .filter(|w| !w.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        "CONCATENATE" => {
+            let mut s = String::new();
+            for i in 0..args.len() {
+                s.push_str(&text(args, i)?);
+            }
+            string(s)
+        }
+        "CONCAT" => {
+            let mut s = String::new();
+            for v in flatten(args) {
+                s.push_str(&to_text(v)?);
+            }
+            string(s)
+        }
+        "TEXTJOIN" => {
+            let delimiter = text(args, 0)?;
+            let ignore_empty = to_bool(&arg(args, 1)?)?;
+            let mut texts = Vec::new();
+            for v in flatten(args.get(2..).unwrap_or_default()) {
+                let t = to_text(v)?;
+                if !(ignore_empty && t.is_empty()) {
+                    texts.push(t);
+                }
+            }
+            string(texts.join(&delimiter))
+        }
+        "REPT" => {
+            let n = num(args, 1)?;
+            if n < 0. {
+                return Err(CellErrorType::Value);
+            }
+            let text = text(args, 0)?;
+            // the longest text of a cell
+            if text.chars().count() as f64 * n.trunc() > 32767. {
+                return Err(CellErrorType::Value);
+            }
+            string(text.repeat(n as usize))
+        }
+        "EXACT" => boolean(text(args, 0)? == text(args, 1)?),
+        "VALUE" => number(to_number(&arg(args, 0)?)?),
+        "SUBSTITUTE" => {
+            let (s, old, new) = (text(args, 0)?, text(args, 1)?, text(args, 2)?);
+            if old.is_empty() {
+                return string(s);
+            }
+            if !given(3) {
+                return string(s.replace(&old, &new));
+            }
+            let instance = num(args, 3)?;
+            if instance < 1. {
+                return Err(CellErrorType::Value);
+            }
+            match s.match_indices(&old).nth(instance as usize - 1) {
+                Some((i, _)) => string(format!("{}{}{}", &s[..i], new, &s[i + old.len()..])),
+                None => string(s),
+            }
+        }
+        "FIND" | "SEARCH" => {
+            let (needle, haystack) = (text(args, 0)?, text(args, 1)?);
+            let start = opt_num(args, 2, 1.)?;
+            let chars: Vec<char> = haystack.chars().collect();
+            if start < 1. || start as usize > chars.len() + 1 {
+                return Err(CellErrorType::Value);
+            }
+            let start = start as usize - 1;
+            let position = if name == "FIND" {
+                let needle: Vec<char> = needle.chars().collect();
+                (start..=chars.len()).find(|&i| chars[i..].starts_with(&needle))
+            } else {
+                // a trailing `*` matches the prefixes of the text
+                let pattern = Wildcard::new(&format!("{needle}*"));
+                (start..=chars.len())
+                    .find(|&i| pattern.matches(&chars[i..].iter().collect::<String>()))
+            };
+            number(position.ok_or(CellErrorType::Value)? as f64 + 1.)
+        }
+
+        // lookup
+        "ROWS" | "COLUMNS" => {
+            let rows = args
+                .first()
+                .ok_or(CellErrorType::Value)?
+                .clone()
+                .into_rows();
+            let n = if name == "ROWS" {
+                rows.len()
+            } else {
+                rows.first().map_or(0, |r| r.len())
+            };
+            number(n as f64)
+        }
+        "INDEX" => {
+            let rows = args
+                .first()
+                .ok_or(CellErrorType::Value)?
+                .clone()
+                .into_rows();
+            let (mut row, mut col) = (num(args, 1)?, opt_num(args, 2, 0.)?);
+            // a single index selects in rows as in columns
+            if rows.len() == 1 && !given(2) {
+                (row, col) = (1., row);
+            }
+            if row < 0. || col < 0. {
+                return Err(CellErrorType::Value);
+            }
+            let (row, col) = (row as usize, col as usize);
+            let width = rows.first().map_or(0, |r| r.len());
+            if row > rows.len() || col > width {
+                return Err(CellErrorType::Ref);
+            }
+            match (row, col) {
+                (0, 0) => Ok(Value::Array(rows)),
+                (0, c) => Ok(Value::Array(
+                    rows.into_iter().map(|r| vec![r[c - 1].clone()]).collect(),
+                )),
+                (r, 0) => Ok(Value::Array(vec![rows[r - 1].clone()])),
+                (r, c) => Ok(rows[r - 1][c - 1].clone().into()),
+            }
+        }
+        "MATCH" => {
+            let lookup = arg(args, 0)?;
+            let items =
+                vector(args.get(1).ok_or(CellErrorType::Value)?).ok_or(CellErrorType::NA)?;
+            let mode = opt_num(args, 2, 1.)?;
+            let position = if mode == 0. {
+                items.iter().position(|v| lookup_eq(&lookup, v, true))
+            } else if mode > 0. {
+                approximate(&lookup, &items)
+            } else {
+                // sorted in descending order: the smallest value greater or equal
+                let mut found = None;
+                for (i, v) in items.iter().enumerate() {
+                    match same_kind(&lookup, v).then(|| compare(v, &lookup)) {
+                        Some(Ordering::Less) => break,
+                        Some(_) => found = Some(i),
+                        None => {}
+                    }
+                }
+                found
+            };
+            number(position.ok_or(CellErrorType::NA)? as f64 + 1.)
+        }
+        "VLOOKUP" | "HLOOKUP" => {
+            let lookup = arg(args, 0)?;
+            if let Data::Error(e) = lookup {
+                return Err(e);
+            }
+            let mut table = args.get(1).ok_or(CellErrorType::Value)?.clone().into_rows();
+            if name == "HLOOKUP" {
+                table = transpose(table);
+            }
+            let index = num(args, 2)?;
+            let approximate_match = if given(3) {
+                to_bool(&arg(args, 3)?)?
+            } else {
+                true
+            };
+            if index < 1. {
+                return Err(CellErrorType::Value);
+            }
+            let index = index as usize - 1;
+            if table.first().map_or(true, |r| index >= r.len()) {
+                return Err(CellErrorType::Ref);
+            }
+            let keys: Vec<Data> = table.iter().map(|r| r[0].clone()).collect();
+            let found = if approximate_match {
+                approximate(&lookup, &keys)
+            } else {
+                keys.iter().position(|v| lookup_eq(&lookup, v, true))
+            };
+            let found = found.ok_or(CellErrorType::NA)?;
+            Ok(table[found][index].clone().into())
+        }
+        "XLOOKUP" => {
+            let lookup = arg(args, 0)?;
+            if let Data::Error(e) = lookup {
+                return Err(e);
+            }
+            let lookup_array = args.get(1).ok_or(CellErrorType::Value)?;
+            let keys = vector(lookup_array).ok_or(CellErrorType::Value)?;
+            let results = args.get(2).ok_or(CellErrorType::Value)?.clone().into_rows();
+            let match_mode = if given(4) { num(args, 4)? } else { 0. };
+            let search_mode = if given(5) { num(args, 5)? } else { 1. };
+            let found = xlookup(&lookup, &keys, match_mode as i64, search_mode < 0.);
+            let Some(found) = found else {
+                return if given(3) {
+                    Ok(arg(args, 3)?.into())
+                } else {
+                    Err(CellErrorType::NA)
+                };
+            };
+            let by_rows = matches!(lookup_array, Value::Array(rows) if rows.len() > 1);
+            let result = if by_rows {
+                results.get(found).cloned().map(|r| vec![r])
+            } else {
+                let column = results.iter().map(|r| r.get(found).cloned());
+                column.map(|v| v.map(|v| vec![v])).collect()
+            };
+            match result {
+                Some(rows) if rows.len() == 1 && rows[0].len() == 1 => {
+                    Ok(rows[0][0].clone().into())
+                }
+                Some(rows) => Ok(Value::Array(rows)),
+                None => Err(CellErrorType::Value),
+            }
+        }
+
+        // dates
+        "DATE" => {
+            let (year, month, day) = (num(args, 0)?, num(args, 1)?, num(args, 2)?);
+            number(date_serial(
+                year.trunc() as i64,
+                month.trunc() as i64,
+                day.trunc() as i64,
+            )?)
+        }
+        "TIME" => {
+            let seconds =
+                num(args, 0)?.trunc() * 3600. + num(args, 1)?.trunc() * 60. + num(args, 2)?.trunc();
+            if seconds < 0. {
+                return Err(CellErrorType::Num);
+            }
+            number(seconds.rem_euclid(86400.) / 86400.)
+        }
+        "YEAR" | "MONTH" | "DAY" => {
+            let (y, m, d) = serial_date(num(args, 0)?)?;
+            number(match name {
+                "YEAR" => y as f64,
+                "MONTH" => m as f64,
+                _ => d as f64,
+            })
+        }
+        "HOUR" | "MINUTE" | "SECOND" => {
+            let serial = num(args, 0)?;
+            if serial < 0. {
+                return Err(CellErrorType::Num);
+            }
+            let seconds = (serial.fract() * 86400.).round() as u32 % 86400;
+            number(match name {
+                "HOUR" => seconds / 3600,
+                "MINUTE" => seconds / 60 % 60,
+                _ => seconds % 60,
+            } as f64)
+        }
+        "WEEKDAY" => {
+            let serial = num(args, 0)?.floor();
+            if serial < 0. {
+                return Err(CellErrorType::Num);
+            }
+            // serial 1, 1900-01-01, is a Sunday for Excel
+            let (offset, first) = match opt_num(args, 1, 1.)? as i64 {
+                1 | 17 => (1., 1.),
+                2 | 11 => (2., 1.),
+                3 => (2., 0.),
+                t @ 12..=16 => ((t - 9) as f64, 1.),
+                _ => return Err(CellErrorType::Num),
+            };
+            number((serial - offset).rem_euclid(7.) + first)
+        }
+        "EDATE" | "EOMONTH" => {
+            let (y, m, d) = serial_date(num(args, 0)?)?;
+            let months = num(args, 1)?.trunc() as i64;
+            let (y, m) = add_months(y, m as i64, months);
+            let last = days_in_month(y, m);
+            let d = if name == "EOMONTH" {
+                last
+            } else {
+                (d as i64).min(last)
+            };
+            number(date_serial(y, m, d)?)
+        }
+        "DAYS" => number(num(args, 0)?.floor() - num(args, 1)?.floor()),
+        "TODAY" => number(now().floor()),
+        "NOW" => number(now()),
+
+        _ => Err(CellErrorType::Name),
+    }
+}
+
+fn round(n: f64, digits: i32, name: &str) -> f64 {
+    let factor = 10f64.powi(digits);
+    // limit the precision to 15 digits first so that 2.675 is rounded up
+    let scaled: f64 = format!("{:.14e}", n * factor).parse().unwrap_or(n * factor);
+    let rounded = match name {
+        "ROUND" => scaled.round(),
+        "ROUNDUP" => scaled.abs().ceil() * scaled.signum(),
+        _ => scaled.trunc(),
+    };
+    rounded / factor
+}
+
+/// Sum, average, count, min or max of the matched values of the `*IF` and `*IFS` functions
+fn aggregate_if<'a, I: Iterator<Item = Option<&'a Data>>>(name: &str, matched: I) -> Res<Value> {
+    let mut count = 0;
+    let mut numbers = Vec::new();
+    for value in matched {
+        count += 1;
+        match value {
+            Some(Data::Error(e)) => return Err(e.clone()),
+            Some(v) if is_number(v) => numbers.push(to_number(v)?),
+            _ => {}
+        }
+    }
+    match name {
+        "COUNTIF" | "COUNTIFS" => number(count as f64),
+        "SUMIF" | "SUMIFS" => number(numbers.iter().sum()),
+        "MINIFS" => number(numbers.into_iter().reduce(f64::min).unwrap_or(0.)),
+        "MAXIFS" => number(numbers.into_iter().reduce(f64::max).unwrap_or(0.)),
+        _ if numbers.is_empty() => Err(CellErrorType::Div0),
+        _ => number(numbers.iter().sum::<f64>() / numbers.len() as f64),
+    }
+}
+
+/// A condition of `COUNTIF` and similar functions, e.g. `">=10"` or `"a*"`
+struct Criteria {
+    op: &'static str,
+    operand: Data,
+    pattern: Option<Wildcard>,
+}
+
+impl Criteria {
+    fn new(criteria: &Data) -> Criteria {
+        let Data::String(s) = criteria else {
+            return Criteria {
+                op: "=",
+                operand: criteria.clone(),
+                pattern: None,
+            };
+        };
+        let (op, rest) = ["<=", ">=", "<>", "<", ">", "="]
+            .iter()
+            .find_map(|op| s.strip_prefix(op).map(|rest| (*op, rest)))
+            .unwrap_or(("=", s));
+        let operand = if let Ok(n) = rest.trim().parse::<f64>() {
+            Data::Float(n)
+        } else if rest.eq_ignore_ascii_case("TRUE") || rest.eq_ignore_ascii_case("FALSE") {
+            Data::Bool(rest.eq_ignore_ascii_case("TRUE"))
+        } else {
+            Data::String(rest.to_string())
+        };
+        let pattern = match (&operand, op) {
+            (Data::String(s), "=" | "<>") if !s.is_empty() => Some(Wildcard::new(s)),
+            _ => None,
+        };
+        Criteria {
+            op,
+            operand,
+            pattern,
+        }
+    }
+
+    fn matches(&self, value: &Data) -> bool {
+        let equal = match (&self.operand, value) {
+            (Data::String(s), v) if s.is_empty() => {
+                v.is_empty() || matches!(v, Data::String(v) if v.is_empty())
+            }
+            (Data::String(_), Data::String(v)) => {
+                self.pattern.as_ref().map_or(false, |p| p.matches(v))
+            }
+            (Data::String(_), _) => false,
+            (o, Data::String(v)) if is_number(o) => {
+                v.trim().parse::<f64>().ok() == to_number(o).ok()
+            }
+            (o, v) => same_kind(o, v) && !v.is_empty() && compare(o, v) == Ordering::Equal,
+        };
+        match self.op {
+            "=" => equal,
+            "<>" => !equal,
+            op => {
+                if !same_kind(&self.operand, value) || value.is_empty() {
+                    return false;
+                }
+                let ordering = compare(value, &self.operand);
+                match op {
+                    "<" => ordering == Ordering::Less,
+                    "<=" => ordering != Ordering::Greater,
+                    ">" => ordering == Ordering::Greater,
+                    _ => ordering != Ordering::Less,
+                }
+            }
+        }
+    }
+}
+
+/// A pattern with `*` (any characters), `?` (any character) and `~` escapes, ignoring the case
+struct Wildcard(Vec<PatternItem>);
+
+enum PatternItem {
+    Char(char),
+    AnyChar,
+    AnyChars,
+}
+
+impl Wildcard {
+    fn new(pattern: &str) -> Wildcard {
+        let mut items = Vec::new();
+        let mut chars = pattern.chars().flat_map(char::to_lowercase);
+        while let Some(c) = chars.next() {
+            items.push(match c {
+                '~' => PatternItem::Char(chars.next().unwrap_or('~')),
+                '?' => PatternItem::AnyChar,
+                '*' => PatternItem::AnyChars,
+                c => PatternItem::Char(c),
+            });
+        }
+        Wildcard(items)
+    }
+
+    fn has_wildcards(pattern: &str) -> bool {
+        pattern.contains(['*', '?', '~'])
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+        let (mut p, mut t) = (0, 0);
+        // position of the last `*` and of the text it matches from
+        let mut star = None;
+        while t < text.len() {
+            match self.0.get(p) {
+                Some(PatternItem::AnyChars) => {
+                    star = Some((p, t));
+                    p += 1;
+                }
+                Some(PatternItem::AnyChar) => {
+                    p += 1;
+                    t += 1;
+                }
+                Some(PatternItem::Char(c)) if *c == text[t] => {
+                    p += 1;
+                    t += 1;
+                }
+                _ => match star {
+                    Some((sp, st)) => {
+                        star = Some((sp, st + 1));
+                        p = sp + 1;
+                        t = st + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+        self.0[p..]
+            .iter()
+            .all(|i| matches!(i, PatternItem::AnyChars))
+    }
+}
+
+/// Are both values numbers, strings or booleans
+fn same_kind(a: &Data, b: &Data) -> bool {
+    (is_number(a) && is_number(b))
+        || (is_text(a) && is_text(b))
+        || matches!((a, b), (Data::Bool(_), Data::Bool(_)))
+}
+
+/// Exact match of lookup functions, with wildcards in text lookup values
+fn lookup_eq(lookup: &Data, value: &Data, wildcards: bool) -> bool {
+    match (lookup, value) {
+        (Data::String(l), Data::String(v)) if wildcards && Wildcard::has_wildcards(l) => {
+            Wildcard::new(l).matches(v)
+        }
+        (l, v) => same_kind(l, v) && compare(l, v) == Ordering::Equal,
+    }
+}
+
+/// Position of the largest value lower or equal to `lookup`, in values sorted in ascending order
+fn approximate(lookup: &Data, values: &[Data]) -> Option<usize> {
+    let mut found = None;
+    for (i, v) in values.iter().enumerate() {
+        match same_kind(lookup, v).then(|| compare(v, lookup)) {
+            Some(Ordering::Greater) => break,
+            Some(_) => found = Some(i),
+            None => {}
+        }
+    }
+    found
+}
+
+/// Position of the `XLOOKUP` match: exact (0), exact or next smaller (-1), exact or next larger
+/// (1) or with wildcards (2)
+fn xlookup(lookup: &Data, values: &[Data], mode: i64, reverse: bool) -> Option<usize> {
+    let mut positions: Vec<usize> = (0..values.len()).collect();
+    if reverse {
+        positions.reverse();
+    }
+    if let Some(&i) = positions
+        .iter()
+        .find(|&&i| lookup_eq(lookup, &values[i], mode == 2))
+    {
+        return Some(i);
+    }
+    let wanted = match mode {
+        -1 => Ordering::Less,
+        1 => Ordering::Greater,
+        _ => return None,
+    };
+    let mut best: Option<usize> = None;
+    for i in positions {
+        let v = &values[i];
+        if !same_kind(lookup, v) || compare(v, lookup) != wanted {
+            continue;
+        }
+        // the closest value to `lookup`
+        if best.map_or(true, |b| compare(v, &values[b]) == wanted.reverse()) {
+            best = Some(i);
+        }
+    }
+    best
+}
+
+/// Days since 1970-01-01 of a date of the proleptic Gregorian calendar
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Date of a number of days since 1970-01-01
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+/// Serial of 1899-12-30, in days since 1970-01-01
+const EXCEL_EPOCH: i64 = -25569;
+
+/// Serial number of a date, years, months and days out of their range being carried over
+fn date_serial(year: i64, month: i64, day: i64) -> Res<f64> {
+    let year = if (0..1900).contains(&year) {
+        year + 1900
+    } else {
+        year
+    };
+    let (year, month) = add_months(year, 1, month - 1);
+    if !(1900..=9999).contains(&year) {
+        return Err(CellErrorType::Num);
+    }
+    let mut serial = days_from_civil(year, month, 1) + day - 1 - EXCEL_EPOCH;
+    // Excel counts a 1900-02-29 which did not exist
+    if serial <= 60 {
+        serial -= 1;
+    }
+    if serial < 0 {
+        return Err(CellErrorType::Num);
+    }
+    Ok(serial as f64)
+}
+
+/// Year, month and day of a serial number
+fn serial_date(serial: f64) -> Res<(i64, u32, u32)> {
+    let serial = serial.floor() as i64;
+    match serial {
+        s if !(0..=2958465).contains(&s) => Err(CellErrorType::Num),
+        0 => Ok((1900, 1, 0)),
+        60 => Ok((1900, 2, 29)),
+        s if s < 60 => Ok(civil_from_days(s + 1 + EXCEL_EPOCH)),
+        s => Ok(civil_from_days(s + EXCEL_EPOCH)),
+    }
+}
+
+fn add_months(year: i64, month: i64, months: i64) -> (i64, i64) {
+    let total = year * 12 + month - 1 + months;
+    (total.div_euclid(12), total.rem_euclid(12) + 1)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    let (next_year, next_month) = add_months(year, month, 1);
+    days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)
+}
+
+/// Current date and time as a serial number, in UTC
+fn now() -> f64 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0., |d| d.as_secs_f64());
+    seconds / 86400. - EXCEL_EPOCH as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serials() {
+        assert_eq!(date_serial(1900, 1, 1), Ok(1.));
+        assert_eq!(date_serial(1900, 3, 1), Ok(61.));
+        assert_eq!(date_serial(2024, 2, 29), Ok(45351.));
+        assert_eq!(date_serial(2023, 14, 1), Ok(45323.));
+        assert_eq!(serial_date(45351.75), Ok((2024, 2, 29)));
+        assert_eq!(serial_date(60.), Ok((1900, 2, 29)));
+        assert_eq!(serial_date(59.), Ok((1900, 2, 28)));
+        assert_eq!(days_in_month(2023, 2), 28);
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(Wildcard::new("a*").matches("Apple"));
+        assert!(Wildcard::new("?pp*e").matches("apple"));
+        assert!(Wildcard::new("*le").matches("apple"));
+        assert!(!Wildcard::new("a?").matches("apple"));
+        assert!(Wildcard::new("a~*").matches("a*"));
+        assert!(!Wildcard::new("a~*").matches("ab"));
+    }
+
+    #[test]
+    fn criteria() {
+        let s = |s: &str| Data::String(s.to_string());
+        assert!(Criteria::new(&s(">=10")).matches(&Data::Int(10)));
+        assert!(!Criteria::new(&s(">=10")).matches(&s("11")));
+        assert!(Criteria::new(&s("<>b*")).matches(&s("apple")));
+        assert!(Criteria::new(&s("")).matches(&Data::Empty));
+        assert!(Criteria::new(&Data::Float(3.)).matches(&Data::Int(3)));
+        assert!(Criteria::new(&s("3")).matches(&s("3")));
+    }
+}
@@ -0,0 +1,505 @@
+//! Dependencies between the formulas of a workbook
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::io::{Read, Seek};
+
+use super::eval::{load, EvalError, Names};
+use super::{BinaryOp, Expr};
+use crate::reference::{CellRef, RefTarget, Reference};
+use crate::Reader;
+
+/// Last row of a worksheet, ending whole columns
+const MAX_ROW: u32 = 1_048_575;
+/// Last column of a worksheet, ending whole rows
+const MAX_COL: u32 = 16_383;
+
+/// A cell of a workbook
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CellAddress {
+    /// Sheet name
+    pub sheet: String,
+    /// Row, 0-based
+    pub row: u32,
+    /// Column, 0-based
+    pub col: u32,
+}
+
+impl fmt::Display for CellAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reference = Reference {
+            sheet: Some(self.sheet.clone()),
+            target: RefTarget::Cell(cell_ref((self.row, self.col))),
+        };
+        write!(f, "{reference}")
+    }
+}
+
+/// A rectangular area of a sheet, whole columns and rows ending at the last row and column
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Area {
+    /// Sheet name
+    pub sheet: String,
+    /// First cell, (row, column), 0-based
+    pub first: (u32, u32),
+    /// Last cell, (row, column), 0-based
+    pub last: (u32, u32),
+}
+
+impl Area {
+    /// Is the cell in this area
+    pub fn contains(&self, cell: &CellAddress) -> bool {
+        self.sheet == cell.sheet
+            && (self.first.0..=self.last.0).contains(&cell.row)
+            && (self.first.1..=self.last.1).contains(&cell.col)
+    }
+}
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let target = if self.first == self.last {
+            RefTarget::Cell(cell_ref(self.first))
+        } else {
+            RefTarget::Area(cell_ref(self.first), cell_ref(self.last))
+        };
+        let reference = Reference {
+            sheet: Some(self.sheet.clone()),
+            target,
+        };
+        write!(f, "{reference}")
+    }
+}
+
+fn cell_ref((row, col): (u32, u32)) -> CellRef {
+    CellRef {
+        row: Some(row),
+        col: Some(col),
+        ..CellRef::default()
+    }
+}
+
+/// A cell, by sheet index
+pub(super) type Node = (usize, u32, u32);
+
+/// An area, by sheet index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Span {
+    pub sheet: usize,
+    pub first: (u32, u32),
+    pub last: (u32, u32),
+}
+
+impl Span {
+    fn contains(&self, (sheet, row, col): Node) -> bool {
+        self.sheet == sheet
+            && (self.first.0..=self.last.0).contains(&row)
+            && (self.first.1..=self.last.1).contains(&col)
+    }
+}
+
+/// Precedents and dependents of the formulas of a workbook
+///
+/// The graph is built from the formulas of all the sheets, following references to the same
+/// sheet, to other sheets and to several sheets (`Sheet1:Sheet3!A1`), and the references of
+/// defined names. Precedents are the cells a formula reads, dependents the formulas reading a
+/// cell. External references and structured references are ignored.
+///
+/// # Examples
+/// ```
+/// use calamine::{open_workbook, Xlsx};
+/// use calamine::formula::DependencyGraph;
+///
+/// # let path = format!("{}/tests/dependencies.xlsx", env!("CARGO_MANIFEST_DIR"));
+/// let mut workbook: Xlsx<_> = open_workbook(path).unwrap();
+/// let graph = DependencyGraph::new(&mut workbook).unwrap();
+/// for cell in graph.all_precedents("Sheet1", (0, 1)).unwrap() {
+///     println!("B1 depends on {cell}");
+/// }
+/// ```
+pub struct DependencyGraph {
+    sheets: Vec<String>,
+    /// Non empty cells and formulas, by sheet
+    used: Vec<BTreeSet<(u32, u32)>>,
+    /// Areas read by each formula
+    precedents: BTreeMap<Node, Vec<Span>>,
+    /// Formulas read by each formula
+    edges: BTreeMap<Node, Vec<Node>>,
+    /// Formulas reading each formula
+    reverse: HashMap<Node, Vec<Node>>,
+}
+
+impl DependencyGraph {
+    /// Builds the graph of all the formulas of a workbook
+    pub fn new<RS, R>(workbook: &mut R) -> Result<DependencyGraph, R::Error>
+    where
+        RS: Read + Seek,
+        R: Reader<RS>,
+    {
+        let (sheets, names) = load(workbook)?;
+        let sheet_names: Vec<String> = sheets.iter().map(|s| s.name.clone()).collect();
+
+        let mut used = Vec::with_capacity(sheets.len());
+        let mut formulas = Vec::with_capacity(sheets.len());
+        let mut precedents = BTreeMap::new();
+        for (index, sheet) in sheets.iter().enumerate() {
+            let start = sheet.values.start().unwrap_or((0, 0));
+            let mut cells: BTreeSet<_> = sheet
+                .values
+                .used_cells()
+                .map(|(row, col, _)| (start.0 + row as u32, start.1 + col as u32))
+                .collect();
+            cells.extend(sheet.formulas.keys().copied());
+            used.push(cells);
+            formulas.push(sheet.formulas.keys().copied().collect::<BTreeSet<_>>());
+            for (&(row, col), expr) in &sheet.formulas {
+                let mut spans = Vec::new();
+                collect(
+                    expr,
+                    index,
+                    &sheet_names,
+                    &names,
+                    &mut Vec::new(),
+                    &mut spans,
+                );
+                precedents.insert((index, row, col), spans);
+            }
+        }
+
+        let mut edges = BTreeMap::new();
+        let mut reverse: HashMap<Node, Vec<Node>> = HashMap::new();
+        for (&node, spans) in &precedents {
+            let mut targets = BTreeSet::new();
+            for span in spans {
+                targets.extend(cells_in(&formulas[span.sheet], span));
+            }
+            for &target in &targets {
+                reverse.entry(target).or_default().push(node);
+            }
+            edges.insert(node, targets.into_iter().collect::<Vec<_>>());
+        }
+
+        Ok(DependencyGraph {
+            sheets: sheet_names,
+            used,
+            precedents,
+            edges,
+            reverse,
+        })
+    }
+
+    /// Areas directly read by the formula of a cell, empty if the cell has no formula
+    pub fn precedents(&self, sheet: &str, position: (u32, u32)) -> Result<Vec<Area>, EvalError> {
+        let node = self.node(sheet, position)?;
+        let spans = self.precedents.get(&node).map_or(&[][..], |s| s);
+        Ok(spans.iter().map(|s| self.area(s)).collect())
+    }
+
+    /// Non empty cells read by the formula of a cell, directly or through other formulas
+    pub fn all_precedents(
+        &self,
+        sheet: &str,
+        position: (u32, u32),
+    ) -> Result<Vec<CellAddress>, EvalError> {
+        let node = self.node(sheet, position)?;
+        let mut visited = BTreeSet::new();
+        let mut queue = vec![node];
+        while let Some(node) = queue.pop() {
+            for span in self.precedents.get(&node).into_iter().flatten() {
+                for cell in cells_in(&self.used[span.sheet], span) {
+                    if visited.insert(cell) && self.precedents.contains_key(&cell) {
+                        queue.push(cell);
+                    }
+                }
+            }
+        }
+        Ok(visited.into_iter().map(|n| self.address(n)).collect())
+    }
+
+    /// Formulas directly reading a cell
+    pub fn dependents(
+        &self,
+        sheet: &str,
+        position: (u32, u32),
+    ) -> Result<Vec<CellAddress>, EvalError> {
+        let node = self.node(sheet, position)?;
+        Ok(self
+            .direct_dependents(node)
+            .map(|n| self.address(n))
+            .collect())
+    }
+
+    /// Formulas reading a cell, directly or through other formulas
+    pub fn all_dependents(
+        &self,
+        sheet: &str,
+        position: (u32, u32),
+    ) -> Result<Vec<CellAddress>, EvalError> {
+        let node = self.node(sheet, position)?;
+        let mut visited: BTreeSet<Node> = BTreeSet::new();
+        let mut queue: Vec<Node> = self.direct_dependents(node).collect();
+        while let Some(node) = queue.pop() {
+            if visited.insert(node) {
+                queue.extend(self.reverse.get(&node).into_iter().flatten());
+            }
+        }
+        Ok(visited.into_iter().map(|n| self.address(n)).collect())
+    }
+
+    /// All formula cells, each after the formulas it reads
+    ///
+    /// The cells of a circular reference are next to each other, in no particular order.
+    pub fn evaluation_order(&self) -> Vec<CellAddress> {
+        self.components()
+            .into_iter()
+            .flatten()
+            .map(|n| self.address(n))
+            .collect()
+    }
+
+    /// Groups of formulas depending on each other, e.g. `A1` reading `B1` which reads `A1`
+    pub fn circular_references(&self) -> Vec<Vec<CellAddress>> {
+        self.components()
+            .into_iter()
+            .filter(|c| c.len() > 1 || self.edges[&c[0]].contains(&c[0]))
+            .map(|mut c| {
+                c.sort_unstable();
+                c.into_iter().map(|n| self.address(n)).collect()
+            })
+            .collect()
+    }
+
+    fn node(&self, sheet: &str, (row, col): (u32, u32)) -> Result<Node, EvalError> {
+        let index = self
+            .sheets
+            .iter()
+            .position(|s| s.eq_ignore_ascii_case(sheet))
+            .ok_or_else(|| EvalError::SheetNotFound(sheet.to_string()))?;
+        Ok((index, row, col))
+    }
+
+    fn address(&self, (sheet, row, col): Node) -> CellAddress {
+        CellAddress {
+            sheet: self.sheets[sheet].clone(),
+            row,
+            col,
+        }
+    }
+
+    fn area(&self, span: &Span) -> Area {
+        Area {
+            sheet: self.sheets[span.sheet].clone(),
+            first: span.first,
+            last: span.last,
+        }
+    }
+
+    fn direct_dependents(&self, node: Node) -> impl Iterator<Item = Node> + '_ {
+        self.precedents
+            .iter()
+            .filter(move |(_, spans)| spans.iter().any(|s| s.contains(node)))
+            .map(|(n, _)| *n)
+    }
+
+    /// Strongly connected components of the formulas (Tarjan), the formulas read by a component
+    /// coming before it
+    fn components(&self) -> Vec<Vec<Node>> {
+        let mut index: HashMap<Node, usize> = HashMap::new();
+        let mut low: HashMap<Node, usize> = HashMap::new();
+        let mut stack = Vec::new();
+        let mut on_stack = HashSet::new();
+        let mut components = Vec::new();
+        for &root in self.edges.keys() {
+            if index.contains_key(&root) {
+                continue;
+            }
+            // iterative depth first search, with the next successor to visit
+            let mut calls = vec![(root, 0)];
+            while let Some((node, next)) = calls.pop() {
+                if next == 0 {
+                    index.insert(node, index.len());
+                    low.insert(node, low.len());
+                    stack.push(node);
+                    on_stack.insert(node);
+                }
+                if let Some(&successor) = self.edges[&node].get(next) {
+                    calls.push((node, next + 1));
+                    if !index.contains_key(&successor) {
+                        calls.push((successor, 0));
+                    } else if on_stack.contains(&successor) {
+                        let l = low[&node].min(index[&successor]);
+                        low.insert(node, l);
+                    }
+                    continue;
+                }
+                if low[&node] == index[&node] {
+                    let mut component = Vec::new();
+                    while let Some(n) = stack.pop() {
+                        on_stack.remove(&n);
+                        component.push(n);
+                        if n == node {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+                if let Some(&(parent, _)) = calls.last() {
+                    let l = low[&parent].min(low[&node]);
+                    low.insert(parent, l);
+                }
+            }
+        }
+        components
+    }
+}
+
+/// Cells of `cells` in the area
+pub(super) fn cells_in<'a>(
+    cells: &'a BTreeSet<(u32, u32)>,
+    span: &'a Span,
+) -> impl Iterator<Item = Node> + 'a {
+    cells
+        .range(span.first..=(span.last.0, MAX_COL))
+        .filter(move |(_, col)| (span.first.1..=span.last.1).contains(col))
+        .map(move |&(row, col)| (span.sheet, row, col))
+}
+
+/// Collects the areas read by an expression
+pub(super) fn collect(
+    expr: &Expr,
+    sheet: usize,
+    sheets: &[String],
+    names: &Names,
+    visiting: &mut Vec<String>,
+    spans: &mut Vec<Span>,
+) {
+    match expr {
+        Expr::Reference(r) => spans.extend(reference_spans(r, sheet, sheets)),
+        Expr::Name { name, .. } => {
+            let key = name.to_uppercase();
+            if let Some(expr) = names.get(&key) {
+                if !visiting.contains(&key) {
+                    visiting.push(key);
+                    collect(expr, sheet, sheets, names, visiting, spans);
+                    visiting.pop();
+                }
+            }
+        }
+        Expr::Binary(BinaryOp::Range, left, right) => {
+            if let (Expr::Reference(l), Expr::Reference(r)) = (left.as_ref(), right.as_ref()) {
+                // `A1:B2` where both sides are references, the smallest area containing them
+                let l = reference_spans(l, sheet, sheets);
+                let r = reference_spans(r, sheet, sheets);
+                if let ([l], [r]) = (&l[..], &r[..]) {
+                    if l.sheet == r.sheet {
+                        spans.push(Span {
+                            sheet: l.sheet,
+                            first: (l.first.0.min(r.first.0), l.first.1.min(r.first.1)),
+                            last: (l.last.0.max(r.last.0), l.last.1.max(r.last.1)),
+                        });
+                        return;
+                    }
+                }
+            }
+            collect(left, sheet, sheets, names, visiting, spans);
+            collect(right, sheet, sheets, names, visiting, spans);
+        }
+        Expr::Binary(_, left, right) => {
+            collect(left, sheet, sheets, names, visiting, spans);
+            collect(right, sheet, sheets, names, visiting, spans);
+        }
+        Expr::Unary(_, e) | Expr::Paren(e) => collect(e, sheet, sheets, names, visiting, spans),
+        Expr::Function { args, .. } => {
+            for arg in args {
+                collect(arg, sheet, sheets, names, visiting, spans);
+            }
+        }
+        Expr::Call { callee, args } => {
+            collect(callee, sheet, sheets, names, visiting, spans);
+            for arg in args {
+                collect(arg, sheet, sheets, names, visiting, spans);
+            }
+        }
+        Expr::Array(rows) => {
+            for e in rows.iter().flatten() {
+                collect(e, sheet, sheets, names, visiting, spans);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Areas of a reference, one per sheet of 3D references
+fn reference_spans(r: &Reference, current: usize, sheets: &[String]) -> Vec<Span> {
+    let position = |name: &str| sheets.iter().position(|s| s.eq_ignore_ascii_case(name));
+    let indexes = match r.sheet.as_deref() {
+        None => vec![current],
+        // external workbook
+        Some(s) if s.starts_with('[') => vec![],
+        Some(s) => match s.split_once(':') {
+            Some((first, last)) => match (position(first), position(last)) {
+                (Some(a), Some(b)) => (a.min(b)..=a.max(b)).collect(),
+                _ => vec![],
+            },
+            None => position(s).into_iter().collect(),
+        },
+    };
+    let (first, last) = match r.target {
+        RefTarget::Cell(c) => (c, c),
+        RefTarget::Area(first, last) => (first, last),
+        RefTarget::Invalid => return vec![],
+    };
+    let bounds = |a: Option<u32>, b: Option<u32>, max: u32| match (a, b) {
+        (Some(a), Some(b)) => (a.min(b), a.max(b)),
+        _ => (0, max),
+    };
+    let rows = bounds(first.row, last.row, MAX_ROW);
+    let cols = bounds(first.col, last.col, MAX_COL);
+    indexes
+        .into_iter()
+        .map(|sheet| Span {
+            sheet,
+            first: (rows.0, cols.0),
+            last: (rows.1, cols.1),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formula::parse;
+
+    fn spans(formula: &str) -> Vec<Span> {
+        let sheets = ["A".to_string(), "B".to_string(), "C".to_string()];
+        let mut spans = Vec::new();
+        let expr = parse(formula).unwrap();
+        collect(
+            &expr,
+            1,
+            &sheets,
+            &Names::new(),
+            &mut Vec::new(),
+            &mut spans,
+        );
+        spans
+    }
+
+    #[test]
+    fn reference_areas() {
+        let span = |sheet, first, last| Span { sheet, first, last };
+        assert_eq!(
+            spans("$B$2+A!C3"),
+            [span(1, (1, 1), (1, 1)), span(0, (2, 2), (2, 2))]
+        );
+        assert_eq!(
+            spans("SUM(C:A!B1:A2)"),
+            [0, 1, 2].map(|s| span(s, (0, 0), (1, 1)))
+        );
+        assert_eq!(spans("2:3"), [span(1, (1, 0), (2, MAX_COL))]);
+        assert_eq!(
+            spans("A1:INDEX(B:B,2)"),
+            [span(1, (0, 0), (0, 0)), span(1, (0, 1), (MAX_ROW, 1))]
+        );
+        assert!(spans("[1]A!A1+Missing!A1+#REF!").is_empty());
+    }
+}
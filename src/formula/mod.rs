@@ -0,0 +1,1100 @@
+//! Formula tokenizer and syntax tree
+//!
+//! Parses formulas written in the A1 notation of Excel, as returned by
+//! [`Reader::worksheet_formula`](crate::Reader::worksheet_formula) for xlsx, xlsb and xls files.
+//! Xls and xlsb formulas are stored as tokens in reverse polish notation and are decoded into the
+//! same [`Expr`] tree by their `worksheet_formula_ast` methods.
+//!
+//! ```
+//! use calamine::formula::{parse, BinaryOp, Expr};
+//!
+//! let expr = parse("SUM(A1:B2)*2").unwrap();
+//! match &expr {
+//!     Expr::Binary(BinaryOp::Mul, left, right) => {
+//!         assert!(matches!(&**left, Expr::Function { name, args } if name == "SUM" && args.len() == 1));
+//!         assert_eq!(**right, Expr::Number(2.));
+//!     }
+//!     _ => panic!("unexpected expression {:?}", expr),
+//! }
+//! // expressions are displayed as formulas
+//! assert_eq!(expr.to_string(), "SUM(A1:B2)*2");
+//! ```
+
+mod display;
+mod eval;
+mod functions;
+mod graph;
+
+use std::fmt;
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use crate::reference::{parse_cell, parse_column, parse_r1c1, parse_row, write_sheet};
+use crate::CellErrorType;
+
+pub use crate::reference::{CellRef, RefTarget, Reference};
+pub use display::{display_formula, localize_formula, Locale};
+pub use eval::{EvalError, Evaluator};
+pub use graph::{Area, CellAddress, DependencyGraph};
+
+/// An error while parsing a formula
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormulaError {
+    /// Unexpected character, with its position (in chars)
+    UnexpectedChar(char, usize),
+    /// Unterminated string, quoted sheet name or brackets
+    Unterminated(&'static str),
+    /// Unexpected token
+    UnexpectedToken(Token),
+    /// The formula ended unexpectedly
+    UnexpectedEnd,
+    /// The text is not a single reference
+    InvalidReference(String),
+}
+
+impl fmt::Display for FormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormulaError::UnexpectedChar(c, pos) => {
+                write!(f, "Unexpected character '{c}' at position {pos}")
+            }
+            FormulaError::Unterminated(what) => write!(f, "Unterminated {what}"),
+            FormulaError::UnexpectedToken(t) => write!(f, "Unexpected token {t:?}"),
+            FormulaError::UnexpectedEnd => write!(f, "Unexpected end of formula"),
+            FormulaError::InvalidReference(s) => write!(f, "Invalid reference '{s}'"),
+        }
+    }
+}
+
+impl std::error::Error for FormulaError {}
+
+/// A token of a formula
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// Number
+    Number(f64),
+    /// String literal, unquoted
+    Text(String),
+    /// `TRUE` or `FALSE`
+    Bool(bool),
+    /// Error literal, e.g. `#N/A`
+    Error(CellErrorType),
+    /// Reference to a cell or an area, e.g. `A1`, `Sheet1!$A$1:$B$2` or `A:A`
+    Reference(Reference),
+    /// Defined name, e.g. `Rate` or `Sheet1!Rate`
+    Name {
+        /// Sheet (or workbook) prefix, unquoted
+        sheet: Option<String>,
+        /// Name
+        name: String,
+    },
+    /// Structured reference to a table, e.g. `Table1[[#This Row],[Amount]]`
+    StructuredRef(String),
+    /// Function name, with its opening parenthesis
+    Function(String),
+    /// Operator, e.g. `+`, `<>`, `%` or `:`
+    ///
+    /// A space between two references, the intersection operator, is returned as `" "`.
+    Operator(&'static str),
+    /// `(`
+    OpenParen,
+    /// `)`
+    CloseParen,
+    /// `{`, start of an array
+    OpenBrace,
+    /// `}`, end of an array
+    CloseBrace,
+    /// `,`, separating arguments or array columns, or the union operator
+    Comma,
+    /// `;`, separating array rows
+    Semicolon,
+    /// Spaces and line breaks
+    Whitespace(String),
+}
+
+impl Token {
+    fn starts_operand(&self) -> bool {
+        matches!(
+            self,
+            Token::Reference(_)
+                | Token::Name { .. }
+                | Token::StructuredRef(_)
+                | Token::Function(_)
+                | Token::OpenParen
+        )
+    }
+
+    fn ends_operand(&self) -> bool {
+        matches!(
+            self,
+            Token::Reference(_) | Token::Name { .. } | Token::StructuredRef(_) | Token::CloseParen
+        )
+    }
+}
+
+/// A unary operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// `+x`
+    Plus,
+    /// `-x`
+    Minus,
+    /// `x%`
+    Percent,
+    /// `@x`, implicit intersection
+    ImplicitIntersection,
+    /// `x#`, spilled range of a dynamic array
+    Spill,
+}
+
+/// A binary operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    /// `+`
+    Add,
+    /// `-`
+    Sub,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+    /// `^`
+    Pow,
+    /// `&`
+    Concat,
+    /// `=`
+    Eq,
+    /// `<>`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+    /// `:`, smallest area containing both references
+    Range,
+    /// `,`, union of references
+    Union,
+    /// ` `, intersection of references
+    Intersect,
+}
+
+impl BinaryOp {
+    fn from_operator(op: &str) -> Option<BinaryOp> {
+        let op = match op {
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Sub,
+            "*" => BinaryOp::Mul,
+            "/" => BinaryOp::Div,
+            "^" => BinaryOp::Pow,
+            "&" => BinaryOp::Concat,
+            "=" => BinaryOp::Eq,
+            "<>" => BinaryOp::Ne,
+            "<" => BinaryOp::Lt,
+            "<=" => BinaryOp::Le,
+            ">" => BinaryOp::Gt,
+            ">=" => BinaryOp::Ge,
+            ":" => BinaryOp::Range,
+            " " => BinaryOp::Intersect,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// Binding powers, on the left and on the right, of the (left associative) operator
+    fn binding_power(self) -> (u8, u8) {
+        match self {
+            BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge => (1, 2),
+            BinaryOp::Concat => (3, 4),
+            BinaryOp::Add | BinaryOp::Sub => (5, 6),
+            BinaryOp::Mul | BinaryOp::Div => (7, 8),
+            BinaryOp::Pow => (9, 10),
+            BinaryOp::Union => (13, 14),
+            BinaryOp::Intersect => (15, 16),
+            BinaryOp::Range => (17, 18),
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Pow => "^",
+            BinaryOp::Concat => "&",
+            BinaryOp::Eq => "=",
+            BinaryOp::Ne => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Range => ":",
+            BinaryOp::Union => ",",
+            BinaryOp::Intersect => " ",
+        })
+    }
+}
+
+/// Binding power of the prefix operators, which apply before `^` in Excel (`-2^2` is 4)
+const PREFIX_BP: u8 = 12;
+/// Binding power of `%`
+const PERCENT_BP: u8 = 11;
+/// Binding power of `#`
+const SPILL_BP: u8 = 19;
+
+/// An expression of a formula
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Expr {
+    /// Number
+    Number(f64),
+    /// String literal
+    Text(String),
+    /// Boolean
+    Bool(bool),
+    /// Error literal
+    Error(CellErrorType),
+    /// Reference to a cell or an area
+    Reference(Reference),
+    /// Defined name
+    Name {
+        /// Sheet (or workbook) prefix, unquoted
+        sheet: Option<String>,
+        /// Name
+        name: String,
+    },
+    /// Structured reference to a table, as written in the formula
+    StructuredRef(String),
+    /// Array constant, by rows
+    Array(Vec<Vec<Expr>>),
+    /// Function call
+    Function {
+        /// Function name, e.g. "SUM" or "_xlfn.XLOOKUP"
+        name: String,
+        /// Arguments, `Expr::Missing` for omitted arguments
+        args: Vec<Expr>,
+    },
+    /// Call of the function returned by an expression, e.g. `_xlfn.LAMBDA(_xlpm.x,_xlpm.x+1)(2)`
+    Call {
+        /// Called expression
+        callee: Box<Expr>,
+        /// Arguments, `Expr::Missing` for omitted arguments
+        args: Vec<Expr>,
+    },
+    /// Unary operation
+    Unary(UnaryOp, Box<Expr>),
+    /// Binary operation
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// Parenthesized expression
+    Paren(Box<Expr>),
+    /// Omitted argument, e.g. the second argument of `IF(A1,,1)`
+    #[default]
+    Missing,
+}
+
+impl Expr {
+    /// Function call decoded from xls or xlsb tokens
+    ///
+    /// Add-in and newer functions are called with the `User` function, whose first argument is
+    /// the name of the called function, e.g. `_xlfn.XLOOKUP`.
+    pub(crate) fn call(name: &str, mut args: Vec<Expr>) -> Expr {
+        if name == "User" && matches!(args.first(), Some(Expr::Name { .. })) {
+            let name = args.remove(0).to_string();
+            return Expr::Function { name, args };
+        }
+        Expr::Function {
+            name: name.to_string(),
+            args,
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{n}"),
+            Expr::Text(s) => write!(f, "\"{}\"", s.replace('"', "\"\"")),
+            Expr::Bool(true) => f.write_str("TRUE"),
+            Expr::Bool(false) => f.write_str("FALSE"),
+            Expr::Error(CellErrorType::GettingData) => f.write_str("#GETTING_DATA"),
+            Expr::Error(e) => write!(f, "{e}"),
+            Expr::Reference(r) => write!(f, "{r}"),
+            Expr::Name { sheet, name } => {
+                if let Some(sheet) = sheet {
+                    write_sheet(f, sheet)?;
+                }
+                f.write_str(name)
+            }
+            Expr::StructuredRef(s) => f.write_str(s),
+            Expr::Array(rows) => {
+                f.write_str("{")?;
+                for (i, row) in rows.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(";")?;
+                    }
+                    write_list(f, row)?;
+                }
+                f.write_str("}")
+            }
+            Expr::Function { name, args } => {
+                write!(f, "{name}(")?;
+                write_list(f, args)?;
+                f.write_str(")")
+            }
+            Expr::Call { callee, args } => {
+                write!(f, "{callee}(")?;
+                write_list(f, args)?;
+                f.write_str(")")
+            }
+            Expr::Unary(UnaryOp::Plus, e) => write!(f, "+{e}"),
+            Expr::Unary(UnaryOp::Minus, e) => write!(f, "-{e}"),
+            Expr::Unary(UnaryOp::ImplicitIntersection, e) => write!(f, "@{e}"),
+            Expr::Unary(UnaryOp::Percent, e) => write!(f, "{e}%"),
+            Expr::Unary(UnaryOp::Spill, e) => write!(f, "{e}#"),
+            Expr::Binary(op, left, right) => write!(f, "{left}{op}{right}"),
+            Expr::Paren(e) => write!(f, "({e})"),
+            Expr::Missing => Ok(()),
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, exprs: &[Expr]) -> fmt::Result {
+    for (i, e) in exprs.iter().enumerate() {
+        if i > 0 {
+            f.write_str(",")?;
+        }
+        write!(f, "{e}")?;
+    }
+    Ok(())
+}
+
+/// Can the character be part of a name, a function name or a reference
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '\\' | '$' | '?')
+}
+
+const ERRORS: [(&str, CellErrorType); 8] = [
+    ("#NULL!", CellErrorType::Null),
+    ("#DIV/0!", CellErrorType::Div0),
+    ("#VALUE!", CellErrorType::Value),
+    ("#REF!", CellErrorType::Ref),
+    ("#NAME?", CellErrorType::Name),
+    ("#NUM!", CellErrorType::Num),
+    ("#N/A", CellErrorType::NA),
+    ("#GETTING_DATA", CellErrorType::GettingData),
+];
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    /// Cell of R1C1 formulas, whose references are relative to it
+    r1c1: Option<(u32, u32)>,
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn unexpected(&self) -> FormulaError {
+        match self.peek() {
+            Some(c) => FormulaError::UnexpectedChar(c, self.pos),
+            None => FormulaError::UnexpectedEnd,
+        }
+    }
+
+    fn text(&self, start: usize, end: usize) -> String {
+        self.chars[start..end].iter().collect()
+    }
+
+    fn word_end(&self, start: usize) -> usize {
+        start
+            + self.chars[start.min(self.chars.len())..]
+                .iter()
+                .take_while(|c| is_word_char(**c))
+                .count()
+    }
+
+    fn starts_with_ignore_case(&self, s: &str) -> bool {
+        let rest = &self.chars[self.pos..];
+        rest.len() >= s.len() && s.chars().zip(rest).all(|(a, b)| a.eq_ignore_ascii_case(b))
+    }
+
+    /// Read a quoted text, the opening quote being the current char, with doubled quotes
+    fn read_quoted(&mut self, what: &'static str) -> Result<String, FormulaError> {
+        let quote = self.chars[self.pos];
+        let mut s = String::new();
+        self.pos += 1;
+        loop {
+            match self.peek() {
+                None => return Err(FormulaError::Unterminated(what)),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    if self.peek() == Some(quote) {
+                        s.push(quote);
+                        self.pos += 1;
+                    } else {
+                        return Ok(s);
+                    }
+                }
+                Some(c) => {
+                    s.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    /// Read nested brackets, the current char being `[`
+    ///
+    /// In structured references, `'` escapes the next character.
+    fn read_brackets(&mut self) -> Result<String, FormulaError> {
+        let start = self.pos;
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '\'' => self.pos += 1,
+                '[' => depth += 1,
+                ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(self.text(start, self.pos));
+                    }
+                }
+                _ => (),
+            }
+        }
+        Err(FormulaError::Unterminated("brackets"))
+    }
+
+    fn read_error(&mut self) -> Result<CellErrorType, FormulaError> {
+        for (text, error) in ERRORS {
+            if self.starts_with_ignore_case(text) {
+                self.pos += text.len();
+                return Ok(error);
+            }
+        }
+        Err(self.unexpected())
+    }
+
+    fn read_number(&mut self) -> Result<Token, FormulaError> {
+        let start = self.pos;
+        let digits = |lexer: &mut Lexer| {
+            while lexer.peek().map_or(false, |c| c.is_ascii_digit()) {
+                lexer.pos += 1;
+            }
+        };
+        digits(self);
+        if self.peek() == Some('.') {
+            self.pos += 1;
+            digits(self);
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.pos += 1;
+            }
+            if self.peek().map_or(false, |c| c.is_ascii_digit()) {
+                digits(self);
+            } else {
+                self.pos = mantissa_end;
+            }
+        }
+        let text = self.text(start, self.pos);
+        text.parse().map(Token::Number).map_err(|_| {
+            self.pos = start;
+            self.unexpected()
+        })
+    }
+
+    /// Read an area, `A1:B2`, `A:B` or `1:2`, whose first part has already been read
+    fn read_area(&mut self, first: &str) -> Option<RefTarget> {
+        if self.peek() != Some(':') {
+            return None;
+        }
+        let end = self.word_end(self.pos + 1);
+        let second = self.text(self.pos + 1, end);
+        let (first, last) = match (parse_cell(first), parse_cell(&second)) {
+            (Some(first), Some(last)) => (first, last),
+            _ => match (parse_column(first), parse_column(&second)) {
+                (Some(first), Some(last)) => (first, last),
+                _ => (parse_row(first)?, parse_row(&second)?),
+            },
+        };
+        self.pos = end;
+        Some(RefTarget::Area(first, last))
+    }
+
+    /// End of a R1C1 reference part starting at `start`, e.g. `R1C1`, `R[-1]C` or `C2`
+    fn r1c1_end(&self, start: usize) -> Option<usize> {
+        let mut pos = start;
+        for letter in ['R', 'C'] {
+            if !self
+                .chars
+                .get(pos)
+                .map_or(false, |c| c.eq_ignore_ascii_case(&letter))
+            {
+                continue;
+            }
+            pos += 1;
+            if self.chars.get(pos) == Some(&'[') {
+                pos += self.chars[pos..].iter().position(|c| *c == ']')? + 1;
+            } else {
+                while self.chars.get(pos).map_or(false, char::is_ascii_digit) {
+                    pos += 1;
+                }
+            }
+        }
+        let followed = self
+            .chars
+            .get(pos)
+            .map_or(false, |c| is_word_char(*c) || matches!(c, '(' | '[' | '!'));
+        (pos > start && !followed).then_some(pos)
+    }
+
+    /// Read a R1C1 reference, in R1C1 formulas only
+    fn read_r1c1(&mut self, sheet: &Option<String>) -> Result<Option<Token>, FormulaError> {
+        let Some(base) = self.r1c1 else {
+            return Ok(None);
+        };
+        let Some(end) = self.r1c1_end(self.pos) else {
+            return Ok(None);
+        };
+        let Some(first) = parse_r1c1(&self.text(self.pos, end), base)? else {
+            return Ok(None);
+        };
+        let mut target = match first {
+            CellRef {
+                row: Some(_),
+                col: Some(_),
+                ..
+            } => RefTarget::Cell(first),
+            // whole row or column
+            _ => RefTarget::Area(first, first),
+        };
+        self.pos = end;
+        if self.peek() == Some(':') {
+            let last = match self.r1c1_end(end + 1) {
+                Some(e) => parse_r1c1(&self.text(end + 1, e), base)?.map(|last| (e, last)),
+                None => None,
+            };
+            if let Some((last_end, last)) = last {
+                let same_kind = (first.row.is_some(), first.col.is_some())
+                    == (last.row.is_some(), last.col.is_some());
+                if same_kind {
+                    target = RefTarget::Area(first, last);
+                    self.pos = last_end;
+                }
+            }
+        }
+        Ok(Some(Token::Reference(Reference {
+            sheet: sheet.clone(),
+            target,
+        })))
+    }
+
+    /// Read a reference or a name, whose text has already been read
+    fn reference_or_name(&mut self, sheet: Option<String>, word: String) -> Token {
+        if self.r1c1.is_some() {
+            return Token::Name { sheet, name: word };
+        }
+        let target = match self.read_area(&word) {
+            Some(area) => area,
+            None => match parse_cell(&word) {
+                Some(cell) => RefTarget::Cell(cell),
+                None => return Token::Name { sheet, name: word },
+            },
+        };
+        Token::Reference(Reference { sheet, target })
+    }
+
+    /// Read what follows a sheet prefix and its `!`
+    fn read_qualified(&mut self, sheet: String) -> Result<Token, FormulaError> {
+        if self.starts_with_ignore_case("#REF!") {
+            self.pos += 5;
+            return Ok(Token::Reference(Reference {
+                sheet: Some(sheet),
+                target: RefTarget::Invalid,
+            }));
+        }
+        let sheet = Some(sheet);
+        if let Some(token) = self.read_r1c1(&sheet)? {
+            return Ok(token);
+        }
+        let start = self.pos;
+        let end = self.word_end(start);
+        if end == start {
+            return Err(self.unexpected());
+        }
+        let word = self.text(start, end);
+        self.pos = end;
+        Ok(self.reference_or_name(sheet, word))
+    }
+
+    /// Read a word: function, boolean, reference, name or structured reference
+    fn read_word(&mut self) -> Result<Token, FormulaError> {
+        if let Some(token) = self.read_r1c1(&None)? {
+            return Ok(token);
+        }
+        let start = self.pos;
+        let end = self.word_end(start);
+        let word = self.text(start, end);
+        match self.chars.get(end) {
+            Some('!') => {
+                self.pos = end + 1;
+                return self.read_qualified(word);
+            }
+            Some(':') => {
+                // sheets of a 3D reference, e.g. `Sheet1:Sheet3!A1`
+                let last_end = self.word_end(end + 1);
+                if last_end > end + 1 && self.chars.get(last_end) == Some(&'!') {
+                    let sheet = format!("{}:{}", word, self.text(end + 1, last_end));
+                    self.pos = last_end + 1;
+                    return self.read_qualified(sheet);
+                }
+            }
+            Some('(') => {
+                self.pos = end + 1;
+                return Ok(Token::Function(word));
+            }
+            Some('[') => {
+                self.pos = end;
+                let brackets = self.read_brackets()?;
+                return Ok(Token::StructuredRef(word + &brackets));
+            }
+            _ => (),
+        }
+        self.pos = end;
+        if word.eq_ignore_ascii_case("TRUE") {
+            Ok(Token::Bool(true))
+        } else if word.eq_ignore_ascii_case("FALSE") {
+            Ok(Token::Bool(false))
+        } else {
+            Ok(self.reference_or_name(None, word))
+        }
+    }
+
+    fn next_token(&mut self, previous: Option<&Token>) -> Result<Token, FormulaError> {
+        let c = self.chars[self.pos];
+        let token = match c {
+            ' ' | '\n' | '\r' | '\t' => {
+                let start = self.pos;
+                while matches!(self.peek(), Some(' ' | '\n' | '\r' | '\t')) {
+                    self.pos += 1;
+                }
+                return Ok(Token::Whitespace(self.text(start, self.pos)));
+            }
+            '"' => return self.read_quoted("string").map(Token::Text),
+            '\'' => {
+                let sheet = self.read_quoted("sheet name")?;
+                if self.peek() != Some('!') {
+                    return Err(self.unexpected());
+                }
+                self.pos += 1;
+                return self.read_qualified(sheet);
+            }
+            '[' => {
+                // workbook prefix, e.g. `[1]Sheet1!A1` or `[1]!Name`, or structured reference
+                let brackets = self.read_brackets()?;
+                let end = self.word_end(self.pos);
+                if self.chars.get(end) == Some(&'!') {
+                    let sheet = brackets + &self.text(self.pos, end);
+                    self.pos = end + 1;
+                    return self.read_qualified(sheet);
+                }
+                return Ok(Token::StructuredRef(brackets));
+            }
+            '#' if previous.map_or(false, Token::ends_operand) => Token::Operator("#"),
+            '#' => return self.read_error().map(Token::Error),
+            '0'..='9' | '.' if self.r1c1.is_some() => return self.read_number(),
+            '0'..='9' | '.' => {
+                // whole rows, e.g. `1:3`
+                let end = self.word_end(self.pos);
+                let word = self.text(self.pos, end);
+                let start = self.pos;
+                self.pos = end;
+                if let Some(target) = self.read_area(&word) {
+                    return Ok(Token::Reference(Reference {
+                        sheet: None,
+                        target,
+                    }));
+                }
+                self.pos = start;
+                return self.read_number();
+            }
+            c if c.is_alphabetic() || matches!(c, '_' | '\\' | '$') => return self.read_word(),
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            '{' => Token::OpenBrace,
+            '}' => Token::CloseBrace,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
+            '+' => Token::Operator("+"),
+            '-' => Token::Operator("-"),
+            '*' => Token::Operator("*"),
+            '/' => Token::Operator("/"),
+            '^' => Token::Operator("^"),
+            '&' => Token::Operator("&"),
+            '%' => Token::Operator("%"),
+            '=' => Token::Operator("="),
+            ':' => Token::Operator(":"),
+            '@' => Token::Operator("@"),
+            '<' => match self.chars.get(self.pos + 1) {
+                Some('=') => {
+                    self.pos += 1;
+                    Token::Operator("<=")
+                }
+                Some('>') => {
+                    self.pos += 1;
+                    Token::Operator("<>")
+                }
+                _ => Token::Operator("<"),
+            },
+            '>' => match self.chars.get(self.pos + 1) {
+                Some('=') => {
+                    self.pos += 1;
+                    Token::Operator(">=")
+                }
+                _ => Token::Operator(">"),
+            },
+            _ => return Err(self.unexpected()),
+        };
+        self.pos += 1;
+        Ok(token)
+    }
+}
+
+/// Split a formula into tokens
+///
+/// A leading `=` is ignored. Whitespace is kept, except between two references where it is the
+/// intersection operator.
+///
+/// # Examples
+/// ```
+/// use calamine::formula::{tokenize, Token};
+///
+/// let tokens = tokenize("=SUM(A1, 2)").unwrap();
+/// assert_eq!(tokens[0], Token::Function("SUM".to_string()));
+/// assert!(matches!(tokens[1], Token::Reference(_)));
+/// assert_eq!(tokens[2], Token::Comma);
+/// assert_eq!(tokens[3], Token::Whitespace(" ".to_string()));
+/// assert_eq!(tokens[4], Token::Number(2.));
+/// assert_eq!(tokens[5], Token::CloseParen);
+/// ```
+pub fn tokenize(formula: &str) -> Result<Vec<Token>, FormulaError> {
+    let formula = formula.strip_prefix('=').unwrap_or(formula);
+    Ok(lex(formula, None)?.into_iter().map(|t| t.0).collect())
+}
+
+/// Split a formula, without its `=`, into tokens with their first and last (excluded) char
+/// positions
+///
+/// References are in the R1C1 notation, relative to the `r1c1` cell, when it is set.
+pub(crate) fn lex(
+    formula: &str,
+    r1c1: Option<(u32, u32)>,
+) -> Result<Vec<(Token, usize, usize)>, FormulaError> {
+    let mut lexer = Lexer {
+        chars: formula.chars().collect(),
+        pos: 0,
+        r1c1,
+    };
+    let mut tokens: Vec<(Token, usize, usize)> = Vec::new();
+    while lexer.pos < lexer.chars.len() {
+        let start = lexer.pos;
+        let token = lexer.next_token(tokens.last().map(|t| &t.0))?;
+        if token.starts_operand() {
+            if let [.., (before, ..), (Token::Whitespace(_), ..)] = &*tokens {
+                if before.ends_operand() {
+                    let (_, start, end) = tokens.pop().unwrap();
+                    tokens.push((Token::Operator(" "), start, end));
+                }
+            }
+        }
+        tokens.push((token, start, lexer.pos));
+    }
+    Ok(tokens)
+}
+
+/// Parse a formula into an expression
+///
+/// # Examples
+/// ```
+/// use calamine::formula::{parse, Expr, RefTarget};
+///
+/// let expr = parse("'My Sheet'!$A$1").unwrap();
+/// let Expr::Reference(reference) = &expr else { panic!() };
+/// assert_eq!(reference.sheet.as_deref(), Some("My Sheet"));
+/// assert!(matches!(reference.target, RefTarget::Cell(cell) if cell.absolute_row));
+/// ```
+pub fn parse(formula: &str) -> Result<Expr, FormulaError> {
+    let tokens: Vec<Token> = tokenize(formula)?
+        .into_iter()
+        .filter(|t| !matches!(t, Token::Whitespace(_)))
+        .collect();
+    let mut parser = Parser {
+        tokens: tokens.into_iter().peekable(),
+    };
+    let expr = parser.parse_expr(0, false)?;
+    match parser.tokens.next() {
+        Some(t) => Err(FormulaError::UnexpectedToken(t)),
+        None => Ok(expr),
+    }
+}
+
+struct Parser {
+    tokens: Peekable<IntoIter<Token>>,
+}
+
+impl Parser {
+    fn next(&mut self) -> Result<Token, FormulaError> {
+        self.tokens.next().ok_or(FormulaError::UnexpectedEnd)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), FormulaError> {
+        match self.next()? {
+            t if t == expected => Ok(()),
+            t => Err(FormulaError::UnexpectedToken(t)),
+        }
+    }
+
+    /// Parse an expression whose operators bind at least `min_bp`
+    ///
+    /// `union` is set inside parentheses, where `,` is the union operator.
+    fn parse_expr(&mut self, min_bp: u8, union: bool) -> Result<Expr, FormulaError> {
+        let mut lhs = match self.next()? {
+            Token::Number(n) => Expr::Number(n),
+            Token::Text(s) => Expr::Text(s),
+            Token::Bool(b) => Expr::Bool(b),
+            Token::Error(e) => Expr::Error(e),
+            Token::Reference(r) => Expr::Reference(r),
+            Token::Name { sheet, name } => Expr::Name { sheet, name },
+            Token::StructuredRef(s) => Expr::StructuredRef(s),
+            Token::Function(name) => Expr::Function {
+                name,
+                args: self.parse_args()?,
+            },
+            Token::OpenParen => {
+                let e = self.parse_expr(0, true)?;
+                self.expect(Token::CloseParen)?;
+                Expr::Paren(Box::new(e))
+            }
+            Token::OpenBrace => self.parse_array()?,
+            Token::Operator(op @ ("+" | "-" | "@")) => {
+                let op = match op {
+                    "+" => UnaryOp::Plus,
+                    "-" => UnaryOp::Minus,
+                    _ => UnaryOp::ImplicitIntersection,
+                };
+                Expr::Unary(op, Box::new(self.parse_expr(PREFIX_BP, union)?))
+            }
+            t => return Err(FormulaError::UnexpectedToken(t)),
+        };
+        loop {
+            let op = match self.tokens.peek() {
+                // the result of a function called right away
+                Some(Token::OpenParen)
+                    if matches!(
+                        lhs,
+                        Expr::Function { .. } | Expr::Call { .. } | Expr::Paren(_)
+                    ) =>
+                {
+                    self.tokens.next();
+                    lhs = Expr::Call {
+                        callee: Box::new(lhs),
+                        args: self.parse_args()?,
+                    };
+                    continue;
+                }
+                Some(Token::Operator("%")) if PERCENT_BP >= min_bp => {
+                    self.tokens.next();
+                    lhs = Expr::Unary(UnaryOp::Percent, Box::new(lhs));
+                    continue;
+                }
+                Some(Token::Operator("#")) if SPILL_BP >= min_bp => {
+                    self.tokens.next();
+                    lhs = Expr::Unary(UnaryOp::Spill, Box::new(lhs));
+                    continue;
+                }
+                Some(Token::Operator(op)) => match BinaryOp::from_operator(op) {
+                    Some(op) => op,
+                    None => break,
+                },
+                Some(Token::Comma) if union => BinaryOp::Union,
+                _ => break,
+            };
+            let (left_bp, right_bp) = op.binding_power();
+            if left_bp < min_bp {
+                break;
+            }
+            self.tokens.next();
+            let rhs = self.parse_expr(right_bp, union)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    /// Parse the arguments of a function, after its opening parenthesis
+    fn parse_args(&mut self) -> Result<Vec<Expr>, FormulaError> {
+        let mut args = Vec::new();
+        if self.tokens.peek() == Some(&Token::CloseParen) {
+            self.tokens.next();
+            return Ok(args);
+        }
+        loop {
+            let arg = match self.tokens.peek() {
+                Some(Token::Comma | Token::CloseParen) => Expr::Missing,
+                _ => self.parse_expr(0, false)?,
+            };
+            args.push(arg);
+            match self.next()? {
+                Token::Comma => (),
+                Token::CloseParen => return Ok(args),
+                t => return Err(FormulaError::UnexpectedToken(t)),
+            }
+        }
+    }
+
+    /// Parse an array constant, after its opening brace
+    fn parse_array(&mut self) -> Result<Expr, FormulaError> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        loop {
+            row.push(self.parse_expr(0, false)?);
+            match self.next()? {
+                Token::Comma => (),
+                Token::Semicolon => rows.push(std::mem::take(&mut row)),
+                Token::CloseBrace => {
+                    rows.push(row);
+                    return Ok(Expr::Array(rows));
+                }
+                t => return Err(FormulaError::UnexpectedToken(t)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(row: u32, col: u32) -> CellRef {
+        CellRef {
+            row: Some(row),
+            col: Some(col),
+            absolute_row: false,
+            absolute_col: false,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for formula in [
+            "SUM(A1:B2)*2",
+            "-2^2",
+            "(A1+B1)*C1%",
+            "IF(A1>=10,\"big \"\"one\"\"\",FALSE)",
+            "'My Sheet'!$A$1+Sheet2!A:B+Sheet1:Sheet3!1:$2",
+            "[1]Rates!A1*2+'[Q1 Budget.xlsx]'!Total",
+            "SUM((A1,B1:C2))+A1:B2 B1:C3",
+            "{1,2;\"a\",TRUE}",
+            "IF(A1,,#N/A)+Sheet1!#REF!",
+            "Table1[[#This Row],[Amount]]*[@Rate]",
+            "_xlfn.XLOOKUP(A1,B:B,C:C)+A1#+@D1:D3",
+            "NOW()-1.5",
+            "_xlfn.LAMBDA(_xlpm.x,_xlpm.x*2)(A1)+(B1)(,1)",
+        ] {
+            let expr = parse(formula).unwrap();
+            assert_eq!(expr.to_string(), formula, "{expr:?}");
+        }
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse("1.5E+3"), Ok(Expr::Number(1500.)));
+        assert_eq!(parse(".5e-1"), Ok(Expr::Number(0.05)));
+        assert_eq!(parse("=12"), Ok(Expr::Number(12.)));
+    }
+
+    #[test]
+    fn precedence() {
+        let expr = parse("1+2*3&\"x\"=-A1^2").unwrap();
+        let Expr::Binary(BinaryOp::Eq, left, right) = expr else {
+            panic!()
+        };
+        let Expr::Binary(BinaryOp::Concat, sum, _) = *left else {
+            panic!()
+        };
+        assert!(matches!(*sum, Expr::Binary(BinaryOp::Add, _, ref product)
+            if matches!(**product, Expr::Binary(BinaryOp::Mul, _, _))));
+        // negation applies before the power
+        let Expr::Binary(BinaryOp::Pow, base, _) = *right else {
+            panic!()
+        };
+        assert_eq!(
+            *base,
+            Expr::Unary(
+                UnaryOp::Minus,
+                Box::new(Expr::Reference(Reference {
+                    sheet: None,
+                    target: RefTarget::Cell(cell(0, 0)),
+                }))
+            )
+        );
+    }
+
+    #[test]
+    fn references() {
+        let Ok(Expr::Reference(r)) = parse("'It''s'!$B$2:C3") else {
+            panic!()
+        };
+        assert_eq!(r.sheet.as_deref(), Some("It's"));
+        let first = CellRef {
+            absolute_row: true,
+            absolute_col: true,
+            ..cell(1, 1)
+        };
+        assert_eq!(r.target, RefTarget::Area(first, cell(2, 2)));
+        assert_eq!(r.to_string(), "'It''s'!$B$2:C3");
+
+        let sheet = |s: &str| {
+            Reference {
+                sheet: Some(s.to_string()),
+                target: RefTarget::Cell(cell(0, 0)),
+            }
+            .to_string()
+        };
+        assert_eq!(sheet("A1"), "'A1'!A1");
+        assert_eq!(sheet("R1C1"), "'R1C1'!A1");
+        assert_eq!(sheet("2019"), "'2019'!A1");
+        assert_eq!(sheet("Data.2019"), "Data.2019!A1");
+        assert_eq!(sheet("[Book 1.xlsx]Data"), "'[Book 1.xlsx]Data'!A1");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse("SUM(A1"),
+            Err(FormulaError::UnexpectedEnd),
+            "missing parenthesis"
+        );
+        assert_eq!(parse("\"abc"), Err(FormulaError::Unterminated("string")));
+        assert_eq!(parse("A1 ~ B1"), Err(FormulaError::UnexpectedChar('~', 3)));
+        assert_eq!(
+            parse("1 2"),
+            Err(FormulaError::UnexpectedToken(Token::Number(2.)))
+        );
+    }
+}
@@ -61,16 +61,31 @@
 mod utils;
 
 mod auto;
+mod autofilter;
 mod cfb;
+mod chart;
+mod conditional_format;
 mod datatype;
+mod external_link;
 mod formats;
 mod ods;
+mod page_setup;
+#[cfg(feature = "picture")]
+mod picture;
+mod pivot;
+mod protection;
+mod relationships;
+mod style;
+mod theme;
+mod validation;
 mod xls;
 mod xlsb;
 mod xlsx;
 
 mod de;
 mod errors;
+pub mod formula;
+pub mod reference;
 pub mod vba;
 
 use serde::de::DeserializeOwned;
@@ -83,10 +98,36 @@ use std::ops::{Index, IndexMut};
 use std::path::Path;
 
 pub use crate::auto::{open_workbook_auto, open_workbook_auto_from_rs, Sheets};
+pub use crate::autofilter::{
+    AutoFilter, CustomFilter, Filter, FilterColumn, FilterOperator, SortBy, SortCondition,
+    SortState,
+};
+pub use crate::chart::{
+    Chart, ChartAxis, ChartAxisKind, ChartAxisPosition, ChartSeries, ChartSeriesData, ChartType,
+};
+pub use crate::conditional_format::{
+    ColorScale, ConditionalFormatRule, ConditionalFormatType, ConditionalFormatting,
+    ConditionalValue, ConditionalValueType, DataBar, IconSet,
+};
 pub use crate::datatype::{Data, DataRef, DataType, ExcelDateTime, ExcelDateTimeType};
 pub use crate::de::{DeError, RangeDeserializer, RangeDeserializerBuilder, ToCellDeserializer};
 pub use crate::errors::Error;
+pub use crate::external_link::{ExternalLink, ExternalSheet};
 pub use crate::ods::{Ods, OdsError};
+pub use crate::page_setup::{
+    HeaderFooter, HeaderFooterText, Orientation, PageMargins, PageOrder, PageSetup,
+};
+#[cfg(feature = "picture")]
+pub use crate::picture::{AnchorMarker, Picture, PictureAnchor};
+pub use crate::pivot::{
+    PivotAxisField, PivotDataField, PivotFunction, PivotPageField, PivotSource, PivotTable,
+};
+pub use crate::protection::{PasswordHash, SheetProtection, WorkbookProtection};
+pub use crate::style::{Color, DifferentialFormat};
+pub use crate::theme::{ColorResolver, Theme};
+pub use crate::validation::{
+    DataValidation, ValidationErrorStyle, ValidationOperator, ValidationType,
+};
 pub use crate::xls::{Xls, XlsError, XlsOptions};
 pub use crate::xlsb::{Xlsb, XlsbError};
 pub use crate::xlsx::{Xlsx, XlsxError};
@@ -131,16 +172,24 @@ impl fmt::Display for CellErrorType {
     }
 }
 
+/// A rectangular area of cells
 #[derive(Debug, PartialEq, Default, Clone, Copy)]
-pub(crate) struct Dimensions {
+pub struct Dimensions {
+    /// Top left cell (row, column), 0-based
     pub start: (u32, u32),
+    /// Bottom right cell (row, column), 0-based and inclusive
     pub end: (u32, u32),
 }
 
 impl Dimensions {
-    pub fn len(&self) -> u64 {
+    pub(crate) fn len(&self) -> u64 {
         (self.end.0 - self.start.0 + 1) as u64 * (self.end.1 - self.start.1 + 1) as u64
     }
+
+    /// Is the cell at (row, column) inside
+    pub fn contains(&self, row: u32, col: u32) -> bool {
+        (self.start.0..=self.end.0).contains(&row) && (self.start.1..=self.end.1).contains(&col)
+    }
 }
 
 /// Common file metadata
@@ -152,6 +201,23 @@ pub struct Metadata {
     sheets: Vec<Sheet>,
     /// Map of sheet names/sheet path within zip archive
     names: Vec<(String, String)>,
+    /// Index of the active sheet
+    active_sheet: usize,
+    /// Index of the first sheet displayed in the tab bar
+    first_visible_sheet: usize,
+    /// Theme and indexed colors
+    colors: ColorResolver,
+    /// Workbooks referenced by formulas
+    external_links: Vec<ExternalLink>,
+}
+
+impl Metadata {
+    /// Is the sheet a chartsheet, which has no cells
+    pub(crate) fn is_chart_sheet(&self, name: &str) -> bool {
+        self.sheets
+            .iter()
+            .any(|s| s.name == name && s.typ == SheetType::ChartSheet)
+    }
 }
 
 /// Type of sheet
@@ -200,6 +266,16 @@ pub struct Sheet {
     pub typ: SheetType,
     /// Visible
     pub visible: SheetVisible,
+    /// Color of the sheet tab
+    pub tab_color: Option<Color>,
+    /// Identifier of the sheet, which does not change when sheets are moved
+    ///
+    /// Only xlsx and xlsb files have sheet identifiers.
+    pub sheet_id: Option<u32>,
+    /// Path of the sheet part in the zip archive (e.g. `xl/worksheets/sheet1.xml`)
+    ///
+    /// Only xlsx and xlsb sheets are stored in their own part.
+    pub path: Option<String>,
 }
 
 // FIXME `Reader` must only be seek `Seek` for `Xls::xls`. Because of the present API this limits
@@ -230,6 +306,84 @@ where
     /// Read worksheet formula in corresponding worksheet path
     fn worksheet_formula(&mut self, _: &str) -> Result<Range<String>, Self::Error>;
 
+    /// Read the rows and columns layout (sizes, hidden state, outline levels) of a worksheet
+    ///
+    /// Defaults to an empty layout, all rows and columns being visible.
+    fn worksheet_layout(&mut self, _name: &str) -> Result<SheetLayout, Self::Error> {
+        Ok(SheetLayout::default())
+    }
+
+    /// Read the view state (panes, selection, zoom, gridlines) of a worksheet
+    ///
+    /// Defaults to the view of a new sheet.
+    fn worksheet_view(&mut self, _name: &str) -> Result<SheetView, Self::Error> {
+        Ok(SheetView::default())
+    }
+
+    /// Read the protection of a worksheet, `None` if it is not protected
+    ///
+    /// Defaults to `None`.
+    fn worksheet_protection(
+        &mut self,
+        _name: &str,
+    ) -> Result<Option<SheetProtection>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Read the protection of the workbook structure and windows, `None` if it is not protected
+    ///
+    /// Defaults to `None`.
+    fn workbook_protection(&mut self) -> Result<Option<WorkbookProtection>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Read the data validation rules (dropdown lists, constraints) of a worksheet
+    ///
+    /// Defaults to no rules.
+    fn worksheet_data_validations(
+        &mut self,
+        _name: &str,
+    ) -> Result<Vec<DataValidation>, Self::Error> {
+        Ok(Vec::new())
+    }
+
+    /// Read worksheet data, without the rows and columns hidden by the user
+    /// (hidden, filtered or collapsed in an outline)
+    fn worksheet_range_visible(&mut self, name: &str) -> Result<Range<Data>, Self::Error> {
+        let range = self.worksheet_range(name)?;
+        let layout = self.worksheet_layout(name)?;
+        Ok(range.without_hidden(&layout))
+    }
+
+    /// Read worksheet formula as Excel displays them, without the `_xlfn.`, `_xlws.` and `_xlpm.`
+    /// prefixes of newer functions
+    ///
+    /// `worksheet_formula` keeps the formulas as stored in the file, see
+    /// [`formula::display_formula`].
+    fn worksheet_formula_display(&mut self, name: &str) -> Result<Range<String>, Self::Error> {
+        let mut range = self.worksheet_formula(name)?;
+        for f in range.inner.iter_mut().filter(|f| f.contains("_xl")) {
+            *f = formula::display_formula(f);
+        }
+        Ok(range)
+    }
+
+    /// Read worksheet formula as Excel displays them in another language, with translated
+    /// function names and separators, see [`formula::localize_formula`]
+    ///
+    /// Only the common functions are translated, see [`formula::Locale`].
+    fn worksheet_formula_localized(
+        &mut self,
+        name: &str,
+        locale: formula::Locale,
+    ) -> Result<Range<String>, Self::Error> {
+        let mut range = self.worksheet_formula(name)?;
+        for f in range.inner.iter_mut().filter(|f| !f.is_empty()) {
+            *f = formula::localize_formula(f, locale);
+        }
+        Ok(range)
+    }
+
     /// Get all sheet names of this workbook, in workbook order
     ///
     /// # Examples
@@ -258,6 +412,34 @@ where
         &self.metadata().names
     }
 
+    /// Index of the active sheet, selected when the workbook is opened
+    ///
+    /// Always 0 for ods files.
+    fn active_sheet(&self) -> usize {
+        self.metadata().active_sheet
+    }
+
+    /// Index of the first sheet displayed in the tab bar, when it is scrolled
+    ///
+    /// Always 0 for ods files.
+    fn first_visible_sheet(&self) -> usize {
+        self.metadata().first_visible_sheet
+    }
+
+    /// Theme and indexed colors of the workbook, to convert `Color`s into ARGB values
+    ///
+    /// Xls files always use the default theme, and the custom indexed colors of xlsb files are not read.
+    fn color_resolver(&self) -> &ColorResolver {
+        &self.metadata().colors
+    }
+
+    /// Workbooks referenced by formulas, in the order of their indexes (`[1]` being the first one)
+    ///
+    /// The external links of xlsb and ods files are not read.
+    fn external_links(&self) -> &[ExternalLink] {
+        &self.metadata().external_links
+    }
+
     /// Get the nth worksheet. Shortcut for getting the nth
     /// sheet_name, then the corresponding worksheet.
     fn worksheet_range_at(&mut self, n: usize) -> Option<Result<Range<Data>, Self::Error>> {
@@ -268,6 +450,14 @@ where
     /// Get all pictures, tuple as (ext: String, data: Vec<u8>)
     #[cfg(feature = "picture")]
     fn pictures(&self) -> Option<Vec<(String, Vec<u8>)>>;
+
+    /// Get all pictures placed on sheets, with their sheet, anchor, size, name and alt text
+    ///
+    /// Defaults to `None`.
+    #[cfg(feature = "picture")]
+    fn sheet_pictures(&self) -> Option<Vec<Picture>> {
+        None
+    }
 }
 
 /// Convenient function to open a file with a BufReader<File>
@@ -295,6 +485,7 @@ pub trait CellType: Default + Clone + PartialEq {}
 impl CellType for Data {}
 impl<'a> CellType for DataRef<'a> {}
 impl CellType for String {}
+impl CellType for formula::Expr {}
 impl CellType for usize {} // for tests
 
 /// A struct to hold cell position and value
@@ -732,6 +923,58 @@ impl<T: CellType> Range<T> {
 
         other
     }
+
+    /// Build a new `Range` without the rows and columns hidden in `layout`
+    ///
+    /// The remaining cells are moved up and left, the new range starts at the same position.
+    ///
+    /// ```
+    /// use calamine::{LayoutSpan, Range, SheetLayout};
+    ///
+    /// let mut range = Range::new((0, 0), (2, 1));
+    /// range.set_value((0, 0), 1);
+    /// range.set_value((2, 0), 3);
+    /// let layout = SheetLayout {
+    ///     rows: vec![LayoutSpan {
+    ///         first: 1,
+    ///         last: 1,
+    ///         hidden: true,
+    ///         ..Default::default()
+    ///     }],
+    ///     ..Default::default()
+    /// };
+    /// let visible = range.without_hidden(&layout);
+    /// assert_eq!(visible.get_size(), (2, 2));
+    /// assert_eq!(visible.get_value((1, 0)), Some(&3));
+    /// ```
+    pub fn without_hidden(&self, layout: &SheetLayout) -> Range<T> {
+        if self.is_empty() {
+            return Range::empty();
+        }
+        let rows: Vec<usize> = (0..self.height())
+            .filter(|r| !layout.is_row_hidden(self.start.0 + *r as u32))
+            .collect();
+        let cols: Vec<usize> = (0..self.width())
+            .filter(|c| !layout.is_column_hidden(self.start.1 + *c as u32))
+            .collect();
+        if rows.is_empty() || cols.is_empty() {
+            return Range::empty();
+        }
+        let width = self.width();
+        let mut inner = Vec::with_capacity(rows.len() * cols.len());
+        for r in &rows {
+            let row = &self.inner[r * width..(r + 1) * width];
+            inner.extend(cols.iter().map(|c| row[*c].clone()));
+        }
+        Range {
+            start: self.start,
+            end: (
+                self.start.0 + rows.len() as u32 - 1,
+                self.start.1 + cols.len() as u32 - 1,
+            ),
+            inner,
+        }
+    }
 }
 
 impl<T: CellType> Index<usize> for Range<T> {
@@ -865,15 +1108,189 @@ impl<'a, T: 'a + CellType> DoubleEndedIterator for Rows<'a, T> {
 
 impl<'a, T: 'a + CellType> ExactSizeIterator for Rows<'a, T> {}
 
+/// Rows and columns layout of a sheet
+///
+/// Only the rows and columns which differ from the defaults are listed, as spans
+/// sorted by position.
+/// Row heights are in points and column widths in number of characters.
+/// Sizes are not read from ods files.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SheetLayout {
+    /// Default row height
+    pub default_row_height: Option<f64>,
+    /// Default column width
+    pub default_column_width: Option<f64>,
+    /// Rows layout
+    pub rows: Vec<LayoutSpan>,
+    /// Columns layout
+    pub columns: Vec<LayoutSpan>,
+}
+
+impl SheetLayout {
+    /// Is the row hidden
+    pub fn is_row_hidden(&self, row: u32) -> bool {
+        find_span(&self.rows, row).map_or(false, |s| s.hidden)
+    }
+
+    /// Is the column hidden
+    pub fn is_column_hidden(&self, col: u32) -> bool {
+        find_span(&self.columns, col).map_or(false, |s| s.hidden)
+    }
+
+    /// Get the layout of a row, if it differs from the default
+    pub fn row(&self, row: u32) -> Option<&LayoutSpan> {
+        find_span(&self.rows, row)
+    }
+
+    /// Get the layout of a column, if it differs from the default
+    pub fn column(&self, col: u32) -> Option<&LayoutSpan> {
+        find_span(&self.columns, col)
+    }
+}
+
+fn find_span(spans: &[LayoutSpan], pos: u32) -> Option<&LayoutSpan> {
+    let i = spans.partition_point(|s| s.last < pos);
+    spans.get(i).filter(|s| s.first <= pos)
+}
+
+/// Layout of consecutive rows, or columns, sharing the same properties
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayoutSpan {
+    /// First row or column (0-based)
+    pub first: u32,
+    /// Last row or column (0-based, inclusive)
+    pub last: u32,
+    /// Height (rows) or width (columns), if not the default
+    pub size: Option<f64>,
+    /// Is it hidden
+    pub hidden: bool,
+    /// Outline (grouping) level, 0 if not grouped
+    pub outline_level: u8,
+    /// Is it the summary of a collapsed outline group
+    pub collapsed: bool,
+}
+
+/// View state of a sheet: how it is presented when the workbook is opened
+///
+/// Only the first view of the sheet is read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetView {
+    /// Are the gridlines shown
+    pub show_grid_lines: bool,
+    /// Are the columns displayed from right to left (not read from ods files)
+    pub right_to_left: bool,
+    /// Zoom, in percent
+    pub zoom: u32,
+    /// First visible cell (row, column), in the top-left pane
+    pub top_left_cell: (u32, u32),
+    /// Frozen or split panes, if any
+    pub pane: Option<Pane>,
+    /// Cell with the cursor (row, column)
+    pub active_cell: (u32, u32),
+    /// Selected cells, in the active pane
+    pub selection: Vec<Dimensions>,
+}
+
+impl Default for SheetView {
+    fn default() -> Self {
+        SheetView {
+            show_grid_lines: true,
+            right_to_left: false,
+            zoom: 100,
+            top_left_cell: (0, 0),
+            pane: None,
+            active_cell: (0, 0),
+            selection: Vec::new(),
+        }
+    }
+}
+
+/// Frozen or split panes of a `SheetView`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pane {
+    /// Are the panes frozen or only split
+    pub state: PaneState,
+    /// Frozen panes: number of columns left of the split.
+    /// Split panes: position of the vertical split, in twips (pixels in ods files)
+    pub x_split: f64,
+    /// Frozen panes: number of rows above the split.
+    /// Split panes: position of the horizontal split, in twips (pixels in ods files)
+    pub y_split: f64,
+    /// First visible cell (row, column) of the bottom-right pane
+    pub top_left_cell: (u32, u32),
+    /// Pane with the cursor
+    pub active_pane: PanePosition,
+}
+
+/// State of a `Pane`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaneState {
+    /// Rows and columns before the split do not scroll
+    Frozen,
+    /// Each pane scrolls independently
+    Split,
+}
+
+/// Position of a pane
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PanePosition {
+    /// Top-left pane, the only one if there is no split
+    #[default]
+    TopLeft,
+    /// Top-right pane
+    TopRight,
+    /// Bottom-left pane
+    BottomLeft,
+    /// Bottom-right pane
+    BottomRight,
+}
+
+impl PanePosition {
+    /// Convert the `pnn` of xls and xlsb records
+    pub(crate) fn from_code(code: u32) -> PanePosition {
+        match code {
+            0 => PanePosition::BottomRight,
+            1 => PanePosition::TopRight,
+            2 => PanePosition::BottomLeft,
+            _ => PanePosition::TopLeft,
+        }
+    }
+
+    /// Convert the xlsx `pane` and `activePane` attributes
+    pub(crate) fn from_name(name: &[u8]) -> PanePosition {
+        match name {
+            b"bottomRight" => PanePosition::BottomRight,
+            b"topRight" => PanePosition::TopRight,
+            b"bottomLeft" => PanePosition::BottomLeft,
+            _ => PanePosition::TopLeft,
+        }
+    }
+}
+
 /// Struct with the key elements of a table
+///
+/// The header, totals, column, style and autofilter metadata are only read from xlsx files
+#[derive(Debug, Clone, Default)]
 pub struct Table<T> {
+    pub(crate) id: u32,
     pub(crate) name: String,
     pub(crate) sheet_name: String,
     pub(crate) columns: Vec<String>,
     pub(crate) data: Range<T>,
+    pub(crate) header_row_count: u32,
+    pub(crate) totals: Option<Vec<T>>,
+    pub(crate) table_columns: Vec<TableColumn>,
+    pub(crate) style: Option<TableStyle>,
+    pub(crate) auto_filter: Option<AutoFilter>,
 }
 impl<T> Table<T> {
+    /// Get the id of the table, unique within the workbook
+    pub fn id(&self) -> u32 {
+        self.id
+    }
     /// Get the name of the table
+    ///
+    /// This is the table `displayName`, i.e. the name used in formulas
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -889,4 +1306,108 @@ impl<T> Table<T> {
     pub fn data(&self) -> &Range<T> {
         &self.data
     }
+    /// Get the number of header rows (0 if the table has no header row)
+    pub fn header_row_count(&self) -> u32 {
+        self.header_row_count
+    }
+    /// Does the table have a totals row
+    pub fn has_totals_row(&self) -> bool {
+        self.totals.is_some()
+    }
+    /// Get the values of the totals row, if any
+    pub fn totals(&self) -> Option<&[T]> {
+        self.totals.as_deref()
+    }
+    /// Get the column definitions (totals row functions, calculated formulas ...)
+    pub fn table_columns(&self) -> &[TableColumn] {
+        &self.table_columns
+    }
+    /// Get the table style, if any
+    pub fn style(&self) -> Option<&TableStyle> {
+        self.style.as_ref()
+    }
+    /// Is an autofilter applied to the table
+    pub fn has_autofilter(&self) -> bool {
+        self.auto_filter.is_some()
+    }
+    /// Get the autofilter of the table, with its filters and sort state
+    pub fn auto_filter(&self) -> Option<&AutoFilter> {
+        self.auto_filter.as_ref()
+    }
+}
+
+/// Column definition of a `Table`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableColumn {
+    pub(crate) id: u32,
+    pub(crate) name: String,
+    pub(crate) calculated_formula: Option<String>,
+    pub(crate) totals_row_function: TotalsRowFunction,
+    pub(crate) totals_row_label: Option<String>,
+}
+
+impl TableColumn {
+    /// Get the id of the column, unique within the table
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    /// Get the name of the column
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Get the formula of a calculated column, applied to every data row
+    pub fn calculated_formula(&self) -> Option<&str> {
+        self.calculated_formula.as_deref()
+    }
+    /// Get the function used in the totals row for this column
+    pub fn totals_row_function(&self) -> &TotalsRowFunction {
+        &self.totals_row_function
+    }
+    /// Get the text displayed in the totals row for this column, if any
+    pub fn totals_row_label(&self) -> Option<&str> {
+        self.totals_row_label.as_deref()
+    }
+}
+
+/// Function aggregating a column in the totals row of a `Table`
+///
+/// [ECMA-376 Part 1](https://www.ecma-international.org/publications-and-standards/standards/ecma-376/) 18.18.80
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum TotalsRowFunction {
+    /// No function
+    #[default]
+    None,
+    /// Sum
+    Sum,
+    /// Minimum
+    Min,
+    /// Maximum
+    Max,
+    /// Average
+    Average,
+    /// Count of non empty cells
+    Count,
+    /// Count of numeric cells
+    CountNums,
+    /// Standard deviation
+    StdDev,
+    /// Variance
+    Var,
+    /// Custom formula
+    Custom(String),
+}
+
+/// Style information of a `Table`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableStyle {
+    /// Name of the table style (e.g. "TableStyleMedium2")
+    pub name: String,
+    /// Is the first column formatted differently
+    pub show_first_column: bool,
+    /// Is the last column formatted differently
+    pub show_last_column: bool,
+    /// Are row stripes displayed
+    pub show_row_stripes: bool,
+    /// Are column stripes displayed
+    pub show_column_stripes: bool,
 }
@@ -15,14 +15,25 @@ use quick_xml::Reader as XmlReader;
 use zip::read::{ZipArchive, ZipFile};
 use zip::result::ZipError;
 
+use crate::protection::decode_base64;
 use crate::vba::VbaProject;
-use crate::{Data, DataType, Metadata, Range, Reader, Sheet, SheetType, SheetVisible};
+#[cfg(feature = "picture")]
+use crate::{picture, AnchorMarker, Picture, PictureAnchor};
+use crate::{
+    Color, Data, DataType, DataValidation, Dimensions, LayoutSpan, Metadata, Pane, PanePosition,
+    PaneState, PasswordHash, Range, Reader, Sheet, SheetLayout, SheetProtection, SheetType,
+    SheetView, SheetVisible, Table, ValidationErrorStyle, ValidationOperator, ValidationType,
+    WorkbookProtection,
+};
 use std::marker::PhantomData;
 
 const MIMETYPE: &[u8] = b"application/vnd.oasis.opendocument.spreadsheet";
 
 type OdsReader<'a> = XmlReader<BufReader<ZipFile<'a>>>;
 
+/// A database range: Name, Sheet, Columns, Data dimensions if not only a header, Header row count
+type DatabaseRange = (String, String, Vec<String>, Option<Dimensions>, u32);
+
 /// An enum for ods specific errors
 #[derive(Debug)]
 pub enum OdsError {
@@ -60,6 +71,8 @@ pub enum OdsError {
     Password,
     /// Worksheet not found
     WorksheetNotFound(String),
+    /// Table not found
+    TableNotFound(String),
 }
 
 from_err!(std::io::Error, OdsError, Io);
@@ -87,6 +100,7 @@ impl std::fmt::Display for OdsError {
             }
             OdsError::Password => write!(f, "Workbook is password protected"),
             OdsError::WorksheetNotFound(name) => write!(f, "Worksheet '{name}' not found"),
+            OdsError::TableNotFound(name) => write!(f, "Table '{name}' not found"),
         }
     }
 }
@@ -112,10 +126,19 @@ impl std::error::Error for OdsError {
 /// http://docs.oasis-open.org/office/v1.2/OpenDocument-v1.2.pdf
 pub struct Ods<RS> {
     sheets: BTreeMap<String, (Range<Data>, Range<String>)>,
+    layouts: BTreeMap<String, SheetLayout>,
+    views: BTreeMap<String, SheetView>,
+    data_validations: BTreeMap<String, Vec<DataValidation>>,
+    protections: BTreeMap<String, Option<SheetProtection>>,
+    workbook_protection: Option<WorkbookProtection>,
+    /// Tables (database ranges)
+    tables: Vec<DatabaseRange>,
     metadata: Metadata,
     marker: PhantomData<RS>,
     #[cfg(feature = "picture")]
     pictures: Option<Vec<(String, Vec<u8>)>>,
+    #[cfg(feature = "picture")]
+    sheet_pictures: Option<Vec<Picture>>,
 }
 
 impl<RS> Reader<RS> for Ods<RS>
@@ -141,26 +164,43 @@ where
         }
 
         check_for_password_protected(&mut zip)?;
+        let views = read_views(&mut zip)?;
 
         #[cfg(feature = "picture")]
         let pictures = read_pictures(&mut zip)?;
+        #[cfg(feature = "picture")]
+        let sheet_pictures = read_sheet_pictures(&mut zip)?;
 
         let Content {
             sheets,
+            layouts,
+            data_validations,
+            protections,
+            workbook_protection,
             sheets_metadata,
             defined_names,
+            tables,
         } = parse_content(zip)?;
         let metadata = Metadata {
             sheets: sheets_metadata,
             names: defined_names,
+            ..Default::default()
         };
 
         Ok(Ods {
             marker: PhantomData,
             metadata,
             sheets,
+            layouts,
+            views,
+            data_validations,
+            protections,
+            workbook_protection,
+            tables,
             #[cfg(feature = "picture")]
             pictures,
+            #[cfg(feature = "picture")]
+            sheet_pictures,
         })
     }
 
@@ -197,16 +237,95 @@ where
             .map(|r| r.1.to_owned())
     }
 
+    fn worksheet_layout(&mut self, name: &str) -> Result<SheetLayout, OdsError> {
+        self.layouts
+            .get(name)
+            .cloned()
+            .ok_or_else(|| OdsError::WorksheetNotFound(name.into()))
+    }
+
+    fn worksheet_view(&mut self, name: &str) -> Result<SheetView, OdsError> {
+        if !self.sheets.contains_key(name) {
+            return Err(OdsError::WorksheetNotFound(name.into()));
+        }
+        Ok(self.views.get(name).cloned().unwrap_or_default())
+    }
+
+    fn worksheet_protection(&mut self, name: &str) -> Result<Option<SheetProtection>, OdsError> {
+        self.protections
+            .get(name)
+            .cloned()
+            .ok_or_else(|| OdsError::WorksheetNotFound(name.into()))
+    }
+
+    fn workbook_protection(&mut self) -> Result<Option<WorkbookProtection>, OdsError> {
+        Ok(self.workbook_protection.clone())
+    }
+
+    fn worksheet_data_validations(&mut self, name: &str) -> Result<Vec<DataValidation>, OdsError> {
+        self.data_validations
+            .get(name)
+            .cloned()
+            .ok_or_else(|| OdsError::WorksheetNotFound(name.into()))
+    }
+
     #[cfg(feature = "picture")]
     fn pictures(&self) -> Option<Vec<(String, Vec<u8>)>> {
         self.pictures.to_owned()
     }
+
+    #[cfg(feature = "picture")]
+    fn sheet_pictures(&self) -> Option<Vec<Picture>> {
+        self.sheet_pictures.to_owned()
+    }
+}
+
+impl<RS> Ods<RS> {
+    /// Get the names of all the tables (named database ranges)
+    pub fn table_names(&self) -> Vec<&String> {
+        self.tables.iter().map(|(name, ..)| name).collect()
+    }
+
+    /// Get the names of all the tables in a sheet
+    pub fn table_names_in_sheet(&self, sheet_name: &str) -> Vec<&String> {
+        self.tables
+            .iter()
+            .filter(|(_, sheet, ..)| sheet == sheet_name)
+            .map(|(name, ..)| name)
+            .collect()
+    }
+
+    /// Get the table by name
+    pub fn table_by_name(&self, table_name: &str) -> Result<Table<Data>, OdsError> {
+        let (name, sheet_name, columns, dims, header_row_count) = self
+            .tables
+            .iter()
+            .find(|(table, ..)| table == table_name)
+            .ok_or_else(|| OdsError::TableNotFound(table_name.into()))?;
+        let range = self
+            .sheets
+            .get(sheet_name)
+            .ok_or_else(|| OdsError::WorksheetNotFound(sheet_name.clone()))?;
+        Ok(Table {
+            name: name.clone(),
+            sheet_name: sheet_name.clone(),
+            columns: columns.clone(),
+            data: dims.map_or_else(Range::empty, |dims| range.0.range(dims.start, dims.end)),
+            header_row_count: *header_row_count,
+            ..Default::default()
+        })
+    }
 }
 
 struct Content {
     sheets: BTreeMap<String, (Range<Data>, Range<String>)>,
+    layouts: BTreeMap<String, SheetLayout>,
+    data_validations: BTreeMap<String, Vec<DataValidation>>,
+    protections: BTreeMap<String, Option<SheetProtection>>,
+    workbook_protection: Option<WorkbookProtection>,
     sheets_metadata: Vec<Sheet>,
     defined_names: Vec<(String, String)>,
+    tables: Vec<DatabaseRange>,
 }
 
 /// Check password protection
@@ -253,6 +372,146 @@ fn check_for_password_protected<RS: Read + Seek>(zip: &mut ZipArchive<RS>) -> Re
     Ok(())
 }
 
+/// Read the view settings of the sheets, in settings.xml
+///
+/// Only the first view is read. Sheets with default settings may be missing.
+fn read_views<RS: Read + Seek>(
+    zip: &mut ZipArchive<RS>,
+) -> Result<BTreeMap<String, SheetView>, OdsError> {
+    let mut reader = match zip.by_name("settings.xml") {
+        Ok(f) => {
+            let mut r = XmlReader::from_reader(BufReader::new(f));
+            r.check_end_names(false)
+                .trim_text(false)
+                .check_comments(false)
+                .expand_empty_elements(true);
+            r
+        }
+        Err(ZipError::FileNotFound) => return Ok(BTreeMap::new()),
+        Err(e) => return Err(OdsError::Zip(e)),
+    };
+
+    // config items of the view, and of each of its tables
+    let mut view_items = HashMap::new();
+    let mut table_items: Vec<(String, HashMap<String, String>)> = Vec::new();
+    let mut in_tables = false;
+    let mut table = None;
+    let mut depth = 0;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if e.name() == QName(b"config:config-item-map-entry") => {
+                depth += 1;
+                if in_tables {
+                    if let Some(a) = e.try_get_attribute(b"config:name")? {
+                        let name = a.decode_and_unescape_value(&reader)?.into_owned();
+                        table_items.push((name, HashMap::new()));
+                        table = Some(table_items.len() - 1);
+                    }
+                }
+            }
+            Ok(Event::End(ref e)) if e.name() == QName(b"config:config-item-map-entry") => {
+                depth -= 1;
+                table = None;
+                // end of the first view
+                if depth == 0 {
+                    break;
+                }
+            }
+            Ok(Event::Start(ref e)) if e.name() == QName(b"config:config-item-map-named") => {
+                in_tables = depth == 1
+                    && e.try_get_attribute(b"config:name")?
+                        .map_or(false, |a| a.value.as_ref() == b"Tables");
+            }
+            Ok(Event::End(ref e)) if e.name() == QName(b"config:config-item-map-named") => {
+                in_tables = false;
+            }
+            Ok(Event::Start(ref e)) if depth > 0 && e.name() == QName(b"config:config-item") => {
+                let name = match e.try_get_attribute(b"config:name")? {
+                    Some(a) => a.decode_and_unescape_value(&reader)?.into_owned(),
+                    None => continue,
+                };
+                let mut value = String::new();
+                let mut inner = Vec::new();
+                loop {
+                    inner.clear();
+                    match reader.read_event_into(&mut inner)? {
+                        Event::Text(t) => value.push_str(&t.unescape()?),
+                        Event::End(_) => break,
+                        Event::Eof => return Err(OdsError::Eof("config:config-item")),
+                        _ => (),
+                    }
+                }
+                match table {
+                    Some(i) => table_items[i].1.insert(name, value),
+                    None if !in_tables && depth == 1 => view_items.insert(name, value),
+                    None => None,
+                };
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(OdsError::Xml(e)),
+            _ => (),
+        }
+    }
+
+    let mut views = BTreeMap::new();
+    for (name, mut items) in table_items {
+        for key in ["ShowGrid", "ZoomValue"] {
+            if let (false, Some(v)) = (items.contains_key(key), view_items.get(key)) {
+                items.insert(key.to_string(), v.clone());
+            }
+        }
+        let int = |key: &str| items.get(key).and_then(|v| v.parse::<u32>().ok());
+        let mut view = SheetView {
+            show_grid_lines: items.get("ShowGrid").map_or(true, |v| v != "false"),
+            zoom: int("ZoomValue").unwrap_or(100),
+            top_left_cell: (
+                int("PositionTop").unwrap_or(0),
+                int("PositionLeft").unwrap_or(0),
+            ),
+            ..Default::default()
+        };
+        let cursor = (
+            int("CursorPositionY").unwrap_or(0),
+            int("CursorPositionX").unwrap_or(0),
+        );
+        view.active_cell = cursor;
+        view.selection = vec![Dimensions {
+            start: cursor,
+            end: cursor,
+        }];
+        // 0: no split, 1: split, 2: frozen
+        let (x_mode, y_mode) = (
+            int("HorizontalSplitMode").unwrap_or(0),
+            int("VerticalSplitMode").unwrap_or(0),
+        );
+        if x_mode != 0 || y_mode != 0 {
+            view.pane = Some(Pane {
+                state: if x_mode == 2 || y_mode == 2 {
+                    PaneState::Frozen
+                } else {
+                    PaneState::Split
+                },
+                x_split: int("HorizontalSplitPosition").unwrap_or(0) as f64,
+                y_split: int("VerticalSplitPosition").unwrap_or(0) as f64,
+                top_left_cell: (
+                    int("PositionBottom").unwrap_or(0),
+                    int("PositionRight").unwrap_or(0),
+                ),
+                active_pane: match int("ActiveSplitRange") {
+                    Some(1) => PanePosition::TopRight,
+                    Some(2) => PanePosition::BottomLeft,
+                    Some(3) => PanePosition::BottomRight,
+                    _ => PanePosition::TopLeft,
+                },
+            });
+        }
+        views.insert(name, view);
+    }
+    Ok(views)
+}
+
 /// Parses content.xml and store the result in `self.content`
 fn parse_content<RS: Read + Seek>(mut zip: ZipArchive<RS>) -> Result<Content, OdsError> {
     let mut reader = match zip.by_name("content.xml") {
@@ -273,6 +532,12 @@ fn parse_content<RS: Read + Seek>(mut zip: ZipArchive<RS>) -> Result<Content, Od
     let mut sheets_metadata = Vec::new();
     let mut styles = HashMap::new();
     let mut style_name: Option<String> = None;
+    let mut database_ranges = Vec::new();
+    let mut layouts = BTreeMap::new();
+    let mut protections = BTreeMap::new();
+    let mut workbook_protection = None;
+    let mut content_validations = HashMap::new();
+    let mut validated_cells = BTreeMap::new();
     loop {
         match reader.read_event_into(&mut buf) {
             Ok(Event::Start(ref e)) if e.name() == QName(b"style:style") => {
@@ -298,10 +563,35 @@ fn parse_content<RS: Read + Seek>(mut zip: ZipArchive<RS>) -> Result<Content, Od
                     },
                     None => SheetVisible::Visible,
                 };
-                styles.insert(style_name.clone(), visible);
+                // `tableooo:tab-color` before ODF 1.3
+                let tab_color = match e.try_get_attribute(b"table:tab-color")? {
+                    Some(a) => Some(a),
+                    None => e.try_get_attribute(b"tableooo:tab-color")?,
+                }
+                .and_then(|a| {
+                    let rgb = a.value.strip_prefix(b"#")?;
+                    let rgb = u32::from_str_radix(std::str::from_utf8(rgb).ok()?, 16).ok()?;
+                    Some(Color::Argb(0xFF00_0000 | rgb))
+                });
+                styles.insert(style_name.clone(), (visible, tab_color));
+            }
+            Ok(Event::Start(ref e)) if e.name() == QName(b"office:spreadsheet") => {
+                if let Some(a) = e.try_get_attribute(b"table:structure-protected")? {
+                    if &*a.value == b"true" {
+                        workbook_protection = Some(WorkbookProtection {
+                            lock_structure: true,
+                            lock_windows: false,
+                            password: read_protection_key(&reader, e)?,
+                        });
+                    }
+                }
             }
             Ok(Event::Start(ref e)) if e.name() == QName(b"table:table") => {
-                let visible = styles
+                let protected = e
+                    .try_get_attribute(b"table:protected")?
+                    .map_or(false, |a| &*a.value == b"true");
+                let password = read_protection_key(&reader, e)?;
+                let (visible, tab_color) = styles
                     .get(
                         &e.try_get_attribute(b"table:style-name")?
                             .map(|a| a.decode_and_unescape_value(&reader))
@@ -310,7 +600,7 @@ fn parse_content<RS: Read + Seek>(mut zip: ZipArchive<RS>) -> Result<Content, Od
                             .map(|x| x.to_string()),
                     )
                     .map(|v| v.to_owned())
-                    .unwrap_or(SheetVisible::Visible);
+                    .unwrap_or((SheetVisible::Visible, None));
                 if let Some(ref a) = e
                     .attributes()
                     .filter_map(|a| a.ok())
@@ -320,39 +610,210 @@ fn parse_content<RS: Read + Seek>(mut zip: ZipArchive<RS>) -> Result<Content, Od
                         .decode_and_unescape_value(&reader)
                         .map_err(OdsError::Xml)?
                         .to_string();
-                    let (range, formulas) = read_table(&mut reader)?;
+                    let table = read_table(&mut reader)?;
+                    layouts.insert(name.clone(), table.layout);
+                    protections.insert(
+                        name.clone(),
+                        protected.then_some(SheetProtection {
+                            password,
+                            ..table.protection
+                        }),
+                    );
+                    validated_cells.insert(name.clone(), table.validated_cells);
                     sheets_metadata.push(Sheet {
                         name: name.clone(),
                         typ: SheetType::WorkSheet,
                         visible,
+                        tab_color,
+                        sheet_id: None,
+                        path: None,
                     });
-                    sheets.insert(name, (range, formulas));
+                    sheets.insert(name, (table.range, table.formulas));
                 }
             }
+            Ok(Event::Start(ref e)) if e.name() == QName(b"table:content-validation") => {
+                let (name, dv) = read_content_validation(&mut reader, e)?;
+                content_validations.insert(name, dv);
+            }
             Ok(Event::Start(ref e)) if e.name() == QName(b"table:named-expressions") => {
                 defined_names = read_named_expressions(&mut reader)?;
             }
+            Ok(Event::Start(ref e)) if e.name() == QName(b"table:database-range") => {
+                let mut name = String::new();
+                let mut address = String::new();
+                let mut contains_header = true;
+                for a in e.attributes() {
+                    let a = a.map_err(OdsError::XmlAttr)?;
+                    match a.key {
+                        QName(b"table:name") => {
+                            name = a
+                                .decode_and_unescape_value(&reader)
+                                .map_err(OdsError::Xml)?
+                                .to_string();
+                        }
+                        QName(b"table:target-range-address") => {
+                            address = a
+                                .decode_and_unescape_value(&reader)
+                                .map_err(OdsError::Xml)?
+                                .to_string();
+                        }
+                        QName(b"table:contains-header") => contains_header = &*a.value != b"false",
+                        _ => (),
+                    }
+                }
+                // unnamed sheet-local ranges are created by LibreOffice for autofilters/sorts
+                if !name.starts_with("__Anonymous_Sheet_DB__") {
+                    if let Some((sheet, dims)) = parse_range_address(&address) {
+                        database_ranges.push((name, sheet, dims, contains_header));
+                    }
+                }
+            }
             Ok(Event::Eof) => break,
             Err(e) => return Err(OdsError::Xml(e)),
             _ => (),
         }
         buf.clear();
     }
+    let tables = database_ranges
+        .into_iter()
+        .map(|(name, sheet, dims, contains_header)| {
+            if !contains_header {
+                let columns = (1..=dims.end.1 - dims.start.1 + 1)
+                    .map(|i| format!("Column{}", i))
+                    .collect();
+                return (name, sheet, columns, Some(dims), 0);
+            }
+            let header = sheets
+                .get(&sheet)
+                .map(|(range, _)| {
+                    range.range((dims.start.0, dims.start.1), (dims.start.0, dims.end.1))
+                })
+                .unwrap_or_default();
+            let columns = header.inner.iter().map(|c| c.to_string()).collect();
+            // a range of only a header has no data
+            let data = (dims.start.0 < dims.end.0).then(|| Dimensions {
+                start: (dims.start.0 + 1, dims.start.1),
+                end: dims.end,
+            });
+            (name, sheet, columns, data, 1)
+        })
+        .collect();
+    let data_validations = validated_cells
+        .into_iter()
+        .map(|(sheet, cells): (String, Vec<(String, Dimensions)>)| {
+            let mut validations: Vec<(&String, DataValidation)> = Vec::new();
+            for (name, dims) in &cells {
+                match validations.iter_mut().find(|(n, _)| *n == name) {
+                    Some((_, dv)) => dv.ranges.push(*dims),
+                    None => {
+                        if let Some(dv) = content_validations.get(name) {
+                            let mut dv: DataValidation = Clone::clone(dv);
+                            dv.ranges.push(*dims);
+                            validations.push((name, dv));
+                        }
+                    }
+                }
+            }
+            (sheet, validations.into_iter().map(|(_, dv)| dv).collect())
+        })
+        .collect();
     Ok(Content {
         sheets,
+        layouts,
+        data_validations,
+        protections,
+        workbook_protection,
         sheets_metadata,
         defined_names,
+        tables,
     })
 }
 
-fn read_table(reader: &mut OdsReader<'_>) -> Result<(Range<Data>, Range<String>), OdsError> {
+/// Reads the `table:protection-key` password hash of a protected element
+///
+/// ODF 1.2-19.699
+fn read_protection_key(
+    reader: &OdsReader<'_>,
+    e: &quick_xml::events::BytesStart<'_>,
+) -> Result<Option<PasswordHash>, OdsError> {
+    let hash = match e.try_get_attribute(b"table:protection-key")? {
+        Some(a) => a.decode_and_unescape_value(reader).map_err(OdsError::Xml)?,
+        None => return Ok(None),
+    };
+    let algorithm = match e.try_get_attribute(b"table:protection-key-digest-algorithm")? {
+        Some(a) => a
+            .decode_and_unescape_value(reader)
+            .map_err(OdsError::Xml)?
+            .to_string(),
+        None => "http://www.w3.org/2000/09/xmldsig#sha1".to_string(),
+    };
+    Ok(decode_base64(&hash)
+        .filter(|hash| !hash.is_empty())
+        .map(|hash| PasswordHash::Digest { algorithm, hash }))
+}
+
+/// Parses a cell range address (e.g. `$'My Sheet'.$A$1:.$B$3`) into its sheet name and dimensions
+///
+/// ODF 1.2-18.3.5
+fn parse_range_address(address: &str) -> Option<(String, Dimensions)> {
+    let (start, end) = address.split_once(':').unwrap_or((address, address));
+    let (sheet, start) = parse_cell_address(start)?;
+    let (_, end) = parse_cell_address(end)?;
+    if sheet.is_empty() || start.0 > end.0 || start.1 > end.1 {
+        return None;
+    }
+    Some((sheet, Dimensions { start, end }))
+}
+
+/// Parses a cell address (e.g. `Sheet1.A1`) into its sheet name and (row, column) position
+fn parse_cell_address(address: &str) -> Option<(String, (u32, u32))> {
+    let (sheet, cell) = address.rsplit_once('.')?;
+    let sheet = sheet.trim_start_matches('$');
+    let sheet = match sheet.strip_prefix('\'') {
+        Some(s) => s.strip_suffix('\'')?.replace("''", "'"),
+        None => sheet.to_string(),
+    };
+    let cell = cell.replace('$', "");
+    let split = cell.find(|c: char| c.is_ascii_digit())?;
+    let (col, row) = cell.split_at(split);
+    if col.is_empty() {
+        return None;
+    }
+    let col = col.bytes().try_fold(0u32, |acc, c| {
+        if !c.is_ascii_alphabetic() {
+            return None;
+        }
+        acc.checked_mul(26)?
+            .checked_add((c.to_ascii_uppercase() - b'A') as u32 + 1)
+    })?;
+    let row: u32 = row.parse().ok()?;
+    Some((sheet, (row.checked_sub(1)?, col - 1)))
+}
+
+/// Content of a `table:table` element
+struct TableContent {
+    range: Range<Data>,
+    formulas: Range<String>,
+    layout: SheetLayout,
+    /// Actions allowed if the table is protected
+    protection: SheetProtection,
+    /// Cells with a content validation: validation name and area
+    validated_cells: Vec<(String, Dimensions)>,
+}
+
+fn read_table(reader: &mut OdsReader<'_>) -> Result<TableContent, OdsError> {
     let mut cells = Vec::new();
+    let mut validated_cells = Vec::new();
     let mut rows_repeats = Vec::new();
     let mut formulas = Vec::new();
     let mut cols = Vec::new();
     let mut buf = Vec::with_capacity(1024);
     let mut row_buf = Vec::with_capacity(1024);
     let mut cell_buf = Vec::with_capacity(1024);
+    let mut layout = SheetLayout::default();
+    let mut protection = SheetProtection::default();
+    let (mut row, mut col) = (0u32, 0u32);
+    let (mut row_level, mut col_level) = (0u8, 0u8);
     cols.push(0);
     loop {
         match reader.read_event_into(&mut buf) {
@@ -365,26 +826,330 @@ fn read_table(reader: &mut OdsReader<'_>) -> Result<(Range<Data>, Range<String>)
                         .map_err(OdsError::ParseInt)?,
                     None => 1,
                 };
+                let hidden = is_collapsed(e)?;
+                if hidden || row_level != 0 {
+                    layout.rows.push(LayoutSpan {
+                        first: row,
+                        last: row + row_repeats as u32 - 1,
+                        hidden,
+                        outline_level: row_level,
+                        ..Default::default()
+                    });
+                }
                 read_row(
                     reader,
                     &mut row_buf,
                     &mut cell_buf,
                     &mut cells,
                     &mut formulas,
+                    (row, row_repeats as u32),
+                    &mut validated_cells,
                 )?;
+                row += row_repeats as u32;
                 cols.push(cells.len());
                 rows_repeats.push(row_repeats);
             }
+            Ok(Event::Start(ref e)) if e.name() == QName(b"table:table-column") => {
+                let col_repeats: u32 =
+                    match e.try_get_attribute(b"table:number-columns-repeated")? {
+                        Some(c) => c
+                            .decode_and_unescape_value(reader)
+                            .map_err(OdsError::Xml)?
+                            .parse()
+                            .map_err(OdsError::ParseInt)?,
+                        None => 1,
+                    };
+                let hidden = is_collapsed(e)?;
+                if hidden || col_level != 0 {
+                    layout.columns.push(LayoutSpan {
+                        first: col,
+                        last: col + col_repeats - 1,
+                        hidden,
+                        outline_level: col_level,
+                        ..Default::default()
+                    });
+                }
+                col += col_repeats;
+            }
+            Ok(Event::Start(ref e)) if e.name() == QName(b"table:table-row-group") => {
+                row_level += 1
+            }
+            Ok(Event::End(ref e)) if e.name() == QName(b"table:table-row-group") => {
+                row_level = row_level.saturating_sub(1)
+            }
+            Ok(Event::Start(ref e)) if e.name() == QName(b"table:table-column-group") => {
+                col_level += 1
+            }
+            Ok(Event::End(ref e)) if e.name() == QName(b"table:table-column-group") => {
+                col_level = col_level.saturating_sub(1)
+            }
+            // `loext:table-protection` before ODF 1.3
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"table-protection" => {
+                for a in e.attributes() {
+                    let a = a.map_err(OdsError::XmlAttr)?;
+                    let allowed = &*a.value == b"true";
+                    match a.key.local_name().as_ref() {
+                        b"select-protected-cells" => protection.select_locked_cells = allowed,
+                        b"select-unprotected-cells" => protection.select_unlocked_cells = allowed,
+                        b"insert-columns" => protection.insert_columns = allowed,
+                        b"insert-rows" => protection.insert_rows = allowed,
+                        b"delete-columns" => protection.delete_columns = allowed,
+                        b"delete-rows" => protection.delete_rows = allowed,
+                        _ => (),
+                    }
+                }
+            }
             Ok(Event::End(ref e)) if e.name() == QName(b"table:table") => break,
             Err(e) => return Err(OdsError::Xml(e)),
             Ok(_) => (),
         }
         buf.clear();
     }
-    Ok((
-        get_range(cells, &cols, &rows_repeats),
-        get_range(formulas, &cols, &rows_repeats),
-    ))
+    Ok(TableContent {
+        range: get_range(cells, &cols, &rows_repeats),
+        formulas: get_range(formulas, &cols, &rows_repeats),
+        layout,
+        protection,
+        validated_cells,
+    })
+}
+
+/// Is a row or column hidden, either collapsed in a group or filtered out
+///
+/// ODF 1.2-19.749
+fn is_collapsed(e: &quick_xml::events::BytesStart<'_>) -> Result<bool, OdsError> {
+    Ok(e.try_get_attribute(b"table:visibility")?
+        .map_or(false, |a| &*a.value != b"visible"))
+}
+
+/// Reads a `table:content-validation` element, returns its name and rule
+///
+/// ODF 1.2-9.4.5
+fn read_content_validation(
+    reader: &mut OdsReader<'_>,
+    e: &quick_xml::events::BytesStart<'_>,
+) -> Result<(String, DataValidation), OdsError> {
+    let mut name = String::new();
+    let mut dv = DataValidation {
+        allow_blank: true,
+        show_dropdown: true,
+        ..Default::default()
+    };
+    for a in e.attributes() {
+        let a = a.map_err(OdsError::XmlAttr)?;
+        match a.key {
+            QName(b"table:name") => {
+                name = a
+                    .decode_and_unescape_value(reader)
+                    .map_err(OdsError::Xml)?
+                    .to_string()
+            }
+            QName(b"table:condition") => {
+                let condition = a.decode_and_unescape_value(reader).map_err(OdsError::Xml)?;
+                parse_validation_condition(&condition, &mut dv);
+            }
+            QName(b"table:allow-empty-cell") => dv.allow_blank = &*a.value != b"false",
+            QName(b"table:display-list") => dv.show_dropdown = &*a.value != b"none",
+            _ => (),
+        }
+    }
+    let mut buf = Vec::new();
+    // the message being read: true for the error message, false for the help message
+    let mut is_error = false;
+    let mut in_paragraph = false;
+    loop {
+        buf.clear();
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e))
+                if e.name() == QName(b"table:help-message")
+                    || e.name() == QName(b"table:error-message") =>
+            {
+                is_error = e.name() == QName(b"table:error-message");
+                for a in e.attributes() {
+                    let a = a.map_err(OdsError::XmlAttr)?;
+                    match a.key {
+                        QName(b"table:title") => {
+                            let title = a
+                                .decode_and_unescape_value(reader)
+                                .map_err(OdsError::Xml)?
+                                .to_string();
+                            if is_error {
+                                dv.error_title = Some(title);
+                            } else {
+                                dv.prompt_title = Some(title);
+                            }
+                        }
+                        QName(b"table:display") => {
+                            let display = &*a.value == b"true";
+                            if is_error {
+                                dv.show_error_message = display;
+                            } else {
+                                dv.show_input_message = display;
+                            }
+                        }
+                        QName(b"table:message-type") => {
+                            dv.error_style = match &*a.value {
+                                b"warning" => ValidationErrorStyle::Warning,
+                                b"information" => ValidationErrorStyle::Information,
+                                _ => ValidationErrorStyle::Stop,
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            Ok(Event::Start(ref e)) if e.name() == QName(b"text:p") => in_paragraph = true,
+            Ok(Event::Text(ref t)) if in_paragraph => {
+                let text = t.unescape()?;
+                let message = if is_error {
+                    &mut dv.error
+                } else {
+                    &mut dv.prompt
+                };
+                match message {
+                    Some(m) => m.push_str(&text),
+                    None => *message = Some(text.into_owned()),
+                }
+            }
+            Ok(Event::End(ref e)) if e.name() == QName(b"text:p") => {
+                in_paragraph = false;
+                let message = if is_error {
+                    &mut dv.error
+                } else {
+                    &mut dv.prompt
+                };
+                if let Some(m) = message {
+                    m.push('\n');
+                }
+            }
+            Ok(Event::End(ref e)) if e.name() == QName(b"table:content-validation") => break,
+            Ok(Event::Eof) => return Err(OdsError::Eof("table:content-validation")),
+            Err(e) => return Err(OdsError::Xml(e)),
+            _ => (),
+        }
+    }
+    for message in [&mut dv.prompt, &mut dv.error] {
+        if let Some(m) = message {
+            m.truncate(m.trim_end_matches('\n').len());
+            if m.is_empty() {
+                *message = None;
+            }
+        }
+    }
+    Ok((name, dv))
+}
+
+/// Parses the `table:condition` of a content validation
+///
+/// e.g. `of:cell-content-is-whole-number() and cell-content-is-between(1;10)`
+fn parse_validation_condition(condition: &str, dv: &mut DataValidation) {
+    let condition = condition.replace("of:", "");
+    let mut condition = condition.trim();
+    for (prefix, typ) in [
+        ("cell-content-is-whole-number()", ValidationType::Whole),
+        ("cell-content-is-decimal-number()", ValidationType::Decimal),
+        ("cell-content-is-date()", ValidationType::Date),
+        ("cell-content-is-time()", ValidationType::Time),
+    ] {
+        if let Some(c) = condition.strip_prefix(prefix) {
+            dv.validation_type = typ;
+            condition = c.trim_start().trim_start_matches("and").trim_start();
+        }
+    }
+    if let Some(list) = function_args(condition, "cell-content-is-in-list") {
+        dv.validation_type = ValidationType::List;
+        let items = split_args(list);
+        dv.formula1 = Some(if items.len() > 1 || list.starts_with('"') {
+            // explicit values, rendered as "a,b,c"
+            let items: Vec<_> = items
+                .iter()
+                .map(|i| {
+                    let i = i.trim();
+                    i.strip_prefix('"')
+                        .and_then(|i| i.strip_suffix('"'))
+                        .unwrap_or(i)
+                })
+                .collect();
+            format!("\"{}\"", items.join(","))
+        } else {
+            list.to_string()
+        });
+    } else if let Some(formula) = function_args(condition, "is-true-formula") {
+        dv.validation_type = ValidationType::Custom;
+        dv.formula1 = Some(formula.to_string());
+    } else {
+        let (comparison, is_length) = match condition.strip_prefix("cell-content-text-length") {
+            Some(c) => (c, true),
+            None => (
+                condition.strip_prefix("cell-content").unwrap_or(condition),
+                false,
+            ),
+        };
+        if is_length {
+            dv.validation_type = ValidationType::TextLength;
+        } else if dv.validation_type == ValidationType::Any && !comparison.is_empty() {
+            dv.validation_type = ValidationType::Decimal;
+        }
+        if let Some(args) = function_args(comparison, "-is-between") {
+            dv.operator = ValidationOperator::Between;
+            set_bounds(dv, args);
+        } else if let Some(args) = function_args(comparison, "-is-not-between") {
+            dv.operator = ValidationOperator::NotBetween;
+            set_bounds(dv, args);
+        } else if let Some(c) = comparison.strip_prefix("()") {
+            let c = c.trim_start();
+            let (operator, value) = [
+                ("<=", ValidationOperator::LessThanOrEqual),
+                (">=", ValidationOperator::GreaterThanOrEqual),
+                ("!=", ValidationOperator::NotEqual),
+                ("<>", ValidationOperator::NotEqual),
+                ("<", ValidationOperator::LessThan),
+                (">", ValidationOperator::GreaterThan),
+                ("=", ValidationOperator::Equal),
+            ]
+            .into_iter()
+            .find_map(|(op, operator)| c.strip_prefix(op).map(|v| (operator, v)))
+            .unwrap_or((ValidationOperator::Equal, c));
+            dv.operator = operator;
+            dv.formula1 = Some(value.trim().to_string());
+        }
+    }
+}
+
+fn set_bounds(dv: &mut DataValidation, args: &str) {
+    let mut args = split_args(args).into_iter();
+    dv.formula1 = args.next().map(|a| a.trim().to_string());
+    dv.formula2 = args.next().map(|a| a.trim().to_string());
+}
+
+/// Gets the arguments of `name(args)`, if the expression is a call to `name`
+fn function_args<'a>(expression: &'a str, name: &str) -> Option<&'a str> {
+    expression
+        .strip_prefix(name)?
+        .trim_start()
+        .strip_prefix('(')?
+        .trim_end()
+        .strip_suffix(')')
+}
+
+/// Splits function arguments on the `;` which are not inside strings or parentheses
+fn split_args(args: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let (mut depth, mut in_string, mut start) = (0, false, 0);
+    for (i, c) in args.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '(' | '[' if !in_string => depth += 1,
+            ')' | ']' if !in_string => depth -= 1,
+            ';' if !in_string && depth == 0 => {
+                res.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    res.push(&args[start..]);
+    res
 }
 
 fn is_empty_row<T: Default + Clone + PartialEq>(row: &[T]) -> bool {
@@ -492,8 +1257,11 @@ fn read_row(
     cell_buf: &mut Vec<u8>,
     cells: &mut Vec<Data>,
     formulas: &mut Vec<String>,
+    (row, row_repeats): (u32, u32),
+    validated_cells: &mut Vec<(String, Dimensions)>,
 ) -> Result<(), OdsError> {
     let mut empty_col_repeats = 0;
+    let mut col = 0;
     loop {
         row_buf.clear();
         match reader.read_event_into(row_buf) {
@@ -502,17 +1270,38 @@ fn read_row(
                     || e.name() == QName(b"table:covered-table-cell") =>
             {
                 let mut repeats = 1;
+                let mut validation = None;
                 for a in e.attributes() {
                     let a = a.map_err(OdsError::XmlAttr)?;
-                    if a.key == QName(b"table:number-columns-repeated") {
-                        repeats = reader
-                            .decoder()
-                            .decode(&a.value)?
-                            .parse()
-                            .map_err(OdsError::ParseInt)?;
-                        break;
+                    match a.key {
+                        QName(b"table:number-columns-repeated") => {
+                            repeats = reader
+                                .decoder()
+                                .decode(&a.value)?
+                                .parse()
+                                .map_err(OdsError::ParseInt)?;
+                        }
+                        QName(b"table:content-validation-name") => {
+                            validation = Some(
+                                a.decode_and_unescape_value(reader)
+                                    .map_err(OdsError::Xml)?
+                                    .to_string(),
+                            );
+                        }
+                        _ => (),
                     }
                 }
+                if let Some(name) = validation {
+                    push_validated_cells(
+                        validated_cells,
+                        name,
+                        Dimensions {
+                            start: (row, col),
+                            end: (row + row_repeats - 1, col + repeats as u32 - 1),
+                        },
+                    );
+                }
+                col += repeats as u32;
 
                 let (value, formula, is_closed) = get_datatype(reader, e.attributes(), cell_buf)?;
 
@@ -547,6 +1336,32 @@ fn read_row(
     Ok(())
 }
 
+/// Adds an area to the cells with a content validation, merging it with adjacent areas
+fn push_validated_cells(cells: &mut Vec<(String, Dimensions)>, name: String, mut dims: Dimensions) {
+    // same rows, previous columns
+    if let Some((n, last)) = cells.last() {
+        if *n == name
+            && last.start.0 == dims.start.0
+            && last.end.0 == dims.end.0
+            && last.end.1 + 1 == dims.start.1
+        {
+            dims.start.1 = last.start.1;
+            cells.pop();
+        }
+    }
+    // same columns, previous rows
+    if let Some((_, prev)) = cells.iter_mut().rev().find(|(n, prev)| {
+        *n == name
+            && prev.start.1 == dims.start.1
+            && prev.end.1 == dims.end.1
+            && prev.end.0 + 1 == dims.start.0
+    }) {
+        prev.end.0 = dims.end.0;
+        return;
+    }
+    cells.push((name, dims));
+}
+
 /// Converts table-cell element into a `Data`
 ///
 /// ODF 1.2-19.385
@@ -723,3 +1538,195 @@ fn read_pictures<RS: Read + Seek>(
         Ok(Some(pics))
     }
 }
+
+/// Read the pictures (`draw:frame`) placed on sheets
+///
+/// Frames within a cell are anchored to that cell, frames within `table:shapes`
+/// have an absolute position.
+#[cfg(feature = "picture")]
+fn read_sheet_pictures<RS: Read + Seek>(
+    zip: &mut ZipArchive<RS>,
+) -> Result<Option<Vec<Picture>>, OdsError> {
+    struct Frame {
+        name: String,
+        description: Option<String>,
+        href: String,
+        position: (i64, i64),
+        size: Option<(u64, u64)>,
+        end: Option<((u32, u32), (i64, i64))>,
+        cell: Option<(u32, u32)>,
+    }
+
+    let mut frames = Vec::new();
+    {
+        let mut reader = match zip.by_name("content.xml") {
+            Ok(f) => {
+                let mut r = XmlReader::from_reader(BufReader::new(f));
+                r.check_end_names(false)
+                    .check_comments(false)
+                    .expand_empty_elements(true);
+                r
+            }
+            Err(ZipError::FileNotFound) => return Err(OdsError::FileNotFound("content.xml")),
+            Err(e) => return Err(OdsError::Zip(e)),
+        };
+        let mut buf = Vec::with_capacity(1024);
+        let mut sheet_name = String::new();
+        let (mut row, mut col) = (0u32, 0u32);
+        let (mut row_repeats, mut col_repeats) = (1u32, 1u32);
+        let mut in_cell = false;
+        let mut in_desc = false;
+        let mut frame: Option<(String, Frame)> = None;
+        loop {
+            buf.clear();
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(ref e) => match e.name().as_ref() {
+                    b"table:table" => {
+                        sheet_name = get_attribute(&reader, e, b"table:name")?.unwrap_or_default();
+                        row = 0;
+                    }
+                    b"table:table-row" => {
+                        col = 0;
+                        row_repeats = get_attribute(&reader, e, b"table:number-rows-repeated")?
+                            .and_then(|r| r.parse().ok())
+                            .unwrap_or(1);
+                    }
+                    b"table:table-cell" | b"table:covered-table-cell" => {
+                        in_cell = true;
+                        col_repeats = get_attribute(&reader, e, b"table:number-columns-repeated")?
+                            .and_then(|r| r.parse().ok())
+                            .unwrap_or(1);
+                    }
+                    b"draw:frame" => {
+                        let length = |name: &[u8]| -> Result<Option<i64>, OdsError> {
+                            Ok(get_attribute(&reader, e, name)?
+                                .and_then(|l| picture::length_to_emu(&l)))
+                        };
+                        let end = get_attribute(&reader, e, b"table:end-cell-address")?
+                            .and_then(|a| parse_cell_address(&a))
+                            .map(|(_, cell)| -> Result<_, OdsError> {
+                                Ok((
+                                    cell,
+                                    (
+                                        length(b"table:end-x")?.unwrap_or(0),
+                                        length(b"table:end-y")?.unwrap_or(0),
+                                    ),
+                                ))
+                            })
+                            .transpose()?;
+                        let size = match (length(b"svg:width")?, length(b"svg:height")?) {
+                            (Some(w), Some(h)) => Some((w as u64, h as u64)),
+                            _ => None,
+                        };
+                        frame = Some((
+                            sheet_name.clone(),
+                            Frame {
+                                name: get_attribute(&reader, e, b"draw:name")?.unwrap_or_default(),
+                                description: None,
+                                href: String::new(),
+                                position: (
+                                    length(b"svg:x")?.unwrap_or(0),
+                                    length(b"svg:y")?.unwrap_or(0),
+                                ),
+                                size,
+                                end,
+                                cell: in_cell.then_some((row, col)),
+                            },
+                        ));
+                    }
+                    b"draw:image" => {
+                        if let Some((_, ref mut f)) = frame {
+                            if f.href.is_empty() {
+                                f.href =
+                                    get_attribute(&reader, e, b"xlink:href")?.unwrap_or_default();
+                            }
+                        }
+                    }
+                    b"svg:desc" => in_desc = frame.is_some(),
+                    _ => (),
+                },
+                Event::Text(ref t) if in_desc => {
+                    if let Some((_, ref mut f)) = frame {
+                        f.description
+                            .get_or_insert_with(String::new)
+                            .push_str(&t.unescape()?);
+                    }
+                }
+                Event::End(ref e) => match e.name().as_ref() {
+                    b"table:table-row" => row += row_repeats,
+                    b"table:table-cell" | b"table:covered-table-cell" => {
+                        in_cell = false;
+                        col += col_repeats;
+                    }
+                    b"svg:desc" => in_desc = false,
+                    b"draw:frame" => frames.extend(frame.take()),
+                    _ => (),
+                },
+                Event::Eof => break,
+                _ => (),
+            }
+        }
+    }
+
+    let mut pics = Vec::with_capacity(frames.len());
+    for (sheet_name, f) in frames {
+        // skip frames without images (charts, objects) and linked images
+        let mut data = Vec::new();
+        match zip.by_name(f.href.trim_start_matches("./")) {
+            Ok(mut z) => z.read_to_end(&mut data)?,
+            Err(_) => continue,
+        };
+        let from = f.cell.map(|(row, col)| AnchorMarker {
+            row,
+            col,
+            row_offset: f.position.1,
+            col_offset: f.position.0,
+        });
+        let anchor = match (from, f.end) {
+            (Some(from), Some(((row, col), (x, y)))) => PictureAnchor::TwoCell {
+                from,
+                to: AnchorMarker {
+                    row,
+                    col,
+                    row_offset: y,
+                    col_offset: x,
+                },
+            },
+            (Some(from), None) => PictureAnchor::OneCell { from },
+            (None, _) => PictureAnchor::Absolute {
+                x: f.position.0,
+                y: f.position.1,
+            },
+        };
+        let extension = f
+            .href
+            .rsplit_once('.')
+            .map_or_else(String::new, |(_, ext)| ext.to_ascii_lowercase());
+        pics.push(Picture {
+            sheet_name,
+            name: f.name,
+            description: f.description,
+            anchor,
+            size: f.size,
+            extension,
+            data,
+        });
+    }
+    if pics.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(pics))
+    }
+}
+
+#[cfg(feature = "picture")]
+fn get_attribute(
+    reader: &OdsReader<'_>,
+    e: &quick_xml::events::BytesStart<'_>,
+    name: &[u8],
+) -> Result<Option<String>, OdsError> {
+    e.try_get_attribute(name)?
+        .map(|a| a.decode_and_unescape_value(reader).map(|v| v.into_owned()))
+        .transpose()
+        .map_err(OdsError::Xml)
+}
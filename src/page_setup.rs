@@ -0,0 +1,385 @@
+//! Page setup of worksheets: paper, margins, headers and footers, page breaks and print areas
+
+use crate::Dimensions;
+
+/// Last row of a worksheet, used by whole column references
+const MAX_ROW: u32 = 1_048_575;
+/// Last column of a worksheet, used by whole row references
+const MAX_COLUMN: u32 = 16_383;
+
+/// Print layout of a worksheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageSetup {
+    /// Page orientation
+    pub orientation: Orientation,
+    /// Paper size code (e.g. 1 for Letter, 9 for A4)
+    pub paper_size: u32,
+    /// Print scale, in percent
+    pub scale: u32,
+    /// Number of pages (width, height) the sheet is fitted to, 0 meaning automatic,
+    /// or `None` if `scale` applies
+    pub fit_to_pages: Option<(u32, u32)>,
+    /// Number of the first page, if not automatic
+    pub first_page_number: Option<i32>,
+    /// Order of the pages
+    pub page_order: PageOrder,
+    /// Print in black and white
+    pub black_and_white: bool,
+    /// Print in draft quality
+    pub draft: bool,
+    /// Page margins
+    pub margins: PageMargins,
+    /// Center the content horizontally on the page
+    pub center_horizontally: bool,
+    /// Center the content vertically on the page
+    pub center_vertically: bool,
+    /// Print the grid lines
+    pub print_grid_lines: bool,
+    /// Print the row and column headings
+    pub print_headings: bool,
+    /// Headers and footers
+    pub header_footer: HeaderFooter,
+    /// Manual row breaks: the first rows of new pages
+    pub row_breaks: Vec<u32>,
+    /// Manual column breaks: the first columns of new pages
+    pub column_breaks: Vec<u32>,
+    /// Print area (`_xlnm.Print_Area` defined name), the whole used range if empty
+    pub print_area: Vec<Dimensions>,
+    /// Rows (first, last) repeated on each page (`_xlnm.Print_Titles` defined name)
+    pub print_title_rows: Option<(u32, u32)>,
+    /// Columns (first, last) repeated on each page (`_xlnm.Print_Titles` defined name)
+    pub print_title_columns: Option<(u32, u32)>,
+}
+
+impl Default for PageSetup {
+    fn default() -> Self {
+        PageSetup {
+            orientation: Orientation::Default,
+            paper_size: 1,
+            scale: 100,
+            fit_to_pages: None,
+            first_page_number: None,
+            page_order: PageOrder::DownThenOver,
+            black_and_white: false,
+            draft: false,
+            margins: PageMargins::default(),
+            center_horizontally: false,
+            center_vertically: false,
+            print_grid_lines: false,
+            print_headings: false,
+            header_footer: HeaderFooter::default(),
+            row_breaks: Vec::new(),
+            column_breaks: Vec::new(),
+            print_area: Vec::new(),
+            print_title_rows: None,
+            print_title_columns: None,
+        }
+    }
+}
+
+impl PageSetup {
+    /// Set the print area and titles from the `_xlnm.Print_Area` and `_xlnm.Print_Titles`
+    /// defined names referencing `sheet`
+    pub(crate) fn set_defined_names(&mut self, sheet: &str, names: &[(String, String)]) {
+        for (name, formula) in names {
+            match name.strip_prefix("_xlnm.").unwrap_or(name) {
+                "Print_Area" => {
+                    let area = sheet_references(formula, sheet);
+                    if !area.is_empty() {
+                        self.print_area = area;
+                    }
+                }
+                "Print_Titles" => {
+                    for dims in sheet_references(formula, sheet) {
+                        if dims.start.1 == 0 && dims.end.1 == MAX_COLUMN {
+                            self.print_title_rows = Some((dims.start.0, dims.end.0));
+                        } else if dims.start.0 == 0 && dims.end.0 == MAX_ROW {
+                            self.print_title_columns = Some((dims.start.1, dims.end.1));
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Page orientation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Orientation {
+    /// Printer default
+    #[default]
+    Default,
+    /// Portrait
+    Portrait,
+    /// Landscape
+    Landscape,
+}
+
+/// Order in which the pages are printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PageOrder {
+    /// Down the rows, then over the columns
+    #[default]
+    DownThenOver,
+    /// Over the columns, then down the rows
+    OverThenDown,
+}
+
+/// Page margins, in inches
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageMargins {
+    /// Left margin
+    pub left: f64,
+    /// Right margin
+    pub right: f64,
+    /// Top margin
+    pub top: f64,
+    /// Bottom margin
+    pub bottom: f64,
+    /// Header margin
+    pub header: f64,
+    /// Footer margin
+    pub footer: f64,
+}
+
+impl Default for PageMargins {
+    fn default() -> Self {
+        PageMargins {
+            left: 0.7,
+            right: 0.7,
+            top: 0.75,
+            bottom: 0.75,
+            header: 0.3,
+            footer: 0.3,
+        }
+    }
+}
+
+/// Headers and footers of the printed pages
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderFooter {
+    /// Header of odd pages, or of all pages
+    pub odd_header: Option<HeaderFooterText>,
+    /// Footer of odd pages, or of all pages
+    pub odd_footer: Option<HeaderFooterText>,
+    /// Header of even pages
+    pub even_header: Option<HeaderFooterText>,
+    /// Footer of even pages
+    pub even_footer: Option<HeaderFooterText>,
+    /// Header of the first page
+    pub first_header: Option<HeaderFooterText>,
+    /// Footer of the first page
+    pub first_footer: Option<HeaderFooterText>,
+    /// Do even pages have their own header and footer
+    pub different_odd_even: bool,
+    /// Does the first page have its own header and footer
+    pub different_first: bool,
+    /// Are headers and footers scaled with the document
+    pub scale_with_doc: bool,
+    /// Are headers and footers aligned with the page margins
+    pub align_with_margins: bool,
+}
+
+impl Default for HeaderFooter {
+    fn default() -> Self {
+        HeaderFooter {
+            odd_header: None,
+            odd_footer: None,
+            even_header: None,
+            even_footer: None,
+            first_header: None,
+            first_footer: None,
+            different_odd_even: false,
+            different_first: false,
+            scale_with_doc: true,
+            align_with_margins: true,
+        }
+    }
+}
+
+/// A header or footer, split into its sections
+///
+/// Formatting and field codes (e.g. `&P` for the page number, `&B` for bold)
+/// are kept as is in the sections.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderFooterText {
+    /// Left section
+    pub left: String,
+    /// Center section
+    pub center: String,
+    /// Right section
+    pub right: String,
+}
+
+impl HeaderFooterText {
+    /// Split a header or footer into its `&L`, `&C` and `&R` sections
+    ///
+    /// Text before any section code belongs to the center section.
+    ///
+    /// # Examples
+    /// ```
+    /// use calamine::HeaderFooterText;
+    ///
+    /// let text = HeaderFooterText::parse("&LQ&&A&CSales&RPage &P of &N");
+    /// assert_eq!(text.left, "Q&&A");
+    /// assert_eq!(text.center, "Sales");
+    /// assert_eq!(text.right, "Page &P of &N");
+    /// ```
+    pub fn parse(text: &str) -> HeaderFooterText {
+        let mut sections = HeaderFooterText::default();
+        let mut section = &mut sections.center;
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != '&' {
+                section.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('L') => section = &mut sections.left,
+                Some('C') => section = &mut sections.center,
+                Some('R') => section = &mut sections.right,
+                Some('"') => {
+                    // font name, which may contain section codes
+                    section.push_str("&\"");
+                    for c in chars.by_ref() {
+                        section.push(c);
+                        if c == '"' {
+                            break;
+                        }
+                    }
+                }
+                Some(c) => {
+                    section.push('&');
+                    section.push(c);
+                }
+                None => section.push('&'),
+            }
+        }
+        sections
+    }
+
+    /// Parse a header or footer, `None` if empty
+    pub(crate) fn parse_non_empty(text: &str) -> Option<HeaderFooterText> {
+        (!text.is_empty()).then(|| HeaderFooterText::parse(text))
+    }
+}
+
+/// Get the ranges of a defined name formula (e.g. `'My Sheet'!$A$1:$B$2,'My Sheet'!$1:$1`)
+/// which belong to `sheet`
+fn sheet_references(formula: &str, sheet: &str) -> Vec<Dimensions> {
+    split_union(formula)
+        .into_iter()
+        .filter_map(|reference| {
+            let (name, range) = reference.rsplit_once('!')?;
+            let name = match name.strip_prefix('\'') {
+                Some(n) => n.strip_suffix('\'')?.replace("''", "'"),
+                None => name.to_string(),
+            };
+            if name != sheet {
+                return None;
+            }
+            let (start, end) = range.split_once(':').unwrap_or((range, range));
+            let (start, end) = (parse_reference(start)?, parse_reference(end)?);
+            Some(Dimensions {
+                start: (start.0.unwrap_or(0), start.1.unwrap_or(0)),
+                end: (end.0.unwrap_or(MAX_ROW), end.1.unwrap_or(MAX_COLUMN)),
+            })
+        })
+        .collect()
+}
+
+/// Split a formula on the commas which are not in a quoted sheet name
+fn split_union(formula: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in formula.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(formula[start..i].trim());
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(formula[start..].trim());
+    parts
+}
+
+/// Parse a A1 reference, where either the row (`$A`) or the column (`$1`) may be missing
+fn parse_reference(reference: &str) -> Option<(Option<u32>, Option<u32>)> {
+    let reference = reference.replace('$', "");
+    let split = reference
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(reference.len());
+    let (col, row) = reference.split_at(split);
+    let col = if col.is_empty() {
+        None
+    } else {
+        let col = col.bytes().try_fold(0u32, |acc, c| {
+            c.is_ascii_alphabetic()
+                .then(|| acc * 26 + (c.to_ascii_uppercase() - b'A') as u32 + 1)
+        })?;
+        Some(col - 1)
+    };
+    let row = if row.is_empty() {
+        None
+    } else {
+        Some(row.parse::<u32>().ok()?.checked_sub(1)?)
+    };
+    if row.is_none() && col.is_none() {
+        return None;
+    }
+    Some((row, col))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_footer_sections() {
+        let text = HeaderFooterText::parse("Title&R&\"Arial,Bold\"Right&L&&L");
+        assert_eq!(text.left, "&&L");
+        assert_eq!(text.center, "Title");
+        assert_eq!(text.right, "&\"Arial,Bold\"Right");
+        assert_eq!(HeaderFooterText::parse_non_empty(""), None);
+    }
+
+    #[test]
+    fn defined_names() {
+        let names = vec![
+            (
+                "_xlnm.Print_Area".to_string(),
+                "'My, Sheet'!$A$1:$C$10,'My, Sheet'!$E$2".to_string(),
+            ),
+            (
+                "_xlnm.Print_Area".to_string(),
+                "Other!$A$1:$B$2".to_string(),
+            ),
+            (
+                "_xlnm.Print_Titles".to_string(),
+                "'My, Sheet'!$A:$B,'My, Sheet'!$1:$2".to_string(),
+            ),
+        ];
+        let mut setup = PageSetup::default();
+        setup.set_defined_names("My, Sheet", &names);
+        assert_eq!(
+            setup.print_area,
+            vec![
+                Dimensions {
+                    start: (0, 0),
+                    end: (9, 2)
+                },
+                Dimensions {
+                    start: (1, 4),
+                    end: (1, 4)
+                },
+            ]
+        );
+        assert_eq!(setup.print_title_rows, Some((0, 1)));
+        assert_eq!(setup.print_title_columns, Some((0, 1)));
+    }
+}
@@ -0,0 +1,358 @@
+//! Pictures placed on sheets, with their anchors
+
+use std::io::{BufRead, Read, Seek};
+
+use quick_xml::events::Event;
+use quick_xml::Reader as XmlReader;
+use zip::read::ZipArchive;
+use zip::result::ZipError;
+
+use crate::relationships::{attribute, read_relationships, resolve_target, xml_reader};
+
+/// A picture placed on a sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct Picture {
+    /// Name of the sheet containing the picture
+    pub sheet_name: String,
+    /// Name of the picture shape
+    pub name: String,
+    /// Alternative text, if any
+    pub description: Option<String>,
+    /// Position of the picture on the sheet
+    pub anchor: PictureAnchor,
+    /// Displayed size (width, height) in EMUs, if known
+    pub size: Option<(u64, u64)>,
+    /// Image extension (e.g. "png")
+    pub extension: String,
+    /// Image data
+    pub data: Vec<u8>,
+}
+
+/// Position of a picture on a sheet
+#[derive(Debug, Clone, PartialEq)]
+pub enum PictureAnchor {
+    /// Anchored to a top left and a bottom right cell, the picture moves
+    /// and is resized with the cells
+    TwoCell {
+        /// Top left corner
+        from: AnchorMarker,
+        /// Bottom right corner
+        to: AnchorMarker,
+    },
+    /// Anchored to a top left cell, the picture moves with the cell but keeps its size
+    OneCell {
+        /// Top left corner
+        from: AnchorMarker,
+    },
+    /// Fixed position (x, y) in EMUs from the top left corner of the sheet
+    Absolute {
+        /// Horizontal position
+        x: i64,
+        /// Vertical position
+        y: i64,
+    },
+}
+
+/// Cell and offset of a picture corner
+///
+/// Offsets are in EMUs (914400 per inch) for xlsx, xlsb and ods files.
+/// In xls files they are in 1/1024th of the column width and 1/256th of the row height.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AnchorMarker {
+    /// Row (0-based)
+    pub row: u32,
+    /// Column (0-based)
+    pub col: u32,
+    /// Vertical offset within the cell
+    pub row_offset: i64,
+    /// Horizontal offset within the cell
+    pub col_offset: i64,
+}
+
+/// Reads the pictures of the drawings of each sheet (xlsx and xlsb files)
+///
+/// `sheets` are the (name, path) of the sheets
+pub(crate) fn read_sheet_pictures<RS, E>(
+    zip: &mut ZipArchive<RS>,
+    sheets: &[(String, String)],
+) -> Result<Vec<Picture>, E>
+where
+    RS: Read + Seek,
+    E: From<std::io::Error> + From<ZipError> + From<quick_xml::Error>,
+{
+    let mut pics = Vec::new();
+    for (sheet_name, sheet_path) in sheets {
+        for (_, typ, target) in read_relationships::<_, E>(zip, sheet_path)? {
+            if typ.ends_with("/drawing") {
+                let drawing_path = resolve_target(sheet_path, &target);
+                pics.extend(read_drawing_pictures::<_, E>(
+                    zip,
+                    &drawing_path,
+                    sheet_name,
+                )?);
+            }
+        }
+    }
+    Ok(pics)
+}
+
+/// Reads the pictures of a drawing part along with their image data
+fn read_drawing_pictures<RS, E>(
+    zip: &mut ZipArchive<RS>,
+    drawing_path: &str,
+    sheet_name: &str,
+) -> Result<Vec<Picture>, E>
+where
+    RS: Read + Seek,
+    E: From<std::io::Error> + From<ZipError> + From<quick_xml::Error>,
+{
+    let drawing = match zip.by_name(drawing_path) {
+        Ok(f) => parse_drawing(&mut xml_reader(f))?,
+        Err(ZipError::FileNotFound) => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let relationships = read_relationships::<_, E>(zip, drawing_path)?;
+    let mut pics = Vec::with_capacity(drawing.len());
+    for pic in drawing {
+        let Some((_, _, target)) = relationships.iter().find(|(id, ..)| *id == pic.embed) else {
+            continue;
+        };
+        let image_path = resolve_target(drawing_path, target);
+        let mut data = Vec::new();
+        match zip.by_name(&image_path) {
+            Ok(mut f) => f.read_to_end(&mut data)?,
+            // linked, not embedded, image
+            Err(ZipError::FileNotFound) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let extension = image_path
+            .rsplit_once('.')
+            .map_or_else(String::new, |(_, ext)| ext.to_ascii_lowercase());
+        pics.push(Picture {
+            sheet_name: sheet_name.to_string(),
+            name: pic.name,
+            description: pic.description,
+            anchor: pic.anchor,
+            size: pic.size,
+            extension,
+            data,
+        });
+    }
+    Ok(pics)
+}
+
+/// A picture of a DrawingML part, its image being identified by a relationship id
+struct DrawingPicture {
+    name: String,
+    description: Option<String>,
+    anchor: PictureAnchor,
+    size: Option<(u64, u64)>,
+    embed: String,
+}
+
+#[derive(Clone, Copy)]
+enum AnchorKind {
+    TwoCell,
+    OneCell,
+    Absolute,
+}
+
+#[derive(Clone, Copy)]
+enum MarkerField {
+    Col,
+    ColOff,
+    Row,
+    RowOff,
+}
+
+/// Parses a spreadsheet DrawingML part (`xl/drawings/drawingN.xml`) [ECMA-376 20.5]
+///
+/// Only pictures are returned, other shapes and charts are skipped.
+fn parse_drawing<B: BufRead>(
+    xml: &mut XmlReader<B>,
+) -> Result<Vec<DrawingPicture>, quick_xml::Error> {
+    let mut pictures = Vec::new();
+    let mut buf = Vec::new();
+
+    let mut kind = AnchorKind::TwoCell;
+    let mut from = AnchorMarker::default();
+    let mut to = AnchorMarker::default();
+    let mut in_to = false;
+    let mut position = (0, 0);
+    let mut anchor_size = None;
+    let mut field = None;
+    // pictures of the current anchor (there may be several within a group)
+    let mut anchor_pics: Vec<DrawingPicture> = Vec::new();
+    let mut pic: Option<DrawingPicture> = None;
+
+    loop {
+        buf.clear();
+        let (e, is_empty) = match xml.read_event_into(&mut buf)? {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::Text(t) => {
+                if let Some(f) = field {
+                    let v: i64 = t.unescape()?.trim().parse().unwrap_or(0);
+                    let marker = if in_to { &mut to } else { &mut from };
+                    match f {
+                        MarkerField::Col => marker.col = v as u32,
+                        MarkerField::ColOff => marker.col_offset = v,
+                        MarkerField::Row => marker.row = v as u32,
+                        MarkerField::RowOff => marker.row_offset = v,
+                    }
+                }
+                continue;
+            }
+            Event::End(e) => {
+                match e.local_name().as_ref() {
+                    b"col" | b"colOff" | b"row" | b"rowOff" => field = None,
+                    b"pic" => anchor_pics.extend(pic.take()),
+                    b"twoCellAnchor" | b"oneCellAnchor" | b"absoluteAnchor" => {
+                        let anchor = match kind {
+                            AnchorKind::TwoCell => PictureAnchor::TwoCell { from, to },
+                            AnchorKind::OneCell => PictureAnchor::OneCell { from },
+                            AnchorKind::Absolute => PictureAnchor::Absolute {
+                                x: position.0,
+                                y: position.1,
+                            },
+                        };
+                        for mut p in anchor_pics.drain(..) {
+                            p.anchor = anchor.clone();
+                            p.size = anchor_size.or(p.size);
+                            pictures.push(p);
+                        }
+                    }
+                    _ => (),
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        match e.local_name().as_ref() {
+            name @ (b"twoCellAnchor" | b"oneCellAnchor" | b"absoluteAnchor") => {
+                kind = match name {
+                    b"twoCellAnchor" => AnchorKind::TwoCell,
+                    b"oneCellAnchor" => AnchorKind::OneCell,
+                    _ => AnchorKind::Absolute,
+                };
+                from = AnchorMarker::default();
+                to = AnchorMarker::default();
+                position = (0, 0);
+                anchor_size = None;
+                anchor_pics.clear();
+            }
+            b"from" => in_to = false,
+            b"to" => in_to = true,
+            b"col" if !is_empty => field = Some(MarkerField::Col),
+            b"colOff" if !is_empty => field = Some(MarkerField::ColOff),
+            b"row" if !is_empty => field = Some(MarkerField::Row),
+            b"rowOff" if !is_empty => field = Some(MarkerField::RowOff),
+            b"pos" => {
+                position = (
+                    attribute(xml, &e, b"x")?.map_or(0, |v| v.parse().unwrap_or(0)),
+                    attribute(xml, &e, b"y")?.map_or(0, |v| v.parse().unwrap_or(0)),
+                );
+            }
+            b"ext" => {
+                // `ext` is also used, without size, for extension lists
+                let cx = attribute(xml, &e, b"cx")?.and_then(|v| v.parse().ok());
+                let cy = attribute(xml, &e, b"cy")?.and_then(|v| v.parse().ok());
+                if let (Some(cx), Some(cy)) = (cx, cy) {
+                    match pic {
+                        Some(ref mut p) => p.size = Some((cx, cy)),
+                        None => anchor_size = Some((cx, cy)),
+                    }
+                }
+            }
+            b"pic" if !is_empty => {
+                pic = Some(DrawingPicture {
+                    name: String::new(),
+                    description: None,
+                    anchor: PictureAnchor::Absolute { x: 0, y: 0 },
+                    size: None,
+                    embed: String::new(),
+                })
+            }
+            b"cNvPr" => {
+                if let Some(ref mut p) = pic {
+                    p.name = attribute(xml, &e, b"name")?.unwrap_or_default();
+                    p.description = attribute(xml, &e, b"descr")?;
+                }
+            }
+            b"blip" => {
+                if let Some(ref mut p) = pic {
+                    p.embed = attribute(xml, &e, b"embed")?.unwrap_or_default();
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(pictures)
+}
+
+/// Converts an ODF length (e.g. "1.5cm") into EMUs
+pub(crate) fn length_to_emu(length: &str) -> Option<i64> {
+    let length = length.trim();
+    let split = length
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(length.len());
+    let (value, unit) = length.split_at(split);
+    let value: f64 = value.parse().ok()?;
+    let emu_per_unit = match unit {
+        "cm" => 360_000.,
+        "mm" => 36_000.,
+        "in" => 914_400.,
+        "pt" => 12_700.,
+        "pc" => 152_400.,
+        "px" => 9_525.,
+        _ => return None,
+    };
+    Some((value * emu_per_unit).round() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drawing_anchors() {
+        let xml = r#"<xdr:wsDr xmlns:xdr="http://schemas.openxmlformats.org/drawingml/2006/spreadsheetDrawing" xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">
+<xdr:oneCellAnchor><xdr:from><xdr:col>2</xdr:col><xdr:colOff>9525</xdr:colOff><xdr:row>4</xdr:row><xdr:rowOff>19050</xdr:rowOff></xdr:from><xdr:ext cx="952500" cy="476250"/>
+<xdr:pic><xdr:nvPicPr><xdr:cNvPr id="2" name="Logo" descr="Company logo"/><xdr:cNvPicPr/></xdr:nvPicPr><xdr:blipFill><a:blip r:embed="rId2"/></xdr:blipFill><xdr:spPr><a:xfrm><a:off x="0" y="0"/><a:ext cx="1" cy="1"/></a:xfrm></xdr:spPr></xdr:pic><xdr:clientData/></xdr:oneCellAnchor>
+<xdr:absoluteAnchor><xdr:pos x="100" y="200"/><xdr:ext cx="300" cy="400"/>
+<xdr:sp><xdr:nvSpPr><xdr:cNvPr id="3" name="Rectangle"/></xdr:nvSpPr></xdr:sp><xdr:clientData/></xdr:absoluteAnchor>
+<xdr:absoluteAnchor><xdr:pos x="100" y="200"/><xdr:ext cx="300" cy="400"/>
+<xdr:pic><xdr:nvPicPr><xdr:cNvPr id="4" name="Stamp"/></xdr:nvPicPr><xdr:blipFill><a:blip r:embed="rId1"/></xdr:blipFill></xdr:pic><xdr:clientData/></xdr:absoluteAnchor>
+</xdr:wsDr>"#;
+        let pics = parse_drawing(&mut XmlReader::from_str(xml)).unwrap();
+        assert_eq!(pics.len(), 2);
+        assert_eq!(pics[0].name, "Logo");
+        assert_eq!(pics[0].description.as_deref(), Some("Company logo"));
+        assert_eq!(pics[0].embed, "rId2");
+        assert_eq!(pics[0].size, Some((952500, 476250)));
+        assert_eq!(
+            pics[0].anchor,
+            PictureAnchor::OneCell {
+                from: AnchorMarker {
+                    row: 4,
+                    col: 2,
+                    row_offset: 19050,
+                    col_offset: 9525,
+                }
+            }
+        );
+        assert_eq!(pics[1].name, "Stamp");
+        assert_eq!(pics[1].description, None);
+        assert_eq!(pics[1].anchor, PictureAnchor::Absolute { x: 100, y: 200 });
+        assert_eq!(pics[1].size, Some((300, 400)));
+    }
+
+    #[test]
+    fn odf_lengths() {
+        assert_eq!(length_to_emu("1cm"), Some(360_000));
+        assert_eq!(length_to_emu("0.5in"), Some(457_200));
+        assert_eq!(length_to_emu("-2mm"), Some(-72_000));
+        assert_eq!(length_to_emu("12"), None);
+    }
+}
@@ -0,0 +1,120 @@
+//! Pivot tables and their caches
+
+use crate::Dimensions;
+
+/// A pivot table placed on a worksheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct PivotTable {
+    /// Name of the pivot table
+    pub name: String,
+    /// Name of the sheet containing the pivot table
+    pub sheet_name: String,
+    /// Range of the pivot table, without its page fields
+    pub location: Dimensions,
+    /// Identifier of the pivot cache, to read its records
+    pub cache_id: u32,
+    /// Data the pivot cache was built from
+    pub source: PivotSource,
+    /// Names of the fields of the pivot cache, which the other fields refer to by index
+    pub fields: Vec<String>,
+    /// Fields shown as rows, from the outermost
+    pub row_fields: Vec<PivotAxisField>,
+    /// Fields shown as columns, from the outermost
+    pub column_fields: Vec<PivotAxisField>,
+    /// Fields filtering the whole pivot table
+    pub page_fields: Vec<PivotPageField>,
+    /// Aggregated fields
+    pub data_fields: Vec<PivotDataField>,
+}
+
+/// Source of the data of a pivot cache
+#[derive(Debug, Clone, PartialEq)]
+pub enum PivotSource {
+    /// Range of a worksheet, including its header row
+    Range {
+        /// Sheet name
+        sheet: String,
+        /// Range
+        range: Dimensions,
+    },
+    /// Table or defined name
+    Name(String),
+    /// Source not modeled here (e.g. "external", "consolidation" or "scenario")
+    Other(String),
+}
+
+/// A field of the rows or columns of a pivot table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PivotAxisField {
+    /// Index in `PivotTable::fields`
+    Field(usize),
+    /// The data fields, when there are several of them
+    Values,
+}
+
+/// A field filtering a pivot table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PivotPageField {
+    /// Index in `PivotTable::fields`
+    pub field: usize,
+    /// Index of the selected item of the field, `None` if all items are selected
+    pub item: Option<u32>,
+}
+
+/// A field aggregated in the values area of a pivot table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PivotDataField {
+    /// Name displayed for the values (e.g. "Sum of Amount")
+    pub name: Option<String>,
+    /// Index in `PivotTable::fields`
+    pub field: usize,
+    /// Aggregation function
+    pub function: PivotFunction,
+}
+
+/// Aggregation function of a `PivotDataField`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PivotFunction {
+    /// Sum
+    #[default]
+    Sum,
+    /// Number of values
+    Count,
+    /// Number of numbers
+    CountNumbers,
+    /// Average
+    Average,
+    /// Maximum
+    Max,
+    /// Minimum
+    Min,
+    /// Product
+    Product,
+    /// Standard deviation of a sample
+    StdDev,
+    /// Standard deviation of a population
+    StdDevP,
+    /// Variance of a sample
+    Var,
+    /// Variance of a population
+    VarP,
+}
+
+impl PivotFunction {
+    /// Convert the xlsx `subtotal` attribute
+    pub(crate) fn from_name(name: &[u8]) -> PivotFunction {
+        match name {
+            b"count" => PivotFunction::Count,
+            b"countNums" => PivotFunction::CountNumbers,
+            b"average" => PivotFunction::Average,
+            b"max" => PivotFunction::Max,
+            b"min" => PivotFunction::Min,
+            b"product" => PivotFunction::Product,
+            b"stdDev" => PivotFunction::StdDev,
+            b"stdDevp" => PivotFunction::StdDevP,
+            b"var" => PivotFunction::Var,
+            b"varp" => PivotFunction::VarP,
+            _ => PivotFunction::Sum,
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Sheet and workbook protection
+
+#[cfg(feature = "password")]
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/// Protection of a sheet: the actions allowed to users and the password hash
+///
+/// The default value matches a sheet protected with Excel default options:
+/// only the selection of cells is allowed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetProtection {
+    /// Hash of the password, if any
+    pub password: Option<PasswordHash>,
+    /// Can locked cells be selected
+    pub select_locked_cells: bool,
+    /// Can unlocked cells be selected
+    pub select_unlocked_cells: bool,
+    /// Can cells be formatted
+    pub format_cells: bool,
+    /// Can columns be formatted
+    pub format_columns: bool,
+    /// Can rows be formatted
+    pub format_rows: bool,
+    /// Can columns be inserted
+    pub insert_columns: bool,
+    /// Can rows be inserted
+    pub insert_rows: bool,
+    /// Can hyperlinks be inserted
+    pub insert_hyperlinks: bool,
+    /// Can columns be deleted
+    pub delete_columns: bool,
+    /// Can rows be deleted
+    pub delete_rows: bool,
+    /// Can ranges be sorted
+    pub sort: bool,
+    /// Can autofilters be used
+    pub auto_filter: bool,
+    /// Can pivot tables be used
+    pub pivot_tables: bool,
+    /// Can objects (shapes, charts, comments) be edited
+    pub edit_objects: bool,
+    /// Can scenarios be edited
+    pub edit_scenarios: bool,
+}
+
+impl Default for SheetProtection {
+    fn default() -> Self {
+        SheetProtection {
+            password: None,
+            select_locked_cells: true,
+            select_unlocked_cells: true,
+            format_cells: false,
+            format_columns: false,
+            format_rows: false,
+            insert_columns: false,
+            insert_rows: false,
+            insert_hyperlinks: false,
+            delete_columns: false,
+            delete_rows: false,
+            sort: false,
+            auto_filter: false,
+            pivot_tables: false,
+            edit_objects: false,
+            edit_scenarios: false,
+        }
+    }
+}
+
+impl SheetProtection {
+    /// Set the allowed actions from the `iprot` flags of xls EnhancedProtection
+    ///
+    /// Bits are set for allowed actions.
+    pub(crate) fn set_allowed_flags(&mut self, flags: u16) {
+        let allowed = |bit: u16| flags & (1 << bit) != 0;
+        self.edit_objects = allowed(0);
+        self.edit_scenarios = allowed(1);
+        self.format_cells = allowed(2);
+        self.format_columns = allowed(3);
+        self.format_rows = allowed(4);
+        self.insert_columns = allowed(5);
+        self.insert_rows = allowed(6);
+        self.insert_hyperlinks = allowed(7);
+        self.delete_columns = allowed(8);
+        self.delete_rows = allowed(9);
+        self.select_locked_cells = allowed(10);
+        self.sort = allowed(11);
+        self.auto_filter = allowed(12);
+        self.pivot_tables = allowed(13);
+        self.select_unlocked_cells = allowed(14);
+    }
+}
+
+/// Protection of the workbook structure and windows
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkbookProtection {
+    /// Are sheets prevented from being added, moved, renamed, hidden or deleted
+    pub lock_structure: bool,
+    /// Are the workbook windows prevented from being moved or resized
+    pub lock_windows: bool,
+    /// Hash of the password, if any
+    pub password: Option<PasswordHash>,
+}
+
+/// Hash of a protection password
+#[derive(Debug, Clone, PartialEq)]
+pub enum PasswordHash {
+    /// 16 bits hash of legacy Excel protections
+    Legacy(u16),
+    /// Salted hash, hashed again `spin_count` times (e.g. Excel 2013+ "SHA-512")
+    Iterated {
+        /// Hash algorithm name (e.g. "SHA-512")
+        algorithm: String,
+        /// Hash value
+        hash: Vec<u8>,
+        /// Salt, prepended to the password before hashing
+        salt: Vec<u8>,
+        /// Number of additional hash iterations
+        spin_count: u32,
+    },
+    /// Single hash of the UTF-8 password (ods `table:protection-key`)
+    Digest {
+        /// Hash algorithm, usually an URI (e.g. `http://www.w3.org/2000/09/xmldsig#sha256`)
+        algorithm: String,
+        /// Hash value
+        hash: Vec<u8>,
+    },
+}
+
+impl PasswordHash {
+    /// Legacy hash of binary files, 0 meaning there is no password
+    pub(crate) fn from_legacy(hash: u16) -> Option<PasswordHash> {
+        (hash != 0).then_some(PasswordHash::Legacy(hash))
+    }
+
+    /// Check a candidate password against the hash
+    ///
+    /// Returns `None` if the hash algorithm is not supported (only SHA-256,
+    /// SHA-384 and SHA-512 are, in addition to the legacy hash). SHA hashes are
+    /// only supported with the `password` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use calamine::PasswordHash;
+    ///
+    /// let hash = PasswordHash::Legacy(0xCBEB);
+    /// assert_eq!(hash.verify("test"), Some(true));
+    /// assert_eq!(hash.verify("Test"), Some(false));
+    /// ```
+    pub fn verify(&self, password: &str) -> Option<bool> {
+        match self {
+            PasswordHash::Legacy(hash) => Some(legacy_hash(password) == *hash),
+            #[cfg(feature = "password")]
+            PasswordHash::Iterated {
+                algorithm,
+                hash,
+                salt,
+                spin_count,
+            } => {
+                let password: Vec<u8> =
+                    password.encode_utf16().flat_map(u16::to_le_bytes).collect();
+                let computed = match hash_algorithm(algorithm)? {
+                    HashAlgorithm::Sha256 => iterated_hash::<Sha256>(salt, &password, *spin_count),
+                    HashAlgorithm::Sha384 => iterated_hash::<Sha384>(salt, &password, *spin_count),
+                    HashAlgorithm::Sha512 => iterated_hash::<Sha512>(salt, &password, *spin_count),
+                };
+                Some(computed == *hash)
+            }
+            #[cfg(feature = "password")]
+            PasswordHash::Digest { algorithm, hash } => {
+                let computed = match hash_algorithm(algorithm)? {
+                    HashAlgorithm::Sha256 => Sha256::digest(password).to_vec(),
+                    HashAlgorithm::Sha384 => Sha384::digest(password).to_vec(),
+                    HashAlgorithm::Sha512 => Sha512::digest(password).to_vec(),
+                };
+                Some(computed == *hash)
+            }
+            #[cfg(not(feature = "password"))]
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "password")]
+enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+/// Recognizes both Office ("SHA-512") and URI ("...#sha256") algorithm names
+#[cfg(feature = "password")]
+fn hash_algorithm(name: &str) -> Option<HashAlgorithm> {
+    let name = name.to_ascii_lowercase().replace('-', "");
+    if name.ends_with("sha256") {
+        Some(HashAlgorithm::Sha256)
+    } else if name.ends_with("sha384") {
+        Some(HashAlgorithm::Sha384)
+    } else if name.ends_with("sha512") {
+        Some(HashAlgorithm::Sha512)
+    } else {
+        None
+    }
+}
+
+/// [MS-OFFCRYPTO 2.4.2.4]: H0 = H(salt + password), Hn = H(Hn-1 + iterator)
+#[cfg(feature = "password")]
+fn iterated_hash<D: Digest>(salt: &[u8], password: &[u8], spin_count: u32) -> Vec<u8> {
+    let mut hash = D::new()
+        .chain_update(salt)
+        .chain_update(password)
+        .finalize();
+    for i in 0..spin_count {
+        hash = D::new()
+            .chain_update(&hash)
+            .chain_update(i.to_le_bytes())
+            .finalize();
+    }
+    hash.to_vec()
+}
+
+/// Legacy password hash [MS-OFFCRYPTO 2.3.7.1], for single byte characters
+fn legacy_hash(password: &str) -> u16 {
+    if password.is_empty() {
+        return 0;
+    }
+    let bytes: Vec<u8> = password.chars().map(|c| c as u32 as u8).collect();
+    let mut hash: u16 = 0;
+    for &b in bytes.iter().rev() {
+        hash = ((hash >> 14) & 0x01) | ((hash << 1) & 0x7FFF);
+        hash ^= b as u16;
+    }
+    hash = ((hash >> 14) & 0x01) | ((hash << 1) & 0x7FFF);
+    hash ^ (bytes.len() as u16) ^ 0xCE4B
+}
+
+/// Decodes standard base64, ignoring whitespaces
+pub(crate) fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return None,
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64() {
+        assert_eq!(decode_base64("TWFu").unwrap(), b"Man");
+        assert_eq!(decode_base64("TWE=").unwrap(), b"Ma");
+        assert_eq!(decode_base64("TQ==").unwrap(), b"M");
+        assert_eq!(decode_base64("T Q\n=="), Some(b"M".to_vec()));
+        assert_eq!(decode_base64("T*=="), None);
+    }
+
+    #[test]
+    fn legacy() {
+        assert_eq!(legacy_hash("password"), 0x83AF);
+        assert_eq!(legacy_hash(""), 0);
+    }
+}
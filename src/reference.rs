@@ -0,0 +1,519 @@
+//! Cell references in the A1 and R1C1 notations
+//!
+//! ```
+//! use calamine::reference::{a1_to_r1c1, r1c1_to_a1, Reference};
+//!
+//! let reference: Reference = "'My Sheet'!$A$1:B2".parse().unwrap();
+//! assert_eq!(reference.sheet.as_deref(), Some("My Sheet"));
+//! // R1C1 references are relative to a cell, here C3
+//! assert_eq!(reference.to_r1c1((2, 2)), "'My Sheet'!R1C1:R[-1]C[-1]");
+//!
+//! let formula = a1_to_r1c1("=SUM(A1:A2)*$B$1", (2, 0)).unwrap();
+//! assert_eq!(formula, "=SUM(R[-2]C:R[-1]C)*R1C2");
+//! // the same formula, one row below
+//! assert_eq!(r1c1_to_a1(&formula, (3, 0)).unwrap(), "=SUM(A2:A3)*$B$1");
+//! ```
+
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use crate::formula::{lex, FormulaError, Token};
+use crate::utils::push_column;
+
+/// Last row of a worksheet, 0-based
+const MAX_ROW: u32 = 1_048_575;
+/// Last column of a worksheet, 0-based
+const MAX_COL: u32 = 16_383;
+
+/// A cell, or a whole row or column, of a reference
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CellRef {
+    /// Row, 0-based, `None` for whole columns
+    pub row: Option<u32>,
+    /// Column, 0-based, `None` for whole rows
+    pub col: Option<u32>,
+    /// Is the row absolute (`A$1`)
+    pub absolute_row: bool,
+    /// Is the column absolute (`$A1`)
+    pub absolute_col: bool,
+}
+
+impl CellRef {
+    /// A cell, with a relative row and column (`A1`)
+    pub fn new(row: u32, col: u32) -> CellRef {
+        CellRef {
+            row: Some(row),
+            col: Some(col),
+            ..CellRef::default()
+        }
+    }
+
+    /// Write the cell in the R1C1 notation, relative to the `base` cell
+    ///
+    /// Absolute rows and columns are written `R1C1`, relative ones `R[-1]C[1]` (or just `R` and
+    /// `C` in the same row or column).
+    pub fn to_r1c1(&self, base: (u32, u32)) -> String {
+        let mut s = String::new();
+        let parts = [
+            ('R', self.row, self.absolute_row, base.0),
+            ('C', self.col, self.absolute_col, base.1),
+        ];
+        for (letter, index, absolute, base) in parts {
+            let Some(index) = index else {
+                continue;
+            };
+            s.push(letter);
+            if absolute {
+                let _ = write!(s, "{}", index as u64 + 1);
+            } else if index != base {
+                let _ = write!(s, "[{}]", index as i64 - base as i64);
+            }
+        }
+        s
+    }
+
+    /// Decode a BIFF (xls and xlsb) reference, whose column holds the relative flags
+    pub(crate) fn from_biff(row: u32, col: u16) -> CellRef {
+        CellRef {
+            row: Some(row),
+            col: Some((col & 0x3FFF) as u32),
+            absolute_row: col & 0x8000 == 0,
+            absolute_col: col & 0x4000 == 0,
+        }
+    }
+}
+
+impl fmt::Display for CellRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(col) = self.col {
+            if self.absolute_col {
+                f.write_str("$")?;
+            }
+            let mut s = String::new();
+            push_column(col, &mut s);
+            f.write_str(&s)?;
+        }
+        if let Some(row) = self.row {
+            if self.absolute_row {
+                f.write_str("$")?;
+            }
+            write!(f, "{}", row as u64 + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Cells of a reference
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefTarget {
+    /// A single cell
+    Cell(CellRef),
+    /// An area, from its first to its last cell
+    Area(CellRef, CellRef),
+    /// A deleted reference, `#REF!`
+    Invalid,
+}
+
+impl RefTarget {
+    /// Decode a BIFF area, showing whole rows and columns as `1:1` and `A:A`
+    pub(crate) fn from_biff_area(
+        rows: (u32, u32),
+        cols: (u16, u16),
+        max_row: u32,
+        max_col: u32,
+    ) -> RefTarget {
+        let mut first = CellRef::from_biff(rows.0, cols.0);
+        let mut last = CellRef::from_biff(rows.1, cols.1);
+        if first.row == Some(0) && last.row == Some(max_row) {
+            for cell in [&mut first, &mut last] {
+                cell.row = None;
+                cell.absolute_row = false;
+            }
+        } else if first.col == Some(0) && last.col == Some(max_col) {
+            for cell in [&mut first, &mut last] {
+                cell.col = None;
+                cell.absolute_col = false;
+            }
+        }
+        RefTarget::Area(first, last)
+    }
+}
+
+/// A reference to a cell or an area, with an optional sheet prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// Sheet, unquoted, e.g. `Sheet1`, `Sheet1:Sheet3` (3D references) or `[1]Sheet1`
+    pub sheet: Option<String>,
+    /// Referenced cells
+    pub target: RefTarget,
+}
+
+impl Reference {
+    /// Parse a reference in the R1C1 notation, relative to the `base` cell
+    ///
+    /// A single row or column, e.g. `R2` or `C[1]`, is a whole row or column. Fails with
+    /// [`FormulaError::InvalidReference`] if the reference is outside of the sheet.
+    pub fn from_r1c1(s: &str, base: (u32, u32)) -> Result<Reference, FormulaError> {
+        single_reference(s, Some(base))
+    }
+
+    /// Write the reference in the R1C1 notation, relative to the `base` cell
+    pub fn to_r1c1(&self, base: (u32, u32)) -> String {
+        let mut s = String::new();
+        if let Some(sheet) = &self.sheet {
+            let _ = write_sheet(&mut s, sheet);
+        }
+        match &self.target {
+            RefTarget::Cell(cell) => s.push_str(&cell.to_r1c1(base)),
+            // a single whole row or column
+            RefTarget::Area(first, last)
+                if first == last && (first.row.is_none() || first.col.is_none()) =>
+            {
+                s.push_str(&first.to_r1c1(base))
+            }
+            RefTarget::Area(first, last) => {
+                s.push_str(&first.to_r1c1(base));
+                s.push(':');
+                s.push_str(&last.to_r1c1(base));
+            }
+            RefTarget::Invalid => s.push_str("#REF!"),
+        }
+        s
+    }
+}
+
+impl FromStr for Reference {
+    type Err = FormulaError;
+
+    /// Parse a reference in the A1 notation, e.g. `A1`, `$A$1:B2`, `A:A`, `1:1`,
+    /// `'My Sheet'!A1` or `Sheet1:Sheet3!A1`
+    fn from_str(s: &str) -> Result<Reference, FormulaError> {
+        single_reference(s, None)
+    }
+}
+
+impl FromStr for CellRef {
+    type Err = FormulaError;
+
+    /// Parse a cell in the A1 notation, e.g. `B2` or `$B$2`
+    fn from_str(s: &str) -> Result<CellRef, FormulaError> {
+        parse_cell(s).ok_or_else(|| FormulaError::InvalidReference(s.to_string()))
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(sheet) = &self.sheet {
+            write_sheet(f, sheet)?;
+        }
+        match &self.target {
+            RefTarget::Cell(cell) => write!(f, "{cell}"),
+            RefTarget::Area(first, last) => write!(f, "{first}:{last}"),
+            RefTarget::Invalid => f.write_str("#REF!"),
+        }
+    }
+}
+
+/// Write a sheet prefix, quoted if needed, with its `!`
+pub(crate) fn write_sheet<W: fmt::Write>(f: &mut W, sheet: &str) -> fmt::Result {
+    if needs_quotes(sheet) {
+        write!(f, "'{}'!", sheet.replace('\'', "''"))
+    } else {
+        write!(f, "{sheet}!")
+    }
+}
+
+/// Does a sheet prefix need to be quoted, e.g. `'My Sheet'!A1` or `'[My Book.xlsx]Sheet1'!A1`
+fn needs_quotes(sheet: &str) -> bool {
+    let (book, sheets) = match sheet.strip_prefix('[').and_then(|s| s.split_once(']')) {
+        Some((book, sheets)) => (Some(book), sheets),
+        None => (None, sheet),
+    };
+    if book.map_or(false, |b| !b.chars().all(is_name_char)) {
+        return true;
+    }
+    if sheets.is_empty() {
+        return book.is_none();
+    }
+    sheets.split(':').any(|s| {
+        s.is_empty()
+            || !s.chars().all(is_name_char)
+            || s.starts_with(|c: char| c.is_ascii_digit())
+            || parse_cell(s).is_some()
+            || is_r1c1(s)
+    })
+}
+
+/// Is the (not empty) text a R1C1 reference, e.g. `R1C1`, `R2` or `C`
+fn is_r1c1(s: &str) -> bool {
+    let s = s.to_ascii_uppercase();
+    let digits = |c: char| c.is_ascii_digit();
+    let rest = s
+        .strip_prefix('R')
+        .map_or(&*s, |r| r.trim_start_matches(digits));
+    let rest = rest
+        .strip_prefix('C')
+        .map_or(rest, |r| r.trim_start_matches(digits));
+    rest.is_empty()
+}
+
+/// Can the character be part of an unquoted sheet name
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+/// Parse a cell reference, e.g. `A1` or `$B$2`
+pub(crate) fn parse_cell(s: &str) -> Option<CellRef> {
+    let (col, rest) = split_column(s)?;
+    let row = parse_row(rest)?;
+    Some(CellRef {
+        col: col.col,
+        absolute_col: col.absolute_col,
+        ..row
+    })
+}
+
+/// Parse a whole column, e.g. `A` or `$B`
+pub(crate) fn parse_column(s: &str) -> Option<CellRef> {
+    match split_column(s)? {
+        (col, "") => Some(col),
+        _ => None,
+    }
+}
+
+/// Parse the column of a reference, returning the rest of the text
+fn split_column(s: &str) -> Option<(CellRef, &str)> {
+    let (absolute_col, s) = match s.strip_prefix('$') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let len = s.bytes().take_while(u8::is_ascii_alphabetic).count();
+    if len == 0 || len > 3 {
+        return None;
+    }
+    let col = s[..len].bytes().fold(0, |col, b| {
+        col * 26 + (b.to_ascii_uppercase() - b'A') as u32 + 1
+    }) - 1;
+    if col >= 16_384 {
+        return None;
+    }
+    let cell = CellRef {
+        col: Some(col),
+        absolute_col,
+        ..CellRef::default()
+    };
+    Some((cell, &s[len..]))
+}
+
+/// Parse a whole row, e.g. `1` or `$2`
+pub(crate) fn parse_row(s: &str) -> Option<CellRef> {
+    let (absolute_row, s) = match s.strip_prefix('$') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let row: u32 = s.parse().ok()?;
+    if row == 0 || row > 1_048_576 {
+        return None;
+    }
+    Some(CellRef {
+        row: Some(row - 1),
+        absolute_row,
+        ..CellRef::default()
+    })
+}
+
+/// Parse the row (`R`) or column (`C`) of a R1C1 reference, returning it with its absolute flag
+/// and the rest of the text
+///
+/// `R2` is absolute, `R[-1]` relative to `base` and `R` alone is the row of `base`. The index
+/// is `None` when it is outside of the sheet.
+fn split_r1c1(s: &str, letter: char, base: u32, max: u32) -> Option<(Option<u32>, bool, &str)> {
+    let rest = s
+        .strip_prefix(letter)
+        .or_else(|| s.strip_prefix(letter.to_ascii_lowercase()))?;
+    if let Some(inner) = rest.strip_prefix('[') {
+        let (offset, rest) = inner.split_once(']')?;
+        let digits = offset.strip_prefix(['-', '+']).unwrap_or(offset);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let index = offset
+            .parse::<i64>()
+            .ok()
+            .and_then(|offset| offset.checked_add(base as i64))
+            .filter(|index| (0..=max as i64).contains(index))
+            .map(|index| index as u32);
+        return Some((index, false, rest));
+    }
+    let len = rest.bytes().take_while(u8::is_ascii_digit).count();
+    if len == 0 {
+        return Some(((base <= max).then_some(base), false, rest));
+    }
+    let n: u32 = rest[..len].parse().unwrap_or(u32::MAX);
+    let index = (1..=max + 1).contains(&n).then(|| n - 1);
+    Some((index, true, &rest[len..]))
+}
+
+/// Parse a cell, a whole row or a whole column in the R1C1 notation, relative to `base`
+///
+/// Fails if the reference is outside of the sheet, e.g. `R[-1]` on the first row.
+pub(crate) fn parse_r1c1(s: &str, base: (u32, u32)) -> Result<Option<CellRef>, FormulaError> {
+    let outside = || FormulaError::InvalidReference(s.to_string());
+    let mut cell = CellRef::default();
+    let mut rest = s;
+    if let Some((row, absolute, r)) = split_r1c1(rest, 'R', base.0, MAX_ROW) {
+        cell.row = Some(row.ok_or_else(outside)?);
+        cell.absolute_row = absolute;
+        rest = r;
+    }
+    if let Some((col, absolute, r)) = split_r1c1(rest, 'C', base.1, MAX_COL) {
+        cell.col = Some(col.ok_or_else(outside)?);
+        cell.absolute_col = absolute;
+        rest = r;
+    }
+    Ok((rest.is_empty() && rest.len() < s.len()).then_some(cell))
+}
+
+/// Parse a text made of a single reference
+fn single_reference(s: &str, r1c1: Option<(u32, u32)>) -> Result<Reference, FormulaError> {
+    match lex(s, r1c1).map(|mut tokens| tokens.pop().filter(|_| tokens.is_empty())) {
+        Ok(Some((Token::Reference(r), ..))) => Ok(r),
+        _ => Err(FormulaError::InvalidReference(s.to_string())),
+    }
+}
+
+/// Name of a column, e.g. `A` for 0 or `AA` for 26
+pub fn column_name(col: u32) -> String {
+    let mut s = String::new();
+    push_column(col, &mut s);
+    s
+}
+
+/// Index of a column, from its name, e.g. 27 for `AB`
+pub fn column_index(name: &str) -> Option<u32> {
+    match split_column(name)? {
+        (CellRef { col, .. }, "") if !name.starts_with('$') => col,
+        _ => None,
+    }
+}
+
+/// Convert a formula from the A1 notation to the R1C1 notation, relative to the `base` cell
+///
+/// Only the references are rewritten, the rest of the formula is kept as is. Formulas of
+/// neighbouring cells which only differ by their relative references, e.g. copied formulas,
+/// have the same R1C1 formula.
+pub fn a1_to_r1c1(formula: &str, base: (u32, u32)) -> Result<String, FormulaError> {
+    convert(formula, None, |r| r.to_r1c1(base))
+}
+
+/// Convert a formula from the R1C1 notation to the A1 notation, relative to the `base` cell
+///
+/// Only the references are rewritten, the rest of the formula is kept as is. Fails with
+/// [`FormulaError::InvalidReference`] if a reference is outside of the sheet, e.g. `R[-1]C`
+/// on the first row.
+pub fn r1c1_to_a1(formula: &str, base: (u32, u32)) -> Result<String, FormulaError> {
+    convert(formula, Some(base), |r| r.to_string())
+}
+
+/// Rewrite the references of a formula
+fn convert<F: Fn(&Reference) -> String>(
+    formula: &str,
+    r1c1: Option<(u32, u32)>,
+    write: F,
+) -> Result<String, FormulaError> {
+    let (prefix, body) = match formula.strip_prefix('=') {
+        Some(body) => ("=", body),
+        None => ("", formula),
+    };
+    let chars: Vec<char> = body.chars().collect();
+    let mut converted = prefix.to_string();
+    let mut last = 0;
+    for (token, start, end) in lex(body, r1c1)? {
+        if let Token::Reference(r) = token {
+            converted.extend(&chars[last..start]);
+            converted.push_str(&write(&r));
+            last = end;
+        }
+    }
+    converted.extend(&chars[last..]);
+    Ok(converted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a1() {
+        for s in [
+            "A1",
+            "$A$1:B$2",
+            "A:$C",
+            "$1:3",
+            "'My Sheet'!A1:B2",
+            "Sheet1:Sheet3!A1",
+        ] {
+            assert_eq!(s.parse::<Reference>().unwrap().to_string(), s);
+        }
+        assert!("A1+1".parse::<Reference>().is_err());
+        assert!("Name".parse::<Reference>().is_err());
+        assert_eq!("$B$2".parse::<CellRef>().unwrap().to_r1c1((0, 0)), "R2C2");
+        assert_eq!(column_name(27), "AB");
+        assert_eq!(column_index("ab"), Some(27));
+        assert_eq!(column_index("A1"), None);
+    }
+
+    #[test]
+    fn r1c1() {
+        let base = (4, 2);
+        for (r1c1, a1) in [
+            ("RC", "C5"),
+            ("R1C1", "$A$1"),
+            ("R[-1]C[2]", "E4"),
+            ("R2C[-2]:R[1]C", "A$2:C6"),
+            ("C", "C:C"),
+            ("C1:C[1]", "$A:D"),
+            ("R", "5:5"),
+            ("'My Sheet'!R[1]", "'My Sheet'!6:6"),
+        ] {
+            let reference = Reference::from_r1c1(r1c1, base).unwrap();
+            assert_eq!(reference.to_string(), a1);
+            assert_eq!(reference.to_r1c1(base), r1c1);
+        }
+        assert!(Reference::from_r1c1("R[-5]C", base).is_err());
+        assert!(Reference::from_r1c1("RC[16382]", base).is_err());
+        assert!(Reference::from_r1c1("R1048577C1", base).is_err());
+        assert!(Reference::from_r1c1("R[99999999999999999999]", base).is_err());
+        assert!(Reference::from_r1c1("RC", (u32::MAX, 0)).is_err());
+        assert!(Reference::from_r1c1("A1", base).is_err());
+    }
+
+    #[test]
+    fn formulas() {
+        let formula = "=IF(A1>$B$2,\"A1\",Sheet2!C:C)+Rate+SUM(1:2)";
+        let r1c1 = a1_to_r1c1(formula, (1, 1)).unwrap();
+        assert_eq!(
+            r1c1,
+            "=IF(R[-1]C[-1]>R2C2,\"A1\",Sheet2!C[1])+Rate+SUM(R[-1]:R)"
+        );
+        assert_eq!(r1c1_to_a1(&r1c1, (1, 1)).unwrap(), formula);
+        // R1C1 function and names are not references
+        assert_eq!(
+            r1c1_to_a1("ROUND(R[1]C,2)+RC2+R1C1X", (0, 0)).unwrap(),
+            "ROUND(A2,2)+$B1+R1C1X"
+        );
+        assert!(matches!(
+            r1c1_to_a1("=SUM(R[-1]C:RC)", (0, 0)),
+            Err(FormulaError::InvalidReference(r)) if r == "R[-1]C"
+        ));
+        let cell = CellRef {
+            row: Some(u32::MAX),
+            col: Some(u32::MAX),
+            ..CellRef::default()
+        };
+        assert_eq!(cell.to_string(), "MWLQKWV4294967296");
+    }
+}
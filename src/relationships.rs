@@ -0,0 +1,132 @@
+//! Relationships between the parts of Office Open XML packages (xlsx and xlsb)
+
+use std::io::{BufRead, BufReader, Read, Seek};
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader as XmlReader;
+use zip::read::{ZipArchive, ZipFile};
+use zip::result::ZipError;
+
+/// Reads the relationships of a part (e.g. `xl/worksheets/_rels/sheet1.xml.rels`
+/// for `xl/worksheets/sheet1.xml`), returns (id, type, target) tuples
+pub(crate) fn read_relationships<RS, E>(
+    zip: &mut ZipArchive<RS>,
+    part: &str,
+) -> Result<Vec<(String, String, String)>, E>
+where
+    RS: Read + Seek,
+    E: From<ZipError> + From<quick_xml::Error>,
+{
+    match zip.by_name(&relationships_path(part)) {
+        Ok(f) => Ok(parse_relationships(&mut xml_reader(f))?),
+        Err(ZipError::FileNotFound) => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Gets the path of the relationships part of a part
+fn relationships_path(part: &str) -> String {
+    match part.rsplit_once('/') {
+        Some((folder, file_name)) => format!("{folder}/_rels/{file_name}.rels"),
+        None => format!("_rels/{part}.rels"),
+    }
+}
+
+/// Creates a XML reader of a part
+pub(crate) fn xml_reader(f: ZipFile<'_>) -> XmlReader<BufReader<ZipFile<'_>>> {
+    let mut xml = XmlReader::from_reader(BufReader::new(f));
+    xml.check_end_names(false).check_comments(false);
+    xml
+}
+
+/// Gets an attribute value by its local name
+pub(crate) fn attribute<B>(
+    xml: &XmlReader<B>,
+    e: &BytesStart<'_>,
+    local_name: &[u8],
+) -> Result<Option<String>, quick_xml::Error> {
+    for a in e.attributes() {
+        let a = a.map_err(quick_xml::Error::InvalidAttr)?;
+        if a.key.local_name().as_ref() == local_name {
+            return Ok(Some(a.decode_and_unescape_value(xml)?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+/// Parses a relationships part, returns (id, type, target) tuples
+fn parse_relationships<B: BufRead>(
+    xml: &mut XmlReader<B>,
+) -> Result<Vec<(String, String, String)>, quick_xml::Error> {
+    let mut relationships = Vec::new();
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf)? {
+            Event::Start(ref e) | Event::Empty(ref e)
+                if e.local_name().as_ref() == b"Relationship" =>
+            {
+                let attribute = |name: &str| -> Result<String, quick_xml::Error> {
+                    Ok(match e.try_get_attribute(name)? {
+                        Some(a) => a.decode_and_unescape_value(xml)?.into_owned(),
+                        None => String::new(),
+                    })
+                };
+                let id = attribute("Id")?;
+                let typ = attribute("Type")?;
+                let target = attribute("Target")?;
+                relationships.push((id, typ, target));
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    Ok(relationships)
+}
+
+/// Resolves a relationship target relative to the part owning the relationships
+///
+/// e.g. `../media/image1.png` from `xl/drawings/drawing1.xml` is `xl/media/image1.png`
+pub(crate) fn resolve_target(part: &str, target: &str) -> String {
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_string();
+    }
+    let mut path: Vec<&str> = part.split('/').collect();
+    path.pop();
+    for segment in target.split('/') {
+        match segment {
+            ".." => {
+                path.pop();
+            }
+            "." => (),
+            s => path.push(s),
+        }
+    }
+    path.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relationship_targets() {
+        assert_eq!(
+            resolve_target("xl/drawings/drawing1.xml", "../media/image1.png"),
+            "xl/media/image1.png"
+        );
+        assert_eq!(
+            resolve_target("xl/worksheets/sheet1.xml", "/xl/drawings/drawing1.xml"),
+            "xl/drawings/drawing1.xml"
+        );
+    }
+
+    #[test]
+    fn relationship_paths() {
+        assert_eq!(
+            relationships_path("xl/worksheets/sheet1.xml"),
+            "xl/worksheets/_rels/sheet1.xml.rels"
+        );
+        assert_eq!(relationships_path("sheet1.bin"), "_rels/sheet1.bin.rels");
+    }
+}
@@ -0,0 +1,45 @@
+//! Colors and differential formats
+
+/// A color, as stored in the file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    /// Explicit ARGB color (e.g. `0xFFFF0000` for opaque red)
+    Argb(u32),
+    /// Color of the workbook theme
+    Theme {
+        /// Index in the theme color scheme
+        index: u32,
+        /// Tint, from -1.0 (darkest) to 1.0 (lightest)
+        tint: f64,
+    },
+    /// Index in the legacy indexed colors palette
+    Indexed(u32),
+    /// Automatic color, depending on the context
+    Auto,
+}
+
+/// A differential format, the formatting applied on top of the cell format
+/// (e.g. by a conditional formatting rule)
+///
+/// Only the properties set by the format are `Some`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DifferentialFormat {
+    /// Bold font
+    pub bold: Option<bool>,
+    /// Italic font
+    pub italic: Option<bool>,
+    /// Underlined font
+    pub underline: Option<bool>,
+    /// Strikethrough font
+    pub strikethrough: Option<bool>,
+    /// Font color
+    pub font_color: Option<Color>,
+    /// Fill pattern (e.g. "solid")
+    pub fill_pattern: Option<String>,
+    /// Fill pattern foreground color
+    pub fill_foreground: Option<Color>,
+    /// Fill background color, the cell color of a solid fill
+    pub fill_background: Option<Color>,
+    /// Number format code
+    pub number_format: Option<String>,
+}
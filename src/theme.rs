@@ -0,0 +1,292 @@
+//! Workbook theme and resolution of theme, indexed and tinted colors into ARGB values
+
+use std::io::BufRead;
+
+use quick_xml::events::Event;
+use quick_xml::Reader as XmlReader;
+
+use crate::Color;
+
+/// Default indexed colors palette, the 8 first colors being repeated at indexes 8 to 15
+const DEFAULT_INDEXED_COLORS: [u32; 66] = [
+    0x000000, 0xFFFFFF, 0xFF0000, 0x00FF00, 0x0000FF, 0xFFFF00, 0xFF00FF, 0x00FFFF, // 0-7
+    0x000000, 0xFFFFFF, 0xFF0000, 0x00FF00, 0x0000FF, 0xFFFF00, 0xFF00FF, 0x00FFFF, // 8-15
+    0x800000, 0x008000, 0x000080, 0x808000, 0x800080, 0x008080, 0xC0C0C0, 0x808080, // 16-23
+    0x9999FF, 0x993366, 0xFFFFCC, 0xCCFFFF, 0x660066, 0xFF8080, 0x0066CC, 0xCCCCFF, // 24-31
+    0x000080, 0xFF00FF, 0xFFFF00, 0x00FFFF, 0x800080, 0x800000, 0x008080, 0x0000FF, // 32-39
+    0x00CCFF, 0xCCFFFF, 0xCCFFCC, 0xFFFF99, 0x99CCFF, 0xFF99CC, 0xCC99FF, 0xFFCC99, // 40-47
+    0x3366FF, 0x33CCCC, 0x99CC00, 0xFFCC00, 0xFF9900, 0xFF6600, 0x666699, 0x969696, // 48-55
+    0x003366, 0x339966, 0x003300, 0x333300, 0x993300, 0x993366, 0x333399, 0x333333, // 56-63
+    0x000000, 0xFFFFFF, // 64: system foreground, 65: system background
+];
+
+/// Color scheme of the default "Office" theme
+const DEFAULT_THEME_COLORS: [u32; 12] = [
+    0x000000, 0xFFFFFF, 0x1F497D, 0xEEECE1, 0x4F81BD, 0xC0504D, 0x9BBB59, 0x8064A2, 0x4BACC6,
+    0xF79646, 0x0000FF, 0x800080,
+];
+
+/// Names of the theme colors, in the color scheme order
+const THEME_COLOR_NAMES: [&[u8]; 12] = [
+    b"dk1",
+    b"lt1",
+    b"dk2",
+    b"lt2",
+    b"accent1",
+    b"accent2",
+    b"accent3",
+    b"accent4",
+    b"accent5",
+    b"accent6",
+    b"hlink",
+    b"folHlink",
+];
+
+/// Workbook theme (`xl/theme/theme1.xml`): its color scheme and fonts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Name of the theme
+    pub name: String,
+    /// ARGB colors of the color scheme, in file order: dark 1, light 1, dark 2, light 2,
+    /// accents 1 to 6, hyperlink and followed hyperlink
+    pub colors: [u32; 12],
+    /// Latin typeface of the headings
+    pub major_font: String,
+    /// Latin typeface of the body
+    pub minor_font: String,
+}
+
+impl Default for Theme {
+    /// The "Office" theme, used by files without a theme
+    fn default() -> Self {
+        Theme {
+            name: "Office Theme".to_string(),
+            colors: DEFAULT_THEME_COLORS.map(|c| 0xFF00_0000 | c),
+            major_font: "Cambria".to_string(),
+            minor_font: "Calibri".to_string(),
+        }
+    }
+}
+
+impl Theme {
+    /// Get the color referenced by a `theme` attribute
+    ///
+    /// The two first pairs of colors are swapped: 0 is light 1, 1 dark 1, 2 light 2 and 3 dark 2.
+    pub fn color(&self, index: u32) -> Option<u32> {
+        let index = match index {
+            0 => 1,
+            1 => 0,
+            2 => 3,
+            3 => 2,
+            i => i as usize,
+        };
+        self.colors.get(index).copied()
+    }
+}
+
+/// Converts `Color`s into ARGB values, using the theme and indexed colors of a workbook
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorResolver {
+    /// Workbook theme
+    pub theme: Theme,
+    /// ARGB indexed colors palette, with the custom colors of the workbook if any
+    pub indexed: Vec<u32>,
+}
+
+impl Default for ColorResolver {
+    fn default() -> Self {
+        ColorResolver {
+            theme: Theme::default(),
+            indexed: DEFAULT_INDEXED_COLORS.map(|c| 0xFF00_0000 | c).to_vec(),
+        }
+    }
+}
+
+impl ColorResolver {
+    /// Get the ARGB value of a color, `None` for automatic colors and unknown indexes
+    ///
+    /// # Examples
+    /// ```
+    /// use calamine::{Color, ColorResolver};
+    ///
+    /// let resolver = ColorResolver::default();
+    /// assert_eq!(resolver.resolve(&Color::Indexed(2)), Some(0xFFFF0000));
+    /// // accent 1 of the Office theme, 25% darker
+    /// let color = Color::Theme { index: 4, tint: -0.25 };
+    /// assert_eq!(resolver.resolve(&color), Some(0xFF376092));
+    /// ```
+    pub fn resolve(&self, color: &Color) -> Option<u32> {
+        match color {
+            Color::Argb(argb) => Some(*argb),
+            Color::Theme { index, tint } => Some(apply_tint(self.theme.color(*index)?, *tint)),
+            Color::Indexed(index) => self.indexed.get(*index as usize).copied(),
+            Color::Auto => None,
+        }
+    }
+
+    /// Replace the indexed colors, starting at `first`, by custom RGB colors
+    pub(crate) fn set_indexed_colors(&mut self, first: usize, colors: impl Iterator<Item = u32>) {
+        for (i, rgb) in colors.enumerate() {
+            if let Some(c) = self.indexed.get_mut(first + i) {
+                *c = 0xFF00_0000 | (rgb & 0xFF_FFFF);
+            }
+        }
+    }
+}
+
+/// Lighten (positive tint) or darken (negative tint) a color, by changing its HSL luminance
+fn apply_tint(argb: u32, tint: f64) -> u32 {
+    if tint == 0. {
+        return argb;
+    }
+    let [a, r, g, b] = argb.to_be_bytes();
+    let (h, s, l) = rgb_to_hsl(r, g, b);
+    let l = if tint < 0. {
+        l * (1. + tint)
+    } else {
+        l * (1. - tint) + tint
+    };
+    let (r, g, b) = hsl_to_rgb(h, s, l.clamp(0., 1.));
+    u32::from_be_bytes([a, r, g, b])
+}
+
+fn rgb_to_hsl(r: u8, g: u8, b: u8) -> (f64, f64, f64) {
+    let (r, g, b) = (r as f64 / 255., g as f64 / 255., b as f64 / 255.);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.;
+    if max == min {
+        return (0., 0., l);
+    }
+    let d = max - min;
+    let s = if l > 0.5 {
+        d / (2. - max - min)
+    } else {
+        d / (max + min)
+    };
+    let h = if max == r {
+        (g - b) / d + if g < b { 6. } else { 0. }
+    } else if max == g {
+        (b - r) / d + 2.
+    } else {
+        (r - g) / d + 4.
+    };
+    (h / 6., s, l)
+}
+
+fn hsl_to_rgb(h: f64, s: f64, l: f64) -> (u8, u8, u8) {
+    let to_u8 = |v: f64| (v * 255.).round() as u8;
+    if s == 0. {
+        return (to_u8(l), to_u8(l), to_u8(l));
+    }
+    let q = if l < 0.5 { l * (1. + s) } else { l + s - l * s };
+    let p = 2. * l - q;
+    let hue = |t: f64| {
+        let t = t.rem_euclid(1.);
+        if t < 1. / 6. {
+            p + (q - p) * 6. * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2. / 3. {
+            p + (q - p) * (2. / 3. - t) * 6.
+        } else {
+            p
+        }
+    };
+    (
+        to_u8(hue(h + 1. / 3.)),
+        to_u8(hue(h)),
+        to_u8(hue(h - 1. / 3.)),
+    )
+}
+
+/// Read a DrawingML theme part (`a:theme`)
+///
+/// Colors and fonts missing from the part keep their default value.
+pub(crate) fn read_theme<R: BufRead>(xml: &mut XmlReader<R>) -> Result<Theme, quick_xml::Error> {
+    let mut theme = Theme::default();
+    let mut buf = Vec::with_capacity(1024);
+    let mut in_color_scheme = false;
+    // index of the color scheme color being read
+    let mut color = None;
+    // are the major (heading) fonts being read
+    let mut major = None;
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf)? {
+            Event::Start(ref e) => match e.local_name().as_ref() {
+                b"theme" => {
+                    if let Some(a) = e.try_get_attribute("name")? {
+                        theme.name = a.decode_and_unescape_value(xml)?.into_owned();
+                    }
+                }
+                b"clrScheme" => in_color_scheme = true,
+                b"majorFont" => major = Some(true),
+                b"minorFont" => major = Some(false),
+                b"latin" => {
+                    let typeface = e.try_get_attribute("typeface")?;
+                    if let (Some(major), Some(a)) = (major, typeface) {
+                        let typeface = a.decode_and_unescape_value(xml)?.into_owned();
+                        if major {
+                            theme.major_font = typeface;
+                        } else {
+                            theme.minor_font = typeface;
+                        }
+                    }
+                }
+                b"srgbClr" | b"sysClr" => {
+                    // sysClr are system colors, whose last value is saved in `lastClr`
+                    let key = if e.local_name().as_ref() == b"srgbClr" {
+                        "val"
+                    } else {
+                        "lastClr"
+                    };
+                    let rgb = e.try_get_attribute(key)?.and_then(|a| {
+                        u32::from_str_radix(&String::from_utf8_lossy(&a.value), 16).ok()
+                    });
+                    if let (Some(i), Some(rgb)) = (color.take(), rgb) {
+                        theme.colors[i] = 0xFF00_0000 | rgb;
+                    }
+                }
+                name if in_color_scheme => {
+                    color = THEME_COLOR_NAMES.iter().position(|n| *n == name);
+                }
+                _ => (),
+            },
+            Event::End(ref e) => match e.local_name().as_ref() {
+                b"majorFont" | b"minorFont" => major = None,
+                b"clrScheme" => in_color_scheme = false,
+                // the format scheme is not read
+                b"fontScheme" | b"themeElements" => break,
+                _ => (),
+            },
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    Ok(theme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tints() {
+        assert_eq!(apply_tint(0xFF4F81BD, 0.), 0xFF4F81BD);
+        assert_eq!(apply_tint(0xFF4F81BD, 0.3999755851924192), 0xFF95B3D7);
+        assert_eq!(apply_tint(0xFF4F81BD, -0.499984740745262), 0xFF254061);
+        assert_eq!(apply_tint(0xFFFFFFFF, -0.0499893185216834), 0xFFF2F2F2);
+        assert_eq!(apply_tint(0xFF000000, 0.5), 0xFF808080);
+    }
+
+    #[test]
+    fn theme_indexes() {
+        let theme = Theme::default();
+        assert_eq!(theme.color(0), Some(0xFFFFFFFF));
+        assert_eq!(theme.color(1), Some(0xFF000000));
+        assert_eq!(theme.color(2), Some(0xFFEEECE1));
+        assert_eq!(theme.color(4), Some(0xFF4F81BD));
+        assert_eq!(theme.color(12), None);
+    }
+}
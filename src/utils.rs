@@ -55,16 +55,16 @@ pub fn read_f64(s: &[u8]) -> f64 {
 }
 
 /// Push literal column into a String buffer
-pub fn push_column(mut col: u32, buf: &mut String) {
+pub fn push_column(col: u32, buf: &mut String) {
     if col < 26 {
         buf.push((b'A' + col as u8) as char);
     } else {
         let mut rev = String::new();
-        while col >= 26 {
-            let c = col % 26;
+        let mut col = col as u64 + 1;
+        while col > 0 {
+            let c = (col - 1) % 26;
             rev.push((b'A' + c as u8) as char);
-            col -= c;
-            col /= 26;
+            col = (col - 1) / 26;
         }
         buf.extend(rev.chars().rev());
     }
@@ -1052,6 +1052,832 @@ pub const FTAB_ARGC: [u8; FTAB_LEN] = [
     129, // "AVERAGEIFS"
 ];
 
+/// Names of the functions in the formulas of other languages, sorted by english name
+///
+/// Columns are German, French, Spanish, Italian, Brazilian Portuguese and Dutch.
+pub const FTAB_LOCALIZED: [(&str, [&str; 6]); 107] = [
+    ("ABS", ["ABS", "ABS", "ABS", "ASS", "ABS", "ABS"]),
+    ("AND", ["UND", "ET", "Y", "E", "E", "EN"]),
+    (
+        "AVERAGE",
+        [
+            "MITTELWERT",
+            "MOYENNE",
+            "PROMEDIO",
+            "MEDIA",
+            "MÉDIA",
+            "GEMIDDELDE",
+        ],
+    ),
+    (
+        "AVERAGEIF",
+        [
+            "MITTELWERTWENN",
+            "MOYENNE.SI",
+            "PROMEDIO.SI",
+            "MEDIA.SE",
+            "MÉDIASE",
+            "GEMIDDELDE.ALS",
+        ],
+    ),
+    (
+        "AVERAGEIFS",
+        [
+            "MITTELWERTWENNS",
+            "MOYENNE.SI.ENS",
+            "PROMEDIO.SI.CONJUNTO",
+            "MEDIA.PIÙ.SE",
+            "MÉDIASES",
+            "GEMIDDELDEN.ALS",
+        ],
+    ),
+    (
+        "CHAR",
+        [
+            "ZEICHEN",
+            "CAR",
+            "CARACTER",
+            "CODICE.CARATT",
+            "CARACT",
+            "TEKEN",
+        ],
+    ),
+    (
+        "CHOOSE",
+        ["WAHL", "CHOISIR", "ELEGIR", "SCEGLI", "ESCOLHER", "KIEZEN"],
+    ),
+    (
+        "CODE",
+        ["CODE", "CODE", "CODIGO", "CODICE", "CÓDIGO", "CODE"],
+    ),
+    (
+        "COLUMN",
+        [
+            "SPALTE",
+            "COLONNE",
+            "COLUMNA",
+            "RIF.COLONNA",
+            "COL",
+            "KOLOM",
+        ],
+    ),
+    (
+        "COLUMNS",
+        [
+            "SPALTEN", "COLONNES", "COLUMNAS", "COLONNE", "COLS", "KOLOMMEN",
+        ],
+    ),
+    (
+        "CONCAT",
+        [
+            "TEXTKETTE",
+            "CONCAT",
+            "CONCAT",
+            "CONCAT",
+            "CONCAT",
+            "TEKST.SAMENV",
+        ],
+    ),
+    (
+        "CONCATENATE",
+        [
+            "VERKETTEN",
+            "CONCATENER",
+            "CONCATENAR",
+            "CONCATENA",
+            "CONCATENAR",
+            "TEKST.SAMENVOEGEN",
+        ],
+    ),
+    (
+        "COUNT",
+        [
+            "ANZAHL",
+            "NB",
+            "CONTAR",
+            "CONTA.NUMERI",
+            "CONT.NÚM",
+            "AANTAL",
+        ],
+    ),
+    (
+        "COUNTA",
+        [
+            "ANZAHL2",
+            "NBVAL",
+            "CONTARA",
+            "CONTA.VALORI",
+            "CONT.VALORES",
+            "AANTALARG",
+        ],
+    ),
+    (
+        "COUNTBLANK",
+        [
+            "ANZAHLLEEREZELLEN",
+            "NB.VIDE",
+            "CONTAR.BLANCO",
+            "CONTA.VUOTE",
+            "CONTAR.VAZIO",
+            "AANTAL.LEGE.CELLEN",
+        ],
+    ),
+    (
+        "COUNTIF",
+        [
+            "ZÄHLENWENN",
+            "NB.SI",
+            "CONTAR.SI",
+            "CONTA.SE",
+            "CONT.SE",
+            "AANTAL.ALS",
+        ],
+    ),
+    (
+        "COUNTIFS",
+        [
+            "ZÄHLENWENNS",
+            "NB.SI.ENS",
+            "CONTAR.SI.CONJUNTO",
+            "CONTA.PIÙ.SE",
+            "CONT.SES",
+            "AANTALLEN.ALS",
+        ],
+    ),
+    ("DATE", ["DATUM", "DATE", "FECHA", "DATA", "DATA", "DATUM"]),
+    ("DAY", ["TAG", "JOUR", "DIA", "GIORNO", "DIA", "DAG"]),
+    ("DAYS", ["TAGE", "JOURS", "DIAS", "GIORNI", "DIAS", "DAGEN"]),
+    (
+        "EDATE",
+        [
+            "EDATUM",
+            "MOIS.DECALER",
+            "FECHA.MES",
+            "DATA.MESE",
+            "DATAM",
+            "ZELFDE.DAG",
+        ],
+    ),
+    (
+        "EOMONTH",
+        [
+            "MONATSENDE",
+            "FIN.MOIS",
+            "FIN.MES",
+            "FINE.MESE",
+            "FIMMÊS",
+            "LAATSTE.DAG",
+        ],
+    ),
+    (
+        "EXACT",
+        ["IDENTISCH", "EXACT", "IGUAL", "IDENTICO", "EXATO", "GELIJK"],
+    ),
+    (
+        "FALSE",
+        ["FALSCH", "FAUX", "FALSO", "FALSO", "FALSO", "ONWAAR"],
+    ),
+    (
+        "FILTER",
+        ["FILTER", "FILTRE", "FILTRAR", "FILTRO", "FILTRO", "FILTER"],
+    ),
+    (
+        "FIND",
+        [
+            "FINDEN",
+            "TROUVE",
+            "ENCONTRAR",
+            "TROVA",
+            "PROCURAR",
+            "VIND.ALLES",
+        ],
+    ),
+    (
+        "HLOOKUP",
+        [
+            "WVERWEIS",
+            "RECHERCHEH",
+            "BUSCARH",
+            "CERCA.ORIZZ",
+            "PROCH",
+            "HORIZ.ZOEKEN",
+        ],
+    ),
+    ("HOUR", ["STUNDE", "HEURE", "HORA", "ORA", "HORA", "UUR"]),
+    ("IF", ["WENN", "SI", "SI", "SE", "SE", "ALS"]),
+    (
+        "IFERROR",
+        [
+            "WENNFEHLER",
+            "SIERREUR",
+            "SI.ERROR",
+            "SE.ERRORE",
+            "SEERRO",
+            "ALS.FOUT",
+        ],
+    ),
+    (
+        "IFNA",
+        [
+            "WENNNV",
+            "SI.NON.DISP",
+            "SI.ND",
+            "SE.NON.DISP.NUM",
+            "SENÃODISP",
+            "ALS.NB",
+        ],
+    ),
+    (
+        "IFS",
+        [
+            "WENNS",
+            "SI.CONDITIONS",
+            "SI.CONJUNTO",
+            "PIÙ.SE",
+            "SES",
+            "ALS.VOORWAARDEN",
+        ],
+    ),
+    (
+        "INDEX",
+        ["INDEX", "INDEX", "INDICE", "INDICE", "ÍNDICE", "INDEX"],
+    ),
+    (
+        "INDIRECT",
+        [
+            "INDIREKT",
+            "INDIRECT",
+            "INDIRECTO",
+            "INDIRETTO",
+            "INDIRETO",
+            "INDIRECT",
+        ],
+    ),
+    (
+        "INT",
+        ["GANZZAHL", "ENT", "ENTERO", "INT", "INT", "INTEGER"],
+    ),
+    (
+        "ISBLANK",
+        [
+            "ISTLEER",
+            "ESTVIDE",
+            "ESBLANCO",
+            "VAL.VUOTO",
+            "ÉCÉL.VAZIA",
+            "ISLEEG",
+        ],
+    ),
+    (
+        "ISERROR",
+        [
+            "ISTFEHLER",
+            "ESTERREUR",
+            "ESERROR",
+            "VAL.ERRORE",
+            "ÉERROS",
+            "ISFOUT",
+        ],
+    ),
+    (
+        "ISNA",
+        [
+            "ISTNV",
+            "ESTNA",
+            "ESNOD",
+            "VAL.NON.DISP",
+            "É.NÃO.DISP",
+            "ISNB",
+        ],
+    ),
+    (
+        "ISNUMBER",
+        [
+            "ISTZAHL",
+            "ESTNUM",
+            "ESNUMERO",
+            "VAL.NUMERO",
+            "ÉNÚM",
+            "ISGETAL",
+        ],
+    ),
+    (
+        "ISTEXT",
+        [
+            "ISTTEXT",
+            "ESTTEXTE",
+            "ESTEXTO",
+            "VAL.TESTO",
+            "ÉTEXTO",
+            "ISTEKST",
+        ],
+    ),
+    (
+        "LARGE",
+        [
+            "KGRÖSSTE",
+            "GRANDE.VALEUR",
+            "K.ESIMO.MAYOR",
+            "GRANDE",
+            "MAIOR",
+            "GROOTSTE",
+        ],
+    ),
+    (
+        "LEFT",
+        [
+            "LINKS",
+            "GAUCHE",
+            "IZQUIERDA",
+            "SINISTRA",
+            "ESQUERDA",
+            "LINKS",
+        ],
+    ),
+    (
+        "LEN",
+        [
+            "LÄNGE",
+            "NBCAR",
+            "LARGO",
+            "LUNGHEZZA",
+            "NÚM.CARACT",
+            "LENGTE",
+        ],
+    ),
+    (
+        "LOOKUP",
+        ["VERWEIS", "RECHERCHE", "BUSCAR", "CERCA", "PROC", "ZOEKEN"],
+    ),
+    (
+        "LOWER",
+        [
+            "KLEIN",
+            "MINUSCULE",
+            "MINUSC",
+            "MINUSC",
+            "MINÚSCULA",
+            "KLEINE.LETTERS",
+        ],
+    ),
+    (
+        "MATCH",
+        [
+            "VERGLEICH",
+            "EQUIV",
+            "COINCIDIR",
+            "CONFRONTA",
+            "CORRESP",
+            "VERGELIJKEN",
+        ],
+    ),
+    ("MAX", ["MAX", "MAX", "MAX", "MAX", "MÁXIMO", "MAX"]),
+    (
+        "MAXIFS",
+        [
+            "MAXWENNS",
+            "MAX.SI.ENS",
+            "MAX.SI.CONJUNTO",
+            "MAX.PIÙ.SE",
+            "MÁXIMOSES",
+            "MAX.ALS.VOORWAARDEN",
+        ],
+    ),
+    (
+        "MEDIAN",
+        ["MEDIAN", "MEDIANE", "MEDIANA", "MEDIANA", "MED", "MEDIAAN"],
+    ),
+    (
+        "MID",
+        [
+            "TEIL",
+            "STXT",
+            "EXTRAE",
+            "STRINGA.ESTRAI",
+            "EXT.TEXTO",
+            "DEEL",
+        ],
+    ),
+    ("MIN", ["MIN", "MIN", "MIN", "MIN", "MÍNIMO", "MIN"]),
+    (
+        "MINIFS",
+        [
+            "MINWENNS",
+            "MIN.SI.ENS",
+            "MIN.SI.CONJUNTO",
+            "MIN.PIÙ.SE",
+            "MÍNIMOSES",
+            "MIN.ALS.VOORWAARDEN",
+        ],
+    ),
+    (
+        "MINUTE",
+        ["MINUTE", "MINUTE", "MINUTO", "MINUTO", "MINUTO", "MINUUT"],
+    ),
+    ("MOD", ["REST", "MOD", "RESIDUO", "RESTO", "MOD", "REST"]),
+    ("MONTH", ["MONAT", "MOIS", "MES", "MESE", "MÊS", "MAAND"]),
+    ("NA", ["NV", "NA", "NOD", "NON.DISP", "NÃO.DISP", "NB"]),
+    (
+        "NETWORKDAYS",
+        [
+            "NETTOARBEITSTAGE",
+            "NB.JOURS.OUVRES",
+            "DIAS.LAB",
+            "GIORNI.LAVORATIVI.TOT",
+            "DIATRABALHOTOTAL",
+            "NETTO.WERKDAGEN",
+        ],
+    ),
+    ("NOT", ["NICHT", "NON", "NO", "NON", "NÃO", "NIET"]),
+    (
+        "NOW",
+        ["JETZT", "MAINTENANT", "AHORA", "ADESSO", "AGORA", "NU"],
+    ),
+    (
+        "OFFSET",
+        [
+            "BEREICH.VERSCHIEBEN",
+            "DECALER",
+            "DESREF",
+            "SCARTO",
+            "DESLOC",
+            "VERSCHUIVING",
+        ],
+    ),
+    ("OR", ["ODER", "OU", "O", "O", "OU", "OF"]),
+    ("PI", ["PI", "PI", "PI", "PI.GRECO", "PI", "PI"]),
+    (
+        "POWER",
+        [
+            "POTENZ",
+            "PUISSANCE",
+            "POTENCIA",
+            "POTENZA",
+            "POTÊNCIA",
+            "MACHT",
+        ],
+    ),
+    (
+        "PRODUCT",
+        [
+            "PRODUKT", "PRODUIT", "PRODUCTO", "PRODOTTO", "MULT", "PRODUCT",
+        ],
+    ),
+    (
+        "PROPER",
+        [
+            "GROSS2",
+            "NOMPROPRE",
+            "NOMPROPIO",
+            "MAIUSC.INIZ",
+            "PRI.MAIÚSCULA",
+            "BEGINLETTERS",
+        ],
+    ),
+    (
+        "RAND",
+        [
+            "ZUFALLSZAHL",
+            "ALEA",
+            "ALEATORIO",
+            "CASUALE",
+            "ALEATÓRIO",
+            "ASELECT",
+        ],
+    ),
+    (
+        "RANDBETWEEN",
+        [
+            "ZUFALLSBEREICH",
+            "ALEA.ENTRE.BORNES",
+            "ALEATORIO.ENTRE",
+            "CASUALE.TRA",
+            "ALEATÓRIOENTRE",
+            "ASELECTTUSSEN",
+        ],
+    ),
+    (
+        "REPLACE",
+        [
+            "ERSETZEN",
+            "REMPLACER",
+            "REEMPLAZAR",
+            "RIMPIAZZA",
+            "MUDAR",
+            "VERVANGEN",
+        ],
+    ),
+    (
+        "REPT",
+        [
+            "WIEDERHOLEN",
+            "REPT",
+            "REPETIR",
+            "RIPETI",
+            "REPT",
+            "HERHALING",
+        ],
+    ),
+    (
+        "RIGHT",
+        ["RECHTS", "DROITE", "DERECHA", "DESTRA", "DIREITA", "RECHTS"],
+    ),
+    (
+        "ROUND",
+        [
+            "RUNDEN",
+            "ARRONDI",
+            "REDONDEAR",
+            "ARROTONDA",
+            "ARRED",
+            "AFRONDEN",
+        ],
+    ),
+    (
+        "ROUNDDOWN",
+        [
+            "ABRUNDEN",
+            "ARRONDI.INF",
+            "REDONDEAR.MENOS",
+            "ARROTONDA.PER.DIF",
+            "ARREDONDAR.PARA.BAIXO",
+            "AFRONDEN.NAAR.BENEDEN",
+        ],
+    ),
+    (
+        "ROUNDUP",
+        [
+            "AUFRUNDEN",
+            "ARRONDI.SUP",
+            "REDONDEAR.MAS",
+            "ARROTONDA.PER.ECC",
+            "ARREDONDAR.PARA.CIMA",
+            "AFRONDEN.NAAR.BOVEN",
+        ],
+    ),
+    ("ROW", ["ZEILE", "LIGNE", "FILA", "RIF.RIGA", "LIN", "RIJ"]),
+    (
+        "ROWS",
+        ["ZEILEN", "LIGNES", "FILAS", "RIGHE", "LINS", "RIJEN"],
+    ),
+    (
+        "SEARCH",
+        [
+            "SUCHEN",
+            "CHERCHE",
+            "HALLAR",
+            "RICERCA",
+            "LOCALIZAR",
+            "VIND.SPEC",
+        ],
+    ),
+    (
+        "SECOND",
+        [
+            "SEKUNDE", "SECONDE", "SEGUNDO", "SECONDO", "SEGUNDO", "SECONDE",
+        ],
+    ),
+    (
+        "SEQUENCE",
+        [
+            "SEQUENZ",
+            "SEQUENCE",
+            "SECUENCIA",
+            "SEQUENZA",
+            "SEQUÊNCIA",
+            "REEKS",
+        ],
+    ),
+    (
+        "SIGN",
+        ["VORZEICHEN", "SIGNE", "SIGNO", "SEGNO", "SINAL", "POS.NEG"],
+    ),
+    (
+        "SMALL",
+        [
+            "KKLEINSTE",
+            "PETITE.VALEUR",
+            "K.ESIMO.MENOR",
+            "PICCOLO",
+            "MENOR",
+            "KLEINSTE",
+        ],
+    ),
+    (
+        "SORT",
+        [
+            "SORTIEREN",
+            "TRIER",
+            "ORDENAR",
+            "DATI.ORDINA",
+            "CLASSIFICAR",
+            "SORTEREN",
+        ],
+    ),
+    (
+        "SQRT",
+        ["WURZEL", "RACINE", "RAIZ", "RADQ", "RAIZ", "WORTEL"],
+    ),
+    (
+        "STDEV",
+        ["STABW", "ECARTYPE", "DESVEST", "DEV.ST", "DESVPAD", "STDEV"],
+    ),
+    (
+        "SUBSTITUTE",
+        [
+            "WECHSELN",
+            "SUBSTITUE",
+            "SUSTITUIR",
+            "SOSTITUISCI",
+            "SUBSTITUIR",
+            "SUBSTITUEREN",
+        ],
+    ),
+    (
+        "SUBTOTAL",
+        [
+            "TEILERGEBNIS",
+            "SOUS.TOTAL",
+            "SUBTOTALES",
+            "SUBTOTALE",
+            "SUBTOTAL",
+            "SUBTOTAAL",
+        ],
+    ),
+    ("SUM", ["SUMME", "SOMME", "SUMA", "SOMMA", "SOMA", "SOM"]),
+    (
+        "SUMIF",
+        [
+            "SUMMEWENN",
+            "SOMME.SI",
+            "SUMAR.SI",
+            "SOMMA.SE",
+            "SOMASE",
+            "SOM.ALS",
+        ],
+    ),
+    (
+        "SUMIFS",
+        [
+            "SUMMEWENNS",
+            "SOMME.SI.ENS",
+            "SUMAR.SI.CONJUNTO",
+            "SOMMA.PIÙ.SE",
+            "SOMASES",
+            "SOMMEN.ALS",
+        ],
+    ),
+    (
+        "SUMPRODUCT",
+        [
+            "SUMMENPRODUKT",
+            "SOMMEPROD",
+            "SUMAPRODUCTO",
+            "MATR.SOMMA.PRODOTTO",
+            "SOMARPRODUTO",
+            "SOMPRODUCT",
+        ],
+    ),
+    (
+        "SWITCH",
+        [
+            "ERSTERWERT",
+            "SI.MULTIPLE",
+            "CAMBIAR",
+            "SWITCH",
+            "PARÂMETRO",
+            "SCHAKELEN",
+        ],
+    ),
+    (
+        "TEXT",
+        ["TEXT", "TEXTE", "TEXTO", "TESTO", "TEXTO", "TEKST"],
+    ),
+    (
+        "TEXTJOIN",
+        [
+            "TEXTVERKETTEN",
+            "JOINDRE.TEXTE",
+            "UNIRCADENAS",
+            "TESTO.UNISCI",
+            "UNIRTEXTO",
+            "TEKST.COMBINEREN",
+        ],
+    ),
+    (
+        "TIME",
+        ["ZEIT", "TEMPS", "NSHORA", "ORARIO", "TEMPO", "TIJD"],
+    ),
+    (
+        "TODAY",
+        ["HEUTE", "AUJOURDHUI", "HOY", "OGGI", "HOJE", "VANDAAG"],
+    ),
+    (
+        "TRANSPOSE",
+        [
+            "MTRANS",
+            "TRANSPOSE",
+            "TRANSPONER",
+            "MATR.TRASPOSTA",
+            "TRANSPOR",
+            "TRANSPONEREN",
+        ],
+    ),
+    (
+        "TRIM",
+        [
+            "GLÄTTEN",
+            "SUPPRESPACE",
+            "ESPACIOS",
+            "ANNULLA.SPAZI",
+            "ARRUMAR",
+            "SPATIES.WISSEN",
+        ],
+    ),
+    (
+        "TRUE",
+        ["WAHR", "VRAI", "VERDADERO", "VERO", "VERDADEIRO", "WAAR"],
+    ),
+    (
+        "TRUNC",
+        [
+            "KÜRZEN", "TRONQUE", "TRUNCAR", "TRONCA", "TRUNCAR", "GEHEEL",
+        ],
+    ),
+    (
+        "UNIQUE",
+        [
+            "EINDEUTIG",
+            "UNIQUE",
+            "UNICOS",
+            "UNICI",
+            "EXCLUSIVOS",
+            "UNIEK",
+        ],
+    ),
+    (
+        "UPPER",
+        [
+            "GROSS",
+            "MAJUSCULE",
+            "MAYUSC",
+            "MAIUSC",
+            "MAIÚSCULA",
+            "HOOFDLETTERS",
+        ],
+    ),
+    (
+        "VALUE",
+        ["WERT", "CNUM", "VALOR", "VALORE", "VALOR", "WAARDE"],
+    ),
+    (
+        "VLOOKUP",
+        [
+            "SVERWEIS",
+            "RECHERCHEV",
+            "BUSCARV",
+            "CERCA.VERT",
+            "PROCV",
+            "VERT.ZOEKEN",
+        ],
+    ),
+    (
+        "WEEKDAY",
+        [
+            "WOCHENTAG",
+            "JOURSEM",
+            "DIASEM",
+            "GIORNO.SETTIMANA",
+            "DIA.DA.SEMANA",
+            "WEEKDAG",
+        ],
+    ),
+    (
+        "XLOOKUP",
+        [
+            "XVERWEIS",
+            "RECHERCHEX",
+            "BUSCARX",
+            "CERCA.X",
+            "PROCX",
+            "X.ZOEKEN",
+        ],
+    ),
+    (
+        "XMATCH",
+        [
+            "XVERGLEICH",
+            "EQUIVX",
+            "COINCIDIRX",
+            "CONFRONTA.X",
+            "CORRESPX",
+            "X.VERGELIJKEN",
+        ],
+    ),
+    ("XOR", ["XODER", "OUX", "XO", "XOR", "OUEXCL", "EX.OF"]),
+    ("YEAR", ["JAHR", "ANNEE", "AÑO", "ANNO", "ANO", "JAAR"]),
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1064,4 +1890,13 @@ mod tests {
             [u32::from_le_bytes(*b"ABCD"), u32::from_le_bytes(*b"EFGH")]
         );
     }
+
+    #[test]
+    fn columns() {
+        for (col, name) in [(0, "A"), (25, "Z"), (26, "AA"), (701, "ZZ"), (16383, "XFD")] {
+            let mut buf = String::new();
+            push_column(col, &mut buf);
+            assert_eq!(buf, name);
+        }
+    }
 }
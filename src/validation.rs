@@ -0,0 +1,254 @@
+//! Data validation rules
+
+use crate::Dimensions;
+
+/// A data validation rule, restricting the values users can enter in some cells
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DataValidation {
+    /// Cell ranges the rule applies to
+    pub ranges: Vec<Dimensions>,
+    /// Kind of values allowed
+    pub validation_type: ValidationType,
+    /// Comparison operator, for whole, decimal, date, time and text length rules
+    pub operator: ValidationOperator,
+    /// First formula: the source of list rules, or the (minimum) value to compare to
+    pub formula1: Option<String>,
+    /// Second formula: the maximum value of `Between` and `NotBetween` rules
+    pub formula2: Option<String>,
+    /// Are empty cells valid
+    pub allow_blank: bool,
+    /// Is a dropdown with the list values shown (list rules only)
+    pub show_dropdown: bool,
+    /// Is the input message shown when the cell is selected
+    pub show_input_message: bool,
+    /// Is the error message shown when an invalid value is entered
+    pub show_error_message: bool,
+    /// What happens when an invalid value is entered
+    pub error_style: ValidationErrorStyle,
+    /// Title of the input message
+    pub prompt_title: Option<String>,
+    /// Input message
+    pub prompt: Option<String>,
+    /// Title of the error message
+    pub error_title: Option<String>,
+    /// Error message
+    pub error: Option<String>,
+}
+
+impl DataValidation {
+    /// Does the rule apply to the cell at (row, column)
+    pub fn applies_to(&self, (row, col): (u32, u32)) -> bool {
+        self.ranges.iter().any(|r| r.contains(row, col))
+    }
+
+    /// Get the values of a list rule defined with explicit values (e.g. `"a,b,c"`)
+    ///
+    /// Returns `None` if this is not a list rule or if the list comes from a cell range
+    pub fn list_values(&self) -> Option<Vec<String>> {
+        if self.validation_type != ValidationType::List {
+            return None;
+        }
+        let f = self.formula1.as_deref()?;
+        let f = f.strip_prefix('"')?.strip_suffix('"')?;
+        Some(
+            f.replace("\"\"", "\"")
+                .split(',')
+                .map(|s| s.to_string())
+                .collect(),
+        )
+    }
+}
+
+/// Kind of values allowed by a `DataValidation`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationType {
+    /// Any value
+    #[default]
+    Any,
+    /// Whole numbers
+    Whole,
+    /// Decimal numbers
+    Decimal,
+    /// A value from a list
+    List,
+    /// Dates
+    Date,
+    /// Times
+    Time,
+    /// Text with a constrained length
+    TextLength,
+    /// Values for which a custom formula is true
+    Custom,
+}
+
+impl ValidationType {
+    /// Convert the `valType` of xls and xlsb records
+    pub(crate) fn from_code(code: u32) -> ValidationType {
+        match code {
+            1 => ValidationType::Whole,
+            2 => ValidationType::Decimal,
+            3 => ValidationType::List,
+            4 => ValidationType::Date,
+            5 => ValidationType::Time,
+            6 => ValidationType::TextLength,
+            7 => ValidationType::Custom,
+            _ => ValidationType::Any,
+        }
+    }
+
+    /// Convert the xlsx `type` attribute
+    pub(crate) fn from_name(name: &[u8]) -> ValidationType {
+        match name {
+            b"whole" => ValidationType::Whole,
+            b"decimal" => ValidationType::Decimal,
+            b"list" => ValidationType::List,
+            b"date" => ValidationType::Date,
+            b"time" => ValidationType::Time,
+            b"textLength" => ValidationType::TextLength,
+            b"custom" => ValidationType::Custom,
+            _ => ValidationType::Any,
+        }
+    }
+}
+
+/// Comparison operator of a `DataValidation`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationOperator {
+    /// Between `formula1` and `formula2`, inclusive
+    #[default]
+    Between,
+    /// Not between `formula1` and `formula2`
+    NotBetween,
+    /// Equal to `formula1`
+    Equal,
+    /// Not equal to `formula1`
+    NotEqual,
+    /// Greater than `formula1`
+    GreaterThan,
+    /// Less than `formula1`
+    LessThan,
+    /// Greater than or equal to `formula1`
+    GreaterThanOrEqual,
+    /// Less than or equal to `formula1`
+    LessThanOrEqual,
+}
+
+impl ValidationOperator {
+    /// Convert the `typOperator` of xls and xlsb records
+    pub(crate) fn from_code(code: u32) -> ValidationOperator {
+        match code {
+            1 => ValidationOperator::NotBetween,
+            2 => ValidationOperator::Equal,
+            3 => ValidationOperator::NotEqual,
+            4 => ValidationOperator::GreaterThan,
+            5 => ValidationOperator::LessThan,
+            6 => ValidationOperator::GreaterThanOrEqual,
+            7 => ValidationOperator::LessThanOrEqual,
+            _ => ValidationOperator::Between,
+        }
+    }
+
+    /// Convert the xlsx `operator` attribute
+    pub(crate) fn from_name(name: &[u8]) -> ValidationOperator {
+        match name {
+            b"notBetween" => ValidationOperator::NotBetween,
+            b"equal" => ValidationOperator::Equal,
+            b"notEqual" => ValidationOperator::NotEqual,
+            b"greaterThan" => ValidationOperator::GreaterThan,
+            b"lessThan" => ValidationOperator::LessThan,
+            b"greaterThanOrEqual" => ValidationOperator::GreaterThanOrEqual,
+            b"lessThanOrEqual" => ValidationOperator::LessThanOrEqual,
+            _ => ValidationOperator::Between,
+        }
+    }
+}
+
+/// Behavior of a `DataValidation` when an invalid value is entered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationErrorStyle {
+    /// The value is rejected
+    #[default]
+    Stop,
+    /// The user is warned and can keep the value
+    Warning,
+    /// The user is informed and the value is kept
+    Information,
+}
+
+impl ValidationErrorStyle {
+    /// Convert the `errStyle` of xls and xlsb records
+    pub(crate) fn from_code(code: u32) -> ValidationErrorStyle {
+        match code {
+            1 => ValidationErrorStyle::Warning,
+            2 => ValidationErrorStyle::Information,
+            _ => ValidationErrorStyle::Stop,
+        }
+    }
+
+    /// Convert the xlsx `errorStyle` attribute
+    pub(crate) fn from_name(name: &[u8]) -> ValidationErrorStyle {
+        match name {
+            b"warning" => ValidationErrorStyle::Warning,
+            b"information" => ValidationErrorStyle::Information,
+            _ => ValidationErrorStyle::Stop,
+        }
+    }
+}
+
+/// Build a `DataValidation` from the `dwDvFlags` shared by xls DV and xlsb BrtDVal records
+pub(crate) fn from_flags(flags: u32) -> DataValidation {
+    DataValidation {
+        validation_type: ValidationType::from_code(flags & 0x0F),
+        error_style: ValidationErrorStyle::from_code((flags >> 4) & 0x07),
+        allow_blank: flags & 0x0100 != 0,
+        // fSuppressCombo
+        show_dropdown: flags & 0x0200 == 0,
+        show_input_message: flags & 0x0004_0000 != 0,
+        show_error_message: flags & 0x0008_0000 != 0,
+        operator: ValidationOperator::from_code((flags >> 20) & 0x0F),
+        ..Default::default()
+    }
+}
+
+/// Binary formats store explicit lists as a string formula with '\0' separated values
+pub(crate) fn fix_list_formula(dv: &mut DataValidation) {
+    if dv.validation_type == ValidationType::List {
+        if let Some(f) = dv.formula1.as_mut() {
+            if f.starts_with('"') && f.contains('\0') {
+                *f = f.replace('\0', ",");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_values() {
+        let mut dv = DataValidation {
+            validation_type: ValidationType::List,
+            formula1: Some("\"Yes,No,\"\"Maybe\"\"\"".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            dv.list_values(),
+            Some(vec!["Yes".into(), "No".into(), "\"Maybe\"".into()])
+        );
+        dv.formula1 = Some("$A$1:$A$3".to_string());
+        assert_eq!(dv.list_values(), None);
+    }
+
+    #[test]
+    fn flags() {
+        let dv = from_flags(0x0034_0113);
+        assert_eq!(dv.validation_type, ValidationType::List);
+        assert_eq!(dv.error_style, ValidationErrorStyle::Warning);
+        assert!(dv.allow_blank);
+        assert!(dv.show_dropdown);
+        assert!(dv.show_input_message);
+        assert!(!dv.show_error_message);
+        assert_eq!(dv.operator, ValidationOperator::NotEqual);
+    }
+}
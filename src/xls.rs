@@ -2,7 +2,6 @@ use std::borrow::Cow;
 use std::cmp::min;
 use std::collections::BTreeMap;
 use std::convert::TryInto;
-use std::fmt::Write;
 use std::io::{Read, Seek, SeekFrom};
 use std::marker::PhantomData;
 
@@ -13,11 +12,20 @@ use crate::formats::{
     builtin_format_by_code, detect_custom_number_format, format_excel_f64, format_excel_i64,
     CellFormat,
 };
+use crate::formula::{BinaryOp, Expr, UnaryOp};
+use crate::reference::{CellRef, RefTarget, Reference};
 #[cfg(feature = "picture")]
 use crate::utils::read_usize;
-use crate::utils::{push_column, read_f64, read_i16, read_i32, read_u16, read_u32};
+use crate::utils::{read_f64, read_i16, read_i32, read_u16, read_u32};
+use crate::validation;
 use crate::vba::VbaProject;
-use crate::{Cell, CellErrorType, Data, Metadata, Range, Reader, Sheet, SheetType, SheetVisible};
+#[cfg(feature = "picture")]
+use crate::{AnchorMarker, Picture, PictureAnchor};
+use crate::{
+    Cell, CellErrorType, Color, Data, DataValidation, Dimensions, ExternalLink, ExternalSheet,
+    LayoutSpan, Metadata, Pane, PanePosition, PaneState, PasswordHash, Range, Reader, Sheet,
+    SheetLayout, SheetProtection, SheetType, SheetView, SheetVisible, WorkbookProtection,
+};
 
 #[derive(Debug)]
 /// An enum to handle Xls specific errors
@@ -137,9 +145,17 @@ pub struct XlsOptions {
     pub force_codepage: Option<u16>,
 }
 
+/// Values, formulas and formula expressions of a sheet
+type SheetRanges = (Range<Data>, Range<String>, Range<Expr>);
+
 /// A struct representing an old xls format file (CFB)
 pub struct Xls<RS> {
-    sheets: BTreeMap<String, (Range<Data>, Range<String>)>,
+    sheets: BTreeMap<String, SheetRanges>,
+    layouts: BTreeMap<String, SheetLayout>,
+    views: BTreeMap<String, SheetView>,
+    protections: BTreeMap<String, Option<SheetProtection>>,
+    workbook_protection: Option<WorkbookProtection>,
+    data_validations: BTreeMap<String, Vec<DataValidation>>,
     vba: Option<VbaProject>,
     metadata: Metadata,
     marker: PhantomData<RS>,
@@ -148,6 +164,8 @@ pub struct Xls<RS> {
     is_1904: bool,
     #[cfg(feature = "picture")]
     pictures: Option<Vec<(String, Vec<u8>)>>,
+    #[cfg(feature = "picture")]
+    sheet_pictures: Option<Vec<Picture>>,
 }
 
 impl<RS: Read + Seek> Xls<RS> {
@@ -186,6 +204,11 @@ impl<RS: Read + Seek> Xls<RS> {
 
         let mut xls = Xls {
             sheets: BTreeMap::new(),
+            layouts: BTreeMap::new(),
+            views: BTreeMap::new(),
+            protections: BTreeMap::new(),
+            workbook_protection: None,
+            data_validations: BTreeMap::new(),
             vba,
             marker: PhantomData,
             metadata: Metadata::default(),
@@ -194,6 +217,8 @@ impl<RS: Read + Seek> Xls<RS> {
             formats: Vec::new(),
             #[cfg(feature = "picture")]
             pictures: None,
+            #[cfg(feature = "picture")]
+            sheet_pictures: None,
         };
 
         xls.parse_workbook(reader, cfb)?;
@@ -202,6 +227,17 @@ impl<RS: Read + Seek> Xls<RS> {
 
         Ok(xls)
     }
+
+    /// Get the formulas of a worksheet as expressions, decoded from their tokens
+    ///
+    /// `worksheet_formula` returns the same formulas as text. Formulas which cannot be decoded
+    /// are missing.
+    pub fn worksheet_formula_ast(&mut self, name: &str) -> Result<Range<Expr>, XlsError> {
+        self.sheets
+            .get(name)
+            .ok_or_else(|| XlsError::WorksheetNotFound(name.into()))
+            .map(|r| r.2.clone())
+    }
 }
 
 impl<RS: Read + Seek> Reader<RS> for Xls<RS> {
@@ -230,7 +266,7 @@ impl<RS: Read + Seek> Reader<RS> for Xls<RS> {
     fn worksheets(&mut self) -> Vec<(String, Range<Data>)> {
         self.sheets
             .iter()
-            .map(|(name, (data, _))| (name.to_owned(), data.clone()))
+            .map(|(name, (data, _, _))| (name.to_owned(), data.clone()))
             .collect()
     }
 
@@ -241,17 +277,122 @@ impl<RS: Read + Seek> Reader<RS> for Xls<RS> {
             .map(|r| r.1.clone())
     }
 
+    fn worksheet_layout(&mut self, name: &str) -> Result<SheetLayout, XlsError> {
+        self.layouts
+            .get(name)
+            .cloned()
+            .ok_or_else(|| XlsError::WorksheetNotFound(name.into()))
+    }
+
+    fn worksheet_view(&mut self, name: &str) -> Result<SheetView, XlsError> {
+        self.views
+            .get(name)
+            .cloned()
+            .ok_or_else(|| XlsError::WorksheetNotFound(name.into()))
+    }
+
+    fn worksheet_protection(&mut self, name: &str) -> Result<Option<SheetProtection>, XlsError> {
+        self.protections
+            .get(name)
+            .cloned()
+            .ok_or_else(|| XlsError::WorksheetNotFound(name.into()))
+    }
+
+    fn workbook_protection(&mut self) -> Result<Option<WorkbookProtection>, XlsError> {
+        Ok(self.workbook_protection.clone())
+    }
+
+    fn worksheet_data_validations(&mut self, name: &str) -> Result<Vec<DataValidation>, XlsError> {
+        self.data_validations
+            .get(name)
+            .cloned()
+            .ok_or_else(|| XlsError::WorksheetNotFound(name.into()))
+    }
+
     #[cfg(feature = "picture")]
     fn pictures(&self) -> Option<Vec<(String, Vec<u8>)>> {
         self.pictures.to_owned()
     }
+
+    #[cfg(feature = "picture")]
+    fn sheet_pictures(&self) -> Option<Vec<Picture>> {
+        self.sheet_pictures.to_owned()
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// An XTI of the ExternSheet record [MS-XLS 2.5.293], resolved into what it refers to
+#[derive(Debug, Clone)]
 struct Xti {
-    _isup_book: u16,
-    itab_first: i16,
-    _itab_last: i16,
+    /// Sheet of the references, e.g. "Sheet1", "Sheet1:Sheet3" or "[Book2.xls]Sheet1"
+    sheet: String,
+    /// File name of the linked workbook, for external references
+    book: Option<String>,
+    /// Names of the supporting link, referenced by PtgNameX
+    names: Vec<String>,
+}
+
+impl Xti {
+    /// Resolve an XTI, whose sheet indexes refer to the sheets of its supporting link
+    fn new(sup_books: &[SupBook], sheets: &[(usize, String)], xti: &[u8]) -> Xti {
+        let (isup_book, itab_first, itab_last) = (
+            read_u16(xti) as usize,
+            read_i16(&xti[2..]),
+            read_i16(&xti[4..]),
+        );
+        let Some(sup_book) = sup_books.get(isup_book) else {
+            return Xti {
+                sheet: "#REF".to_string(),
+                book: None,
+                names: Vec::new(),
+            };
+        };
+        let (file, names): (_, Vec<&str>) = match sup_book.kind {
+            SupBookKind::Internal => (None, sheets.iter().map(|s| &*s.1).collect()),
+            SupBookKind::AddIn => (None, Vec::new()),
+            SupBookKind::External => (
+                Some(sup_book.link.file_name()),
+                sup_book.link.sheets.iter().map(|s| &*s.name).collect(),
+            ),
+        };
+        let sheet_name = |i: i16| usize::try_from(i).ok().and_then(|i| names.get(i).copied());
+        // negative indexes are deleted sheets or workbook level references
+        let sheet = match (sheet_name(itab_first), sheet_name(itab_last)) {
+            (Some(f), Some(l)) if itab_first != itab_last => format!("{f}:{l}"),
+            (Some(f), _) => f.to_string(),
+            _ if file.is_some() => String::new(),
+            _ => "#REF".to_string(),
+        };
+        match file {
+            Some(file) => Xti {
+                sheet: format!("[{file}]{sheet}"),
+                book: Some(file.to_string()),
+                names: sup_book.link.names.clone(),
+            },
+            None => Xti {
+                sheet,
+                book: None,
+                names: sup_book.link.names.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SupBookKind {
+    /// The workbook itself
+    Internal,
+    /// Add-in functions
+    AddIn,
+    /// Another workbook, or a DDE or OLE link
+    External,
+}
+
+/// A supporting link [MS-XLS 2.4.271], with its external names and cached cells
+#[derive(Debug)]
+struct SupBook {
+    kind: SupBookKind,
+    link: ExternalLink,
+    cells: Vec<Vec<Cell<Data>>>,
 }
 
 impl<RS: Read + Seek> Xls<RS> {
@@ -264,12 +405,17 @@ impl<RS: Read + Seek> Xls<RS> {
         let mut sheet_names = Vec::new();
         let mut strings = Vec::new();
         let mut defined_names = Vec::new();
-        let mut xtis = Vec::new();
+        let mut xtis_data = Vec::new();
+        let mut sup_books: Vec<SupBook> = Vec::new();
+        // sheet of the supporting link whose cells are read by the following CRN records
+        let mut crn_sheet = None;
         let mut formats = BTreeMap::new();
         let mut xfs = Vec::new();
         let mut biff = Biff::Biff8; // Binary Interchange File Format (BIFF) version
         let codepage = self.options.force_codepage.unwrap_or(1200);
         let mut encoding = XlsEncoding::from_codepage(codepage)?;
+        let mut workbook_protection = WorkbookProtection::default();
+        let mut window_read = false;
         #[cfg(feature = "picture")]
         let mut draw_group: Vec<u8> = Vec::new();
         {
@@ -297,11 +443,38 @@ impl<RS: Read + Seek> Xls<RS> {
                             self.is_1904 = true
                         }
                     }
+                    // Protect: structure locked
+                    0x0012 if r.data.len() >= 2 => {
+                        workbook_protection.lock_structure = read_u16(r.data) != 0
+                    }
+                    // WinProtect
+                    0x0019 if r.data.len() >= 2 => {
+                        workbook_protection.lock_windows = read_u16(r.data) != 0
+                    }
+                    // Window1 [MS-XLS 2.4.345], the first one being used when the workbook is opened
+                    0x003D if r.data.len() >= 14 && !window_read => {
+                        self.metadata.active_sheet = read_u16(&r.data[10..]) as usize;
+                        self.metadata.first_visible_sheet = read_u16(&r.data[12..]) as usize;
+                        window_read = true;
+                    }
+                    // Password
+                    0x0013 if r.data.len() >= 2 => {
+                        workbook_protection.password = PasswordHash::from_legacy(read_u16(r.data))
+                    }
                     // FORMATTING
                     0x041E => {
                         let (idx, format) = parse_format(&mut r, &encoding)?;
                         formats.insert(idx, format);
                     }
+                    // Palette [MS-XLS 2.4.188], custom colors from index 8
+                    0x0092 if r.data.len() >= 2 => {
+                        let ccv = read_u16(r.data) as usize;
+                        let colors = r.data[2..].chunks_exact(4).take(ccv);
+                        self.metadata.colors.set_indexed_colors(
+                            8,
+                            colors.map(|c| u32::from_be_bytes([0, c[0], c[1], c[2]])),
+                        );
+                    }
                     // XFS
                     0x00E0 => {
                         xfs.push(parse_xf(&r)?);
@@ -317,24 +490,52 @@ impl<RS: Read + Seek> Xls<RS> {
                         let bof = parse_bof(&mut r)?;
                         biff = bof.biff;
                     }
-                    0x0018 => {
-                        // Lbl for defined_names
+                    // Lbl [MS-XLS 2.4.150], decoded once all the names and sheets are known
+                    0x0018 if r.data.len() >= 15 => {
                         let cch = r.data[3] as usize;
-                        let cce = read_u16(&r.data[4..]) as usize;
                         let mut name = String::new();
-                        read_unicode_string_no_cch(&encoding, &r.data[14..], &cch, &mut name);
-                        let rgce = &r.data[r.data.len() - cce..];
-                        let formula = parse_defined_names(rgce)?;
+                        let high_byte = Some(r.data[14] & 0x1 != 0);
+                        let (_, len) = encoding.decode_to(&r.data[15..], cch, &mut name, high_byte);
+                        // fBuiltin
+                        if read_u16(r.data) & 0x0020 != 0 {
+                            name = builtin_name(&name);
+                        }
+                        // NameParsedFormula, prefixed by the length of its tokens like cell formulas
+                        let mut formula = r.data[4..6].to_vec();
+                        formula.extend_from_slice(&r.data[15 + len..]);
                         defined_names.push((name, formula));
                     }
                     0x0017 => {
-                        // ExternSheet
+                        // ExternSheet, resolved once all the sheets and links are known
                         let cxti = read_u16(r.data) as usize;
-                        xtis.extend(r.data[2..].chunks(6).take(cxti).map(|xti| Xti {
-                            _isup_book: read_u16(&xti[..2]),
-                            itab_first: read_i16(&xti[2..4]),
-                            _itab_last: read_i16(&xti[4..]),
-                        }));
+                        xtis_data
+                            .extend(r.data[2..].chunks_exact(6).take(cxti).map(<[u8]>::to_vec));
+                    }
+                    // SupBook [MS-XLS 2.4.271]
+                    0x01AE => sup_books.push(parse_sup_book(r.data, &encoding)?),
+                    // ExternName [MS-XLS 2.4.106]
+                    0x0023 if r.data.len() >= 8 => {
+                        if let Some(sup_book) = sup_books.last_mut() {
+                            let cch = r.data[6] as usize;
+                            let high_byte = Some(r.data[7] & 0x1 != 0);
+                            let mut name = String::with_capacity(cch);
+                            encoding.decode_to(&r.data[8..], cch, &mut name, high_byte);
+                            sup_book.link.names.push(name);
+                        }
+                    }
+                    // XCT [MS-XLS 2.4.352]: the following CRN records are cells of a sheet
+                    0x0059 if r.data.len() >= 4 => {
+                        crn_sheet = Some(read_u16(&r.data[2..]) as usize)
+                    }
+                    // CRN [MS-XLS 2.4.65]
+                    0x005A => {
+                        let sup_book = sup_books.last_mut();
+                        if let Some(cells) = sup_book
+                            .zip(crn_sheet)
+                            .and_then(|(b, i)| b.cells.get_mut(i))
+                        {
+                            cells.extend(parse_crn(r.data, &encoding)?);
+                        }
                     }
                     0x00FC => strings = parse_sst(&mut r, &encoding)?, // SST
                     #[cfg(feature = "picture")]
@@ -351,6 +552,10 @@ impl<RS: Read + Seek> Xls<RS> {
             }
         }
 
+        if workbook_protection.lock_structure || workbook_protection.lock_windows {
+            self.workbook_protection = Some(workbook_protection);
+        }
+
         self.formats = xfs
             .into_iter()
             .map(|fmt| match formats.get(&fmt) {
@@ -361,33 +566,65 @@ impl<RS: Read + Seek> Xls<RS> {
 
         debug!("formats: {:?}", self.formats);
 
-        let defined_names = defined_names
+        let xtis: Vec<Xti> = xtis_data
+            .iter()
+            .map(|xti| Xti::new(&sup_books, &sheet_names, xti))
+            .collect();
+        self.metadata.external_links = sup_books
             .into_iter()
-            .map(|(name, (i, mut f))| {
-                if let Some(i) = i {
-                    let sh = xtis
-                        .get(i)
-                        .and_then(|xti| sheet_names.get(xti.itab_first as usize))
-                        .map_or("#REF", |sh| &sh.1);
-                    f = format!("{sh}!{f}");
+            .filter(|b| b.kind == SupBookKind::External)
+            .map(|mut b| {
+                for (sheet, cells) in b.link.sheets.iter_mut().zip(b.cells) {
+                    sheet.cells = Range::from_sparse(cells);
                 }
-                (name, f)
+                b.link
             })
-            .collect::<Vec<_>>();
+            .collect();
+
+        // names are decoded once all are known, as they may reference the next ones
+        let mut names: Vec<_> = defined_names
+            .iter()
+            .map(|(name, _)| (name.clone(), String::new()))
+            .collect();
+        let formulas: Vec<_> = defined_names
+            .iter()
+            .map(
+                |(name, rgce)| match parse_formula(rgce, &names, &xtis, &encoding) {
+                    Ok(formula) => formula,
+                    Err(_) if read_u16(rgce) == 0 => String::new(),
+                    // shown as the error Excel gives for names it cannot evaluate
+                    Err(e) => {
+                        debug!("cannot parse defined name {}: {}", name, e);
+                        "#NAME?".to_string()
+                    }
+                },
+            )
+            .collect();
+        for (name, formula) in names.iter_mut().zip(formulas) {
+            name.1 = formula;
+        }
+        let defined_names = names;
 
         debug!("defined_names: {:?}", defined_names);
 
         let mut sheets = BTreeMap::new();
-        let fmla_sheet_names = sheet_names
-            .iter()
-            .map(|(_, n)| n.clone())
-            .collect::<Vec<_>>();
-        for (pos, name) in sheet_names {
+        #[cfg(feature = "picture")]
+        let mut sheet_drawings = Vec::new();
+        for (i, (pos, name)) in sheet_names.into_iter().enumerate() {
             let sh = &stream[pos..];
             let records = RecordIter { stream: sh };
             let mut cells = Vec::new();
             let mut formulas = Vec::new();
+            let mut exprs = Vec::new();
             let mut fmla_pos = (0, 0);
+            let mut layout = SheetLayout::default();
+            let mut view = SheetView::default();
+            let mut frozen = false;
+            let mut validations = Vec::new();
+            let mut protection = SheetProtection::default();
+            let mut locked = false;
+            #[cfg(feature = "picture")]
+            let mut drawing: Vec<u8> = Vec::new();
             for record in records {
                 let r = record?;
                 match r.typ {
@@ -411,6 +648,143 @@ impl<RS: Read + Seek> Xls<RS> {
                     0x00FD => cells.extend(parse_label_sst(r.data, &strings)?), // LabelSst
                     0x00BD => parse_mul_rk(r.data, &mut cells, &self.formats, self.is_1904)?, // 189: MulRk
                     0x000A => break, // 10: EOF,
+                    // 520: Row [MS-XLS 2.4.221]
+                    0x0208 if r.data.len() >= 16 => {
+                        let row = read_u16(r.data) as u32;
+                        let flags = read_u16(&r.data[12..]);
+                        let span = LayoutSpan {
+                            first: row,
+                            last: row,
+                            // fUnsynced: custom height
+                            size: (flags & 0x0040 != 0)
+                                .then(|| (read_u16(&r.data[6..]) & 0x7FFF) as f64 / 20.),
+                            hidden: flags & 0x0020 != 0,
+                            outline_level: (flags & 0x0007) as u8,
+                            collapsed: flags & 0x0010 != 0,
+                        };
+                        if span.size.is_some()
+                            || span.hidden
+                            || span.outline_level != 0
+                            || span.collapsed
+                        {
+                            layout.rows.push(span);
+                        }
+                    }
+                    // 125: ColInfo [MS-XLS 2.4.53]
+                    0x007D if r.data.len() >= 10 => {
+                        let flags = read_u16(&r.data[8..]);
+                        layout.columns.push(LayoutSpan {
+                            first: read_u16(r.data) as u32,
+                            last: read_u16(&r.data[2..]) as u32,
+                            size: Some(read_u16(&r.data[4..]) as f64 / 256.),
+                            hidden: flags & 0x0001 != 0,
+                            outline_level: ((flags >> 8) & 0x0007) as u8,
+                            collapsed: flags & 0x1000 != 0,
+                        });
+                    }
+                    // 574: Window2 [MS-XLS 2.4.346]
+                    0x023E if r.data.len() >= 6 => {
+                        let flags = read_u16(r.data);
+                        view.show_grid_lines = flags & 0x0002 != 0;
+                        view.right_to_left = flags & 0x0040 != 0;
+                        frozen = flags & 0x0008 != 0;
+                        view.top_left_cell =
+                            (read_u16(&r.data[2..]) as u32, read_u16(&r.data[4..]) as u32);
+                        if r.data.len() >= 14 && read_u16(&r.data[12..]) != 0 {
+                            view.zoom = read_u16(&r.data[12..]) as u32;
+                        }
+                    }
+                    // 160: Scl [MS-XLS 2.4.247], the zoom as a fraction
+                    0x00A0 if r.data.len() >= 4 => {
+                        let (num, den) = (read_i16(r.data), read_i16(&r.data[2..]));
+                        if num > 0 && den > 0 {
+                            view.zoom = (num as u32 * 100) / den as u32;
+                        }
+                    }
+                    // 65: Pane [MS-XLS 2.4.189]
+                    0x0041 if r.data.len() >= 10 => {
+                        view.pane = Some(Pane {
+                            state: if frozen {
+                                PaneState::Frozen
+                            } else {
+                                PaneState::Split
+                            },
+                            x_split: read_u16(r.data) as f64,
+                            y_split: read_u16(&r.data[2..]) as f64,
+                            top_left_cell: (
+                                read_u16(&r.data[4..]) as u32,
+                                read_u16(&r.data[6..]) as u32,
+                            ),
+                            active_pane: PanePosition::from_code(read_u16(&r.data[8..]) as u32),
+                        });
+                    }
+                    // 29: Selection [MS-XLS 2.4.248]
+                    0x001D if r.data.len() >= 9 => {
+                        let active_pane = view
+                            .pane
+                            .as_ref()
+                            .map_or(PanePosition::TopLeft, |p| p.active_pane);
+                        if PanePosition::from_code(r.data[0] as u32) == active_pane {
+                            view.active_cell =
+                                (read_u16(&r.data[1..]) as u32, read_u16(&r.data[3..]) as u32);
+                            let cref = read_u16(&r.data[7..]) as usize;
+                            view.selection = r.data[9..]
+                                .chunks_exact(6)
+                                .take(cref)
+                                .map(|rref| Dimensions {
+                                    start: (read_u16(rref) as u32, rref[4] as u32),
+                                    end: (read_u16(&rref[2..]) as u32, rref[5] as u32),
+                                })
+                                .collect();
+                        }
+                    }
+                    // 18: Protect
+                    0x0012 if r.data.len() >= 2 => locked = read_u16(r.data) != 0,
+                    // 19: Password
+                    0x0013 if r.data.len() >= 2 => {
+                        protection.password = PasswordHash::from_legacy(read_u16(r.data))
+                    }
+                    // 99: ObjProtect
+                    0x0063 if r.data.len() >= 2 => protection.edit_objects = read_u16(r.data) == 0,
+                    // 221: ScenarioProtect
+                    0x00DD if r.data.len() >= 2 => {
+                        protection.edit_scenarios = read_u16(r.data) == 0
+                    }
+                    // 2151: FeatHdr [MS-XLS 2.4.112], with EnhancedProtection when isf is 2
+                    0x0867
+                        if r.data.len() >= 21
+                            && read_u16(&r.data[12..]) == 2
+                            && read_u32(&r.data[15..]) != 0 =>
+                    {
+                        protection.set_allowed_flags(read_u16(&r.data[19..]))
+                    }
+                    // 2146: SheetExt [MS-XLS 2.4.269], 0x7F being the default tab color
+                    0x0862 if r.data.len() >= 20 => {
+                        let icv = read_u32(&r.data[16..]) & 0x7F;
+                        if icv != 0x7F {
+                            self.metadata.sheets[i].tab_color = Some(Color::Indexed(icv));
+                        }
+                    }
+                    // 446: DV [MS-XLS 2.4.107]
+                    0x01BE => {
+                        validations.extend(parse_dv(r.data, &defined_names, &xtis, &encoding)?)
+                    }
+                    // 549: DefaultRowHeight
+                    0x0225 if r.data.len() >= 4 => {
+                        layout.default_row_height = Some(read_i16(&r.data[2..]) as f64 / 20.);
+                    }
+                    // 153: StandardWidth
+                    0x0099 if r.data.len() >= 2 => {
+                        layout.default_column_width = Some(read_u16(r.data) as f64 / 256.);
+                    }
+                    #[cfg(feature = "picture")]
+                    0x00EC => {
+                        // MsoDrawing, all the records of a sheet form an OfficeArtDgContainer
+                        drawing.extend(r.data);
+                        if let Some(cont) = r.cont {
+                            drawing.extend(cont.iter().flat_map(|v| *v));
+                        }
+                    }
                     0x0006 => {
                         // 6: Formula
                         if r.data.len() < 20 {
@@ -428,29 +802,40 @@ impl<RS: Read + Seek> Xls<RS> {
                             // it will appear in 0x0207 record coming next
                             cells.push(Cell::new(fmla_pos, val));
                         }
-                        let fmla = parse_formula(
-                            &r.data[20..],
-                            &fmla_sheet_names,
-                            &defined_names,
-                            &xtis,
-                            &encoding,
-                        )
-                        .unwrap_or_else(|e| {
-                            debug!("{}", e);
-                            format!(
-                                "Unrecognised formula \
+                        match parse_formula_expr(&r.data[20..], &defined_names, &xtis, &encoding) {
+                            Ok(expr) => {
+                                formulas.push(Cell::new(fmla_pos, expr.to_string()));
+                                exprs.push(Cell::new(fmla_pos, expr));
+                            }
+                            Err(e) => {
+                                debug!("{}", e);
+                                let fmla = format!(
+                                    "Unrecognised formula \
                                  for cell ({}, {}): {:?}",
-                                row, col, e
-                            )
-                        });
-                        formulas.push(Cell::new(fmla_pos, fmla));
+                                    row, col, e
+                                );
+                                formulas.push(Cell::new(fmla_pos, fmla));
+                            }
+                        }
                     }
                     _ => (),
                 }
             }
+            #[cfg(feature = "picture")]
+            if !drawing.is_empty() {
+                sheet_drawings.push((name.clone(), drawing));
+            }
+            layout.columns.sort_by_key(|s| s.first);
+            layout.rows.sort_by_key(|s| s.first);
+            self.layouts.insert(name.clone(), layout);
+            self.views.insert(name.clone(), view);
+            self.protections
+                .insert(name.clone(), locked.then_some(protection));
+            self.data_validations.insert(name.clone(), validations);
             let range = Range::from_sparse(cells);
             let formula = Range::from_sparse(formulas);
-            sheets.insert(name, (range, formula));
+            let exprs = Range::from_sparse(exprs);
+            sheets.insert(name, (range, formula, exprs));
         }
 
         self.sheets = sheets;
@@ -462,6 +847,34 @@ impl<RS: Read + Seek> Xls<RS> {
             if !pics.is_empty() {
                 self.pictures = Some(pics);
             }
+            let blips = parse_blip_store(&draw_group)?;
+            let mut sheet_pics = Vec::new();
+            for (sheet_name, drawing) in sheet_drawings {
+                let mut shapes = Vec::new();
+                parse_shapes(&drawing, &mut shapes)?;
+                for shape in shapes {
+                    // pib is a 1-based index in the blip store
+                    let blip = shape
+                        .pib
+                        .checked_sub(1)
+                        .and_then(|i| blips.get(i as usize))
+                        .and_then(|b| b.as_ref());
+                    if let Some((extension, data)) = blip {
+                        sheet_pics.push(Picture {
+                            sheet_name: sheet_name.clone(),
+                            name: shape.name,
+                            description: shape.description,
+                            anchor: shape.anchor,
+                            size: None,
+                            extension: extension.clone(),
+                            data: data.clone(),
+                        });
+                    }
+                }
+            }
+            if !sheet_pics.is_empty() {
+                self.sheet_pictures = Some(sheet_pics);
+            }
         }
 
         Ok(())
@@ -553,7 +966,17 @@ fn parse_sheet_metadata(
         .filter(|b| *b != 0)
         .collect::<Vec<_>>();
     let name = String::from_utf8(sheet_name).unwrap();
-    Ok((pos, Sheet { name, visible, typ }))
+    Ok((
+        pos,
+        Sheet {
+            name,
+            visible,
+            typ,
+            tab_color: None,
+            sheet_id: None,
+            path: None,
+        },
+    ))
 }
 
 fn parse_number(r: &[u8], formats: &[CellFormat], is_1904: bool) -> Result<Cell<Data>, XlsError> {
@@ -594,20 +1017,139 @@ fn parse_bool_err(r: &[u8]) -> Result<Cell<Data>, XlsError> {
 }
 
 fn parse_err(e: u8) -> Result<Data, XlsError> {
-    match e {
-        0x00 => Ok(Data::Error(CellErrorType::Null)),
-        0x07 => Ok(Data::Error(CellErrorType::Div0)),
-        0x0F => Ok(Data::Error(CellErrorType::Value)),
-        0x17 => Ok(Data::Error(CellErrorType::Ref)),
-        0x1D => Ok(Data::Error(CellErrorType::Name)),
-        0x24 => Ok(Data::Error(CellErrorType::Num)),
-        0x2A => Ok(Data::Error(CellErrorType::NA)),
-        0x2B => Ok(Data::Error(CellErrorType::GettingData)),
-        e => Err(XlsError::Unrecognized {
-            typ: "error",
-            val: e,
-        }),
+    parse_berr(e).map(Data::Error)
+}
+
+/// BErr, an error value
+fn parse_berr(code: u8) -> Result<CellErrorType, XlsError> {
+    Ok(match code {
+        0x00 => CellErrorType::Null,
+        0x07 => CellErrorType::Div0,
+        0x0F => CellErrorType::Value,
+        0x17 => CellErrorType::Ref,
+        0x1D => CellErrorType::Name,
+        0x24 => CellErrorType::Num,
+        0x2A => CellErrorType::NA,
+        0x2B => CellErrorType::GettingData,
+        e => {
+            return Err(XlsError::Unrecognized {
+                typ: "BErr",
+                val: e,
+            })
+        }
+    })
+}
+
+/// SupBook [MS-XLS 2.4.271]
+fn parse_sup_book(r: &[u8], encoding: &XlsEncoding) -> Result<SupBook, XlsError> {
+    if r.len() < 4 {
+        return Err(XlsError::Len {
+            typ: "SupBook",
+            expected: 4,
+            found: r.len(),
+        });
     }
+    let ctab = read_u16(r) as usize;
+    let cch = read_u16(&r[2..]) as usize;
+    let kind = match cch {
+        0x0401 => SupBookKind::Internal,
+        0x3A01 => SupBookKind::AddIn,
+        _ => SupBookKind::External,
+    };
+    let mut link = ExternalLink::default();
+    if kind == SupBookKind::External && r.len() > 4 {
+        let mut path = String::with_capacity(cch);
+        let (_, read) = encoding.decode_to(&r[5..], cch, &mut path, Some(r[4] & 0x1 != 0));
+        link.target = decode_virtual_path(&path);
+        let mut r = &r[5 + read..];
+        for _ in 0..ctab {
+            if r.len() < 3 {
+                break;
+            }
+            let cch = read_u16(r) as usize;
+            let mut name = String::with_capacity(cch);
+            let (_, read) = encoding.decode_to(&r[3..], cch, &mut name, Some(r[2] & 0x1 != 0));
+            link.sheets.push(ExternalSheet {
+                name,
+                cells: Range::empty(),
+            });
+            r = &r[3 + read..];
+        }
+    }
+    let cells = vec![Vec::new(); link.sheets.len()];
+    Ok(SupBook { kind, link, cells })
+}
+
+/// Decode the file path of a SupBook [MS-XLS 2.5.277 VirtualPath]
+fn decode_virtual_path(path: &str) -> String {
+    let Some(encoded) = path.strip_prefix('\u{1}') else {
+        return path.to_string();
+    };
+    let mut decoded = String::with_capacity(path.len());
+    let mut chars = encoded.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // volume, '@' being a UNC path
+            '\u{1}' => match chars.next() {
+                Some('@') => decoded.push_str("\\\\"),
+                Some(v) => {
+                    decoded.push(v);
+                    decoded.push_str(":\\");
+                }
+                None => (),
+            },
+            // root of the same volume, or directory separator
+            '\u{2}' | '\u{3}' => decoded.push('\\'),
+            '\u{4}' => decoded.push_str("..\\"),
+            // long volume (e.g. an URL), prefixed by its length
+            '\u{5}' => {
+                let len = chars.next().map_or(0, |c| c as usize);
+                decoded.extend(chars.by_ref().take(len));
+            }
+            // startup, alternate startup and library folders
+            '\u{6}' | '\u{7}' | '\u{8}' => (),
+            c => decoded.push(c),
+        }
+    }
+    decoded
+}
+
+/// CRN [MS-XLS 2.4.65]: cached values of a row of an external sheet
+fn parse_crn(r: &[u8], encoding: &XlsEncoding) -> Result<Vec<Cell<Data>>, XlsError> {
+    if r.len() < 4 {
+        return Err(XlsError::Len {
+            typ: "CRN",
+            expected: 4,
+            found: r.len(),
+        });
+    }
+    let (col_last, col_first, row) = (r[0] as u32, r[1] as u32, read_u16(&r[2..]) as u32);
+    let mut cells = Vec::with_capacity((col_last + 1).saturating_sub(col_first) as usize);
+    let mut r = &r[4..];
+    for col in col_first..=col_last {
+        // SerAr [MS-XLS 2.5.224]
+        let Some((&typ, data)) = r.split_first() else {
+            break;
+        };
+        let (value, len) = match typ {
+            0x01 if data.len() >= 8 => (Data::Float(read_f64(data)), 8),
+            0x02 if data.len() >= 3 => {
+                let cch = read_u16(data) as usize;
+                let mut s = String::with_capacity(cch);
+                let (_, read) =
+                    encoding.decode_to(&data[3..], cch, &mut s, Some(data[2] & 0x1 != 0));
+                (Data::String(s), 3 + read)
+            }
+            0x04 if data.len() >= 8 => (Data::Bool(data[0] != 0), 8),
+            0x10 if data.len() >= 8 => (parse_err(data[0])?, 8),
+            _ => (Data::Empty, 8),
+        };
+        if value != Data::Empty {
+            cells.push(Cell::new((row, col), value));
+        }
+        r = data.get(len..).unwrap_or_default();
+    }
+    Ok(cells)
 }
 
 fn parse_rk(r: &[u8], formats: &[CellFormat], is_1904: bool) -> Result<Cell<Data>, XlsError> {
@@ -753,6 +1295,90 @@ fn parse_label(
     )));
 }
 
+/// DV [MS-XLS 2.4.107], `None` if a formula cannot be decoded
+fn parse_dv(
+    mut r: &[u8],
+    names: &[(String, String)],
+    xtis: &[Xti],
+    encoding: &XlsEncoding,
+) -> Result<Option<DataValidation>, XlsError> {
+    fn check_len(r: &[u8], expected: usize) -> Result<(), XlsError> {
+        if r.len() < expected {
+            return Err(XlsError::Len {
+                typ: "DV",
+                expected,
+                found: r.len(),
+            });
+        }
+        Ok(())
+    }
+    check_len(r, 4)?;
+    let mut dv = validation::from_flags(read_u32(r));
+    r = &r[4..];
+
+    // PromptTitle, ErrorTitle, Prompt and Error, a single '\0' meaning no text
+    let mut texts = [None, None, None, None];
+    for text in texts.iter_mut() {
+        check_len(r, 3)?;
+        let cch = read_u16(r) as usize;
+        let high_byte = r[2] & 0x1 != 0;
+        let len = 3 + if high_byte { 2 * cch } else { cch };
+        check_len(r, len)?;
+        let mut s = String::with_capacity(cch);
+        encoding.decode_to(&r[3..], cch, &mut s, Some(high_byte));
+        if s != "\0" {
+            *text = Some(s);
+        }
+        r = &r[len..];
+    }
+    let [prompt_title, error_title, prompt, error] = texts;
+    dv.prompt_title = prompt_title;
+    dv.error_title = error_title;
+    dv.prompt = prompt;
+    dv.error = error;
+
+    // DVParsedFormula: cce, 2 unused bytes, then rgce
+    let mut formulas = [None, None];
+    for formula in formulas.iter_mut() {
+        check_len(r, 4)?;
+        let cce = read_u16(r) as usize;
+        check_len(r, 4 + cce)?;
+        if cce > 0 {
+            let mut rgce = Vec::with_capacity(2 + cce);
+            rgce.extend_from_slice(&r[..2]);
+            rgce.extend_from_slice(&r[4..4 + cce]);
+            match parse_formula(&rgce, names, xtis, encoding) {
+                Ok(f) => *formula = Some(f),
+                Err(e) => {
+                    debug!(
+                        "skipping data validation with an unsupported formula: {}",
+                        e
+                    );
+                    return Ok(None);
+                }
+            }
+        }
+        r = &r[4 + cce..];
+    }
+    let [formula1, formula2] = formulas;
+    dv.formula1 = formula1;
+    dv.formula2 = formula2;
+    validation::fix_list_formula(&mut dv);
+
+    // SqRefU
+    check_len(r, 2)?;
+    let cref = read_u16(r) as usize;
+    check_len(r, 2 + cref * 8)?;
+    dv.ranges = r[2..2 + cref * 8]
+        .chunks(8)
+        .map(|c| Dimensions {
+            start: (read_u16(c) as u32, read_u16(&c[4..]) as u32),
+            end: (read_u16(&c[2..]) as u32, read_u16(&c[6..]) as u32),
+        })
+        .collect();
+    Ok(Some(dv))
+}
+
 fn parse_label_sst(r: &[u8], strings: &[String]) -> Result<Option<Cell<Data>>, XlsError> {
     if r.len() < 10 {
         return Err(XlsError::Len {
@@ -775,11 +1401,6 @@ fn parse_label_sst(r: &[u8], strings: &[String]) -> Result<Option<Cell<Data>>, X
     Ok(None)
 }
 
-struct Dimensions {
-    start: (u32, u32),
-    end: (u32, u32),
-}
-
 fn parse_dimensions(r: &[u8]) -> Result<Dimensions, XlsError> {
     let (rf, rl, cf, cl) = match r.len() {
         10 => (
@@ -946,10 +1567,6 @@ fn read_dbcs(
     Ok(s)
 }
 
-fn read_unicode_string_no_cch(encoding: &XlsEncoding, buf: &[u8], len: &usize, s: &mut String) {
-    encoding.decode_to(&buf[1..=*len], *len, s, Some(buf[0] & 0x1 != 0));
-}
-
 struct Record<'a> {
     typ: u16,
     data: &'a [u8],
@@ -1033,66 +1650,58 @@ impl<'a> Iterator for RecordIter<'a> {
     }
 }
 
-/// Formula parsing
-///
-/// Does not implement ALL possibilities, only Area are parsed
-fn parse_defined_names(rgce: &[u8]) -> Result<(Option<usize>, String), XlsError> {
-    if rgce.is_empty() {
-        // TODO: do something better here ...
-        return Ok((None, "empty rgce".to_string()));
-    }
-    let ptg = rgce[0];
-    let res = match ptg {
-        0x3a | 0x5a | 0x7a => {
-            // PtgRef3d
-            let ixti = read_u16(&rgce[1..3]) as usize;
-            let mut f = String::new();
-            // TODO: check with relative columns
-            f.push('$');
-            push_column(read_u16(&rgce[5..7]) as u32, &mut f);
-            f.push('$');
-            f.push_str(&format!("{}", read_u16(&rgce[3..5]) as u32 + 1));
-            (Some(ixti), f)
-        }
-        0x3b | 0x5b | 0x7b => {
-            // PtgArea3d
-            let ixti = read_u16(&rgce[1..3]) as usize;
-            let mut f = String::new();
-            // TODO: check with relative columns
-            f.push('$');
-            push_column(read_u16(&rgce[7..9]) as u32, &mut f);
-            f.push('$');
-            f.push_str(&format!("{}", read_u16(&rgce[3..5]) as u32 + 1));
-            f.push(':');
-            f.push('$');
-            push_column(read_u16(&rgce[9..11]) as u32, &mut f);
-            f.push('$');
-            f.push_str(&format!("{}", read_u16(&rgce[5..7]) as u32 + 1));
-            (Some(ixti), f)
-        }
-        0x3c | 0x5c | 0x7c | 0x3d | 0x5d | 0x7d => {
-            // PtgAreaErr3d or PtfRefErr3d
-            let ixti = read_u16(&rgce[1..3]) as usize;
-            (Some(ixti), "#REF!".to_string())
-        }
-        _ => (None, format!("Unsupported ptg: {:x}", ptg)),
+/// Name of a built-in defined name [MS-XLS 2.5.66], stored as a single character code, as
+/// written in xlsx files
+fn builtin_name(code: &str) -> String {
+    let name = match code.chars().next().map_or(u32::MAX, u32::from) {
+        0x00 => "Consolidate_Area",
+        0x01 => "Auto_Open",
+        0x02 => "Auto_Close",
+        0x03 => "Extract",
+        0x04 => "Database",
+        0x05 => "Criteria",
+        0x06 => "Print_Area",
+        0x07 => "Print_Titles",
+        0x08 => "Recorder",
+        0x09 => "Data_Form",
+        0x0A => "Auto_Activate",
+        0x0B => "Auto_Deactivate",
+        0x0C => "Sheet_Title",
+        0x0D => "_FilterDatabase",
+        _ => return code.to_string(),
     };
-    Ok(res)
+    format!("_xlnm.{name}")
 }
 
 /// Formula parsing
 ///
 /// CellParsedFormula [MS-XLS 2.5.198.3]
 fn parse_formula(
-    mut rgce: &[u8],
-    sheets: &[String],
+    rgce: &[u8],
     names: &[(String, String)],
     xtis: &[Xti],
     encoding: &XlsEncoding,
 ) -> Result<String, XlsError> {
+    parse_formula_expr(rgce, names, xtis, encoding).map(|e| e.to_string())
+}
+
+/// Decode the formula tokens into an expression
+fn parse_formula_expr(
+    mut rgce: &[u8],
+    names: &[(String, String)],
+    xtis: &[Xti],
+    encoding: &XlsEncoding,
+) -> Result<Expr, XlsError> {
+    // last row and column, for whole columns and rows
+    let (max_row, max_col) = (0xFFFF, 0xFF);
+    let sheet = |ixti: u16| {
+        let sheet = xtis.get(ixti as usize).map_or("#REF", |xti| &xti.sheet);
+        Some(sheet.to_string())
+    };
     let mut stack = Vec::new();
-    let mut formula = String::with_capacity(rgce.len());
     let cce = read_u16(rgce) as usize;
+    // extra data of the tokens, following them
+    let mut rgcb = rgce.get(2 + cce..).unwrap_or_default();
     rgce = &rgce[2..2 + cce];
     while !rgce.is_empty() {
         let ptg = rgce[0];
@@ -1100,116 +1709,92 @@ fn parse_formula(
         match ptg {
             0x3a | 0x5a | 0x7a => {
                 // PtgRef3d
-                let ixti = read_u16(&rgce[0..2]);
-                let rowu = read_u16(&rgce[2..]);
-                let colu = read_u16(&rgce[4..]);
-                let sh = xtis
-                    .get(ixti as usize)
-                    .and_then(|xti| sheets.get(xti.itab_first as usize))
-                    .map_or("#REF", |sh| sh);
-                stack.push(formula.len());
-                formula.push_str(sh);
-                formula.push('!');
-                let col = colu << 2; // first 14 bits only
-                if colu & 2 != 0 {
-                    formula.push('$');
-                }
-                push_column(col as u32, &mut formula);
-                if colu & 1 != 0 {
-                    formula.push('$');
-                }
-                write!(&mut formula, "{}", rowu + 1).unwrap();
+                let cell = CellRef::from_biff(read_u16(&rgce[2..]) as u32, read_u16(&rgce[4..]));
+                stack.push(Expr::Reference(Reference {
+                    sheet: sheet(read_u16(&rgce[0..2])),
+                    target: RefTarget::Cell(cell),
+                }));
                 rgce = &rgce[6..];
             }
             0x3b | 0x5b | 0x7b => {
                 // PtgArea3d
-                let ixti = read_u16(&rgce[0..2]);
-                stack.push(formula.len());
-                formula.push_str(sheets.get(ixti as usize).map_or("#REF", |s| &**s));
-                formula.push('!');
-                // TODO: check with relative columns
-                formula.push('$');
-                push_column(read_u16(&rgce[6..8]) as u32, &mut formula);
-                write!(&mut formula, "${}:$", read_u16(&rgce[2..4]) as u32 + 1).unwrap();
-                push_column(read_u16(&rgce[8..10]) as u32, &mut formula);
-                write!(&mut formula, "${}", read_u16(&rgce[4..6]) as u32 + 1).unwrap();
+                let target = RefTarget::from_biff_area(
+                    (read_u16(&rgce[2..4]) as u32, read_u16(&rgce[4..6]) as u32),
+                    (read_u16(&rgce[6..8]), read_u16(&rgce[8..10])),
+                    max_row,
+                    max_col,
+                );
+                stack.push(Expr::Reference(Reference {
+                    sheet: sheet(read_u16(&rgce[0..2])),
+                    target,
+                }));
                 rgce = &rgce[10..];
             }
             0x3c | 0x5c | 0x7c => {
                 // PtfRefErr3d
-                let ixti = read_u16(&rgce[0..2]);
-                stack.push(formula.len());
-                formula.push_str(sheets.get(ixti as usize).map_or("#REF", |s| &**s));
-                formula.push('!');
-                formula.push_str("#REF!");
+                stack.push(Expr::Reference(Reference {
+                    sheet: sheet(read_u16(&rgce[0..2])),
+                    target: RefTarget::Invalid,
+                }));
                 rgce = &rgce[6..];
             }
             0x3d | 0x5d | 0x7d => {
                 // PtgAreaErr3d
-                let ixti = read_u16(&rgce[0..2]);
-                stack.push(formula.len());
-                formula.push_str(sheets.get(ixti as usize).map_or("#REF", |s| &**s));
-                formula.push('!');
-                formula.push_str("#REF!");
+                stack.push(Expr::Reference(Reference {
+                    sheet: sheet(read_u16(&rgce[0..2])),
+                    target: RefTarget::Invalid,
+                }));
                 rgce = &rgce[10..];
             }
             0x01 => {
                 // PtgExp: array/shared formula, ignore
                 debug!("ignoring PtgExp array/shared formula");
-                stack.push(formula.len());
+                stack.push(Expr::Missing);
                 rgce = &rgce[4..];
             }
             0x03..=0x11 => {
                 // binary operation
                 let e2 = stack.pop().ok_or(XlsError::StackLen)?;
-                // imaginary 'e1' will actually already be the start of the binary op
+                let e1 = stack.pop().ok_or(XlsError::StackLen)?;
                 let op = match ptg {
-                    0x03 => "+",
-                    0x04 => "-",
-                    0x05 => "*",
-                    0x06 => "/",
-                    0x07 => "^",
-                    0x08 => "&",
-                    0x09 => "<",
-                    0x0A => "<=",
-                    0x0B => "=",
-                    0x0C => ">",
-                    0x0D => ">=",
-                    0x0E => "<>",
-                    0x0F => " ",
-                    0x10 => ",",
-                    0x11 => ":",
+                    0x03 => BinaryOp::Add,
+                    0x04 => BinaryOp::Sub,
+                    0x05 => BinaryOp::Mul,
+                    0x06 => BinaryOp::Div,
+                    0x07 => BinaryOp::Pow,
+                    0x08 => BinaryOp::Concat,
+                    0x09 => BinaryOp::Lt,
+                    0x0A => BinaryOp::Le,
+                    0x0B => BinaryOp::Eq,
+                    0x0C => BinaryOp::Ge,
+                    0x0D => BinaryOp::Gt,
+                    0x0E => BinaryOp::Ne,
+                    0x0F => BinaryOp::Intersect,
+                    0x10 => BinaryOp::Union,
+                    0x11 => BinaryOp::Range,
                     _ => unreachable!(),
                 };
-                let e2 = formula.split_off(e2);
-                write!(&mut formula, "{}{}", op, e2).unwrap();
-            }
-            0x12 => {
-                let e = stack.last().ok_or(XlsError::StackLen)?;
-                formula.insert(*e, '+');
-            }
-            0x13 => {
-                let e = stack.last().ok_or(XlsError::StackLen)?;
-                formula.insert(*e, '-');
-            }
-            0x14 => {
-                formula.push('%');
+                stack.push(Expr::Binary(op, Box::new(e1), Box::new(e2)));
             }
-            0x15 => {
-                let e = stack.last().ok_or(XlsError::StackLen)?;
-                formula.insert(*e, '(');
-                formula.push(')');
+            0x12..=0x15 => {
+                let e = Box::new(stack.pop().ok_or(XlsError::StackLen)?);
+                stack.push(match ptg {
+                    0x12 => Expr::Unary(UnaryOp::Plus, e),
+                    0x13 => Expr::Unary(UnaryOp::Minus, e),
+                    0x14 => Expr::Unary(UnaryOp::Percent, e),
+                    _ => Expr::Paren(e),
+                });
             }
             0x16 => {
-                stack.push(formula.len());
+                stack.push(Expr::Missing);
             }
             0x17 => {
-                stack.push(formula.len());
-                formula.push('\"');
+                let mut s = String::new();
                 let cch = rgce[0] as usize;
-                read_unicode_string_no_cch(encoding, &rgce[1..], &cch, &mut formula);
-                formula.push('\"');
-                rgce = &rgce[2 + cch..];
+                let high_byte = rgce[1] & 0x1 != 0;
+                let (_, len) = encoding.decode_to(&rgce[2..], cch, &mut s, Some(high_byte));
+                stack.push(Expr::Text(s));
+                rgce = &rgce[2 + len..];
             }
             0x18 => {
                 rgce = &rgce[5..];
@@ -1218,80 +1803,44 @@ fn parse_formula(
                 let etpg = rgce[0];
                 rgce = &rgce[1..];
                 match etpg {
-                    0x01 | 0x02 | 0x08 | 0x20 | 0x21 => rgce = &rgce[2..],
+                    // PtgAttrSpace (0x40 and 0x41): spaces are not kept
+                    0x01 | 0x02 | 0x08 | 0x20 | 0x21 | 0x40 | 0x41 => rgce = &rgce[2..],
                     0x04 => {
                         // PtgAttrChoose
                         let n = read_u16(&rgce[..2]) as usize + 1;
                         rgce = &rgce[2 + 2 * n..]; // ignore
                     }
                     0x10 => {
+                        // PtgAttrSum
                         rgce = &rgce[2..];
-                        let e = *stack.last().ok_or(XlsError::StackLen)?;
-                        let e = formula.split_off(e);
-                        write!(&mut formula, "SUM({})", e).unwrap();
-                    }
-                    0x40 | 0x41 => {
-                        // PtfAttrSpace
-                        let e = *stack.last().ok_or(XlsError::StackLen)?;
-                        let space = match rgce[0] {
-                            0x00 | 0x02 | 0x04 | 0x06 => ' ',
-                            0x01 | 0x03 | 0x05 => '\r',
-                            val => {
-                                return Err(XlsError::Unrecognized {
-                                    typ: "PtgAttrSpaceType",
-                                    val,
-                                });
-                            }
-                        };
-                        let cch = rgce[1];
-                        for _ in 0..cch {
-                            formula.insert(e, space);
-                        }
-                        rgce = &rgce[2..];
+                        let e = stack.pop().ok_or(XlsError::StackLen)?;
+                        stack.push(Expr::Function {
+                            name: "SUM".to_string(),
+                            args: vec![e],
+                        });
                     }
                     e => return Err(XlsError::Etpg(e)),
                 }
             }
             0x1C => {
-                stack.push(formula.len());
-                let err = rgce[0];
+                stack.push(Expr::Error(parse_berr(rgce[0])?));
                 rgce = &rgce[1..];
-                match err {
-                    0x00 => formula.push_str("#NULL!"),
-                    0x07 => formula.push_str("#DIV/0!"),
-                    0x0F => formula.push_str("#VALUE!"),
-                    0x17 => formula.push_str("#REF!"),
-                    0x1D => formula.push_str("#NAME?"),
-                    0x24 => formula.push_str("#NUM!"),
-                    0x2A => formula.push_str("#N/A"),
-                    0x2B => formula.push_str("#GETTING_DATA"),
-                    e => {
-                        return Err(XlsError::Unrecognized {
-                            typ: "BErr",
-                            val: e,
-                        });
-                    }
-                }
             }
             0x1D => {
-                stack.push(formula.len());
-                formula.push_str(if rgce[0] == 0 { "FALSE" } else { "TRUE" });
+                stack.push(Expr::Bool(rgce[0] != 0));
                 rgce = &rgce[1..];
             }
             0x1E => {
-                stack.push(formula.len());
-                write!(&mut formula, "{}", read_u16(rgce)).unwrap();
+                stack.push(Expr::Number(read_u16(rgce) as f64));
                 rgce = &rgce[2..];
             }
             0x1F => {
-                stack.push(formula.len());
-                write!(&mut formula, "{}", read_f64(rgce)).unwrap();
+                stack.push(Expr::Number(read_f64(rgce)));
                 rgce = &rgce[8..];
             }
             0x20 | 0x40 | 0x60 => {
-                // PtgArray: ignore
-                stack.push(formula.len());
-                formula.push_str("{PtgArray}");
+                // PtgArray, whose values are stored in the extra data
+                stack.push(Expr::Array(parse_extra_array(&mut rgcb, encoding)?));
                 rgce = &rgce[7..];
             }
             0x21 | 0x22 | 0x41 | 0x42 | 0x61 | 0x62 => {
@@ -1315,77 +1864,98 @@ fn parse_formula(
                 if stack.len() < argc {
                     return Err(XlsError::StackLen);
                 }
-                if argc > 0 {
-                    let args_start = stack.len() - argc;
-                    let mut args = stack.split_off(args_start);
-                    let start = args[0];
-                    for s in &mut args {
-                        *s -= start;
-                    }
-                    let fargs = formula.split_off(start);
-                    stack.push(formula.len());
-                    args.push(fargs.len());
-                    formula.push_str(
-                        crate::utils::FTAB
-                            .get(iftab)
-                            .ok_or(XlsError::IfTab(iftab))?,
-                    );
-                    formula.push('(');
-                    for w in args.windows(2) {
-                        formula.push_str(&fargs[w[0]..w[1]]);
-                        formula.push(',');
-                    }
-                    formula.pop();
-                    formula.push(')');
-                } else {
-                    stack.push(formula.len());
-                    formula.push_str(crate::utils::FTAB[iftab]);
-                    formula.push_str("()");
-                }
+                let name = crate::utils::FTAB
+                    .get(iftab)
+                    .ok_or(XlsError::IfTab(iftab))?;
+                let args = stack.split_off(stack.len() - argc);
+                stack.push(Expr::call(name, args));
             }
             0x23 | 0x43 | 0x63 => {
-                let iname = read_u32(rgce) as usize - 1; // one-based
-                stack.push(formula.len());
-                formula.push_str(names.get(iname).map_or("#REF!", |n| &*n.0));
+                let iname = (read_u32(rgce) as usize).wrapping_sub(1); // one-based
+                stack.push(match names.get(iname) {
+                    Some(name) => Expr::Name {
+                        sheet: None,
+                        name: name.0.clone(),
+                    },
+                    None => Expr::Error(CellErrorType::Ref),
+                });
                 rgce = &rgce[4..];
             }
             0x24 | 0x44 | 0x64 => {
-                stack.push(formula.len());
-                let row = read_u16(rgce) + 1;
-                let col = read_u16(&[rgce[2], rgce[3] & 0x3F]);
-                if rgce[3] & 0x80 != 0x80 {
-                    formula.push('$');
-                }
-                push_column(col as u32, &mut formula);
-                if rgce[3] & 0x40 != 0x40 {
-                    formula.push('$');
-                }
-                formula.push_str(&format!("{}", row));
+                let cell = CellRef::from_biff(read_u16(rgce) as u32, read_u16(&rgce[2..]));
+                stack.push(Expr::Reference(Reference {
+                    sheet: None,
+                    target: RefTarget::Cell(cell),
+                }));
                 rgce = &rgce[4..];
             }
             0x25 | 0x45 | 0x65 => {
-                stack.push(formula.len());
-                formula.push('$');
-                push_column(read_u16(&rgce[4..6]) as u32, &mut formula);
-                write!(&mut formula, "${}:$", read_u16(&rgce[0..2]) as u32 + 1).unwrap();
-                push_column(read_u16(&rgce[6..8]) as u32, &mut formula);
-                write!(&mut formula, "${}", read_u16(&rgce[2..4]) as u32 + 1).unwrap();
+                let target = RefTarget::from_biff_area(
+                    (read_u16(&rgce[0..2]) as u32, read_u16(&rgce[2..4]) as u32),
+                    (read_u16(&rgce[4..6]), read_u16(&rgce[6..8])),
+                    max_row,
+                    max_col,
+                );
+                stack.push(Expr::Reference(Reference {
+                    sheet: None,
+                    target,
+                }));
                 rgce = &rgce[8..];
             }
+            0x26 | 0x46 | 0x66 => {
+                // PtgMemArea, whose areas are stored in the extra data, followed by the tokens
+                // of its expression
+                let count = rgcb.get(..2).map_or(0, read_u16) as usize;
+                let len = 2 + 8 * count;
+                if rgcb.len() < len {
+                    return Err(XlsError::Len {
+                        expected: len,
+                        found: rgcb.len(),
+                        typ: "PtgExtraMem",
+                    });
+                }
+                rgcb = &rgcb[len..];
+                rgce = &rgce[6..];
+            }
+            0x27 | 0x47 | 0x67 | 0x28 | 0x48 | 0x68 => {
+                // PtgMemErr | PtgMemNoMem, followed by the tokens of their expression
+                rgce = &rgce[6..];
+            }
+            0x29 | 0x49 | 0x69 => {
+                // PtgMemFunc, followed by the tokens of its expression
+                rgce = &rgce[2..];
+            }
             0x2A | 0x4A | 0x6A => {
-                stack.push(formula.len());
-                formula.push_str("#REF!");
+                stack.push(Expr::Reference(Reference {
+                    sheet: None,
+                    target: RefTarget::Invalid,
+                }));
                 rgce = &rgce[4..];
             }
             0x2B | 0x4B | 0x6B => {
-                stack.push(formula.len());
-                formula.push_str("#REF!");
+                stack.push(Expr::Reference(Reference {
+                    sheet: None,
+                    target: RefTarget::Invalid,
+                }));
                 rgce = &rgce[8..];
             }
-            0x39 | 0x59 => {
-                // PfgNameX
-                stack.push(formula.len());
-                formula.push_str("[PtgNameX]");
+            0x39 | 0x59 | 0x79 => {
+                // PtgNameX: name of an external workbook or add-in function
+                let ixti = read_u16(rgce) as usize;
+                let iname = read_u32(&rgce[2..]) as usize; // one-based
+                let xti = xtis.get(ixti);
+                let name = xti.and_then(|xti| match (&xti.book, xti.names.is_empty()) {
+                    // names of the workbook itself
+                    (None, true) => names.get(iname.wrapping_sub(1)).map(|(name, _)| name),
+                    _ => xti.names.get(iname.wrapping_sub(1)),
+                });
+                stack.push(match (xti, name) {
+                    (Some(xti), Some(name)) => Expr::Name {
+                        sheet: xti.book.as_ref().map(|book| format!("[{book}]")),
+                        name: name.clone(),
+                    },
+                    _ => Expr::Error(CellErrorType::Ref),
+                });
                 rgce = &rgce[6..];
             }
             _ => {
@@ -1397,7 +1967,7 @@ fn parse_formula(
         }
     }
     if stack.len() == 1 {
-        Ok(formula)
+        Ok(stack.pop().unwrap_or_default())
     } else {
         Err(XlsError::InvalidFormula {
             stack_size: stack.len(),
@@ -1405,6 +1975,56 @@ fn parse_formula(
     }
 }
 
+/// PtgExtraArray, the values of a `PtgArray`, by rows
+fn parse_extra_array(rgcb: &mut &[u8], encoding: &XlsEncoding) -> Result<Vec<Vec<Expr>>, XlsError> {
+    let len_error = |expected: usize, found: usize| XlsError::Len {
+        expected,
+        found,
+        typ: "PtgExtraArray",
+    };
+    if rgcb.len() < 3 {
+        return Err(len_error(3, rgcb.len()));
+    }
+    let cols = rgcb[0] as usize + 1;
+    let rows = read_u16(&rgcb[1..]) as usize + 1;
+    let mut buf = &rgcb[3..];
+    let mut array = Vec::with_capacity(rows.min(1024));
+    for _ in 0..rows {
+        let mut row = Vec::with_capacity(cols);
+        for _ in 0..cols {
+            // SerAr, 9 bytes except strings
+            if buf.len() < 9 {
+                return Err(len_error(9, buf.len()));
+            }
+            let (value, len) = match buf[0] {
+                0x00 => (Expr::Missing, 9),
+                0x01 => (Expr::Number(read_f64(&buf[1..])), 9),
+                0x02 => {
+                    // XLUnicodeString
+                    let mut s = String::new();
+                    let cch = read_u16(&buf[1..]) as usize;
+                    let high_byte = Some(buf[3] & 0x1 != 0);
+                    let (_, len) = encoding.decode_to(&buf[4..], cch, &mut s, high_byte);
+                    (Expr::Text(s), 4 + len)
+                }
+                0x04 => (Expr::Bool(buf[1] != 0), 9),
+                0x10 => (Expr::Error(parse_berr(buf[1])?), 9),
+                typ => {
+                    return Err(XlsError::Unrecognized {
+                        typ: "SerAr",
+                        val: typ,
+                    })
+                }
+            };
+            row.push(value);
+            buf = &buf[len..];
+        }
+        array.push(row);
+    }
+    *rgcb = buf;
+    Ok(array)
+}
+
 /// FormulaValue [MS-XLS 2.5.133]
 fn parse_formula_value(r: &[u8]) -> Result<Option<Data>, XlsError> {
     match *r {
@@ -1468,6 +2088,144 @@ impl<'a> Iterator for ArtRecordIter<'a> {
     }
 }
 
+/// Image extension and data
+#[cfg(feature = "picture")]
+type Blip = (String, Vec<u8>);
+
+/// Parses the OfficeArtBStoreContainer of a drawing group, the blip of each
+/// OfficeArtFBSE, if any, is returned in order
+#[cfg(feature = "picture")]
+fn parse_blip_store(stream: &[u8]) -> Result<Vec<Option<Blip>>, XlsError> {
+    let mut blips = Vec::new();
+    for record in (ArtRecordIter { stream }) {
+        let r = record?;
+        match r.typ {
+            // OfficeArtDggContainer [MS-ODRAW 2.2.12]
+            0xF000 => blips.extend(parse_blip_store(r.data)?),
+            // OfficeArtBStoreContainer [MS-ODRAW 2.2.20]
+            0xF001 => {
+                for fbse in (ArtRecordIter { stream: r.data }) {
+                    let fbse = fbse?;
+                    // OfficeArtFBSE [MS-ODRAW 2.2.32]
+                    let blip = match fbse.typ {
+                        0xF007 if fbse.data.len() > 36 => {
+                            let skip = 36 + fbse.data[33] as usize;
+                            parse_pictures(fbse.data.get(skip..).unwrap_or_default())?
+                                .into_iter()
+                                .next()
+                        }
+                        _ => None,
+                    };
+                    blips.push(blip);
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(blips)
+}
+
+/// A picture shape of a sheet drawing
+#[cfg(feature = "picture")]
+struct Shape {
+    pib: u32,
+    name: String,
+    description: Option<String>,
+    anchor: PictureAnchor,
+}
+
+/// Parses the shapes of an OfficeArtDgContainer [MS-ODRAW 2.2.13]
+///
+/// Only shapes filled with a blip (pictures) are returned.
+#[cfg(feature = "picture")]
+fn parse_shapes(stream: &[u8], shapes: &mut Vec<Shape>) -> Result<(), XlsError> {
+    for record in (ArtRecordIter { stream }) {
+        let r = record?;
+        match r.typ {
+            // OfficeArtDgContainer, OfficeArtSpgrContainer [MS-ODRAW 2.2.16]
+            0xF002 | 0xF003 => parse_shapes(r.data, shapes)?,
+            // OfficeArtSpContainer [MS-ODRAW 2.2.14]
+            0xF004 => {
+                let mut pib = 0;
+                let mut name = String::new();
+                let mut description = None;
+                let mut anchor = None;
+                for child in (ArtRecordIter { stream: r.data }) {
+                    let child = child?;
+                    match child.typ {
+                        // OfficeArtFOPT, OfficeArtTertiaryFOPT [MS-ODRAW 2.2.9, 2.2.11]
+                        0xF00B | 0xF122 => {
+                            let n = child.instance as usize;
+                            if child.data.len() < n * 6 {
+                                return Err(XlsError::Art("FOPT properties"));
+                            }
+                            let mut complex = &child.data[n * 6..];
+                            for prop in child.data[..n * 6].chunks(6) {
+                                let opid = read_u16(prop);
+                                let op = read_u32(&prop[2..]);
+                                let value = if opid & 0x8000 != 0 {
+                                    let len = (op as usize).min(complex.len());
+                                    let (value, next) = complex.split_at(len);
+                                    complex = next;
+                                    value
+                                } else {
+                                    &[]
+                                };
+                                match opid & 0x3FFF {
+                                    0x0104 => pib = op,
+                                    0x0380 => name = read_utf16_z(value),
+                                    0x0381 => description = Some(read_utf16_z(value)),
+                                    _ => (),
+                                }
+                            }
+                        }
+                        // OfficeArtClientAnchorSheet [MS-XLS 2.2.2]
+                        0xF010 if child.data.len() >= 18 => {
+                            let d = child.data;
+                            anchor = Some(PictureAnchor::TwoCell {
+                                from: AnchorMarker {
+                                    col: read_u16(&d[2..]) as u32,
+                                    col_offset: read_i16(&d[4..]) as i64,
+                                    row: read_u16(&d[6..]) as u32,
+                                    row_offset: read_i16(&d[8..]) as i64,
+                                },
+                                to: AnchorMarker {
+                                    col: read_u16(&d[10..]) as u32,
+                                    col_offset: read_i16(&d[12..]) as i64,
+                                    row: read_u16(&d[14..]) as u32,
+                                    row_offset: read_i16(&d[16..]) as i64,
+                                },
+                            });
+                        }
+                        _ => (),
+                    }
+                }
+                if let Some(anchor) = anchor.filter(|_| pib != 0) {
+                    shapes.push(Shape {
+                        pib,
+                        name,
+                        description: description.filter(|d| !d.is_empty()),
+                        anchor,
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Reads a null terminated UTF-16 string
+#[cfg(feature = "picture")]
+fn read_utf16_z(buf: &[u8]) -> String {
+    let wide: Vec<u16> = buf
+        .chunks_exact(2)
+        .map(read_u16)
+        .take_while(|c| *c != 0)
+        .collect();
+    String::from_utf16_lossy(&wide)
+}
+
 /// Parsing pictures
 #[cfg(feature = "picture")]
 fn parse_pictures(stream: &[u8]) -> Result<Vec<(String, Vec<u8>)>, XlsError> {
@@ -1,19 +1,28 @@
+use std::collections::BTreeMap;
+
 use crate::{
     datatype::DataRef,
     formats::{format_excel_f64_ref, CellFormat},
+    formula::Expr,
     utils::{read_f64, read_i32, read_u32, read_usize},
     Cell, CellErrorType, Dimensions, XlsbError,
 };
 
-use super::{cell_format, parse_formula, wide_str, RecordIter};
+use super::{cell_format, parse_formula_expr, wide_str, FormulaContext, RecordIter};
+
+/// A shared or array formula: range, tokens, extra data and whether it is an array formula
+type SharedFormula = (Dimensions, Vec<u8>, Vec<u8>, bool);
 
 /// A cells reader for xlsb files
 pub struct XlsbCellsReader<'a> {
     iter: RecordIter<'a>,
     formats: &'a [CellFormat],
     strings: &'a [String],
-    extern_sheets: &'a [String],
-    metadata_names: &'a [(String, String)],
+    ctx: FormulaContext<'a>,
+    /// Shared and array formulas by their first cell
+    shared: BTreeMap<(u32, u32), SharedFormula>,
+    /// Is the record in `buf` read but not processed yet
+    replay: bool,
     typ: u16,
     row: u32,
     is_1904: bool,
@@ -26,8 +35,7 @@ impl<'a> XlsbCellsReader<'a> {
         mut iter: RecordIter<'a>,
         formats: &'a [CellFormat],
         strings: &'a [String],
-        extern_sheets: &'a [String],
-        metadata_names: &'a [(String, String)],
+        ctx: FormulaContext<'a>,
         is_1904: bool,
     ) -> Result<Self, XlsbError> {
         let mut buf = Vec::with_capacity(1024);
@@ -59,8 +67,9 @@ impl<'a> XlsbCellsReader<'a> {
             formats,
             is_1904,
             strings,
-            extern_sheets,
-            metadata_names,
+            ctx,
+            shared: BTreeMap::new(),
+            replay: false,
             dimensions,
             typ: 0,
             row: 0,
@@ -150,34 +159,23 @@ impl<'a> XlsbCellsReader<'a> {
     }
 
     pub fn next_formula(&mut self) -> Result<Option<Cell<String>>, XlsbError> {
-        let value = loop {
-            self.typ = self.iter.read_type()?;
-            let _ = self.iter.fill_buffer(&mut self.buf)?;
+        let cell = self.next_formula_ast()?;
+        Ok(cell.map(|c| Cell::new(c.pos, c.val.to_string())))
+    }
 
-            let value = match self.typ {
+    /// Get the next formula, as an expression
+    pub fn next_formula_ast(&mut self) -> Result<Option<Cell<Expr>>, XlsbError> {
+        loop {
+            if !std::mem::take(&mut self.replay) {
+                self.typ = self.iter.read_type()?;
+                let _ = self.iter.fill_buffer(&mut self.buf)?;
+            }
+
+            let start = match self.typ {
                 // 0x0001 => continue, // Data::Empty, // BrtCellBlank
-                0x0008 => {
-                    // BrtFmlaString
-                    let cch = read_u32(&self.buf[8..]) as usize;
-                    let formula = &self.buf[14 + cch * 2..];
-                    let cce = read_u32(formula) as usize;
-                    let rgce = &formula[4..4 + cce];
-                    parse_formula(rgce, &self.extern_sheets, &self.metadata_names)?
-                }
-                0x0009 => {
-                    // BrtFmlaNum
-                    let formula = &self.buf[18..];
-                    let cce = read_u32(formula) as usize;
-                    let rgce = &formula[4..4 + cce];
-                    parse_formula(rgce, &self.extern_sheets, &self.metadata_names)?
-                }
-                0x000A | 0x000B => {
-                    // BrtFmlaBool | BrtFmlaError
-                    let formula = &self.buf[11..];
-                    let cce = read_u32(formula) as usize;
-                    let rgce = &formula[4..4 + cce];
-                    parse_formula(rgce, &self.extern_sheets, &self.metadata_names)?
-                }
+                0x0008 => 14 + read_u32(&self.buf[8..]) as usize * 2, // BrtFmlaString
+                0x0009 => 18,                                         // BrtFmlaNum
+                0x000A | 0x000B => 11,                                // BrtFmlaBool | BrtFmlaError
                 0x0000 => {
                     // BrtRowHdr
                     self.row = read_u32(&self.buf);
@@ -189,11 +187,70 @@ impl<'a> XlsbCellsReader<'a> {
                 0x0092 => return Ok(None), // BrtEndSheetData
                 _ => continue, // anything else, ignore and try next, without changing idx
             };
-            break value;
-        };
-        let col = read_u32(&self.buf);
-        Ok(Some(Cell::new((self.row, col), value)))
+            let pos = (self.row, read_u32(&self.buf));
+            let (rgce, mut rgcb) = parsed_formula(&self.buf[start..]);
+            let value = if rgce.len() == 5 && rgce[0] == 0x01 {
+                // PtgExp
+                self.shared_formula(read_u32(&rgce[1..]), pos)?
+            } else {
+                parse_formula_expr(rgce, &mut rgcb, pos, self.ctx)?
+            };
+            return Ok(Some(Cell::new(pos, value)));
+        }
+    }
+
+    /// Decode the shared or array formula of a cell
+    ///
+    /// The BrtShrFmla or BrtArrFmla record follows the formula record of the first cell of its
+    /// range. The PtgExp token only has the row of that first cell.
+    fn shared_formula(&mut self, row: u32, pos: (u32, u32)) -> Result<Expr, XlsbError> {
+        if self.find_shared(row, pos).is_none() {
+            self.typ = self.iter.read_type()?;
+            let len = self.iter.fill_buffer(&mut self.buf)?;
+            match self.typ {
+                0x01AA | 0x01AB if len >= 16 => {
+                    // BrtArrFmla | BrtShrFmla
+                    let is_array = self.typ == 0x01AA;
+                    let start = if is_array { 17 } else { 16 };
+                    let (rgce, rgcb) = parsed_formula(&self.buf[start.min(len)..len]);
+                    let range = parse_dimensions(&self.buf[..16]);
+                    self.shared
+                        .insert(range.start, (range, rgce.to_vec(), rgcb.to_vec(), is_array));
+                }
+                _ => self.replay = true,
+            }
+        }
+        match self.find_shared(row, pos) {
+            // array formulas are only written in their first cell
+            Some((range, _, _, true)) if range.start != pos => Ok(Expr::Missing),
+            Some((_, rgce, rgcb, _)) => parse_formula_expr(rgce, &mut &rgcb[..], pos, self.ctx),
+            None => Ok(Expr::Missing),
+        }
+    }
+
+    /// Finds the shared or array formula of a cell, among the ones starting on `row` at or
+    /// before its column
+    fn find_shared(&self, row: u32, pos: (u32, u32)) -> Option<&SharedFormula> {
+        self.shared
+            .range((row, 0)..=(row, pos.1))
+            .rev()
+            .map(|(_, f)| f)
+            .find(|f| f.0.contains(pos.0, pos.1))
+    }
+}
+
+/// Split a CellParsedFormula into its tokens and their extra data
+fn parsed_formula(buf: &[u8]) -> (&[u8], &[u8]) {
+    if buf.len() < 4 {
+        return (&[], &[]);
     }
+    let cce = read_usize(buf);
+    let (rgce, rest) = buf[4..].split_at(cce.min(buf.len() - 4));
+    let rgcb = match rest.get(4..) {
+        Some(rgcb) => &rgcb[..read_usize(rest).min(rgcb.len())],
+        None => &[],
+    };
+    (rgce, rgcb)
 }
 
 fn parse_dimensions(buf: &[u8]) -> Dimensions {
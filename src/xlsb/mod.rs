@@ -19,9 +19,24 @@ use zip::result::ZipError;
 
 use crate::datatype::DataRef;
 use crate::formats::{builtin_format_by_code, detect_custom_number_format, CellFormat};
-use crate::utils::{push_column, read_f64, read_i32, read_u16, read_u32, read_usize};
+use crate::formula::{BinaryOp, Expr, UnaryOp};
+use crate::reference::{CellRef, RefTarget, Reference};
+use crate::relationships::{read_relationships, resolve_target};
+use crate::theme::read_theme;
+use crate::utils::{read_f64, read_i16, read_i32, read_u16, read_u32, read_usize};
+use crate::validation;
 use crate::vba::VbaProject;
-use crate::{Cell, Data, Metadata, Range, Reader, Sheet, SheetType, SheetVisible};
+use crate::{chart, Chart};
+#[cfg(feature = "picture")]
+use crate::{picture, Picture};
+use crate::{
+    Cell, CellErrorType, Color, ColorScale, ConditionalFormatRule, ConditionalFormatType,
+    ConditionalFormatting, ConditionalValue, ConditionalValueType, Data, DataBar, DataValidation,
+    DifferentialFormat, Dimensions, HeaderFooterText, IconSet, LayoutSpan, Metadata, Orientation,
+    PageMargins, PageOrder, PageSetup, Pane, PanePosition, PaneState, PasswordHash, Range, Reader,
+    Sheet, SheetLayout, SheetProtection, SheetType, SheetView, SheetVisible, Table,
+    ValidationOperator, WorkbookProtection,
+};
 
 /// A Xlsb specific error
 #[derive(Debug)]
@@ -79,6 +94,19 @@ pub enum XlsbError {
     Password,
     /// Worksheet not found
     WorksheetNotFound(String),
+    /// Table not found
+    TableNotFound(String),
+    /// Tables must be loaded (`load_tables`) to decode structured references
+    TablesNotLoaded,
+    /// Invalid record length
+    Len {
+        /// record type
+        typ: &'static str,
+        /// expected length
+        expected: usize,
+        /// found length
+        found: usize,
+    },
 }
 
 from_err!(std::io::Error, XlsbError, Io);
@@ -113,6 +141,16 @@ impl std::fmt::Display for XlsbError {
             }
             XlsbError::Password => write!(f, "Workbook is password protected"),
             XlsbError::WorksheetNotFound(name) => write!(f, "Worksheet '{name}' not found"),
+            XlsbError::TableNotFound(name) => write!(f, "Table '{name}' not found"),
+            XlsbError::TablesNotLoaded => write!(f, "Tables must be loaded first"),
+            XlsbError::Len {
+                typ,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Invalid {typ} length, expected at least {expected}, found {found}"
+            ),
         }
     }
 }
@@ -129,18 +167,53 @@ impl std::error::Error for XlsbError {
     }
 }
 
+/// Name, sheet, columns, data dimensions, id and header row count of a table
+type TableMetadata = (String, String, Vec<String>, Dimensions, u32, u32);
+
+type Tables = Option<Vec<TableMetadata>>;
+
+/// Names referenced by the `PtgNameX` tokens of an XTI
+#[derive(Debug, Clone)]
+enum ExternNames {
+    /// The defined names of this workbook
+    Internal,
+    /// The names of an external workbook, with its 1-based index in the formulas
+    External(usize, Vec<String>),
+}
+
+/// Workbook data needed to decode formulas
+#[derive(Clone, Copy)]
+pub(crate) struct FormulaContext<'a> {
+    /// Sheet prefix of each XTI
+    sheets: &'a [String],
+    /// Defined names
+    names: &'a [(String, String)],
+    /// Names of each XTI, for `PtgNameX`
+    extern_names: &'a [ExternNames],
+    /// Tables, for `PtgList`, `None` if not loaded yet
+    tables: Option<&'a [TableMetadata]>,
+}
+
 /// A Xlsb reader
 pub struct Xlsb<RS> {
     zip: ZipArchive<RS>,
     extern_sheets: Vec<String>,
+    extern_names: Vec<ExternNames>,
     sheets: Vec<(String, String)>,
+    /// Tables, loaded on demand
+    tables: Tables,
     strings: Vec<String>,
     /// Cell (number) formats
     formats: Vec<CellFormat>,
+    /// Differential formats
+    dxfs: Vec<DifferentialFormat>,
     is_1904: bool,
+    workbook_protection: Option<WorkbookProtection>,
     metadata: Metadata,
     #[cfg(feature = "picture")]
     pictures: Option<Vec<(String, Vec<u8>)>>,
+    #[cfg(feature = "picture")]
+    sheet_pictures: Option<Vec<Picture>>,
 }
 
 impl<RS: Read + Seek> Xlsb<RS> {
@@ -195,6 +268,23 @@ impl<RS: Read + Seek> Xlsb<RS> {
         Ok(relationships)
     }
 
+    /// The theme part is shared with xlsx files
+    fn read_theme(&mut self) -> Result<(), XlsbError> {
+        match self.zip.by_name("xl/theme/theme1.xml") {
+            Ok(f) => {
+                let mut xml = XmlReader::from_reader(BufReader::new(f));
+                xml.check_end_names(false)
+                    .trim_text(false)
+                    .check_comments(false)
+                    .expand_empty_elements(true);
+                self.metadata.colors.theme = read_theme(&mut xml)?;
+            }
+            Err(ZipError::FileNotFound) => (),
+            Err(e) => return Err(XlsbError::Zip(e)),
+        }
+        Ok(())
+    }
+
     /// MS-XLSB 2.1.7.50 Styles
     fn read_styles(&mut self) -> Result<(), XlsbError> {
         let mut iter = match RecordIter::from_zip(&mut self.zip, "xl/styles.bin") {
@@ -247,6 +337,21 @@ impl<RS: Read + Seek> Xlsb<RS> {
             buf.clear();
         }
 
+        // differential formats, after the cell styles
+        loop {
+            let typ = match iter.read_type() {
+                Ok(typ) => typ,
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            };
+            let len = iter.fill_buffer(&mut buf)?;
+            match typ {
+                0x01FB => self.dxfs.push(parse_dxf(&buf[..len])?), // BrtDXF
+                0x01FA => break,                                   // BrtEndDXFs
+                _ => (),
+            }
+        }
+
         Ok(())
     }
 
@@ -282,6 +387,7 @@ impl<RS: Read + Seek> Xlsb<RS> {
     ) -> Result<(), XlsbError> {
         let mut iter = RecordIter::from_zip(&mut self.zip, "xl/workbook.bin")?;
         let mut buf = Vec::with_capacity(1024);
+        let mut view_read = false;
 
         loop {
             match iter.read_type()? {
@@ -289,6 +395,20 @@ impl<RS: Read + Seek> Xlsb<RS> {
                     let _ = iter.fill_buffer(&mut buf)?;
                     self.is_1904 = &buf[0] & 0x1 != 0;
                 } // BrtWbProp
+                0x0216 => {
+                    // BrtBookProtection
+                    let len = iter.fill_buffer(&mut buf)?;
+                    if len >= 8 {
+                        let protection = WorkbookProtection {
+                            lock_structure: read_u16(&buf[4..]) != 0,
+                            lock_windows: read_u16(&buf[6..]) != 0,
+                            password: PasswordHash::from_legacy(read_u16(&buf)),
+                        };
+                        if protection.lock_structure || protection.lock_windows {
+                            self.workbook_protection = Some(protection);
+                        }
+                    }
+                }
                 0x009C => {
                     // BrtBundleSh
                     let len = iter.fill_buffer(&mut buf)?;
@@ -327,65 +447,164 @@ impl<RS: Read + Seek> Xlsb<RS> {
                             name: name.to_string(),
                             typ,
                             visible,
+                            tab_color: None,
+                            sheet_id: Some(read_u32(&buf[4..])),
+                            path: Some(path.clone()),
                         });
                         self.sheets.push((name.into_owned(), path));
                     };
                 }
-                0x0090 => break, // BrtEndBundleShs
-                _ => (),
+                // BrtBookView, the first one being used when the workbook is opened
+                0x009E if !view_read => {
+                    let len = iter.fill_buffer(&mut buf)?;
+                    if len >= 28 {
+                        self.metadata.first_visible_sheet = read_u32(&buf[20..]) as usize;
+                        self.metadata.active_sheet = read_u32(&buf[24..]) as usize;
+                    }
+                    view_read = true;
+                }
+                0x0090 => {
+                    // BrtEndBundleShs
+                    let _ = iter.fill_buffer(&mut buf)?;
+                    break;
+                }
+                _ => {
+                    let _ = iter.fill_buffer(&mut buf)?;
+                }
             }
             buf.clear();
         }
 
         // BrtName
-        let mut defined_names = Vec::new();
+        let mut supbooks = Vec::new();
+        let mut names = Vec::new();
         loop {
             let typ = iter.read_type()?;
             match typ {
+                0x0163 => {
+                    // BrtSupBookSrc, an external workbook
+                    let _ = iter.fill_buffer(&mut buf)?;
+                    let index = supbooks
+                        .iter()
+                        .filter(|(b, _)| matches!(b, ExternNames::External(..)))
+                        .count();
+                    supbooks.push((ExternNames::External(index + 1, Vec::new()), Vec::new()));
+                }
+                0x0165 | 0x0166 => {
+                    // BrtSupSelf | BrtSupSame
+                    let _ = iter.fill_buffer(&mut buf)?;
+                    supbooks.push((ExternNames::Internal, Vec::new()));
+                }
+                0x0167 => {
+                    // BrtSupTabs, the sheets of an external workbook
+                    let len = iter.fill_buffer(&mut buf)?;
+                    let mut tabs = Vec::new();
+                    let mut pos = 4;
+                    for _ in 0..read_u32(&buf[..len]) {
+                        let mut str_len = 0;
+                        tabs.push(wide_str(&buf[pos..len], &mut str_len)?.into_owned());
+                        pos += str_len;
+                    }
+                    if let Some(book) = supbooks.last_mut() {
+                        book.1 = tabs;
+                    }
+                }
+                0x0169 => {
+                    // BrtPlaceholderName, a name of an external workbook
+                    let len = iter.fill_buffer(&mut buf)?;
+                    let name = wide_str(&buf[..len], &mut 0)?.into_owned();
+                    if let Some((ExternNames::External(_, names), _)) = supbooks.last_mut() {
+                        names.push(name);
+                    }
+                }
                 0x016A => {
                     // BrtExternSheet
-                    let _len = iter.fill_buffer(&mut buf)?;
+                    let len = iter.fill_buffer(&mut buf)?;
                     let cxti = read_u32(&buf[..4]) as usize;
                     if cxti < 1_000_000 {
                         self.extern_sheets.reserve(cxti);
+                        self.extern_names.reserve(cxti);
+                    }
+                    let sheets: Vec<String> = self.sheets.iter().map(|(n, _)| n.clone()).collect();
+                    for xti in buf[4..len].chunks_exact(12).take(cxti) {
+                        let supbook = supbooks.get(read_u32(xti) as usize);
+                        let (book, tabs) = match supbook {
+                            Some((ExternNames::External(book, _), tabs)) => (Some(*book), tabs),
+                            _ => (None, &sheets),
+                        };
+                        let tab = |itab: i32| usize::try_from(itab).ok().and_then(|i| tabs.get(i));
+                        let (first, last) = (read_i32(&xti[4..8]), read_i32(&xti[8..12]));
+                        let mut sheet = match (first, tab(first), tab(last)) {
+                            (-2, ..) if book.is_some() => String::new(),
+                            (-2, ..) => "#ThisWorkbook".to_string(),
+                            (-1, ..) => "#InvalidWorkSheet".to_string(),
+                            (_, Some(f), Some(l)) if first != last => format!("{f}:{l}"),
+                            (_, Some(f), _) => f.clone(),
+                            _ => "#Unknown".to_string(),
+                        };
+                        if let Some(book) = book {
+                            sheet.insert_str(0, &format!("[{book}]"));
+                        }
+                        self.extern_sheets.push(sheet);
+                        self.extern_names.push(match supbook {
+                            Some((names, _)) => names.clone(),
+                            None => ExternNames::Internal,
+                        });
                     }
-                    let sheets = &self.sheets;
-                    let extern_sheets = buf[4..]
-                        .chunks(12)
-                        .map(|xti| {
-                            match read_i32(&xti[4..8]) {
-                                -2 => "#ThisWorkbook",
-                                -1 => "#InvalidWorkSheet",
-                                p if p >= 0 && (p as usize) < sheets.len() => &sheets[p as usize].0,
-                                _ => "#Unknown",
-                            }
-                            .to_string()
-                        })
-                        .take(cxti)
-                        .collect();
-                    self.extern_sheets = extern_sheets;
                 }
                 0x0027 => {
                     // BrtName
                     let len = iter.fill_buffer(&mut buf)?;
+                    let buf = &buf[..len];
                     let mut str_len = 0;
-                    let name = wide_str(&buf[9..len], &mut str_len)?.into_owned();
+                    let name = wide_str(&buf[9..], &mut str_len)?.into_owned();
+                    // NameParsedFormula, whose extra data follows the tokens
+                    let pos = 13 + str_len;
                     let rgce_len = read_u32(&buf[9 + str_len..]) as usize;
-                    let rgce = &buf[13 + str_len..13 + str_len + rgce_len];
-                    let formula = parse_formula(rgce, &self.extern_sheets, &defined_names)?;
-                    defined_names.push((name, formula));
+                    let rgce = buf[pos..pos + rgce_len].to_vec();
+                    let rgcb = buf
+                        .get(pos + rgce_len..)
+                        .filter(|b| b.len() >= 4)
+                        .and_then(|b| b[4..].get(..read_usize(b)))
+                        .unwrap_or_default()
+                        .to_vec();
+                    names.push((name, rgce, rgcb));
                 }
                 0x009D | 0x0225 | 0x018D | 0x0180 | 0x009A | 0x0252 | 0x0229 | 0x009B | 0x0084 => {
                     // record supposed to happen AFTER BrtNames
+                    // names are decoded once all are known, as they may reference the next ones
+                    let mut defined_names: Vec<_> = names
+                        .iter()
+                        .map(|(name, ..)| (name.clone(), String::new()))
+                        .collect();
+                    let ctx = FormulaContext {
+                        sheets: &self.extern_sheets,
+                        names: &defined_names,
+                        extern_names: &self.extern_names,
+                        tables: Some(&[]),
+                    };
+                    let formulas = names
+                        .iter()
+                        .map(|(_, rgce, rgcb)| parse_formula(rgce, rgcb, (0, 0), ctx))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    for (name, formula) in defined_names.iter_mut().zip(formulas) {
+                        name.1 = formula;
+                    }
                     self.metadata.names = defined_names;
                     return Ok(());
                 }
-                _ => debug!("Unsupported type {:X}", typ),
+                _ => {
+                    debug!("Unsupported type {:X}", typ);
+                    let _ = iter.fill_buffer(&mut buf)?;
+                }
             }
         }
     }
 
     /// Get a cells reader for a given worksheet
+    ///
+    /// Formulas with structured references fail with `XlsbError::TablesNotLoaded` if the tables
+    /// are not loaded (`load_tables`).
     pub fn worksheet_cells_reader<'a>(
         &'a mut self,
         name: &str,
@@ -395,14 +614,439 @@ impl<RS: Read + Seek> Xlsb<RS> {
             None => return Err(XlsbError::WorksheetNotFound(name.into())),
         };
         let iter = RecordIter::from_zip(&mut self.zip, &path)?;
-        XlsbCellsReader::new(
-            iter,
-            &self.formats,
-            &self.strings,
-            &self.extern_sheets,
-            &self.metadata.names,
-            self.is_1904,
-        )
+        let ctx = FormulaContext {
+            sheets: &self.extern_sheets,
+            names: &self.metadata.names,
+            extern_names: &self.extern_names,
+            tables: self.tables.as_deref(),
+        };
+        XlsbCellsReader::new(iter, &self.formats, &self.strings, ctx, self.is_1904)
+    }
+
+    // sheets must be added before this is called!!
+    fn read_table_metadata(&mut self) -> Result<(), XlsbError> {
+        let mut new_tables = Vec::new();
+        for (sheet_name, sheet_path) in &self.sheets {
+            let table_locations: Vec<String> = read_relationships::<_, XlsbError>(
+                &mut self.zip,
+                sheet_path,
+            )?
+            .into_iter()
+            .filter(|(_, typ, target)| {
+                typ == "http://schemas.openxmlformats.org/officeDocument/2006/relationships/table"
+                    && !target.is_empty()
+            })
+            .map(|(_, _, target)| resolve_target(sheet_path, &target))
+            .collect();
+            for table_file in table_locations {
+                let mut iter = match RecordIter::from_zip(&mut self.zip, &table_file) {
+                    Ok(iter) => iter,
+                    Err(XlsbError::FileNotFound(_)) => continue,
+                    Err(e) => return Err(e),
+                };
+                let mut buf = Vec::with_capacity(1024);
+                let mut name = String::new();
+                let mut columns = Vec::new();
+                let mut dims = Dimensions::default();
+                let mut id = 0;
+                let mut header_rows = 0;
+                loop {
+                    match iter.read_type()? {
+                        0x0157 => {
+                            // BrtBeginList
+                            let len = iter.fill_buffer(&mut buf)?;
+                            let buf = &buf[..len];
+                            check_len("BrtBeginList", buf, 72)?;
+                            dims = Dimensions {
+                                start: (read_u32(&buf[0..4]), read_u32(&buf[8..12])),
+                                end: (read_u32(&buf[4..8]), read_u32(&buf[12..16])),
+                            };
+                            id = read_u32(&buf[20..24]);
+                            header_rows = read_u32(&buf[24..28]);
+                            let totals_rows = read_u32(&buf[28..32]);
+                            // fForceInsertToBeVisible
+                            let insert_row = read_u32(&buf[32..36]) & 0x4 != 0;
+                            dims.start.0 += header_rows;
+                            dims.end.0 = dims.end.0.saturating_sub(totals_rows);
+                            if insert_row {
+                                dims.end.0 = dims.end.0.saturating_sub(1);
+                            }
+                            let mut str_len = 0;
+                            let _ = nullable_wide_str(&buf[64..], &mut str_len)?; // stName
+                            check_len("BrtBeginList", buf, 64 + str_len + 4)?;
+                            name = wide_str(&buf[64 + str_len..], &mut 0)?.into_owned();
+                            // stDisplayName
+                        }
+                        0x015B => {
+                            // BrtBeginListCol
+                            let len = iter.fill_buffer(&mut buf)?;
+                            let buf = &buf[..len];
+                            check_len("BrtBeginListCol", buf, 28)?;
+                            let mut str_len = 0;
+                            let unique_name = nullable_wide_str(&buf[24..], &mut str_len)?;
+                            check_len("BrtBeginListCol", buf, 24 + str_len + 4)?;
+                            let caption = nullable_wide_str(&buf[24 + str_len..], &mut 0)?;
+                            columns.push(caption.or(unique_name).unwrap_or_default().into_owned());
+                        }
+                        0x0158 => break, // BrtEndList
+                        _ => {
+                            let _ = iter.fill_buffer(&mut buf)?;
+                        }
+                    }
+                }
+                new_tables.push((name, sheet_name.clone(), columns, dims, id, header_rows));
+            }
+        }
+        self.tables = Some(new_tables);
+        Ok(())
+    }
+
+    /// Load the tables of all the sheets, needed by the other table methods
+    pub fn load_tables(&mut self) -> Result<(), XlsbError> {
+        if self.tables.is_none() {
+            self.read_table_metadata()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get the names of all the tables
+    pub fn table_names(&self) -> Vec<&String> {
+        self.tables
+            .as_ref()
+            .expect("Tables must be loaded before they are referenced")
+            .iter()
+            .map(|(name, ..)| name)
+            .collect()
+    }
+
+    /// Get the names of all the tables in a sheet
+    pub fn table_names_in_sheet(&self, sheet_name: &str) -> Vec<&String> {
+        self.tables
+            .as_ref()
+            .expect("Tables must be loaded before they are referenced")
+            .iter()
+            .filter(|(_, sheet, ..)| sheet == sheet_name)
+            .map(|(name, ..)| name)
+            .collect()
+    }
+
+    /// Get the table by name
+    pub fn table_by_name(&mut self, table_name: &str) -> Result<Table<Data>, XlsbError> {
+        let (name, sheet_name, columns, dims, _, header_row_count) = self
+            .tables
+            .as_ref()
+            .expect("Tables must be loaded before they are referenced")
+            .iter()
+            .find(|(table, ..)| table == table_name)
+            .cloned()
+            .ok_or_else(|| XlsbError::TableNotFound(table_name.into()))?;
+        let range = self.worksheet_range(&sheet_name)?;
+        Ok(Table {
+            name,
+            sheet_name,
+            columns,
+            data: range.range(dims.start, dims.end),
+            header_row_count,
+            ..Default::default()
+        })
+    }
+
+    /// Get the conditional formatting rules of a worksheet
+    pub fn worksheet_conditional_formatting(
+        &mut self,
+        name: &str,
+    ) -> Result<Vec<ConditionalFormatting>, XlsbError> {
+        let path = match self.sheets.iter().find(|&(n, _)| n == name) {
+            Some((_, path)) => path.clone(),
+            None => return Err(XlsbError::WorksheetNotFound(name.into())),
+        };
+        let mut iter = RecordIter::from_zip(&mut self.zip, &path)?;
+        let ctx = FormulaContext {
+            sheets: &self.extern_sheets,
+            names: &self.metadata.names,
+            extern_names: &self.extern_names,
+            tables: Some(&[]),
+        };
+        let mut formattings: Vec<ConditionalFormatting> = Vec::new();
+        // thresholds read in the current color scale, data bar or icon set
+        let mut cfvos = None;
+        let mut buf = Vec::with_capacity(1024);
+        loop {
+            let typ = match iter.read_type() {
+                Ok(typ) => typ,
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            };
+            let len = iter.fill_buffer(&mut buf)?;
+            // formulas are relative to the first cell of the ranges
+            let base = formattings
+                .last()
+                .and_then(|cf| cf.ranges.first())
+                .map_or((0, 0), |range| range.start);
+            let rule_type = formattings
+                .last_mut()
+                .and_then(|cf| cf.rules.last_mut())
+                .map(|rule| &mut rule.rule_type);
+            match typ {
+                // BrtBeginConditionalFormatting
+                0x01CD if len >= 12 => formattings.push(ConditionalFormatting {
+                    ranges: parse_sqrfx(&buf[8..len]),
+                    rules: Vec::new(),
+                }),
+                // BrtBeginCFRule
+                0x01CF => {
+                    let rule = parse_cf_rule(&buf[..len], ctx, base, &self.dxfs)?;
+                    if let Some(cf) = formattings.last_mut() {
+                        cf.rules.push(rule);
+                    }
+                }
+                // BrtBeginIconSet
+                0x01D1 => {
+                    if len < 12 {
+                        return Err(XlsbError::Len {
+                            typ: "BrtBeginIconSet",
+                            expected: 12,
+                            found: len,
+                        });
+                    }
+                    if let Some(rule_type @ ConditionalFormatType::IconSet(_)) = rule_type {
+                        let icon_set = usize::try_from(read_i32(&buf))
+                            .ok()
+                            .and_then(|i| ICON_SETS.get(i))
+                            .unwrap_or(&ICON_SETS[3]);
+                        *rule_type = ConditionalFormatType::IconSet(IconSet {
+                            icon_set: icon_set.to_string(),
+                            values: Vec::new(),
+                            show_value: read_u32(&buf[4..]) == 0,
+                            reverse: read_u32(&buf[8..]) != 0,
+                        });
+                    }
+                    cfvos = Some(0);
+                }
+                // BrtBeginDatabar
+                0x01D3 => {
+                    if let Some(ConditionalFormatType::DataBar(bar)) = rule_type {
+                        bar.show_value = len < 3 || buf[2] != 0;
+                    }
+                    cfvos = Some(0);
+                }
+                // BrtBeginColorScale
+                0x01D5 => cfvos = Some(0),
+                // BrtEndIconSet, BrtEndDatabar, BrtEndColorScale
+                0x01D2 | 0x01D4 | 0x01D6 => cfvos = None,
+                // BrtCFVO
+                0x01D7 => {
+                    let Some(count) = cfvos.as_mut() else {
+                        continue;
+                    };
+                    let value = parse_cfvo(&buf[..len], ctx, base)?;
+                    match rule_type {
+                        Some(ConditionalFormatType::ColorScale(scale)) => scale.values.push(value),
+                        Some(ConditionalFormatType::IconSet(icons)) => icons.values.push(value),
+                        Some(ConditionalFormatType::DataBar(bar)) if *count == 0 => bar.min = value,
+                        Some(ConditionalFormatType::DataBar(bar)) if *count == 1 => bar.max = value,
+                        _ => (),
+                    }
+                    *count += 1;
+                }
+                // BrtColor
+                0x023C if cfvos.is_some() => match rule_type {
+                    Some(ConditionalFormatType::ColorScale(scale)) => {
+                        scale.colors.extend(parse_color(&buf[..len]))
+                    }
+                    Some(ConditionalFormatType::DataBar(bar)) if bar.color.is_none() => {
+                        bar.color = parse_color(&buf[..len])
+                    }
+                    _ => (),
+                },
+                // BrtEndSheet
+                0x0082 => break,
+                _ => (),
+            }
+        }
+        Ok(formattings)
+    }
+
+    /// Read the tab color of each sheet, from the BrtWsProp record at the beginning of the sheet parts
+    fn read_tab_colors(&mut self) -> Result<(), XlsbError> {
+        let mut buf = Vec::with_capacity(1024);
+        for sheet in &mut self.metadata.sheets {
+            let Some(path) = &sheet.path else { continue };
+            let mut iter = match RecordIter::from_zip(&mut self.zip, path) {
+                Ok(iter) => iter,
+                Err(XlsbError::FileNotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            loop {
+                let typ = match iter.read_type() {
+                    Ok(typ) => typ,
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                    Err(e) => return Err(e.into()),
+                };
+                let len = iter.fill_buffer(&mut buf)?;
+                match typ {
+                    // BrtBeginSheet
+                    0x0081 => (),
+                    // BrtWsProp
+                    0x0093 if len >= 11 => {
+                        sheet.tab_color = parse_color(&buf[3..]).filter(|c| *c != Color::Auto);
+                        break;
+                    }
+                    _ => break,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the page setup of a worksheet, with its print area and titles
+    pub fn worksheet_page_setup(&mut self, name: &str) -> Result<PageSetup, XlsbError> {
+        let path = match self.sheets.iter().find(|&(n, _)| n == name) {
+            Some((_, path)) => path.clone(),
+            None => return Err(XlsbError::WorksheetNotFound(name.into())),
+        };
+        let mut iter = RecordIter::from_zip(&mut self.zip, &path)?;
+        let mut setup = PageSetup::default();
+        let mut fit_to_page = false;
+        let mut row_breaks = true;
+        let mut buf = Vec::with_capacity(1024);
+        loop {
+            let typ = match iter.read_type() {
+                Ok(typ) => typ,
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            };
+            let len = iter.fill_buffer(&mut buf)?;
+            match typ {
+                // BrtWsProp
+                0x0093 if len >= 2 => fit_to_page = read_u16(&buf) & 0x0100 != 0,
+                // BrtMargins
+                0x01DC if len >= 48 => {
+                    setup.margins = PageMargins {
+                        left: read_f64(&buf),
+                        right: read_f64(&buf[8..]),
+                        top: read_f64(&buf[16..]),
+                        bottom: read_f64(&buf[24..]),
+                        header: read_f64(&buf[32..]),
+                        footer: read_f64(&buf[40..]),
+                    };
+                }
+                // BrtPrintOptions
+                0x01DD if len >= 2 => {
+                    let flags = read_u16(&buf);
+                    setup.center_horizontally = flags & 0x0001 != 0;
+                    setup.center_vertically = flags & 0x0002 != 0;
+                    setup.print_headings = flags & 0x0004 != 0;
+                    setup.print_grid_lines = flags & 0x0008 != 0;
+                }
+                // BrtPageSetup
+                0x01DE if len >= 34 => {
+                    let flags = read_u16(&buf[32..]);
+                    // fNoPls: paper size, scale and orientation are undefined
+                    if flags & 0x0004 == 0 {
+                        setup.paper_size = read_u32(&buf);
+                        setup.scale = read_u32(&buf[4..]);
+                        setup.orientation = if flags & 0x0040 != 0 {
+                            Orientation::Default
+                        } else if flags & 0x0002 != 0 {
+                            Orientation::Landscape
+                        } else {
+                            Orientation::Portrait
+                        };
+                    }
+                    if flags & 0x0080 != 0 {
+                        setup.first_page_number = Some(read_i32(&buf[20..]));
+                    }
+                    if fit_to_page {
+                        setup.fit_to_pages = Some((read_u32(&buf[24..]), read_u32(&buf[28..])));
+                    }
+                    if flags & 0x0001 != 0 {
+                        setup.page_order = PageOrder::OverThenDown;
+                    }
+                    setup.black_and_white = flags & 0x0008 != 0;
+                    setup.draft = flags & 0x0010 != 0;
+                }
+                // BrtBeginHeaderFooter
+                0x01DF if len >= 2 => {
+                    let flags = read_u16(&buf);
+                    let header_footer = &mut setup.header_footer;
+                    header_footer.different_odd_even = flags & 0x0001 != 0;
+                    header_footer.different_first = flags & 0x0002 != 0;
+                    header_footer.scale_with_doc = flags & 0x0004 != 0;
+                    header_footer.align_with_margins = flags & 0x0008 != 0;
+                    let mut texts = [
+                        &mut header_footer.odd_header,
+                        &mut header_footer.odd_footer,
+                        &mut header_footer.even_header,
+                        &mut header_footer.even_footer,
+                        &mut header_footer.first_header,
+                        &mut header_footer.first_footer,
+                    ];
+                    let mut offset = 2;
+                    for text in texts.iter_mut() {
+                        if offset + 4 > len {
+                            break;
+                        }
+                        let mut str_len = 0;
+                        **text = nullable_wide_str(&buf[offset..len], &mut str_len)?
+                            .and_then(|s| HeaderFooterText::parse_non_empty(&s));
+                        offset += str_len;
+                    }
+                }
+                // BrtBeginRwBrk
+                0x0186 => row_breaks = true,
+                // BrtBeginColBrk
+                0x0188 => row_breaks = false,
+                // BrtBrk
+                0x018C if len >= 4 => {
+                    if row_breaks {
+                        setup.row_breaks.push(read_u32(&buf));
+                    } else {
+                        setup.column_breaks.push(read_u32(&buf));
+                    }
+                }
+                // BrtEndSheet
+                0x0082 => break,
+                _ => (),
+            }
+        }
+        setup.set_defined_names(name, &self.metadata.names);
+        Ok(setup)
+    }
+
+    /// Get the charts of all the sheets, chartsheets included, in sheet order
+    pub fn charts(&mut self) -> Result<Vec<Chart>, XlsbError> {
+        chart::read_sheet_charts(&mut self.zip, &self.sheets)
+    }
+
+    /// Get the formulas of a worksheet as expressions, decoded from their tokens
+    ///
+    /// `worksheet_formula` returns the same formulas as text.
+    pub fn worksheet_formula_ast(&mut self, name: &str) -> Result<Range<Expr>, XlsbError> {
+        if self.metadata.is_chart_sheet(name) {
+            return Ok(Range::empty());
+        }
+        Ok(Range::from_sparse(self.read_formulas(name)?))
+    }
+
+    /// Decode the formulas of a worksheet, loading the tables once a structured reference needs
+    /// them
+    fn read_formulas(&mut self, name: &str) -> Result<Vec<Cell<Expr>>, XlsbError> {
+        let mut cells_reader = self.worksheet_cells_reader(name)?;
+        let mut cells = Vec::with_capacity(cells_reader.dimensions().len().min(1_000_000) as _);
+        loop {
+            match cells_reader.next_formula_ast() {
+                Ok(Some(cell)) if cell.val == Expr::Missing => {}
+                Ok(Some(cell)) => cells.push(cell),
+                Ok(None) => return Ok(cells),
+                Err(XlsbError::TablesNotLoaded) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        drop(cells_reader);
+        self.load_tables()?;
+        self.read_formulas(name)
     }
 
     #[cfg(feature = "picture")]
@@ -430,6 +1074,11 @@ impl<RS: Read + Seek> Xlsb<RS> {
         if !pics.is_empty() {
             self.pictures = Some(pics);
         }
+        // drawings are stored as xml parts, as in xlsx files
+        let sheet_pics = picture::read_sheet_pictures::<_, XlsbError>(&mut self.zip, &self.sheets)?;
+        if !sheet_pics.is_empty() {
+            self.sheet_pictures = Some(sheet_pics);
+        }
         Ok(())
     }
 }
@@ -443,18 +1092,26 @@ impl<RS: Read + Seek> Reader<RS> for Xlsb<RS> {
         let mut xlsb = Xlsb {
             zip: ZipArchive::new(reader)?,
             sheets: Vec::new(),
+            tables: None,
             strings: Vec::new(),
             extern_sheets: Vec::new(),
+            extern_names: Vec::new(),
             formats: Vec::new(),
+            dxfs: Vec::new(),
             is_1904: false,
+            workbook_protection: None,
             metadata: Metadata::default(),
             #[cfg(feature = "picture")]
             pictures: None,
+            #[cfg(feature = "picture")]
+            sheet_pictures: None,
         };
         xlsb.read_shared_strings()?;
         xlsb.read_styles()?;
+        xlsb.read_theme()?;
         let relationships = xlsb.read_relationships()?;
         xlsb.read_workbook(&relationships)?;
+        xlsb.read_tab_colors()?;
         #[cfg(feature = "picture")]
         xlsb.read_pictures()?;
 
@@ -476,6 +1133,9 @@ impl<RS: Read + Seek> Reader<RS> for Xlsb<RS> {
 
     /// MS-XLSB 2.1.7.62
     fn worksheet_range(&mut self, name: &str) -> Result<Range<Data>, XlsbError> {
+        if self.metadata.is_chart_sheet(name) {
+            return Ok(Range::empty());
+        }
         let mut cells_reader = self.worksheet_cells_reader(name)?;
         let mut cells = Vec::with_capacity(cells_reader.dimensions().len().min(1_000_000) as _);
         while let Some(cell) = cells_reader.next_cell()? {
@@ -487,14 +1147,226 @@ impl<RS: Read + Seek> Reader<RS> for Xlsb<RS> {
     }
 
     /// MS-XLSB 2.1.7.62
-    fn worksheet_formula(&mut self, name: &str) -> Result<Range<String>, XlsbError> {
-        let mut cells_reader = self.worksheet_cells_reader(name)?;
-        let mut cells = Vec::with_capacity(cells_reader.dimensions().len().min(1_000_000) as _);
-        while let Some(cell) = cells_reader.next_formula()? {
-            if !cell.val.is_empty() {
-                cells.push(cell);
+    fn worksheet_layout(&mut self, name: &str) -> Result<SheetLayout, XlsbError> {
+        let path = match self.sheets.iter().find(|&(n, _)| n == name) {
+            Some((_, path)) => path.clone(),
+            None => return Err(XlsbError::WorksheetNotFound(name.into())),
+        };
+        let mut iter = RecordIter::from_zip(&mut self.zip, &path)?;
+        let mut layout = SheetLayout::default();
+        let mut buf = Vec::with_capacity(1024);
+        loop {
+            let typ = match iter.read_type() {
+                Ok(typ) => typ,
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            };
+            let len = iter.fill_buffer(&mut buf)?;
+            match typ {
+                // BrtWsFmtInfo
+                0x01E5 if len >= 8 => {
+                    let dx_g_col = read_u32(&buf[..4]);
+                    if dx_g_col != u32::MAX {
+                        layout.default_column_width = Some(dx_g_col as f64 / 256.);
+                    }
+                    layout.default_row_height = Some(read_u16(&buf[6..8]) as f64 / 20.);
+                }
+                // BrtColInfo
+                0x003C if len >= 18 => {
+                    let flags = read_u16(&buf[16..18]);
+                    layout.columns.push(LayoutSpan {
+                        first: read_u32(&buf[..4]),
+                        last: read_u32(&buf[4..8]),
+                        size: Some(read_u32(&buf[8..12]) as f64 / 256.),
+                        hidden: flags & 0x0001 != 0,
+                        outline_level: ((flags >> 8) & 0x07) as u8,
+                        collapsed: flags & 0x1000 != 0,
+                    });
+                }
+                // BrtRowHdr
+                0x0000 if len >= 12 => {
+                    let flags = buf[11];
+                    let row = read_u32(&buf[..4]);
+                    let span = LayoutSpan {
+                        first: row,
+                        last: row,
+                        // fUnsynced: custom height
+                        size: (flags & 0x20 != 0).then(|| read_u16(&buf[8..10]) as f64 / 20.),
+                        hidden: flags & 0x10 != 0,
+                        outline_level: flags & 0x07,
+                        collapsed: flags & 0x08 != 0,
+                    };
+                    if span.size.is_some()
+                        || span.hidden
+                        || span.outline_level != 0
+                        || span.collapsed
+                    {
+                        layout.rows.push(span);
+                    }
+                }
+                // BrtEndSheetData
+                0x0092 => break,
+                _ => (),
+            }
+        }
+        layout.columns.sort_by_key(|s| s.first);
+        layout.rows.sort_by_key(|s| s.first);
+        Ok(layout)
+    }
+
+    /// MS-XLSB 2.1.7.62
+    fn worksheet_view(&mut self, name: &str) -> Result<SheetView, XlsbError> {
+        let path = match self.sheets.iter().find(|&(n, _)| n == name) {
+            Some((_, path)) => path.clone(),
+            None => return Err(XlsbError::WorksheetNotFound(name.into())),
+        };
+        let mut iter = RecordIter::from_zip(&mut self.zip, &path)?;
+        let mut view = SheetView::default();
+        let mut buf = Vec::with_capacity(1024);
+        loop {
+            let typ = match iter.read_type() {
+                Ok(typ) => typ,
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            };
+            let len = iter.fill_buffer(&mut buf)?;
+            match typ {
+                // BrtBeginWsView
+                0x0089 if len >= 18 => {
+                    let flags = read_u16(&buf);
+                    view.show_grid_lines = flags & 0x0004 != 0;
+                    view.right_to_left = flags & 0x0020 != 0;
+                    view.top_left_cell = (read_u32(&buf[6..]), read_u32(&buf[10..]));
+                    view.zoom = match read_u16(&buf[16..]) {
+                        0 => 100,
+                        zoom => zoom as u32,
+                    };
+                }
+                // BrtPane
+                0x0097 if len >= 29 => {
+                    view.pane = Some(Pane {
+                        state: if buf[28] & 0x01 != 0 {
+                            PaneState::Frozen
+                        } else {
+                            PaneState::Split
+                        },
+                        x_split: read_f64(&buf),
+                        y_split: read_f64(&buf[8..]),
+                        top_left_cell: (read_u32(&buf[16..]), read_u32(&buf[20..])),
+                        active_pane: PanePosition::from_code(read_u32(&buf[24..])),
+                    });
+                }
+                // BrtSel
+                0x0098 if len >= 20 => {
+                    let active_pane = view
+                        .pane
+                        .as_ref()
+                        .map_or(PanePosition::TopLeft, |p| p.active_pane);
+                    if PanePosition::from_code(read_u32(&buf)) == active_pane {
+                        view.active_cell = (read_u32(&buf[4..]), read_u32(&buf[8..]));
+                        view.selection = parse_sqrfx(&buf[16..len]);
+                    }
+                }
+                // BrtEndWsView, only read the first view
+                0x008A => break,
+                // BrtBeginSheetData
+                0x0091 => break,
+                _ => (),
+            }
+        }
+        Ok(view)
+    }
+
+    fn worksheet_protection(&mut self, name: &str) -> Result<Option<SheetProtection>, XlsbError> {
+        let path = match self.sheets.iter().find(|&(n, _)| n == name) {
+            Some((_, path)) => path.clone(),
+            None => return Err(XlsbError::WorksheetNotFound(name.into())),
+        };
+        let mut iter = RecordIter::from_zip(&mut self.zip, &path)?;
+        let mut buf = Vec::with_capacity(1024);
+        loop {
+            let typ = match iter.read_type() {
+                Ok(typ) => typ,
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            };
+            let len = iter.fill_buffer(&mut buf)?;
+            // BrtSheetProtection, flags are set for *prohibited* actions
+            if typ == 0x0217 && len >= 66 {
+                if read_u32(&buf[2..]) == 0 {
+                    return Ok(None);
+                }
+                let allowed = |i: usize| read_u32(&buf[2 + 4 * i..]) == 0;
+                return Ok(Some(SheetProtection {
+                    password: PasswordHash::from_legacy(read_u16(&buf)),
+                    edit_objects: allowed(1),
+                    edit_scenarios: allowed(2),
+                    format_cells: allowed(3),
+                    format_columns: allowed(4),
+                    format_rows: allowed(5),
+                    insert_columns: allowed(6),
+                    insert_rows: allowed(7),
+                    insert_hyperlinks: allowed(8),
+                    delete_columns: allowed(9),
+                    delete_rows: allowed(10),
+                    select_locked_cells: allowed(11),
+                    sort: allowed(12),
+                    auto_filter: allowed(13),
+                    pivot_tables: allowed(14),
+                    select_unlocked_cells: allowed(15),
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    fn workbook_protection(&mut self) -> Result<Option<WorkbookProtection>, XlsbError> {
+        Ok(self.workbook_protection.clone())
+    }
+
+    fn worksheet_data_validations(&mut self, name: &str) -> Result<Vec<DataValidation>, XlsbError> {
+        let path = match self.sheets.iter().find(|&(n, _)| n == name) {
+            Some((_, path)) => path.clone(),
+            None => return Err(XlsbError::WorksheetNotFound(name.into())),
+        };
+        let mut iter = RecordIter::from_zip(&mut self.zip, &path)?;
+        let ctx = FormulaContext {
+            sheets: &self.extern_sheets,
+            names: &self.metadata.names,
+            extern_names: &self.extern_names,
+            tables: Some(&[]),
+        };
+        let mut validations = Vec::new();
+        let mut buf = Vec::with_capacity(1024);
+        loop {
+            let typ = match iter.read_type() {
+                Ok(typ) => typ,
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            };
+            let len = iter.fill_buffer(&mut buf)?;
+            match typ {
+                // BrtDVal
+                0x0040 => validations.extend(parse_dval(&buf[..len], ctx)?),
+                // BrtEndSheet
+                0x0082 => break,
+                _ => (),
             }
         }
+        Ok(validations)
+    }
+
+    /// MS-XLSB 2.1.7.62
+    fn worksheet_formula(&mut self, name: &str) -> Result<Range<String>, XlsbError> {
+        if self.metadata.is_chart_sheet(name) {
+            return Ok(Range::empty());
+        }
+        let cells = self
+            .read_formulas(name)?
+            .into_iter()
+            .map(|c| Cell::new(c.pos, c.val.to_string()))
+            .filter(|c| !c.val.is_empty())
+            .collect();
         Ok(Range::from_sparse(cells))
     }
 
@@ -518,6 +1390,11 @@ impl<RS: Read + Seek> Reader<RS> for Xlsb<RS> {
     fn pictures(&self) -> Option<Vec<(String, Vec<u8>)>> {
         self.pictures.to_owned()
     }
+
+    #[cfg(feature = "picture")]
+    fn sheet_pictures(&self) -> Option<Vec<Picture>> {
+        self.sheet_pictures.to_owned()
+    }
 }
 
 pub(crate) struct RecordIter<'a> {
@@ -610,6 +1487,358 @@ fn wide_str<'a>(buf: &'a [u8], str_len: &mut usize) -> Result<Cow<'a, str>, Xlsb
     Ok(UTF_16LE.decode(s).0)
 }
 
+/// Checks that a record is at least `expected` bytes long
+fn check_len(typ: &'static str, buf: &[u8], expected: usize) -> Result<(), XlsbError> {
+    if buf.len() < expected {
+        Err(XlsbError::Len {
+            typ,
+            expected,
+            found: buf.len(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// XLNullableWideString [MS-XLSB 2.5.166]
+fn nullable_wide_str<'a>(
+    buf: &'a [u8],
+    str_len: &mut usize,
+) -> Result<Option<Cow<'a, str>>, XlsbError> {
+    if read_u32(buf) == 0xFFFF_FFFF {
+        *str_len = 4;
+        Ok(None)
+    } else {
+        wide_str(buf, str_len).map(Some)
+    }
+}
+
+/// SqRfX, UncheckedSqRfX [MS-XLSB 2.5.156]: a count followed by the ranges
+fn parse_sqrfx(buf: &[u8]) -> Vec<Dimensions> {
+    if buf.len() < 4 {
+        return Vec::new();
+    }
+    let crfx = read_usize(buf);
+    buf[4..]
+        .chunks_exact(16)
+        .take(crfx)
+        .map(|rfx| Dimensions {
+            start: (read_u32(rfx), read_u32(&rfx[8..])),
+            end: (read_u32(&rfx[4..]), read_u32(&rfx[12..])),
+        })
+        .collect()
+}
+
+/// BrtBeginCFRule [MS-XLSB 2.4.23]
+fn parse_cf_rule(
+    buf: &[u8],
+    ctx: FormulaContext<'_>,
+    base: (u32, u32),
+    dxfs: &[DifferentialFormat],
+) -> Result<ConditionalFormatRule, XlsbError> {
+    if buf.len() < 46 {
+        return Err(XlsbError::Len {
+            typ: "BrtBeginCFRule",
+            expected: 46,
+            found: buf.len(),
+        });
+    }
+    let typ = read_u32(buf);
+    let template = read_u32(&buf[4..]);
+    let dxf_id = read_i32(&buf[8..]);
+    let param = read_u32(&buf[16..]);
+    let flags = read_u16(&buf[28..]);
+    let mut str_len = 0;
+    let text = nullable_wide_str(&buf[42..], &mut str_len)?
+        .map(|s| s.into_owned())
+        .unwrap_or_default();
+
+    let mut formulas = Vec::new();
+    let mut pos = 42 + str_len;
+    for i in 0..3 {
+        let cb = read_usize(&buf[30 + 4 * i..]);
+        if cb >= 4 && buf.len() >= pos + cb {
+            // CFParsedFormula
+            let cce = read_usize(&buf[pos..]).min(cb - 4);
+            let rgce = &buf[pos + 4..pos + 4 + cce];
+            let rgcb = buf.get(pos + 8 + cce..pos + cb).unwrap_or_default();
+            formulas.push(parse_formula(rgce, rgcb, base, ctx)?);
+        }
+        pos += cb;
+    }
+
+    let rule_type = match (typ, template) {
+        // CFOper, 1-based
+        (1, _) => {
+            ConditionalFormatType::CellIs(ValidationOperator::from_code(param.saturating_sub(1)))
+        }
+        (3, _) => ConditionalFormatType::ColorScale(ColorScale::default()),
+        (4, _) => ConditionalFormatType::DataBar(DataBar::default()),
+        (5, _) => ConditionalFormatType::Top10 {
+            rank: param,
+            percent: flags & 0x0010 != 0,
+            bottom: flags & 0x0008 != 0,
+        },
+        (6, _) => ConditionalFormatType::IconSet(IconSet::default()),
+        (_, 0x07) => ConditionalFormatType::UniqueValues,
+        (_, 0x08) => match param {
+            1 => ConditionalFormatType::NotContainsText(text),
+            2 => ConditionalFormatType::BeginsWith(text),
+            3 => ConditionalFormatType::EndsWith(text),
+            _ => ConditionalFormatType::ContainsText(text),
+        },
+        (_, 0x09) => ConditionalFormatType::ContainsBlanks,
+        (_, 0x0A) => ConditionalFormatType::NotContainsBlanks,
+        (_, 0x0B) => ConditionalFormatType::ContainsErrors,
+        (_, 0x0C) => ConditionalFormatType::NotContainsErrors,
+        (_, 0x0F..=0x18) => ConditionalFormatType::TimePeriod(
+            [
+                "today",
+                "tomorrow",
+                "yesterday",
+                "last7Days",
+                "lastMonth",
+                "nextMonth",
+                "thisWeek",
+                "nextWeek",
+                "lastWeek",
+                "thisMonth",
+            ][template as usize - 0x0F]
+                .to_string(),
+        ),
+        (_, 0x19 | 0x1A | 0x1D | 0x1E) => ConditionalFormatType::AboveAverage {
+            above: matches!(template, 0x19 | 0x1D),
+            equal: matches!(template, 0x1D | 0x1E),
+            std_dev: (param != 0).then_some(param),
+        },
+        (_, 0x1B) => ConditionalFormatType::DuplicateValues,
+        _ => ConditionalFormatType::Expression,
+    };
+    Ok(ConditionalFormatRule {
+        rule_type,
+        priority: read_i32(&buf[12..]),
+        stop_if_true: flags & 0x0002 != 0,
+        formulas,
+        format: usize::try_from(dxf_id)
+            .ok()
+            .and_then(|i| dxfs.get(i))
+            .cloned(),
+    })
+}
+
+/// Names of the icon sets, by their KPISets index
+const ICON_SETS: [&str; 17] = [
+    "3Arrows",
+    "3ArrowsGray",
+    "3Flags",
+    "3TrafficLights1",
+    "3TrafficLights2",
+    "3Signs",
+    "3Symbols",
+    "3Symbols2",
+    "4Arrows",
+    "4ArrowsGray",
+    "4RedToBlack",
+    "4Rating",
+    "4TrafficLights",
+    "5Arrows",
+    "5ArrowsGray",
+    "5Rating",
+    "5Quarters",
+];
+
+/// BrtCFVO, a threshold of a color scale, data bar or icon set
+fn parse_cfvo(
+    buf: &[u8],
+    ctx: FormulaContext<'_>,
+    base: (u32, u32),
+) -> Result<ConditionalValue, XlsbError> {
+    if buf.len() < 24 {
+        return Err(XlsbError::Len {
+            typ: "BrtCFVO",
+            expected: 24,
+            found: buf.len(),
+        });
+    }
+    let value_type = ConditionalValueType::from_code(read_u32(buf));
+    let cb = read_usize(&buf[20..]);
+    let value = if cb >= 4 && buf.len() >= 24 + cb {
+        // CFVOParsedFormula
+        let cce = read_usize(&buf[24..]).min(cb - 4);
+        let rgce = &buf[28..28 + cce];
+        let rgcb = buf.get(32 + cce..24 + cb).unwrap_or_default();
+        Some(parse_formula(rgce, rgcb, base, ctx)?)
+    } else {
+        match value_type {
+            ConditionalValueType::Min
+            | ConditionalValueType::Max
+            | ConditionalValueType::AutoMin
+            | ConditionalValueType::AutoMax => None,
+            _ => Some(read_f64(&buf[4..]).to_string()),
+        }
+    };
+    Ok(ConditionalValue { value_type, value })
+}
+
+/// BrtDXF [MS-XLSB 2.4.358]
+fn parse_dxf(buf: &[u8]) -> Result<DifferentialFormat, XlsbError> {
+    let mut dxf = DifferentialFormat::default();
+    if buf.len() < 6 {
+        return Ok(dxf);
+    }
+    let cprops = read_u16(&buf[4..]) as usize;
+    let mut props = &buf[6..];
+    for _ in 0..cprops {
+        // XFProp
+        if props.len() < 4 {
+            break;
+        }
+        let typ = read_u16(props);
+        let cb = (read_u16(&props[2..]) as usize).clamp(4, props.len());
+        let data = &props[4..cb];
+        props = &props[cb..];
+        if data.is_empty() {
+            continue;
+        }
+        match typ {
+            0x0000 => {
+                dxf.fill_pattern = Some(
+                    [
+                        "none",
+                        "solid",
+                        "mediumGray",
+                        "darkGray",
+                        "lightGray",
+                        "darkHorizontal",
+                        "darkVertical",
+                        "darkDown",
+                        "darkUp",
+                        "darkGrid",
+                        "darkTrellis",
+                        "lightHorizontal",
+                        "lightVertical",
+                        "lightDown",
+                        "lightUp",
+                        "lightGrid",
+                        "lightTrellis",
+                        "gray125",
+                        "gray0625",
+                    ]
+                    .get(data[0] as usize)
+                    .unwrap_or(&"none")
+                    .to_string(),
+                )
+            }
+            0x0001 => dxf.fill_foreground = parse_color(data),
+            0x0002 => dxf.fill_background = parse_color(data),
+            0x0005 => dxf.font_color = parse_color(data),
+            0x0019 if data.len() >= 2 => dxf.bold = Some(read_u16(data) >= 700),
+            0x001A => dxf.underline = Some(data[0] != 0),
+            0x001C => dxf.italic = Some(data[0] != 0),
+            0x001D => dxf.strikethrough = Some(data[0] != 0),
+            // XFPropNumFmt
+            0x0026 if data.len() >= 6 => {
+                dxf.number_format = Some(wide_str(&data[2..], &mut 0)?.into_owned())
+            }
+            _ => (),
+        }
+    }
+    Ok(dxf)
+}
+
+/// XFPropColor, BrtColor [MS-XLSB 2.5.178]
+fn parse_color(buf: &[u8]) -> Option<Color> {
+    if buf.len() < 8 {
+        return None;
+    }
+    let index = buf[1] as u32;
+    match buf[0] >> 1 {
+        0 => Some(Color::Auto),
+        1 => Some(Color::Indexed(index)),
+        2 => Some(Color::Argb(u32::from_be_bytes([
+            buf[7], buf[4], buf[5], buf[6],
+        ]))),
+        3 => Some(Color::Theme {
+            index,
+            tint: read_i16(&buf[2..]) as f64 / 32767.,
+        }),
+        _ => None,
+    }
+}
+
+/// BrtDVal [MS-XLSB 2.4.380], `None` if a formula cannot be decoded
+fn parse_dval(buf: &[u8], ctx: FormulaContext<'_>) -> Result<Option<DataValidation>, XlsbError> {
+    let check_len = |expected: usize| {
+        if buf.len() < expected {
+            Err(XlsbError::Len {
+                typ: "BrtDVal",
+                expected,
+                found: buf.len(),
+            })
+        } else {
+            Ok(())
+        }
+    };
+    check_len(8)?;
+    let mut dv = validation::from_flags(read_u32(buf));
+
+    // SqRfX
+    let crfx = read_usize(&buf[4..]);
+    let mut pos = 8 + crfx * 16;
+    check_len(pos)?;
+    dv.ranges = parse_sqrfx(&buf[4..pos]);
+
+    let mut texts = [None, None, None, None];
+    for text in texts.iter_mut() {
+        check_len(pos + 4)?;
+        let mut str_len = 0;
+        *text = nullable_wide_str(&buf[pos..], &mut str_len)?
+            .filter(|s| !s.is_empty())
+            .map(|s| s.into_owned());
+        pos += str_len;
+    }
+    let [error_title, error, prompt_title, prompt] = texts;
+    dv.error_title = error_title;
+    dv.error = error;
+    dv.prompt_title = prompt_title;
+    dv.prompt = prompt;
+
+    // formula1 and formula2, CellParsedFormula
+    let mut formulas = [None, None];
+    for formula in formulas.iter_mut() {
+        if buf.len() < pos + 8 {
+            break;
+        }
+        let cce = read_usize(&buf[pos..]);
+        check_len(pos + 8 + cce)?;
+        let rgce = &buf[pos + 4..pos + 4 + cce];
+        let cb = read_usize(&buf[pos + 4 + cce..]);
+        let rgcb = buf
+            .get(pos + 8 + cce..pos + 8 + cce + cb)
+            .unwrap_or_default();
+        if cce > 0 {
+            // formulas are relative to the first cell of the ranges
+            let base = dv.ranges.first().map_or((0, 0), |range| range.start);
+            match parse_formula(rgce, rgcb, base, ctx) {
+                Ok(f) => *formula = Some(f),
+                Err(e) => {
+                    debug!(
+                        "skipping data validation with an unsupported formula: {}",
+                        e
+                    );
+                    return Ok(None);
+                }
+            }
+        }
+        pos += 8 + cce + cb;
+    }
+    let [formula1, formula2] = formulas;
+    dv.formula1 = formula1;
+    dv.formula2 = formula2;
+    validation::fix_list_formula(&mut dv);
+    Ok(Some(dv))
+}
+
 /// Formula parsing
 ///
 /// [MS-XLSB 2.2.2]
@@ -617,135 +1846,141 @@ fn wide_str<'a>(buf: &'a [u8], str_len: &mut usize) -> Result<Cow<'a, str>, Xlsb
 ///
 /// See Ptg [2.5.97.16]
 fn parse_formula(
-    mut rgce: &[u8],
-    sheets: &[String],
-    names: &[(String, String)],
+    rgce: &[u8],
+    mut rgcb: &[u8],
+    base: (u32, u32),
+    ctx: FormulaContext<'_>,
 ) -> Result<String, XlsbError> {
+    parse_formula_expr(rgce, &mut rgcb, base, ctx).map(|e| e.to_string())
+}
+
+/// Decode the formula tokens into an expression, `Expr::Missing` if there is none
+///
+/// `rgcb` holds the extra data of the tokens, which is consumed as they are decoded, and `base`
+/// is the cell the relative references of shared formulas are resolved from.
+fn parse_formula_expr(
+    mut rgce: &[u8],
+    rgcb: &mut &[u8],
+    base: (u32, u32),
+    ctx: FormulaContext<'_>,
+) -> Result<Expr, XlsbError> {
     if rgce.is_empty() {
-        return Ok(String::new());
+        return Ok(Expr::Missing);
     }
 
+    // last row and column, for whole columns and rows
+    let (max_row, max_col) = (0xF_FFFF, 0x3FFF);
+    let sheet = |ixti: u16| ctx.sheets.get(ixti as usize).cloned();
     let mut stack = Vec::new();
-    let mut formula = String::with_capacity(rgce.len());
     while !rgce.is_empty() {
         let ptg = rgce[0];
         rgce = &rgce[1..];
         match ptg {
             0x3a | 0x5a | 0x7a => {
                 // PtgRef3d
-                let ixti = read_u16(&rgce[0..2]);
-                stack.push(formula.len());
-                formula.push_str(&sheets[ixti as usize]);
-                formula.push('!');
-                // TODO: check with relative columns
-                formula.push('$');
-                push_column(read_u16(&rgce[6..8]) as u32, &mut formula);
-                formula.push('$');
-                formula.push_str(&format!("{}", read_u32(&rgce[2..6]) + 1));
+                let cell = CellRef::from_biff(read_u32(&rgce[2..6]), read_u16(&rgce[6..8]));
+                stack.push(Expr::Reference(Reference {
+                    sheet: sheet(read_u16(&rgce[0..2])),
+                    target: RefTarget::Cell(cell),
+                }));
                 rgce = &rgce[8..];
             }
             0x3b | 0x5b | 0x7b => {
                 // PtgArea3d
-                let ixti = read_u16(&rgce[0..2]);
-                stack.push(formula.len());
-                formula.push_str(&sheets[ixti as usize]);
-                formula.push('!');
-                // TODO: check with relative columns
-                formula.push('$');
-                push_column(read_u16(&rgce[10..12]) as u32, &mut formula);
-                formula.push('$');
-                formula.push_str(&format!("{}", read_u32(&rgce[2..6]) + 1));
-                formula.push(':');
-                formula.push('$');
-                push_column(read_u16(&rgce[12..14]) as u32, &mut formula);
-                formula.push('$');
-                formula.push_str(&format!("{}", read_u32(&rgce[6..10]) + 1));
+                let target = RefTarget::from_biff_area(
+                    (read_u32(&rgce[2..6]), read_u32(&rgce[6..10])),
+                    (read_u16(&rgce[10..12]), read_u16(&rgce[12..14])),
+                    max_row,
+                    max_col,
+                );
+                stack.push(Expr::Reference(Reference {
+                    sheet: sheet(read_u16(&rgce[0..2])),
+                    target,
+                }));
                 rgce = &rgce[14..];
             }
             0x3c | 0x5c | 0x7c => {
                 // PtfRefErr3d
-                let ixti = read_u16(&rgce[0..2]);
-                stack.push(formula.len());
-                formula.push_str(&sheets[ixti as usize]);
-                formula.push('!');
-                formula.push_str("#REF!");
+                stack.push(Expr::Reference(Reference {
+                    sheet: sheet(read_u16(&rgce[0..2])),
+                    target: RefTarget::Invalid,
+                }));
                 rgce = &rgce[8..];
             }
             0x3d | 0x5d | 0x7d => {
                 // PtgAreaErr3d
-                let ixti = read_u16(&rgce[0..2]);
-                stack.push(formula.len());
-                formula.push_str(&sheets[ixti as usize]);
-                formula.push('!');
-                formula.push_str("#REF!");
+                stack.push(Expr::Reference(Reference {
+                    sheet: sheet(read_u16(&rgce[0..2])),
+                    target: RefTarget::Invalid,
+                }));
                 rgce = &rgce[14..];
             }
-            0x01 => {
-                // PtgExp: array/shared formula, ignore
-                debug!("ignoring PtgExp array/shared formula");
-                stack.push(formula.len());
+            0x01 | 0x02 => {
+                // PtgExp | PtgTbl: the formula is stored in a BrtShrFmla, BrtArrFmla or BrtTable
+                // record, decoded by the cells reader
+                stack.push(Expr::Missing);
                 rgce = &rgce[4..];
             }
             0x03..=0x11 => {
                 // binary operation
                 let e2 = stack.pop().ok_or(XlsbError::StackLen)?;
-                let e2 = formula.split_off(e2);
-                // imaginary 'e1' will actually already be the start of the binary op
+                let e1 = stack.pop().ok_or(XlsbError::StackLen)?;
                 let op = match ptg {
-                    0x03 => "+",
-                    0x04 => "-",
-                    0x05 => "*",
-                    0x06 => "/",
-                    0x07 => "^",
-                    0x08 => "&",
-                    0x09 => "<",
-                    0x0A => "<=",
-                    0x0B => "=",
-                    0x0C => ">",
-                    0x0D => ">=",
-                    0x0E => "<>",
-                    0x0F => " ",
-                    0x10 => ",",
-                    0x11 => ":",
+                    0x03 => BinaryOp::Add,
+                    0x04 => BinaryOp::Sub,
+                    0x05 => BinaryOp::Mul,
+                    0x06 => BinaryOp::Div,
+                    0x07 => BinaryOp::Pow,
+                    0x08 => BinaryOp::Concat,
+                    0x09 => BinaryOp::Lt,
+                    0x0A => BinaryOp::Le,
+                    0x0B => BinaryOp::Eq,
+                    0x0C => BinaryOp::Ge,
+                    0x0D => BinaryOp::Gt,
+                    0x0E => BinaryOp::Ne,
+                    0x0F => BinaryOp::Intersect,
+                    0x10 => BinaryOp::Union,
+                    0x11 => BinaryOp::Range,
                     _ => unreachable!(),
                 };
-                formula.push_str(op);
-                formula.push_str(&e2);
-            }
-            0x12 => {
-                let e = stack.last().ok_or(XlsbError::StackLen)?;
-                formula.insert(*e, '+');
-            }
-            0x13 => {
-                let e = stack.last().ok_or(XlsbError::StackLen)?;
-                formula.insert(*e, '-');
+                stack.push(Expr::Binary(op, Box::new(e1), Box::new(e2)));
             }
-            0x14 => {
-                formula.push('%');
-            }
-            0x15 => {
-                let e = stack.last().ok_or(XlsbError::StackLen)?;
-                formula.insert(*e, '(');
-                formula.push(')');
+            0x12..=0x15 => {
+                let e = Box::new(stack.pop().ok_or(XlsbError::StackLen)?);
+                stack.push(match ptg {
+                    0x12 => Expr::Unary(UnaryOp::Plus, e),
+                    0x13 => Expr::Unary(UnaryOp::Minus, e),
+                    0x14 => Expr::Unary(UnaryOp::Percent, e),
+                    _ => Expr::Paren(e),
+                });
             }
             0x16 => {
-                stack.push(formula.len());
+                stack.push(Expr::Missing);
             }
             0x17 => {
-                stack.push(formula.len());
-                formula.push('\"');
                 let cch = read_u16(&rgce[0..2]) as usize;
-                formula.push_str(&UTF_16LE.decode(&rgce[2..2 + 2 * cch]).0);
-                formula.push('\"');
+                let s = UTF_16LE.decode(&rgce[2..2 + 2 * cch]).0;
+                stack.push(Expr::Text(s.into_owned()));
                 rgce = &rgce[2 + 2 * cch..];
             }
             0x18 => {
-                stack.push(formula.len());
                 let eptg = rgce[0];
                 rgce = &rgce[1..];
                 match eptg {
-                    0x19 => rgce = &rgce[12..],
-                    0x1D => rgce = &rgce[4..],
+                    0x19 => {
+                        // PtgList
+                        let tables = ctx.tables.ok_or(XlsbError::TablesNotLoaded)?;
+                        stack.push(match structured_reference(&rgce[..12], tables) {
+                            Some(s) => Expr::StructuredRef(s),
+                            None => Expr::Error(CellErrorType::Ref),
+                        });
+                        rgce = &rgce[12..];
+                    }
+                    0x1D => {
+                        // PtgSxName, a field of a PivotTable calculated item
+                        stack.push(Expr::Error(CellErrorType::Ref));
+                        rgce = &rgce[4..];
+                    }
                     e => return Err(XlsbError::Etpg(e)),
                 }
             }
@@ -754,52 +1989,42 @@ fn parse_formula(
                 rgce = &rgce[1..];
                 match eptg {
                     0x01 | 0x02 | 0x08 | 0x20 | 0x21 | 0x40 | 0x41 | 0x80 => rgce = &rgce[2..],
-                    0x04 => rgce = &rgce[10..],
+                    0x04 => {
+                        // PtgAttrChoose, followed by the offsets of the choices
+                        let offsets = read_u16(rgce) as usize + 1;
+                        rgce = &rgce[2 + 2 * offsets..];
+                    }
                     0x10 => {
+                        // PtgAttrSum
                         rgce = &rgce[2..];
-                        let e = stack.last().ok_or(XlsbError::StackLen)?;
-                        let e = formula.split_off(*e);
-                        formula.push_str("SUM(");
-                        formula.push_str(&e);
-                        formula.push(')');
+                        let e = stack.pop().ok_or(XlsbError::StackLen)?;
+                        stack.push(Expr::Function {
+                            name: "SUM".to_string(),
+                            args: vec![e],
+                        });
                     }
                     e => return Err(XlsbError::Etpg(e)),
                 }
             }
             0x1C => {
-                stack.push(formula.len());
-                let err = rgce[0];
+                stack.push(Expr::Error(parse_err(rgce[0])?));
                 rgce = &rgce[1..];
-                match err {
-                    0x00 => formula.push_str("#NULL!"),
-                    0x07 => formula.push_str("#DIV/0!"),
-                    0x0F => formula.push_str("#VALUE!"),
-                    0x17 => formula.push_str("#REF!"),
-                    0x1D => formula.push_str("#NAME?"),
-                    0x24 => formula.push_str("#NUM!"),
-                    0x2A => formula.push_str("#N/A"),
-                    0x2B => formula.push_str("#GETTING_DATA"),
-                    e => return Err(XlsbError::BErr(e)),
-                }
             }
             0x1D => {
-                stack.push(formula.len());
-                formula.push_str(if rgce[0] == 0 { "FALSE" } else { "TRUE" });
+                stack.push(Expr::Bool(rgce[0] != 0));
                 rgce = &rgce[1..];
             }
             0x1E => {
-                stack.push(formula.len());
-                formula.push_str(&format!("{}", read_u16(rgce)));
+                stack.push(Expr::Number(read_u16(rgce) as f64));
                 rgce = &rgce[2..];
             }
             0x1F => {
-                stack.push(formula.len());
-                formula.push_str(&format!("{}", read_f64(rgce)));
+                stack.push(Expr::Number(read_f64(rgce)));
                 rgce = &rgce[8..];
             }
             0x20 | 0x40 | 0x60 => {
-                // PtgArray: ignore
-                stack.push(formula.len());
+                // PtgArray, whose values are stored in the extra data
+                stack.push(Expr::Array(parse_extra_array(rgcb)?));
                 rgce = &rgce[14..];
             }
             0x21 | 0x22 | 0x41 | 0x42 | 0x61 | 0x62 => {
@@ -823,98 +2048,265 @@ fn parse_formula(
                 if stack.len() < argc {
                     return Err(XlsbError::StackLen);
                 }
-                if argc > 0 {
-                    let args_start = stack.len() - argc;
-                    let mut args = stack.split_off(args_start);
-                    let start = args[0];
-                    for s in &mut args {
-                        *s -= start;
-                    }
-                    let fargs = formula.split_off(start);
-                    stack.push(formula.len());
-                    args.push(fargs.len());
-                    formula.push_str(crate::utils::FTAB[iftab]);
-                    formula.push('(');
-                    for w in args.windows(2) {
-                        formula.push_str(&fargs[w[0]..w[1]]);
-                        formula.push(',');
-                    }
-                    formula.pop();
-                    formula.push(')');
-                } else {
-                    stack.push(formula.len());
-                    formula.push_str(crate::utils::FTAB[iftab]);
-                    formula.push_str("()");
-                }
+                let name = crate::utils::FTAB
+                    .get(iftab)
+                    .ok_or(XlsbError::IfTab(iftab))?;
+                let args = stack.split_off(stack.len() - argc);
+                stack.push(Expr::call(name, args));
             }
             0x23 | 0x43 | 0x63 => {
-                let iname = read_u32(rgce) as usize - 1; // one-based
-                stack.push(formula.len());
-                if let Some(name) = names.get(iname) {
-                    formula.push_str(&name.0);
-                }
+                let iname = (read_u32(rgce) as usize).wrapping_sub(1); // one-based
+                stack.push(match ctx.names.get(iname) {
+                    Some(name) => Expr::Name {
+                        sheet: None,
+                        name: name.0.clone(),
+                    },
+                    None => Expr::Error(CellErrorType::Ref),
+                });
                 rgce = &rgce[4..];
             }
             0x24 | 0x44 | 0x64 => {
-                let row = read_u32(rgce) + 1;
-                let col = [rgce[4], rgce[5] & 0x3F];
-                let col = read_u16(&col);
-                stack.push(formula.len());
-                if rgce[5] & 0x80 != 0x80 {
-                    formula.push('$');
-                }
-                push_column(col as u32, &mut formula);
-                if rgce[5] & 0x40 != 0x40 {
-                    formula.push('$');
-                }
-                formula.push_str(&format!("{}", row));
+                let cell = CellRef::from_biff(read_u32(rgce), read_u16(&rgce[4..6]));
+                stack.push(Expr::Reference(Reference {
+                    sheet: None,
+                    target: RefTarget::Cell(cell),
+                }));
                 rgce = &rgce[6..];
             }
             0x25 | 0x45 | 0x65 => {
-                stack.push(formula.len());
-                formula.push('$');
-                push_column(read_u16(&rgce[8..10]) as u32, &mut formula);
-                formula.push('$');
-                formula.push_str(&format!("{}", read_u32(&rgce[0..4]) + 1));
-                formula.push(':');
-                formula.push('$');
-                push_column(read_u16(&rgce[10..12]) as u32, &mut formula);
-                formula.push('$');
-                formula.push_str(&format!("{}", read_u32(&rgce[4..8]) + 1));
+                let target = RefTarget::from_biff_area(
+                    (read_u32(&rgce[0..4]), read_u32(&rgce[4..8])),
+                    (read_u16(&rgce[8..10]), read_u16(&rgce[10..12])),
+                    max_row,
+                    max_col,
+                );
+                stack.push(Expr::Reference(Reference {
+                    sheet: None,
+                    target,
+                }));
                 rgce = &rgce[12..];
             }
-            0x2A | 0x4A | 0x6A => {
-                stack.push(formula.len());
-                formula.push_str("#REF!");
+            0x26 | 0x46 | 0x66 => {
+                // PtgMemArea, whose areas are stored in the extra data
+                let count = rgcb.get(..4).map_or(0, read_usize);
+                let len = 4 + count.saturating_mul(16);
+                if rgcb.len() < len {
+                    return Err(XlsbError::Len {
+                        typ: "PtgExtraMem",
+                        expected: len,
+                        found: rgcb.len(),
+                    });
+                }
+                *rgcb = &rgcb[len..];
+                let cce = read_u16(&rgce[4..6]) as usize;
                 rgce = &rgce[6..];
+                stack.push(parse_formula_expr(&rgce[..cce], rgcb, base, ctx)?);
+                rgce = &rgce[cce..];
             }
-            0x2B | 0x4B | 0x6B => {
-                stack.push(formula.len());
-                formula.push_str("#REF!");
-                rgce = &rgce[12..];
+            0x27 | 0x47 | 0x67 | 0x28 | 0x48 | 0x68 => {
+                // PtgMemErr | PtgMemNoMem
+                let cce = read_u16(&rgce[4..6]) as usize;
+                rgce = &rgce[6..];
+                stack.push(parse_formula_expr(&rgce[..cce], rgcb, base, ctx)?);
+                rgce = &rgce[cce..];
             }
             0x29 | 0x49 | 0x69 => {
+                // PtgMemFunc
                 let cce = read_u16(rgce) as usize;
                 rgce = &rgce[2..];
-                let f = parse_formula(&rgce[..cce], sheets, names)?;
-                stack.push(formula.len());
-                formula.push_str(&f);
+                stack.push(parse_formula_expr(&rgce[..cce], rgcb, base, ctx)?);
                 rgce = &rgce[cce..];
             }
+            0x2A | 0x4A | 0x6A => {
+                stack.push(Expr::Reference(Reference {
+                    sheet: None,
+                    target: RefTarget::Invalid,
+                }));
+                rgce = &rgce[6..];
+            }
+            0x2B | 0x4B | 0x6B => {
+                stack.push(Expr::Reference(Reference {
+                    sheet: None,
+                    target: RefTarget::Invalid,
+                }));
+                rgce = &rgce[12..];
+            }
+            0x2C | 0x4C | 0x6C => {
+                // PtgRefN, relative to the base cell
+                let (row, col) = relative_cell(read_u32(rgce), read_u16(&rgce[4..6]), base);
+                stack.push(Expr::Reference(Reference {
+                    sheet: None,
+                    target: RefTarget::Cell(CellRef::from_biff(row, col)),
+                }));
+                rgce = &rgce[6..];
+            }
+            0x2D | 0x4D | 0x6D => {
+                // PtgAreaN, relative to the base cell
+                let first = relative_cell(read_u32(rgce), read_u16(&rgce[8..10]), base);
+                let last = relative_cell(read_u32(&rgce[4..8]), read_u16(&rgce[10..12]), base);
+                let target = RefTarget::from_biff_area(
+                    (first.0, last.0),
+                    (first.1, last.1),
+                    max_row,
+                    max_col,
+                );
+                stack.push(Expr::Reference(Reference {
+                    sheet: None,
+                    target,
+                }));
+                rgce = &rgce[12..];
+            }
             0x39 | 0x59 | 0x79 => {
-                // TODO: external workbook ... ignore this formula ...
-                stack.push(formula.len());
-                formula.push_str("EXTERNAL_WB_NAME");
+                // PtgNameX, a name of this or of an external workbook
+                let ixti = read_u16(rgce) as usize;
+                let iname = (read_u32(&rgce[2..6]) as usize).wrapping_sub(1); // one-based
+                let name = match ctx.extern_names.get(ixti) {
+                    Some(ExternNames::Internal) => ctx.names.get(iname).map(|name| Expr::Name {
+                        sheet: None,
+                        name: name.0.clone(),
+                    }),
+                    Some(ExternNames::External(book, names)) => {
+                        names.get(iname).map(|name| Expr::Name {
+                            sheet: Some(format!("[{book}]")),
+                            name: name.clone(),
+                        })
+                    }
+                    None => None,
+                };
+                stack.push(name.unwrap_or(Expr::Error(CellErrorType::Ref)));
                 rgce = &rgce[6..];
             }
             _ => return Err(XlsbError::Ptg(ptg)),
         }
     }
 
-    if stack.len() == 1 {
-        Ok(formula)
+    match (stack.pop(), stack.is_empty()) {
+        (Some(e), true) => Ok(e),
+        _ => Err(XlsbError::StackLen),
+    }
+}
+
+/// Error value of a `PtgErr` or of an array constant
+fn parse_err(code: u8) -> Result<CellErrorType, XlsbError> {
+    Ok(match code {
+        0x00 => CellErrorType::Null,
+        0x07 => CellErrorType::Div0,
+        0x0F => CellErrorType::Value,
+        0x17 => CellErrorType::Ref,
+        0x1D => CellErrorType::Name,
+        0x24 => CellErrorType::Num,
+        0x2A => CellErrorType::NA,
+        0x2B => CellErrorType::GettingData,
+        e => return Err(XlsbError::BErr(e)),
+    })
+}
+
+/// Coordinates of a `PtgRefN` or `PtgAreaN`, whose relative row and column are offsets from the
+/// `base` cell
+fn relative_cell(row: u32, col: u16, base: (u32, u32)) -> (u32, u16) {
+    let row = if col & 0x8000 != 0 {
+        base.0.wrapping_add(row) & 0xF_FFFF
     } else {
-        Err(XlsbError::StackLen)
+        row
+    };
+    let col = if col & 0x4000 != 0 {
+        ((base.1 as u16).wrapping_add(col) & 0x3FFF) | (col & 0xC000)
+    } else {
+        col
+    };
+    (row, col)
+}
+
+/// PtgExtraArray, the values of a `PtgArray`, by rows
+fn parse_extra_array(rgcb: &mut &[u8]) -> Result<Vec<Vec<Expr>>, XlsbError> {
+    let len_error = |expected: usize, found: usize| XlsbError::Len {
+        typ: "PtgExtraArray",
+        expected,
+        found,
+    };
+    if rgcb.len() < 8 {
+        return Err(len_error(8, rgcb.len()));
+    }
+    let rows = read_usize(rgcb);
+    let cols = read_usize(&rgcb[4..]);
+    let mut buf = &rgcb[8..];
+    let mut array = Vec::with_capacity(rows.min(1024));
+    for _ in 0..rows {
+        let mut row = Vec::with_capacity(cols.min(1024));
+        for _ in 0..cols {
+            // SerAr
+            let (&typ, value) = buf.split_first().ok_or_else(|| len_error(1, 0))?;
+            let len = match typ {
+                0x00 => 8,
+                0x01 if value.len() >= 2 => 2 + 2 * read_u16(value) as usize,
+                0x01 => 2,
+                0x02 | 0x04 => 1,
+                t => {
+                    return Err(XlsbError::Unrecognized {
+                        typ: "SerAr",
+                        val: t.to_string(),
+                    })
+                }
+            };
+            if value.len() < len {
+                return Err(len_error(len, value.len()));
+            }
+            row.push(match typ {
+                0x00 => Expr::Number(read_f64(value)),
+                0x01 => Expr::Text(UTF_16LE.decode(&value[2..len]).0.into_owned()),
+                0x02 => Expr::Bool(value[0] != 0),
+                _ => Expr::Error(parse_err(value[0])?),
+            });
+            buf = &value[len..];
+        }
+        array.push(row);
+    }
+    *rgcb = buf;
+    Ok(array)
+}
+
+/// Structured reference of a `PtgList`, `None` if its table is unknown
+///
+/// The reference is written as stored in xlsx files, e.g. `Table1[[#This Row],[Amount]]`.
+fn structured_reference(buf: &[u8], tables: &[TableMetadata]) -> Option<String> {
+    let flags = read_u16(&buf[2..4]);
+    // invalid or nonresident
+    if flags & 0x3000 != 0 {
+        return None;
+    }
+    let (name, _, columns, ..) = tables.iter().find(|t| t.4 == read_u32(&buf[4..8]))?;
+    let column = |i: u16| {
+        let mut escaped = String::new();
+        for c in columns.get(i as usize)?.chars() {
+            if matches!(c, '[' | ']' | '#' | '\'') {
+                escaped.push('\'');
+            }
+            escaped.push(c);
+        }
+        Some(escaped)
+    };
+    let rows: &[&str] = match (flags >> 2) & 0x1F {
+        0x00 => &[],
+        0x01 => &["#All"],
+        0x02 => &["#Data"],
+        0x04 => &["#Headers"],
+        0x06 => &["#Headers", "#Data"],
+        0x08 => &["#Totals"],
+        0x0A => &["#Data", "#Totals"],
+        0x10 => &["#This Row"],
+        _ => return None,
+    };
+    let mut items: Vec<String> = rows.iter().map(|r| format!("[{r}]")).collect();
+    let (first, last) = (read_u16(&buf[8..10]), read_u16(&buf[10..12]));
+    match flags & 0x3 {
+        0 => (),
+        1 => items.push(format!("[{}]", column(first)?)),
+        _ => items.push(format!("[{}]:[{}]", column(first)?, column(last)?)),
+    }
+    match &*items {
+        // a single item is not nested, except column ranges
+        [item] if flags & 0x3 != 2 => Some(format!("{name}{item}")),
+        _ => Some(format!("{name}[{}]", items.join(","))),
     }
 }
 
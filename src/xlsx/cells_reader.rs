@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use quick_xml::{
     events::{attributes::Attribute, BytesStart, Event},
     name::QName,
@@ -8,8 +10,10 @@ use super::{
 };
 use crate::{
     datatype::DataRef,
+    external_link::replace_external_indexes,
     formats::{format_excel_f64_ref, CellFormat},
-    Cell, XlsxError,
+    reference::{a1_to_r1c1, r1c1_to_a1},
+    Cell, ExternalLink, XlsxError,
 };
 
 /// An xlsx Cell Iterator
@@ -17,12 +21,15 @@ pub struct XlsxCellReader<'a> {
     xml: XlReader<'a>,
     strings: &'a [String],
     formats: &'a [CellFormat],
+    external_links: &'a [ExternalLink],
     is_1904: bool,
     dimensions: Dimensions,
     row_index: u32,
     col_index: u32,
     buf: Vec<u8>,
     cell_buf: Vec<u8>,
+    /// Shared formulas in the R1C1 notation, by index
+    shared_formulas: HashMap<u32, String>,
 }
 
 impl<'a> XlsxCellReader<'a> {
@@ -30,6 +37,7 @@ impl<'a> XlsxCellReader<'a> {
         mut xml: XlReader<'a>,
         strings: &'a [String],
         formats: &'a [CellFormat],
+        external_links: &'a [ExternalLink],
         is_1904: bool,
     ) -> Result<Self, XlsxError> {
         let mut buf = Vec::with_capacity(1024);
@@ -62,15 +70,25 @@ impl<'a> XlsxCellReader<'a> {
             xml,
             strings,
             formats,
+            external_links,
             is_1904,
             dimensions,
             row_index: 0,
             col_index: 0,
             buf: Vec::with_capacity(1024),
             cell_buf: Vec::with_capacity(1024),
+            shared_formulas: HashMap::new(),
         })
     }
 
+    fn replace_external_indexes(&self, formula: String) -> String {
+        if self.external_links.is_empty() {
+            formula
+        } else {
+            replace_external_indexes(&formula, self.external_links)
+        }
+    }
+
     pub(crate) fn dimensions(&self) -> Dimensions {
         self.dimensions
     }
@@ -165,8 +183,26 @@ impl<'a> XlsxCellReader<'a> {
                         self.cell_buf.clear();
                         match self.xml.read_event_into(&mut self.cell_buf) {
                             Ok(Event::Start(ref e)) => {
-                                if let Some(f) = read_formula(&mut self.xml, e)? {
-                                    value = Some(f);
+                                let shared = shared_formula_index(e)?;
+                                match (read_formula(&mut self.xml, e)?, shared) {
+                                    // other cells of a shared formula only have its index
+                                    (Some(f), Some(index)) if f.is_empty() => {
+                                        let f = self
+                                            .shared_formulas
+                                            .get(&index)
+                                            .and_then(|f| r1c1_to_a1(f, pos).ok());
+                                        value = f.map(|f| self.replace_external_indexes(f));
+                                    }
+                                    (Some(f), shared) => {
+                                        // the first cell of a shared formula has its text
+                                        if let Some(index) = shared {
+                                            if let Ok(f) = a1_to_r1c1(&f, pos) {
+                                                self.shared_formulas.insert(index, f);
+                                            }
+                                        }
+                                        value = Some(self.replace_external_indexes(f));
+                                    }
+                                    (None, _) => (),
                                 }
                             }
                             Ok(Event::End(ref e)) if e.local_name().as_ref() == b"c" => break,
@@ -302,6 +338,18 @@ fn read_v<'s>(
     }
 }
 
+/// Index of a shared formula, `<f t="shared" si="0">`
+fn shared_formula_index(e: &BytesStart<'_>) -> Result<Option<u32>, XlsxError> {
+    if e.local_name().as_ref() != b"f"
+        || get_attribute(e.attributes(), QName(b"t"))? != Some(b"shared")
+    {
+        return Ok(None);
+    }
+    let index = get_attribute(e.attributes(), QName(b"si"))?
+        .and_then(|si| std::str::from_utf8(si).ok()?.parse().ok());
+    Ok(index)
+}
+
 fn read_formula<'s>(
     xml: &mut XlReader<'_>,
     e: &BytesStart<'_>,
@@ -0,0 +1,131 @@
+//! External links (`xl/externalLinks`): linked workbooks and their cached values
+
+use quick_xml::events::Event;
+use quick_xml::name::QName;
+
+use super::{get_attribute, get_row_column, XlReader, XlsxError};
+use crate::{Cell, CellErrorType, Data, ExternalLink, ExternalSheet, Range};
+
+/// Read an external link part
+///
+/// Returns the link, without its target, and the relationship id of the target if any.
+pub(crate) fn read_external_link(
+    xml: &mut XlReader<'_>,
+    strings: &[String],
+) -> Result<(ExternalLink, Option<String>), XlsxError> {
+    let mut link = ExternalLink::default();
+    let mut target_id = None;
+    let mut cells: Vec<Vec<Cell<Data>>> = Vec::new();
+    // index of the sheet whose cells are being read
+    let mut sheet = None;
+    let mut buf = Vec::with_capacity(1024);
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf) {
+            // cell without cached value
+            Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"cell" => (),
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => match e.local_name().as_ref() {
+                b"externalBook" | b"oleLink" => {
+                    if let Some(id) = get_attribute(e.attributes(), QName(b"r:id"))? {
+                        target_id = Some(xml.decoder().decode(id)?.into_owned());
+                    }
+                }
+                b"ddeLink" => {
+                    let mut service = String::new();
+                    let mut topic = String::new();
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"ddeService") => {
+                                service = a.decode_and_unescape_value(xml)?.into_owned()
+                            }
+                            QName(b"ddeTopic") => {
+                                topic = a.decode_and_unescape_value(xml)?.into_owned()
+                            }
+                            _ => (),
+                        }
+                    }
+                    link.target = format!("{service}|{topic}");
+                }
+                b"sheetName" => {
+                    if let Some(a) = e.try_get_attribute("val")? {
+                        link.sheets.push(ExternalSheet {
+                            name: a.decode_and_unescape_value(xml)?.into_owned(),
+                            cells: Range::empty(),
+                        });
+                        cells.push(Vec::new());
+                    }
+                }
+                b"definedName" => {
+                    if let Some(a) = e.try_get_attribute("name")? {
+                        link.names
+                            .push(a.decode_and_unescape_value(xml)?.into_owned());
+                    }
+                }
+                b"sheetData" => {
+                    sheet = match get_attribute(e.attributes(), QName(b"sheetId"))? {
+                        Some(id) => Some(xml.decoder().decode(id)?.parse::<usize>()?),
+                        None => None,
+                    };
+                }
+                b"cell" => {
+                    let Some(r) = get_attribute(e.attributes(), QName(b"r"))? else {
+                        continue;
+                    };
+                    let pos = get_row_column(r)?;
+                    let typ = get_attribute(e.attributes(), QName(b"t"))?.map(<[u8]>::to_vec);
+                    let value = read_cell_value(xml, typ.as_deref(), strings)?;
+                    if let Some(cells) = sheet.and_then(|i| cells.get_mut(i)) {
+                        cells.push(Cell::new(pos, value));
+                    }
+                }
+                _ => (),
+            },
+            Ok(Event::End(ref e)) => match e.local_name().as_ref() {
+                b"sheetData" => sheet = None,
+                b"externalLink" => break,
+                _ => (),
+            },
+            Ok(Event::Eof) => return Err(XlsxError::XmlEof("externalLink")),
+            Err(e) => return Err(XlsxError::Xml(e)),
+            _ => (),
+        }
+    }
+    for (sheet, cells) in link.sheets.iter_mut().zip(cells) {
+        sheet.cells = Range::from_sparse(cells);
+    }
+    Ok((link, target_id))
+}
+
+/// Read the `v` element of a cached `cell`
+fn read_cell_value(
+    xml: &mut XlReader<'_>,
+    typ: Option<&[u8]>,
+    strings: &[String],
+) -> Result<Data, XlsxError> {
+    let mut v = String::new();
+    let mut buf = Vec::with_capacity(64);
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf)? {
+            Event::Text(t) => v.push_str(&t.unescape()?),
+            Event::End(e) if e.local_name().as_ref() == b"cell" => break,
+            Event::Eof => return Err(XlsxError::XmlEof("cell")),
+            _ => (),
+        }
+    }
+    let value = match typ {
+        _ if v.is_empty() => Data::Empty,
+        Some(b"b") => Data::Bool(v == "1"),
+        Some(b"e") => Data::Error(v.parse::<CellErrorType>()?),
+        Some(b"s") => {
+            let i: usize = v.parse()?;
+            strings
+                .get(i)
+                .map_or(Data::Empty, |s| Data::String(s.clone()))
+        }
+        Some(b"str") | Some(b"inlineStr") => Data::String(v),
+        _ => Data::Float(v.parse()?),
+    };
+    Ok(value)
+}
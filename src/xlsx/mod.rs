@@ -1,4 +1,7 @@
 mod cells_reader;
+mod external_link;
+mod pivot;
+mod structured_ref;
 
 use std::borrow::Cow;
 use std::collections::BTreeMap;
@@ -6,20 +9,34 @@ use std::io::BufReader;
 use std::io::{Read, Seek};
 use std::str::FromStr;
 
-use log::warn;
+use log::{debug, warn};
 use quick_xml::events::attributes::{Attribute, Attributes};
-use quick_xml::events::Event;
+use quick_xml::events::{BytesStart, Event};
 use quick_xml::name::QName;
 use quick_xml::Reader as XmlReader;
 use zip::read::{ZipArchive, ZipFile};
 use zip::result::ZipError;
 
 use crate::datatype::DataRef;
+use crate::external_link::replace_external_indexes;
 use crate::formats::{builtin_format_by_id, detect_custom_number_format, CellFormat};
+use crate::formula::Expr;
+use crate::protection::decode_base64;
+use crate::relationships::{read_relationships, resolve_target};
+use crate::theme::read_theme;
 use crate::vba::VbaProject;
+use crate::{chart, Chart};
+#[cfg(feature = "picture")]
+use crate::{picture, Picture};
 use crate::{
-    Cell, CellErrorType, Data, Dimensions, Metadata, Range, Reader, Sheet, SheetType, SheetVisible,
-    Table,
+    AutoFilter, Cell, CellErrorType, Color, ColorScale, ConditionalFormatRule,
+    ConditionalFormatType, ConditionalFormatting, ConditionalValue, ConditionalValueType,
+    CustomFilter, Data, DataBar, DataValidation, DifferentialFormat, Dimensions, ExternalLink,
+    Filter, FilterColumn, FilterOperator, HeaderFooterText, IconSet, LayoutSpan, Metadata,
+    Orientation, PageOrder, PageSetup, Pane, PanePosition, PaneState, PasswordHash, PivotTable,
+    Range, Reader, Sheet, SheetLayout, SheetProtection, SheetType, SheetView, SheetVisible, SortBy,
+    SortCondition, SortState, Table, TableColumn, TableStyle, TotalsRowFunction,
+    ValidationErrorStyle, ValidationOperator, ValidationType, WorkbookProtection,
 };
 pub use cells_reader::XlsxCellReader;
 
@@ -87,6 +104,12 @@ pub enum XlsxError {
     WorksheetNotFound(String),
     /// Table not found
     TableNotFound(String),
+    /// Structured reference cannot be resolved
+    StructuredReference(String),
+    /// Pivot cache not found
+    PivotCacheNotFound(u32),
+    /// Formula cannot be parsed
+    Formula(crate::formula::FormulaError),
 }
 
 from_err!(std::io::Error, XlsxError, Io);
@@ -96,6 +119,7 @@ from_err!(quick_xml::Error, XlsxError, Xml);
 from_err!(std::string::ParseError, XlsxError, Parse);
 from_err!(std::num::ParseFloatError, XlsxError, ParseFloat);
 from_err!(std::num::ParseIntError, XlsxError, ParseInt);
+from_err!(crate::formula::FormulaError, XlsxError, Formula);
 
 impl std::fmt::Display for XlsxError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -136,6 +160,11 @@ impl std::fmt::Display for XlsxError {
             XlsxError::WorksheetNotFound(n) => write!(f, "Worksheet '{n}' not found"),
             XlsxError::Password => write!(f, "Workbook is password protected"),
             XlsxError::TableNotFound(n) => write!(f, "Table '{n}' not found"),
+            XlsxError::PivotCacheNotFound(id) => write!(f, "Pivot cache '{id}' not found"),
+            XlsxError::Formula(e) => write!(f, "Formula error: {e}"),
+            XlsxError::StructuredReference(r) => {
+                write!(f, "Cannot resolve structured reference '{r}'")
+            }
         }
     }
 }
@@ -150,6 +179,7 @@ impl std::error::Error for XlsxError {
             XlsxError::Parse(e) => Some(e),
             XlsxError::ParseInt(e) => Some(e),
             XlsxError::ParseFloat(e) => Some(e),
+            XlsxError::Formula(e) => Some(e),
             _ => None,
         }
     }
@@ -171,7 +201,24 @@ impl FromStr for CellErrorType {
     }
 }
 
-type Tables = Option<Vec<(String, String, Vec<String>, Dimensions)>>;
+type Tables = Option<Vec<TableMetadata>>;
+
+/// Metadata of a table, as read from its table part
+struct TableMetadata {
+    id: u32,
+    /// Table `displayName`
+    name: String,
+    sheet_name: String,
+    columns: Vec<TableColumn>,
+    /// Whole table, including header and totals rows
+    ref_dims: Dimensions,
+    /// Data rows only
+    data_dims: Dimensions,
+    header_row_count: u32,
+    totals_row_count: u32,
+    style: Option<TableStyle>,
+    auto_filter: Option<AutoFilter>,
+}
 
 /// A struct representing xml zipped excel file
 /// Xlsx, Xlsm, Xlam
@@ -181,17 +228,26 @@ pub struct Xlsx<RS> {
     strings: Vec<String>,
     /// Sheets paths
     sheets: Vec<(String, String)>,
-    /// Tables: Name, Sheet, Columns, Data dimensions
+    /// Tables
     tables: Tables,
     /// Cell (number) formats
     formats: Vec<CellFormat>,
+    /// Differential formats
+    dxfs: Vec<DifferentialFormat>,
     /// 1904 datetime system
     is_1904: bool,
+    /// Workbook protection
+    workbook_protection: Option<WorkbookProtection>,
+    /// Pivot caches id and definition path
+    pivot_caches: Vec<(u32, String)>,
     /// Metadata
     metadata: Metadata,
     /// Pictures
     #[cfg(feature = "picture")]
     pictures: Option<Vec<(String, Vec<u8>)>>,
+    /// Pictures placed on sheets
+    #[cfg(feature = "picture")]
+    sheet_pictures: Option<Vec<Picture>>,
 }
 
 impl<RS: Read + Seek> Xlsx<RS> {
@@ -282,6 +338,37 @@ impl<RS: Read + Seek> Xlsx<RS> {
                         _ => (),
                     }
                 },
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"dxf" => {
+                    let dxf = read_dxf(&mut xml)?;
+                    self.dxfs.push(dxf);
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"indexedColors" => {
+                    let mut colors = Vec::new();
+                    loop {
+                        inner_buf.clear();
+                        match xml.read_event_into(&mut inner_buf) {
+                            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"rgbColor" => {
+                                if let Some(rgb) = get_attribute(e.attributes(), QName(b"rgb"))? {
+                                    colors.push(u32::from_str_radix(
+                                        &xml.decoder().decode(rgb)?,
+                                        16,
+                                    )?);
+                                }
+                            }
+                            Ok(Event::End(ref e))
+                                if e.local_name().as_ref() == b"indexedColors" =>
+                            {
+                                break
+                            }
+                            Ok(Event::Eof) => return Err(XlsxError::XmlEof("indexedColors")),
+                            Err(e) => return Err(XlsxError::Xml(e)),
+                            _ => (),
+                        }
+                    }
+                    self.metadata
+                        .colors
+                        .set_indexed_colors(0, colors.into_iter());
+                }
                 Ok(Event::End(ref e)) if e.local_name().as_ref() == b"styleSheet" => break,
                 Ok(Event::Eof) => return Err(XlsxError::XmlEof("styleSheet")),
                 Err(e) => return Err(XlsxError::Xml(e)),
@@ -300,6 +387,8 @@ impl<RS: Read + Seek> Xlsx<RS> {
             Some(x) => x?,
         };
         let mut defined_names = Vec::new();
+        let mut external_paths = Vec::new();
+        let mut view_read = false;
         let mut buf = Vec::with_capacity(1024);
         let mut val_buf = Vec::with_capacity(1024);
         loop {
@@ -309,6 +398,7 @@ impl<RS: Read + Seek> Xlsx<RS> {
                     let mut name = String::new();
                     let mut path = String::new();
                     let mut visible = SheetVisible::Visible;
+                    let mut sheet_id = None;
                     for a in e.attributes() {
                         let a = a.map_err(XlsxError::XmlAttr)?;
                         match a {
@@ -334,6 +424,10 @@ impl<RS: Read + Seek> Xlsx<RS> {
                                     }
                                 }
                             }
+                            Attribute {
+                                key: QName(b"sheetId"),
+                                value: v,
+                            } => sheet_id = Some(xml.decoder().decode(&v)?.parse()?),
                             Attribute {
                                 key: QName(b"r:id"),
                                 value: v,
@@ -373,9 +467,32 @@ impl<RS: Read + Seek> Xlsx<RS> {
                         name: name.to_string(),
                         typ,
                         visible,
+                        tab_color: None,
+                        sheet_id,
+                        path: Some(path.clone()),
                     });
                     self.sheets.push((name, path));
                 }
+                // only the first view is used when the workbook is opened
+                Ok(Event::Start(ref e))
+                    if e.local_name().as_ref() == b"workbookView" && !view_read =>
+                {
+                    view_read = true;
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"activeTab") => {
+                                self.metadata.active_sheet =
+                                    xml.decoder().decode(&a.value)?.parse()?
+                            }
+                            QName(b"firstSheet") => {
+                                self.metadata.first_visible_sheet =
+                                    xml.decoder().decode(&a.value)?.parse()?
+                            }
+                            _ => (),
+                        }
+                    }
+                }
                 Ok(Event::Start(ref e)) if e.name().as_ref() == b"workbookPr" => {
                     self.is_1904 = match e.try_get_attribute("date1904")? {
                         Some(c) => ["1", "true"].contains(
@@ -386,6 +503,25 @@ impl<RS: Read + Seek> Xlsx<RS> {
                         None => false,
                     };
                 }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"workbookProtection" => {
+                    let mut protection = WorkbookProtection {
+                        password: read_password_hash(&xml, e, "workbook")?,
+                        ..Default::default()
+                    };
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"lockStructure") => {
+                                protection.lock_structure = is_true(&a.value)
+                            }
+                            QName(b"lockWindows") => protection.lock_windows = is_true(&a.value),
+                            _ => (),
+                        }
+                    }
+                    if protection.lock_structure || protection.lock_windows {
+                        self.workbook_protection = Some(protection);
+                    }
+                }
                 Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"definedName" => {
                     if let Some(a) = e
                         .attributes()
@@ -406,16 +542,114 @@ impl<RS: Read + Seek> Xlsx<RS> {
                         defined_names.push((name, value));
                     }
                 }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"externalReference" => {
+                    if let Some(id) = get_attribute(e.attributes(), QName(b"r:id"))? {
+                        let target = relationships
+                            .get(id)
+                            .ok_or(XlsxError::RelationshipNotFound)?;
+                        external_paths.push(resolve_target("xl/workbook.xml", target));
+                    }
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"pivotCache" => {
+                    let mut id = None;
+                    let mut path = None;
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"cacheId") => {
+                                id = Some(xml.decoder().decode(&a.value)?.parse()?)
+                            }
+                            QName(b"r:id") => {
+                                let target = relationships
+                                    .get(&*a.value)
+                                    .ok_or(XlsxError::RelationshipNotFound)?;
+                                path = Some(resolve_target("xl/workbook.xml", target));
+                            }
+                            _ => (),
+                        }
+                    }
+                    if let (Some(id), Some(path)) = (id, path) {
+                        self.pivot_caches.push((id, path));
+                    }
+                }
                 Ok(Event::End(ref e)) if e.local_name().as_ref() == b"workbook" => break,
                 Ok(Event::Eof) => return Err(XlsxError::XmlEof("workbook")),
                 Err(e) => return Err(XlsxError::Xml(e)),
                 _ => (),
             }
         }
+        drop(xml);
+        for path in external_paths {
+            let link = self.read_external_link(&path)?;
+            self.metadata.external_links.push(link);
+        }
+        if !self.metadata.external_links.is_empty() {
+            for (_, formula) in &mut defined_names {
+                *formula = replace_external_indexes(formula, &self.metadata.external_links);
+            }
+        }
         self.metadata.names = defined_names;
         Ok(())
     }
 
+    /// Read an external link part and the target of the link
+    fn read_external_link(&mut self, path: &str) -> Result<ExternalLink, XlsxError> {
+        let (mut link, target_id) = match xml_reader(&mut self.zip, path) {
+            None => return Ok(ExternalLink::default()),
+            Some(xml) => external_link::read_external_link(&mut xml?, &self.strings)?,
+        };
+        if let Some(target_id) = target_id {
+            let relationships = read_relationships::<_, XlsxError>(&mut self.zip, path)?;
+            if let Some((_, _, target)) =
+                relationships.into_iter().find(|(id, ..)| *id == target_id)
+            {
+                link.target = target;
+            }
+        }
+        Ok(link)
+    }
+
+    fn read_theme(&mut self) -> Result<(), XlsxError> {
+        if let Some(xml) = xml_reader(&mut self.zip, "xl/theme/theme1.xml") {
+            self.metadata.colors.theme = read_theme(&mut xml?)?;
+        }
+        Ok(())
+    }
+
+    /// Read the `tabColor` of each sheet, at the very beginning of the sheet parts
+    fn read_tab_colors(&mut self) -> Result<(), XlsxError> {
+        for sheet in &mut self.metadata.sheets {
+            let Some(path) = &sheet.path else { continue };
+            let mut xml = match xml_reader(&mut self.zip, path) {
+                None => continue,
+                Some(x) => x?,
+            };
+            let mut buf = Vec::with_capacity(1024);
+            loop {
+                buf.clear();
+                match xml.read_event_into(&mut buf) {
+                    Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"tabColor" => {
+                        sheet.tab_color = read_color(&xml, e)?;
+                        break;
+                    }
+                    Ok(Event::Start(ref e))
+                        if !matches!(
+                            e.local_name().as_ref(),
+                            b"worksheet" | b"chartsheet" | b"dialogsheet" | b"sheetPr"
+                        ) =>
+                    {
+                        break
+                    }
+                    Ok(Event::End(ref e)) if e.local_name().as_ref() == b"sheetPr" => break,
+                    Ok(Event::Eof) => break,
+                    Err(e) => return Err(XlsxError::Xml(e)),
+                    _ => (),
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn read_relationships(&mut self) -> Result<BTreeMap<Vec<u8>, String>, XlsxError> {
         let mut xml = match xml_reader(&mut self.zip, "xl/_rels/workbook.xml.rels") {
             None => {
@@ -466,6 +700,7 @@ impl<RS: Read + Seek> Xlsx<RS> {
 
             let mut table_locations = Vec::new();
             let mut buf = Vec::with_capacity(64);
+            let mut val_buf = Vec::with_capacity(64);
             // we need another mutable borrow of self.zip later so we enclose this borrow within braces
             {
                 let mut xml = match xml_reader(&mut self.zip, &rel_path) {
@@ -528,7 +763,6 @@ impl<RS: Read + Seek> Xlsx<RS> {
                     None => continue,
                     Some(x) => x?,
                 };
-                let mut column_names = Vec::new();
                 let mut table_meta = InnerTableMetadata::new();
                 loop {
                     buf.clear();
@@ -536,6 +770,10 @@ impl<RS: Read + Seek> Xlsx<RS> {
                         Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"table" => {
                             for a in e.attributes() {
                                 match a.map_err(XlsxError::XmlAttr)? {
+                                    Attribute {
+                                        key: QName(b"id"),
+                                        value: v,
+                                    } => table_meta.id = xml.decoder().decode(&v)?.parse()?,
                                     Attribute {
                                         key: QName(b"displayName"),
                                         value: v,
@@ -560,7 +798,7 @@ impl<RS: Read + Seek> Xlsx<RS> {
                                     Attribute {
                                         key: QName(b"insertRow"),
                                         value: v,
-                                    } => table_meta.insert_row = *v != b"0"[..],
+                                    } => table_meta.insert_row = is_true(&v),
                                     Attribute {
                                         key: QName(b"totalsRowCount"),
                                         value: v,
@@ -572,16 +810,111 @@ impl<RS: Read + Seek> Xlsx<RS> {
                                 }
                             }
                         }
+                        Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"autoFilter" => {
+                            table_meta.auto_filter = Some(read_auto_filter(&mut xml, e)?);
+                        }
+                        Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"sortState" => {
+                            table_meta.sort_state = Some(read_sort_state(&mut xml, e)?);
+                        }
                         Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"tableColumn" => {
-                            for a in e.attributes().flatten() {
-                                if let Attribute {
-                                    key: QName(b"name"),
-                                    value: v,
-                                } = a
-                                {
-                                    column_names.push(xml.decoder().decode(&v)?.into_owned())
+                            let mut column = TableColumn::default();
+                            for a in e.attributes() {
+                                match a.map_err(XlsxError::XmlAttr)? {
+                                    Attribute {
+                                        key: QName(b"id"),
+                                        value: v,
+                                    } => column.id = xml.decoder().decode(&v)?.parse()?,
+                                    a @ Attribute {
+                                        key: QName(b"name"),
+                                        ..
+                                    } => {
+                                        column.name =
+                                            a.decode_and_unescape_value(&xml)?.into_owned()
+                                    }
+                                    a @ Attribute {
+                                        key: QName(b"totalsRowLabel"),
+                                        ..
+                                    } => {
+                                        column.totals_row_label =
+                                            Some(a.decode_and_unescape_value(&xml)?.into_owned())
+                                    }
+                                    Attribute {
+                                        key: QName(b"totalsRowFunction"),
+                                        value: v,
+                                    } => {
+                                        column.totals_row_function = match &*v {
+                                            b"sum" => TotalsRowFunction::Sum,
+                                            b"min" => TotalsRowFunction::Min,
+                                            b"max" => TotalsRowFunction::Max,
+                                            b"average" => TotalsRowFunction::Average,
+                                            b"count" => TotalsRowFunction::Count,
+                                            b"countNums" => TotalsRowFunction::CountNums,
+                                            b"stdDev" => TotalsRowFunction::StdDev,
+                                            b"var" => TotalsRowFunction::Var,
+                                            // the formula is read from <totalsRowFormula>
+                                            b"custom" => TotalsRowFunction::Custom(String::new()),
+                                            _ => TotalsRowFunction::None,
+                                        }
+                                    }
+                                    _ => (),
+                                }
+                            }
+                            val_buf.clear();
+                            loop {
+                                match xml.read_event_into(&mut val_buf)? {
+                                    Event::Start(ref f)
+                                        if f.local_name().as_ref()
+                                            == b"calculatedColumnFormula" =>
+                                    {
+                                        column.calculated_formula =
+                                            Some(read_text(&mut xml, f.name())?);
+                                    }
+                                    Event::Start(ref f)
+                                        if f.local_name().as_ref() == b"totalsRowFormula" =>
+                                    {
+                                        column.totals_row_function = TotalsRowFunction::Custom(
+                                            read_text(&mut xml, f.name())?,
+                                        );
+                                    }
+                                    Event::End(ref end)
+                                        if end.local_name().as_ref() == b"tableColumn" =>
+                                    {
+                                        break
+                                    }
+                                    Event::Eof => return Err(XlsxError::XmlEof("tableColumn")),
+                                    _ => (),
+                                }
+                            }
+                            table_meta.columns.push(column);
+                        }
+                        Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"tableStyleInfo" => {
+                            let mut style = TableStyle::default();
+                            for a in e.attributes() {
+                                match a.map_err(XlsxError::XmlAttr)? {
+                                    Attribute {
+                                        key: QName(b"name"),
+                                        value: v,
+                                    } => style.name = xml.decoder().decode(&v)?.into_owned(),
+                                    Attribute {
+                                        key: QName(b"showFirstColumn"),
+                                        value: v,
+                                    } => style.show_first_column = is_true(&v),
+                                    Attribute {
+                                        key: QName(b"showLastColumn"),
+                                        value: v,
+                                    } => style.show_last_column = is_true(&v),
+                                    Attribute {
+                                        key: QName(b"showRowStripes"),
+                                        value: v,
+                                    } => style.show_row_stripes = is_true(&v),
+                                    Attribute {
+                                        key: QName(b"showColumnStripes"),
+                                        value: v,
+                                    } => style.show_column_stripes = is_true(&v),
+                                    _ => (),
                                 }
                             }
+                            table_meta.style = Some(style);
                         }
                         Ok(Event::End(ref e)) if e.local_name().as_ref() == b"table" => break,
                         Ok(Event::Eof) => return Err(XlsxError::XmlEof("Table")),
@@ -589,22 +922,34 @@ impl<RS: Read + Seek> Xlsx<RS> {
                         _ => (),
                     }
                 }
-                let mut dims = get_dimension(table_meta.ref_cells.as_bytes())?;
+                let ref_dims = get_dimension(table_meta.ref_cells.as_bytes())?;
+                let mut dims = ref_dims;
                 if table_meta.header_row_count != 0 {
                     dims.start.0 += table_meta.header_row_count;
                 }
                 if table_meta.totals_row_count != 0 {
-                    dims.end.0 -= table_meta.header_row_count;
+                    dims.end.0 -= table_meta.totals_row_count;
                 }
                 if table_meta.insert_row {
                     dims.end.0 -= 1;
                 }
-                new_tables.push((
-                    table_meta.display_name,
-                    sheet_name.clone(),
-                    column_names,
-                    dims,
-                ));
+                if let (Some(filter), Some(sort_state)) =
+                    (&mut table_meta.auto_filter, table_meta.sort_state)
+                {
+                    filter.sort_state.get_or_insert(sort_state);
+                }
+                new_tables.push(TableMetadata {
+                    id: table_meta.id,
+                    name: table_meta.display_name,
+                    sheet_name: sheet_name.clone(),
+                    columns: table_meta.columns,
+                    ref_dims,
+                    data_dims: dims,
+                    header_row_count: table_meta.header_row_count,
+                    totals_row_count: table_meta.totals_row_count,
+                    style: table_meta.style,
+                    auto_filter: table_meta.auto_filter,
+                });
             }
             if let Some(tables) = &mut self.tables {
                 tables.append(&mut new_tables);
@@ -641,6 +986,10 @@ impl<RS: Read + Seek> Xlsx<RS> {
         if !pics.is_empty() {
             self.pictures = Some(pics);
         }
+        let sheet_pics = picture::read_sheet_pictures::<_, XlsxError>(&mut self.zip, &self.sheets)?;
+        if !sheet_pics.is_empty() {
+            self.sheet_pictures = Some(sheet_pics);
+        }
         Ok(())
     }
 
@@ -659,7 +1008,7 @@ impl<RS: Read + Seek> Xlsx<RS> {
             .as_ref()
             .expect("Tables must be loaded before they are referenced")
             .iter()
-            .map(|(name, ..)| name)
+            .map(|t| &t.name)
             .collect()
     }
     /// Get the names of all the tables in a sheet
@@ -668,11 +1017,42 @@ impl<RS: Read + Seek> Xlsx<RS> {
             .as_ref()
             .expect("Tables must be loaded before they are referenced")
             .iter()
-            .filter(|(_, sheet, ..)| sheet == sheet_name)
-            .map(|(name, ..)| name)
+            .filter(|t| t.sheet_name == sheet_name)
+            .map(|t| &t.name)
             .collect()
     }
 
+    /// Resolve the table structured references of a formula into A1 references
+    ///
+    /// `sheet_name` and `cell` (row, column) are the location of the formula, used
+    /// to resolve `[#This Row]` (or `@`) items and unqualified references such as
+    /// `[@Amount]` within a table. References to a table in another sheet are
+    /// prefixed with its sheet name.
+    ///
+    /// ```
+    /// use calamine::{open_workbook, Xlsx};
+    ///
+    /// # let path = format!("{}/tests/table-totals.xlsx", env!("CARGO_MANIFEST_DIR"));
+    /// let mut workbook: Xlsx<_> = open_workbook(path).unwrap();
+    /// workbook.load_tables().unwrap();
+    /// let formula = workbook
+    ///     .resolve_structured_references("SUM(SalesTable[Amount])", "Sales", (6, 0))
+    ///     .unwrap();
+    /// assert_eq!(formula, "SUM(D2:D4)");
+    /// ```
+    pub fn resolve_structured_references(
+        &self,
+        formula: &str,
+        sheet_name: &str,
+        cell: (u32, u32),
+    ) -> Result<String, XlsxError> {
+        let tables = self
+            .tables
+            .as_ref()
+            .expect("Tables must be loaded before they are referenced");
+        structured_ref::resolve(formula, tables, sheet_name, cell)
+    }
+
     /// Get the table by name
     // TODO: If retrieving multiple tables from a single sheet, get tables by sheet will be more efficient
     pub fn table_by_name(&mut self, table_name: &str) -> Result<Table<Data>, XlsxError> {
@@ -681,40 +1061,71 @@ impl<RS: Read + Seek> Xlsx<RS> {
             .as_ref()
             .expect("Tables must be loaded before they are referenced")
             .iter()
-            .find(|(table, ..)| table == table_name)
+            .find(|t| t.name == table_name)
             .ok_or_else(|| XlsxError::TableNotFound(table_name.into()))?;
-        let name = match_table_meta.0.to_owned();
-        let sheet_name = match_table_meta.1.clone();
-        let columns = match_table_meta.2.clone();
-        let start_dim = match_table_meta.3.start;
-        let end_dim = match_table_meta.3.end;
+        let name = match_table_meta.name.clone();
+        let sheet_name = match_table_meta.sheet_name.clone();
+        let table_columns = match_table_meta.columns.clone();
+        let columns = table_columns.iter().map(|c| c.name.clone()).collect();
+        let id = match_table_meta.id;
+        let header_row_count = match_table_meta.header_row_count;
+        let style = match_table_meta.style.clone();
+        let auto_filter = match_table_meta.auto_filter.clone();
+        let data_dims = match_table_meta.data_dims;
+        let totals_dims = (match_table_meta.totals_row_count != 0).then(|| {
+            let end = match_table_meta.ref_dims.end;
+            ((end.0, match_table_meta.ref_dims.start.1), end)
+        });
         let range = self.worksheet_range(&sheet_name)?;
-        let tbl_rng = range.range(start_dim, end_dim);
+        let tbl_rng = range.range(data_dims.start, data_dims.end);
+        let totals = totals_dims.map(|(start, end)| {
+            let totals_rng = range.range(start, end);
+            totals_rng
+                .rows()
+                .next()
+                .map_or_else(Vec::new, |r| r.to_vec())
+        });
         Ok(Table {
+            id,
             name,
             sheet_name,
             columns,
             data: tbl_rng,
+            header_row_count,
+            totals,
+            table_columns,
+            style,
+            auto_filter,
         })
     }
 }
 
 struct InnerTableMetadata {
+    id: u32,
     display_name: String,
     ref_cells: String,
     header_row_count: u32,
     insert_row: bool,
     totals_row_count: u32,
+    columns: Vec<TableColumn>,
+    style: Option<TableStyle>,
+    auto_filter: Option<AutoFilter>,
+    sort_state: Option<SortState>,
 }
 
 impl InnerTableMetadata {
     fn new() -> Self {
         Self {
+            id: 0,
             display_name: String::new(),
             ref_cells: String::new(),
             header_row_count: 1,
             insert_row: false,
             totals_row_count: 0,
+            columns: Vec::new(),
+            style: None,
+            auto_filter: None,
+            sort_state: None,
         }
     }
 }
@@ -735,7 +1146,8 @@ impl<RS: Read + Seek> Xlsx<RS> {
         let is_1904 = self.is_1904;
         let strings = &self.strings;
         let formats = &self.formats;
-        XlsxCellReader::new(xml, strings, formats, is_1904)
+        let external_links = &self.metadata.external_links;
+        XlsxCellReader::new(xml, strings, formats, external_links, is_1904)
     }
 
     /// Get worksheet range where shared string values are only borrowed
@@ -743,6 +1155,9 @@ impl<RS: Read + Seek> Xlsx<RS> {
         &'a mut self,
         name: &str,
     ) -> Result<Range<DataRef<'a>>, XlsxError> {
+        if self.metadata.is_chart_sheet(name) {
+            return Ok(Range::empty());
+        }
         let mut cell_reader = self.worksheet_cells_reader(name)?;
         let len = cell_reader.dimensions().len();
         let mut cells = Vec::new();
@@ -762,123 +1177,1309 @@ impl<RS: Read + Seek> Xlsx<RS> {
         }
         Ok(Range::from_sparse(cells))
     }
-}
-
-impl<RS: Read + Seek> Reader<RS> for Xlsx<RS> {
-    type Error = XlsxError;
-
-    fn new(mut reader: RS) -> Result<Self, XlsxError> {
-        check_for_password_protected(&mut reader)?;
-
-        let mut xlsx = Xlsx {
-            zip: ZipArchive::new(reader)?,
-            strings: Vec::new(),
-            formats: Vec::new(),
-            is_1904: false,
-            sheets: Vec::new(),
-            tables: None,
-            metadata: Metadata::default(),
-            #[cfg(feature = "picture")]
-            pictures: None,
-        };
-        xlsx.read_shared_strings()?;
-        xlsx.read_styles()?;
-        let relationships = xlsx.read_relationships()?;
-        xlsx.read_workbook(&relationships)?;
-        #[cfg(feature = "picture")]
-        xlsx.read_pictures()?;
-
-        Ok(xlsx)
-    }
-
-    fn vba_project(&mut self) -> Option<Result<Cow<'_, VbaProject>, XlsxError>> {
-        let mut f = self.zip.by_name("xl/vbaProject.bin").ok()?;
-        let len = f.size() as usize;
-        Some(
-            VbaProject::new(&mut f, len)
-                .map(Cow::Owned)
-                .map_err(XlsxError::Vba),
-        )
-    }
-
-    fn metadata(&self) -> &Metadata {
-        &self.metadata
-    }
-
-    fn worksheet_range(&mut self, name: &str) -> Result<Range<Data>, XlsxError> {
-        let rge = self.worksheet_range_ref(name)?;
-        let inner = rge.inner.into_iter().map(|v| v.into()).collect();
-        Ok(Range {
-            start: rge.start,
-            end: rge.end,
-            inner,
-        })
-    }
 
-    fn worksheet_formula(&mut self, name: &str) -> Result<Range<String>, XlsxError> {
-        let mut cell_reader = self.worksheet_cells_reader(name)?;
-        let len = cell_reader.dimensions().len();
-        let mut cells = Vec::new();
-        if len < 100_000 {
-            cells.reserve(len as usize);
-        }
-        while let Some(cell) = cell_reader.next_formula()? {
-            if !cell.val.is_empty() {
-                cells.push(cell);
+    /// Get the conditional formatting rules of a worksheet
+    pub fn worksheet_conditional_formatting(
+        &mut self,
+        name: &str,
+    ) -> Result<Vec<ConditionalFormatting>, XlsxError> {
+        let (_, path) = self
+            .sheets
+            .iter()
+            .find(|(n, _)| n == name)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))?;
+        let mut xml = xml_reader(&mut self.zip, path)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))??;
+        let mut formattings = Vec::new();
+        let mut buf = Vec::with_capacity(1024);
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"sheetData" => {
+                    xml.read_to_end_into(e.name(), &mut Vec::new())?;
+                }
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"conditionalFormatting" => {
+                    let ranges = match get_attribute(e.attributes(), QName(b"sqref"))? {
+                        Some(sqref) => get_sqref(sqref)?,
+                        None => Vec::new(),
+                    };
+                    let mut rules = Vec::new();
+                    let mut inner_buf = Vec::new();
+                    loop {
+                        inner_buf.clear();
+                        match xml.read_event_into(&mut inner_buf)? {
+                            Event::Start(ref e) if e.local_name().as_ref() == b"cfRule" => {
+                                rules.push(read_cf_rule(&mut xml, e, &self.dxfs)?);
+                            }
+                            Event::End(ref e) if e.name().as_ref() == b"conditionalFormatting" => {
+                                break
+                            }
+                            Event::Eof => return Err(XlsxError::XmlEof("conditionalFormatting")),
+                            _ => (),
+                        }
+                    }
+                    formattings.push(ConditionalFormatting { ranges, rules });
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(XlsxError::Xml(e)),
+                _ => (),
             }
         }
-        Ok(Range::from_sparse(cells))
+        Ok(formattings)
     }
 
-    fn worksheets(&mut self) -> Vec<(String, Range<Data>)> {
-        let names = self
+    /// Get the autofilter of a worksheet, with its filters and sort state
+    ///
+    /// The autofilters of tables are available with `Table::auto_filter`.
+    pub fn worksheet_auto_filter(&mut self, name: &str) -> Result<Option<AutoFilter>, XlsxError> {
+        let (_, path) = self
             .sheets
             .iter()
-            .map(|(n, _)| n.clone())
-            .collect::<Vec<_>>();
-        names
-            .into_iter()
-            .filter_map(|n| {
-                let rge = self.worksheet_range(&n).ok()?;
-                Some((n, rge))
-            })
-            .collect()
-    }
-
-    #[cfg(feature = "picture")]
-    fn pictures(&self) -> Option<Vec<(String, Vec<u8>)>> {
-        self.pictures.to_owned()
-    }
-}
-
-fn xml_reader<'a, RS: Read + Seek>(
-    zip: &'a mut ZipArchive<RS>,
-    path: &str,
-) -> Option<Result<XlReader<'a>, XlsxError>> {
-    match zip.by_name(path) {
-        Ok(f) => {
-            let mut r = XmlReader::from_reader(BufReader::new(f));
-            r.check_end_names(false)
-                .trim_text(false)
-                .check_comments(false)
-                .expand_empty_elements(true);
-            Some(Ok(r))
+            .find(|(n, _)| n == name)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))?;
+        let mut xml = xml_reader(&mut self.zip, path)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))??;
+        let mut auto_filter = None;
+        let mut sort_state = None;
+        let mut buf = Vec::with_capacity(1024);
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                // custom views have their own autofilter
+                Ok(Event::Start(ref e))
+                    if matches!(e.local_name().as_ref(), b"sheetData" | b"customSheetViews") =>
+                {
+                    xml.read_to_end_into(e.name(), &mut Vec::new())?;
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"autoFilter" => {
+                    auto_filter = Some(read_auto_filter(&mut xml, e)?);
+                }
+                // the sort state may also be a sibling of the autofilter
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"sortState" => {
+                    sort_state = Some(read_sort_state(&mut xml, e)?);
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(XlsxError::Xml(e)),
+                _ => (),
+            }
         }
-        Err(ZipError::FileNotFound) => None,
-        Err(e) => Some(Err(e.into())),
+        if let (Some(filter), Some(sort_state)) = (&mut auto_filter, sort_state) {
+            filter.sort_state.get_or_insert(sort_state);
+        }
+        Ok(auto_filter)
     }
-}
 
-/// search through an Element's attributes for the named one
-pub(crate) fn get_attribute<'a>(
-    atts: Attributes<'a>,
-    n: QName,
-) -> Result<Option<&'a [u8]>, XlsxError> {
-    for a in atts {
-        match a {
-            Ok(Attribute {
-                key,
-                value: Cow::Borrowed(value),
+    /// Get the page setup of a worksheet, with its print area and titles
+    pub fn worksheet_page_setup(&mut self, name: &str) -> Result<PageSetup, XlsxError> {
+        let (_, path) = self
+            .sheets
+            .iter()
+            .find(|(n, _)| n == name)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))?;
+        let mut xml = xml_reader(&mut self.zip, path)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))??;
+        let mut setup = PageSetup::default();
+        let mut fit_to_page = false;
+        let mut fit_to_pages = (1, 1);
+        let mut use_first_page_number = false;
+        let mut first_page_number = 1;
+        let mut row_breaks = true;
+        let mut buf = Vec::with_capacity(1024);
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                // custom views have their own page setup
+                Ok(Event::Start(ref e))
+                    if matches!(e.local_name().as_ref(), b"sheetData" | b"customSheetViews") =>
+                {
+                    xml.read_to_end_into(e.name(), &mut Vec::new())?;
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"pageSetUpPr" => {
+                    if let Some(v) = get_attribute(e.attributes(), QName(b"fitToPage"))? {
+                        fit_to_page = is_true(v);
+                    }
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"printOptions" => {
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        let value = is_true(&a.value);
+                        match a.key {
+                            QName(b"horizontalCentered") => setup.center_horizontally = value,
+                            QName(b"verticalCentered") => setup.center_vertically = value,
+                            QName(b"gridLines") => setup.print_grid_lines = value,
+                            QName(b"headings") => setup.print_headings = value,
+                            _ => (),
+                        }
+                    }
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"pageMargins" => {
+                    let margins = &mut setup.margins;
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        let margin = match a.key {
+                            QName(b"left") => &mut margins.left,
+                            QName(b"right") => &mut margins.right,
+                            QName(b"top") => &mut margins.top,
+                            QName(b"bottom") => &mut margins.bottom,
+                            QName(b"header") => &mut margins.header,
+                            QName(b"footer") => &mut margins.footer,
+                            _ => continue,
+                        };
+                        *margin = xml.decoder().decode(&a.value)?.parse()?;
+                    }
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"pageSetup" => {
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"paperSize") => {
+                                setup.paper_size = xml.decoder().decode(&a.value)?.parse()?
+                            }
+                            QName(b"scale") => {
+                                setup.scale = xml.decoder().decode(&a.value)?.parse()?
+                            }
+                            QName(b"fitToWidth") => {
+                                fit_to_pages.0 = xml.decoder().decode(&a.value)?.parse()?
+                            }
+                            QName(b"fitToHeight") => {
+                                fit_to_pages.1 = xml.decoder().decode(&a.value)?.parse()?
+                            }
+                            QName(b"firstPageNumber") => {
+                                first_page_number = xml.decoder().decode(&a.value)?.parse()?
+                            }
+                            QName(b"useFirstPageNumber") => {
+                                use_first_page_number = is_true(&a.value)
+                            }
+                            QName(b"pageOrder") if *a.value == b"overThenDown"[..] => {
+                                setup.page_order = PageOrder::OverThenDown
+                            }
+                            QName(b"orientation") => {
+                                setup.orientation = match &*a.value {
+                                    b"portrait" => Orientation::Portrait,
+                                    b"landscape" => Orientation::Landscape,
+                                    _ => Orientation::Default,
+                                }
+                            }
+                            QName(b"blackAndWhite") => setup.black_and_white = is_true(&a.value),
+                            QName(b"draft") => setup.draft = is_true(&a.value),
+                            _ => (),
+                        }
+                    }
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"headerFooter" => {
+                    let header_footer = &mut setup.header_footer;
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        let value = is_true(&a.value);
+                        match a.key {
+                            QName(b"differentOddEven") => header_footer.different_odd_even = value,
+                            QName(b"differentFirst") => header_footer.different_first = value,
+                            QName(b"scaleWithDoc") => header_footer.scale_with_doc = value,
+                            QName(b"alignWithMargins") => header_footer.align_with_margins = value,
+                            _ => (),
+                        }
+                    }
+                }
+                Ok(Event::Start(ref e))
+                    if matches!(
+                        e.local_name().as_ref(),
+                        b"oddHeader"
+                            | b"oddFooter"
+                            | b"evenHeader"
+                            | b"evenFooter"
+                            | b"firstHeader"
+                            | b"firstFooter"
+                    ) =>
+                {
+                    let header_footer = &mut setup.header_footer;
+                    let text = match e.local_name().as_ref() {
+                        b"oddHeader" => &mut header_footer.odd_header,
+                        b"oddFooter" => &mut header_footer.odd_footer,
+                        b"evenHeader" => &mut header_footer.even_header,
+                        b"evenFooter" => &mut header_footer.even_footer,
+                        b"firstHeader" => &mut header_footer.first_header,
+                        _ => &mut header_footer.first_footer,
+                    };
+                    *text = HeaderFooterText::parse_non_empty(&read_text(&mut xml, e.name())?);
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"rowBreaks" => {
+                    row_breaks = true
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"colBreaks" => {
+                    row_breaks = false
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"brk" => {
+                    if let Some(id) = get_attribute(e.attributes(), QName(b"id"))? {
+                        let id = xml.decoder().decode(id)?.parse()?;
+                        if row_breaks {
+                            setup.row_breaks.push(id);
+                        } else {
+                            setup.column_breaks.push(id);
+                        }
+                    }
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(XlsxError::Xml(e)),
+                _ => (),
+            }
+        }
+        setup.fit_to_pages = fit_to_page.then_some(fit_to_pages);
+        setup.first_page_number = use_first_page_number.then_some(first_page_number);
+        setup.set_defined_names(name, &self.metadata.names);
+        Ok(setup)
+    }
+
+    /// Get the pivot tables of all the worksheets, in sheet order
+    pub fn pivot_tables(&mut self) -> Result<Vec<PivotTable>, XlsxError> {
+        let mut tables = Vec::new();
+        for (sheet_name, sheet_path) in &self.sheets {
+            for (_, typ, target) in read_relationships::<_, XlsxError>(&mut self.zip, sheet_path)? {
+                if !typ.ends_with("/pivotTable") {
+                    continue;
+                }
+                let path = resolve_target(sheet_path, &target);
+                let mut table = match xml_reader(&mut self.zip, &path) {
+                    None => continue,
+                    Some(xml) => pivot::read_pivot_table(&mut xml?, sheet_name)?,
+                };
+                let relationships = read_relationships::<_, XlsxError>(&mut self.zip, &path)?;
+                let cache_path = relationships
+                    .iter()
+                    .find(|(_, typ, _)| typ.ends_with("/pivotCacheDefinition"))
+                    .map(|(_, _, target)| resolve_target(&path, target));
+                if let Some(xml) = cache_path.and_then(|p| xml_reader(&mut self.zip, &p)) {
+                    let (source, fields) = pivot::read_cache_definition(&mut xml?)?;
+                    table.source = source;
+                    table.fields = fields.into_iter().map(|f| f.name).collect();
+                }
+                tables.push(table);
+            }
+        }
+        Ok(tables)
+    }
+
+    /// Get the records of a pivot cache, the first row being the names of the fields
+    ///
+    /// The records are saved in the workbook, they are available even when
+    /// the source of the pivot table no longer exists. Calculated fields are not included.
+    pub fn pivot_cache_records(&mut self, cache_id: u32) -> Result<Range<Data>, XlsxError> {
+        let path = match self.pivot_caches.iter().find(|(id, _)| *id == cache_id) {
+            Some((_, path)) => path.clone(),
+            None => return Err(XlsxError::PivotCacheNotFound(cache_id)),
+        };
+        let fields = match xml_reader(&mut self.zip, &path) {
+            None => return Err(XlsxError::FileNotFound(path)),
+            Some(xml) => pivot::read_cache_definition(&mut xml?)?.1,
+        };
+        let relationships = read_relationships::<_, XlsxError>(&mut self.zip, &path)?;
+        let records_path = relationships
+            .iter()
+            .find(|(_, typ, _)| typ.ends_with("/pivotCacheRecords"))
+            .map(|(_, _, target)| resolve_target(&path, target));
+        let records = match records_path.and_then(|p| xml_reader(&mut self.zip, &p)) {
+            // records are not saved when the cache is refreshed on load
+            None => Vec::new(),
+            Some(xml) => pivot::read_cache_records(&mut xml?, &fields)?,
+        };
+
+        let names: Vec<_> = fields
+            .into_iter()
+            .filter(|f| f.database)
+            .map(|f| f.name)
+            .collect();
+        if names.is_empty() {
+            return Ok(Range::empty());
+        }
+        let mut range = Range::new((0, 0), (records.len() as u32, names.len() as u32 - 1));
+        for (col, name) in names.into_iter().enumerate() {
+            range.set_value((0, col as u32), Data::String(name));
+        }
+        for (row, record) in records.into_iter().enumerate() {
+            for (col, value) in record.into_iter().enumerate() {
+                range.set_value((row as u32 + 1, col as u32), value);
+            }
+        }
+        Ok(range)
+    }
+
+    /// Get the charts of all the sheets, chartsheets included, in sheet order
+    pub fn charts(&mut self) -> Result<Vec<Chart>, XlsxError> {
+        chart::read_sheet_charts(&mut self.zip, &self.sheets)
+    }
+
+    /// Get the formulas of a worksheet as expressions, parsed from their text
+    ///
+    /// Formulas which cannot be parsed are missing.
+    ///
+    /// # Examples
+    /// ```
+    /// use calamine::formula::Expr;
+    /// use calamine::{open_workbook, Reader, Xlsx};
+    ///
+    /// # let path = format!("{}/tests/issues.xlsx", env!("CARGO_MANIFEST_DIR"));
+    /// let mut workbook: Xlsx<_> = open_workbook(path).unwrap();
+    /// let formulas = workbook.worksheet_formula_ast("Sheet1").unwrap();
+    /// // B1+OneRange
+    /// assert!(matches!(formulas.get((0, 0)), Some(Expr::Binary(..))));
+    /// ```
+    pub fn worksheet_formula_ast(&mut self, name: &str) -> Result<Range<Expr>, XlsxError> {
+        let formulas = self.worksheet_formula(name)?;
+        let start = formulas.start().unwrap_or_default();
+        let mut cells = Vec::new();
+        for (row, col, formula) in formulas.used_cells() {
+            let pos = (start.0 + row as u32, start.1 + col as u32);
+            match crate::formula::parse(formula) {
+                Ok(expr) => cells.push(Cell::new(pos, expr)),
+                Err(e) => debug!("cannot parse formula {formula} at {pos:?}: {e}"),
+            }
+        }
+        Ok(Range::from_sparse(cells))
+    }
+}
+
+/// Reads an `autoFilter` element
+fn read_auto_filter(xml: &mut XlReader<'_>, e: &BytesStart<'_>) -> Result<AutoFilter, XlsxError> {
+    let mut auto_filter = AutoFilter::default();
+    if let Some(r) = get_attribute(e.attributes(), QName(b"ref"))? {
+        auto_filter.range = get_dimension(r)?;
+    }
+    let mut column = None;
+    let mut filter = None;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf)? {
+            Event::Start(ref e) => match e.local_name().as_ref() {
+                b"filterColumn" => {
+                    column = match get_attribute(e.attributes(), QName(b"colId"))? {
+                        Some(id) => Some(xml.decoder().decode(id)?.parse()?),
+                        None => Some(0),
+                    };
+                }
+                b"filters" => {
+                    let blank =
+                        get_attribute(e.attributes(), QName(b"blank"))?.map_or(false, is_true);
+                    filter = Some(Filter::Values {
+                        values: Vec::new(),
+                        blank,
+                    });
+                }
+                b"filter" => {
+                    if let Some(Filter::Values { values, .. }) = filter.as_mut() {
+                        for a in e.attributes() {
+                            let a = a.map_err(XlsxError::XmlAttr)?;
+                            if a.key == QName(b"val") {
+                                values.push(a.decode_and_unescape_value(xml)?.into_owned());
+                            }
+                        }
+                    }
+                }
+                b"dateGroupItem" => filter = Some(Filter::Other("dateGroupItem".to_string())),
+                b"customFilters" => {
+                    let and = get_attribute(e.attributes(), QName(b"and"))?.map_or(false, is_true);
+                    filter = Some(Filter::Custom {
+                        and,
+                        conditions: Vec::new(),
+                    });
+                }
+                b"customFilter" => {
+                    if let Some(Filter::Custom { conditions, .. }) = filter.as_mut() {
+                        let mut condition = CustomFilter {
+                            operator: FilterOperator::Equal,
+                            value: String::new(),
+                        };
+                        for a in e.attributes() {
+                            let a = a.map_err(XlsxError::XmlAttr)?;
+                            match a.key {
+                                QName(b"operator") => {
+                                    condition.operator = FilterOperator::from_name(&a.value)
+                                }
+                                QName(b"val") => {
+                                    condition.value = a.decode_and_unescape_value(xml)?.into_owned()
+                                }
+                                _ => (),
+                            }
+                        }
+                        conditions.push(condition);
+                    }
+                }
+                b"top10" => {
+                    let (mut top, mut percent, mut value, mut filter_value) =
+                        (true, false, 0., None);
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"top") => top = is_true(&a.value),
+                            QName(b"percent") => percent = is_true(&a.value),
+                            QName(b"val") => value = xml.decoder().decode(&a.value)?.parse()?,
+                            QName(b"filterVal") => {
+                                filter_value = Some(xml.decoder().decode(&a.value)?.parse()?)
+                            }
+                            _ => (),
+                        }
+                    }
+                    filter = Some(Filter::Top10 {
+                        top,
+                        percent,
+                        value,
+                        filter_value,
+                    });
+                }
+                b"dynamicFilter" => {
+                    let (mut dynamic_type, mut value, mut max_value) = (String::new(), None, None);
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"type") => {
+                                dynamic_type = xml.decoder().decode(&a.value)?.into_owned()
+                            }
+                            QName(b"val") => value = Some(xml.decoder().decode(&a.value)?.parse()?),
+                            QName(b"maxVal") => {
+                                max_value = Some(xml.decoder().decode(&a.value)?.parse()?)
+                            }
+                            _ => (),
+                        }
+                    }
+                    filter = Some(Filter::Dynamic {
+                        dynamic_type,
+                        value,
+                        max_value,
+                    });
+                }
+                b"colorFilter" => filter = Some(Filter::Other("colorFilter".to_string())),
+                b"iconFilter" => filter = Some(Filter::Other("iconFilter".to_string())),
+                b"sortState" => auto_filter.sort_state = Some(read_sort_state(xml, e)?),
+                _ => (),
+            },
+            Event::End(ref e) if e.local_name().as_ref() == b"filterColumn" => {
+                if let (Some(column), Some(filter)) = (column.take(), filter.take()) {
+                    auto_filter.columns.push(FilterColumn { column, filter });
+                }
+            }
+            Event::End(ref e) if e.local_name().as_ref() == b"autoFilter" => break,
+            Event::Eof => return Err(XlsxError::XmlEof("autoFilter")),
+            _ => (),
+        }
+    }
+    Ok(auto_filter)
+}
+
+/// Reads a `sortState` element
+fn read_sort_state(xml: &mut XlReader<'_>, e: &BytesStart<'_>) -> Result<SortState, XlsxError> {
+    let mut sort_state = SortState::default();
+    for a in e.attributes() {
+        let a = a.map_err(XlsxError::XmlAttr)?;
+        match a.key {
+            QName(b"ref") => sort_state.range = get_dimension(&a.value)?,
+            QName(b"caseSensitive") => sort_state.case_sensitive = is_true(&a.value),
+            _ => (),
+        }
+    }
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf)? {
+            Event::Start(ref e) if e.local_name().as_ref() == b"sortCondition" => {
+                let mut condition = SortCondition::default();
+                for a in e.attributes() {
+                    let a = a.map_err(XlsxError::XmlAttr)?;
+                    match a.key {
+                        QName(b"ref") => condition.range = get_dimension(&a.value)?,
+                        QName(b"descending") => condition.descending = is_true(&a.value),
+                        QName(b"sortBy") => condition.sort_by = SortBy::from_name(&a.value),
+                        QName(b"customList") => {
+                            condition.custom_list =
+                                Some(a.decode_and_unescape_value(xml)?.into_owned())
+                        }
+                        _ => (),
+                    }
+                }
+                sort_state.conditions.push(condition);
+            }
+            Event::End(ref e) if e.local_name().as_ref() == b"sortState" => break,
+            Event::Eof => return Err(XlsxError::XmlEof("sortState")),
+            _ => (),
+        }
+    }
+    Ok(sort_state)
+}
+
+/// Reads a `cfRule` element
+fn read_cf_rule(
+    xml: &mut XlReader<'_>,
+    e: &BytesStart<'_>,
+    dxfs: &[DifferentialFormat],
+) -> Result<ConditionalFormatRule, XlsxError> {
+    let mut typ = Vec::new();
+    let mut rule = ConditionalFormatRule {
+        rule_type: ConditionalFormatType::Expression,
+        priority: 0,
+        stop_if_true: false,
+        formulas: Vec::new(),
+        format: None,
+    };
+    let mut operator = ValidationOperator::Between;
+    let mut text = String::new();
+    let mut time_period = String::new();
+    let (mut rank, mut percent, mut bottom) = (10, false, false);
+    let (mut above, mut equal, mut std_dev) = (true, false, None);
+    for a in e.attributes() {
+        let a = a.map_err(XlsxError::XmlAttr)?;
+        match a.key {
+            QName(b"type") => typ = a.value.to_vec(),
+            QName(b"dxfId") => {
+                let id: usize = xml.decoder().decode(&a.value)?.parse()?;
+                rule.format = dxfs.get(id).cloned();
+            }
+            QName(b"priority") => rule.priority = xml.decoder().decode(&a.value)?.parse()?,
+            QName(b"stopIfTrue") => rule.stop_if_true = is_true(&a.value),
+            QName(b"operator") => operator = ValidationOperator::from_name(&a.value),
+            QName(b"text") => text = a.decode_and_unescape_value(xml)?.into_owned(),
+            QName(b"timePeriod") => time_period = xml.decoder().decode(&a.value)?.into_owned(),
+            QName(b"rank") => rank = xml.decoder().decode(&a.value)?.parse()?,
+            QName(b"percent") => percent = is_true(&a.value),
+            QName(b"bottom") => bottom = is_true(&a.value),
+            QName(b"aboveAverage") => above = is_true(&a.value),
+            QName(b"equalAverage") => equal = is_true(&a.value),
+            QName(b"stdDev") => std_dev = Some(xml.decoder().decode(&a.value)?.parse()?),
+            _ => (),
+        }
+    }
+
+    let mut values = Vec::new();
+    let mut colors = Vec::new();
+    let mut icon_set = IconSet {
+        icon_set: "3TrafficLights1".to_string(),
+        show_value: true,
+        ..Default::default()
+    };
+    let mut show_value = true;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf)? {
+            Event::Start(ref e) => match e.local_name().as_ref() {
+                b"formula" => rule.formulas.push(read_text(xml, e.name())?),
+                b"cfvo" => {
+                    let mut value = ConditionalValue::default();
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"type") => {
+                                value.value_type = ConditionalValueType::from_name(&a.value)
+                            }
+                            QName(b"val") => {
+                                value.value = Some(a.decode_and_unescape_value(xml)?.into_owned())
+                            }
+                            _ => (),
+                        }
+                    }
+                    values.push(value);
+                }
+                b"color" => colors.extend(read_color(xml, e)?),
+                b"dataBar" | b"iconSet" => {
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"showValue") => show_value = is_true(&a.value),
+                            QName(b"reverse") => icon_set.reverse = is_true(&a.value),
+                            QName(b"iconSet") => {
+                                icon_set.icon_set = xml.decoder().decode(&a.value)?.into_owned()
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                _ => (),
+            },
+            Event::End(ref e) if e.local_name().as_ref() == b"cfRule" => break,
+            Event::Eof => return Err(XlsxError::XmlEof("cfRule")),
+            _ => (),
+        }
+    }
+
+    rule.rule_type = match &*typ {
+        b"cellIs" => ConditionalFormatType::CellIs(operator),
+        b"expression" => ConditionalFormatType::Expression,
+        b"colorScale" => ConditionalFormatType::ColorScale(ColorScale { values, colors }),
+        b"dataBar" => {
+            let mut values = values.into_iter();
+            ConditionalFormatType::DataBar(DataBar {
+                min: values.next().unwrap_or_default(),
+                max: values.next().unwrap_or_default(),
+                color: colors.first().copied(),
+                show_value,
+            })
+        }
+        b"iconSet" => {
+            icon_set.values = values;
+            icon_set.show_value = show_value;
+            ConditionalFormatType::IconSet(icon_set)
+        }
+        b"top10" => ConditionalFormatType::Top10 {
+            rank,
+            percent,
+            bottom,
+        },
+        b"aboveAverage" => ConditionalFormatType::AboveAverage {
+            above,
+            equal,
+            std_dev,
+        },
+        b"duplicateValues" => ConditionalFormatType::DuplicateValues,
+        b"uniqueValues" => ConditionalFormatType::UniqueValues,
+        b"containsText" => ConditionalFormatType::ContainsText(text),
+        b"notContainsText" => ConditionalFormatType::NotContainsText(text),
+        b"beginsWith" => ConditionalFormatType::BeginsWith(text),
+        b"endsWith" => ConditionalFormatType::EndsWith(text),
+        b"containsBlanks" => ConditionalFormatType::ContainsBlanks,
+        b"notContainsBlanks" => ConditionalFormatType::NotContainsBlanks,
+        b"containsErrors" => ConditionalFormatType::ContainsErrors,
+        b"notContainsErrors" => ConditionalFormatType::NotContainsErrors,
+        b"timePeriod" => ConditionalFormatType::TimePeriod(time_period),
+        t => ConditionalFormatType::Other(xml.decoder().decode(t)?.into_owned()),
+    };
+    Ok(rule)
+}
+
+/// Reads a `dxf` element of the styles
+fn read_dxf(xml: &mut XlReader<'_>) -> Result<DifferentialFormat, XlsxError> {
+    let mut dxf = DifferentialFormat::default();
+    let mut buf = Vec::new();
+    let mut in_font = false;
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf)? {
+            Event::Start(ref e) => {
+                // <b/> is bold, <b val="0"/> is not
+                let is_set = || -> Result<bool, XlsxError> {
+                    Ok(get_attribute(e.attributes(), QName(b"val"))?.map_or(true, is_true))
+                };
+                match e.local_name().as_ref() {
+                    b"font" => in_font = true,
+                    b"b" => dxf.bold = Some(is_set()?),
+                    b"i" => dxf.italic = Some(is_set()?),
+                    // the underline style, e.g. "double", or "none"
+                    b"u" => {
+                        dxf.underline = Some(
+                            get_attribute(e.attributes(), QName(b"val"))?
+                                .map_or(true, |v| v != b"none"),
+                        )
+                    }
+                    b"strike" => dxf.strikethrough = Some(is_set()?),
+                    b"color" if in_font => dxf.font_color = read_color(xml, e)?,
+                    b"numFmt" => {
+                        dxf.number_format = get_attribute(e.attributes(), QName(b"formatCode"))?
+                            .map(|f| xml.decoder().decode(f))
+                            .transpose()?
+                            .map(|f| f.into_owned())
+                    }
+                    b"patternFill" => {
+                        dxf.fill_pattern = get_attribute(e.attributes(), QName(b"patternType"))?
+                            .map(|f| xml.decoder().decode(f))
+                            .transpose()?
+                            .map(|f| f.into_owned())
+                    }
+                    b"fgColor" => dxf.fill_foreground = read_color(xml, e)?,
+                    b"bgColor" => dxf.fill_background = read_color(xml, e)?,
+                    _ => (),
+                }
+            }
+            Event::End(ref e) if e.local_name().as_ref() == b"font" => in_font = false,
+            Event::End(ref e) if e.local_name().as_ref() == b"dxf" => break,
+            Event::Eof => return Err(XlsxError::XmlEof("dxf")),
+            _ => (),
+        }
+    }
+    Ok(dxf)
+}
+
+/// Reads the password attributes of a protection element, prefixed with `prefix`
+/// (e.g. `workbookHashValue`)
+fn read_password_hash(
+    xml: &XlReader<'_>,
+    e: &BytesStart<'_>,
+    prefix: &str,
+) -> Result<Option<PasswordHash>, XlsxError> {
+    let (mut legacy, mut algorithm, mut hash, mut salt, mut spin_count) =
+        (None, None, Vec::new(), Vec::new(), 0);
+    for a in e.attributes() {
+        let a = a.map_err(XlsxError::XmlAttr)?;
+        let key = match a.key.as_ref().strip_prefix(prefix.as_bytes()) {
+            Some(key) => key.to_ascii_lowercase(),
+            None => continue,
+        };
+        let value = xml.decoder().decode(&a.value)?;
+        match &*key {
+            b"password" => legacy = Some(u16::from_str_radix(&value, 16)?),
+            b"algorithmname" => algorithm = Some(value.into_owned()),
+            b"hashvalue" => hash = decode_base64(&value).unwrap_or_default(),
+            b"saltvalue" => salt = decode_base64(&value).unwrap_or_default(),
+            b"spincount" => spin_count = value.parse()?,
+            _ => (),
+        }
+    }
+    Ok(match algorithm {
+        Some(algorithm) => Some(PasswordHash::Iterated {
+            algorithm,
+            hash,
+            salt,
+            spin_count,
+        }),
+        None => legacy.and_then(PasswordHash::from_legacy),
+    })
+}
+
+/// Reads the attributes of a color element (`color`, `fgColor`, ...)
+fn read_color(xml: &XlReader<'_>, e: &BytesStart<'_>) -> Result<Option<Color>, XlsxError> {
+    let mut color = None;
+    let mut tint = 0.;
+    for a in e.attributes() {
+        let a = a.map_err(XlsxError::XmlAttr)?;
+        match a.key {
+            QName(b"rgb") => {
+                let rgb = xml.decoder().decode(&a.value)?;
+                let argb = u32::from_str_radix(&rgb, 16)?;
+                // some writers omit the alpha channel
+                color = Some(Color::Argb(if rgb.len() == 6 {
+                    0xFF00_0000 | argb
+                } else {
+                    argb
+                }));
+            }
+            QName(b"theme") => {
+                color = Some(Color::Theme {
+                    index: xml.decoder().decode(&a.value)?.parse()?,
+                    tint: 0.,
+                })
+            }
+            QName(b"indexed") => {
+                color = Some(Color::Indexed(xml.decoder().decode(&a.value)?.parse()?))
+            }
+            QName(b"auto") if is_true(&a.value) => color = Some(Color::Auto),
+            QName(b"tint") => tint = xml.decoder().decode(&a.value)?.parse()?,
+            _ => (),
+        }
+    }
+    if let Some(Color::Theme { tint: t, .. }) = color.as_mut() {
+        *t = tint;
+    }
+    Ok(color)
+}
+
+impl<RS: Read + Seek> Reader<RS> for Xlsx<RS> {
+    type Error = XlsxError;
+
+    fn new(mut reader: RS) -> Result<Self, XlsxError> {
+        check_for_password_protected(&mut reader)?;
+
+        let mut xlsx = Xlsx {
+            zip: ZipArchive::new(reader)?,
+            strings: Vec::new(),
+            formats: Vec::new(),
+            dxfs: Vec::new(),
+            is_1904: false,
+            workbook_protection: None,
+            sheets: Vec::new(),
+            tables: None,
+            pivot_caches: Vec::new(),
+            metadata: Metadata::default(),
+            #[cfg(feature = "picture")]
+            pictures: None,
+            #[cfg(feature = "picture")]
+            sheet_pictures: None,
+        };
+        xlsx.read_shared_strings()?;
+        xlsx.read_styles()?;
+        xlsx.read_theme()?;
+        let relationships = xlsx.read_relationships()?;
+        xlsx.read_workbook(&relationships)?;
+        xlsx.read_tab_colors()?;
+        #[cfg(feature = "picture")]
+        xlsx.read_pictures()?;
+
+        Ok(xlsx)
+    }
+
+    fn vba_project(&mut self) -> Option<Result<Cow<'_, VbaProject>, XlsxError>> {
+        let mut f = self.zip.by_name("xl/vbaProject.bin").ok()?;
+        let len = f.size() as usize;
+        Some(
+            VbaProject::new(&mut f, len)
+                .map(Cow::Owned)
+                .map_err(XlsxError::Vba),
+        )
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn worksheet_range(&mut self, name: &str) -> Result<Range<Data>, XlsxError> {
+        let rge = self.worksheet_range_ref(name)?;
+        let inner = rge.inner.into_iter().map(|v| v.into()).collect();
+        Ok(Range {
+            start: rge.start,
+            end: rge.end,
+            inner,
+        })
+    }
+
+    fn worksheet_layout(&mut self, name: &str) -> Result<SheetLayout, XlsxError> {
+        let (_, path) = self
+            .sheets
+            .iter()
+            .find(|(n, _)| n == name)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))?;
+        let mut xml = xml_reader(&mut self.zip, path)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))??;
+        let mut layout = SheetLayout::default();
+        let mut buf = Vec::with_capacity(1024);
+        let mut next_row = 0;
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"sheetFormatPr" => {
+                    for a in e.attributes() {
+                        match a.map_err(XlsxError::XmlAttr)? {
+                            Attribute {
+                                key: QName(b"defaultRowHeight"),
+                                value: v,
+                            } => {
+                                layout.default_row_height = Some(xml.decoder().decode(&v)?.parse()?)
+                            }
+                            Attribute {
+                                key: QName(b"defaultColWidth"),
+                                value: v,
+                            } => {
+                                layout.default_column_width =
+                                    Some(xml.decoder().decode(&v)?.parse()?)
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"col" => {
+                    let mut span = LayoutSpan::default();
+                    for a in e.attributes() {
+                        match a.map_err(XlsxError::XmlAttr)? {
+                            Attribute {
+                                key: QName(b"min"),
+                                value: v,
+                            } => {
+                                span.first =
+                                    xml.decoder().decode(&v)?.parse::<u32>()?.saturating_sub(1)
+                            }
+                            Attribute {
+                                key: QName(b"max"),
+                                value: v,
+                            } => {
+                                span.last =
+                                    xml.decoder().decode(&v)?.parse::<u32>()?.saturating_sub(1)
+                            }
+                            Attribute {
+                                key: QName(b"width"),
+                                value: v,
+                            } => span.size = Some(xml.decoder().decode(&v)?.parse()?),
+                            Attribute {
+                                key: QName(b"hidden"),
+                                value: v,
+                            } => span.hidden = is_true(&v),
+                            Attribute {
+                                key: QName(b"outlineLevel"),
+                                value: v,
+                            } => span.outline_level = xml.decoder().decode(&v)?.parse()?,
+                            Attribute {
+                                key: QName(b"collapsed"),
+                                value: v,
+                            } => span.collapsed = is_true(&v),
+                            _ => (),
+                        }
+                    }
+                    layout.columns.push(span);
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"row" => {
+                    let mut span = LayoutSpan {
+                        first: next_row,
+                        last: next_row,
+                        ..Default::default()
+                    };
+                    for a in e.attributes() {
+                        match a.map_err(XlsxError::XmlAttr)? {
+                            Attribute {
+                                key: QName(b"r"),
+                                value: v,
+                            } => {
+                                span.first =
+                                    xml.decoder().decode(&v)?.parse::<u32>()?.saturating_sub(1);
+                                span.last = span.first;
+                            }
+                            Attribute {
+                                key: QName(b"ht"),
+                                value: v,
+                            } => span.size = Some(xml.decoder().decode(&v)?.parse()?),
+                            Attribute {
+                                key: QName(b"hidden"),
+                                value: v,
+                            } => span.hidden = is_true(&v),
+                            Attribute {
+                                key: QName(b"outlineLevel"),
+                                value: v,
+                            } => span.outline_level = xml.decoder().decode(&v)?.parse()?,
+                            Attribute {
+                                key: QName(b"collapsed"),
+                                value: v,
+                            } => span.collapsed = is_true(&v),
+                            _ => (),
+                        }
+                    }
+                    next_row = span.first + 1;
+                    if span.size.is_some()
+                        || span.hidden
+                        || span.outline_level != 0
+                        || span.collapsed
+                    {
+                        layout.rows.push(span);
+                    }
+                    xml.read_to_end_into(e.name(), &mut Vec::new())?;
+                }
+                Ok(Event::End(ref e)) if e.local_name().as_ref() == b"sheetData" => break,
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(XlsxError::Xml(e)),
+                _ => (),
+            }
+        }
+        layout.columns.sort_by_key(|s| s.first);
+        layout.rows.sort_by_key(|s| s.first);
+        Ok(layout)
+    }
+
+    fn worksheet_view(&mut self, name: &str) -> Result<SheetView, XlsxError> {
+        let (_, path) = self
+            .sheets
+            .iter()
+            .find(|(n, _)| n == name)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))?;
+        let mut xml = xml_reader(&mut self.zip, path)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))??;
+        let mut view = SheetView::default();
+        let mut selections = Vec::new();
+        let mut buf = Vec::with_capacity(1024);
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"sheetView" => {
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"showGridLines") => view.show_grid_lines = is_true(&a.value),
+                            QName(b"rightToLeft") => view.right_to_left = is_true(&a.value),
+                            QName(b"zoomScale") => {
+                                view.zoom = xml.decoder().decode(&a.value)?.parse()?
+                            }
+                            QName(b"topLeftCell") => view.top_left_cell = get_row_column(&a.value)?,
+                            _ => (),
+                        }
+                    }
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"pane" => {
+                    let mut pane = Pane {
+                        state: PaneState::Split,
+                        x_split: 0.,
+                        y_split: 0.,
+                        top_left_cell: (0, 0),
+                        active_pane: PanePosition::TopLeft,
+                    };
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            // "frozen" and "frozenSplit"
+                            QName(b"state") if a.value.starts_with(b"frozen") => {
+                                pane.state = PaneState::Frozen
+                            }
+                            QName(b"xSplit") => {
+                                pane.x_split = xml.decoder().decode(&a.value)?.parse()?
+                            }
+                            QName(b"ySplit") => {
+                                pane.y_split = xml.decoder().decode(&a.value)?.parse()?
+                            }
+                            QName(b"topLeftCell") => pane.top_left_cell = get_row_column(&a.value)?,
+                            QName(b"activePane") => {
+                                pane.active_pane = PanePosition::from_name(&a.value)
+                            }
+                            _ => (),
+                        }
+                    }
+                    view.pane = Some(pane);
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"selection" => {
+                    let mut position = PanePosition::TopLeft;
+                    let mut active_cell = None;
+                    let mut sqref = Vec::new();
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"pane") => position = PanePosition::from_name(&a.value),
+                            QName(b"activeCell") => active_cell = Some(get_row_column(&a.value)?),
+                            QName(b"sqref") => sqref = get_sqref(&a.value)?,
+                            _ => (),
+                        }
+                    }
+                    selections.push((position, active_cell, sqref));
+                }
+                // only read the first view
+                Ok(Event::End(ref e)) if e.local_name().as_ref() == b"sheetView" => break,
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"sheetData" => break,
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(XlsxError::Xml(e)),
+                _ => (),
+            }
+        }
+        let active_pane = view
+            .pane
+            .as_ref()
+            .map_or(PanePosition::TopLeft, |p| p.active_pane);
+        if let Some((_, active_cell, sqref)) = selections
+            .iter()
+            .find(|(p, ..)| *p == active_pane)
+            .or(selections.last())
+        {
+            view.active_cell = active_cell.unwrap_or((0, 0));
+            view.selection = if sqref.is_empty() {
+                active_cell
+                    .map(|c| Dimensions { start: c, end: c })
+                    .into_iter()
+                    .collect()
+            } else {
+                sqref.clone()
+            };
+        }
+        Ok(view)
+    }
+
+    fn worksheet_protection(&mut self, name: &str) -> Result<Option<SheetProtection>, XlsxError> {
+        let (_, path) = self
+            .sheets
+            .iter()
+            .find(|(n, _)| n == name)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))?;
+        let mut xml = xml_reader(&mut self.zip, path)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))??;
+        let mut buf = Vec::with_capacity(1024);
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"sheetData" => {
+                    xml.read_to_end_into(e.name(), &mut Vec::new())?;
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"sheetProtection" => {
+                    let mut protection = SheetProtection {
+                        password: read_password_hash(&xml, e, "")?,
+                        ..Default::default()
+                    };
+                    let mut locked = false;
+                    // attributes set to 1 *prevent* the action
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        let allowed = !is_true(&a.value);
+                        match a.key {
+                            QName(b"sheet") => locked = !allowed,
+                            QName(b"objects") => protection.edit_objects = allowed,
+                            QName(b"scenarios") => protection.edit_scenarios = allowed,
+                            QName(b"formatCells") => protection.format_cells = allowed,
+                            QName(b"formatColumns") => protection.format_columns = allowed,
+                            QName(b"formatRows") => protection.format_rows = allowed,
+                            QName(b"insertColumns") => protection.insert_columns = allowed,
+                            QName(b"insertRows") => protection.insert_rows = allowed,
+                            QName(b"insertHyperlinks") => protection.insert_hyperlinks = allowed,
+                            QName(b"deleteColumns") => protection.delete_columns = allowed,
+                            QName(b"deleteRows") => protection.delete_rows = allowed,
+                            QName(b"selectLockedCells") => protection.select_locked_cells = allowed,
+                            QName(b"selectUnlockedCells") => {
+                                protection.select_unlocked_cells = allowed
+                            }
+                            QName(b"sort") => protection.sort = allowed,
+                            QName(b"autoFilter") => protection.auto_filter = allowed,
+                            QName(b"pivotTables") => protection.pivot_tables = allowed,
+                            _ => (),
+                        }
+                    }
+                    return Ok(locked.then_some(protection));
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(XlsxError::Xml(e)),
+                _ => (),
+            }
+        }
+        Ok(None)
+    }
+
+    fn workbook_protection(&mut self) -> Result<Option<WorkbookProtection>, XlsxError> {
+        Ok(self.workbook_protection.clone())
+    }
+
+    fn worksheet_data_validations(&mut self, name: &str) -> Result<Vec<DataValidation>, XlsxError> {
+        let (_, path) = self
+            .sheets
+            .iter()
+            .find(|(n, _)| n == name)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))?;
+        let mut xml = xml_reader(&mut self.zip, path)
+            .ok_or_else(|| XlsxError::WorksheetNotFound(name.into()))??;
+        let mut validations = Vec::new();
+        let mut buf = Vec::with_capacity(1024);
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"sheetData" => {
+                    xml.read_to_end_into(e.name(), &mut Vec::new())?;
+                }
+                // both <dataValidation> and the <x14:dataValidation> of extLst
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"dataValidation" => {
+                    let mut dv = DataValidation {
+                        show_dropdown: true,
+                        ..Default::default()
+                    };
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"type") => {
+                                dv.validation_type = ValidationType::from_name(&a.value)
+                            }
+                            QName(b"operator") => {
+                                dv.operator = ValidationOperator::from_name(&a.value)
+                            }
+                            QName(b"errorStyle") => {
+                                dv.error_style = ValidationErrorStyle::from_name(&a.value)
+                            }
+                            QName(b"allowBlank") => dv.allow_blank = is_true(&a.value),
+                            // confusingly, showDropDown="1" hides the dropdown
+                            QName(b"showDropDown") => dv.show_dropdown = !is_true(&a.value),
+                            QName(b"showInputMessage") => dv.show_input_message = is_true(&a.value),
+                            QName(b"showErrorMessage") => dv.show_error_message = is_true(&a.value),
+                            QName(b"promptTitle") => {
+                                dv.prompt_title = Some(a.decode_and_unescape_value(&xml)?.into())
+                            }
+                            QName(b"prompt") => {
+                                dv.prompt = Some(a.decode_and_unescape_value(&xml)?.into())
+                            }
+                            QName(b"errorTitle") => {
+                                dv.error_title = Some(a.decode_and_unescape_value(&xml)?.into())
+                            }
+                            QName(b"error") => {
+                                dv.error = Some(a.decode_and_unescape_value(&xml)?.into())
+                            }
+                            QName(b"sqref") => dv.ranges = get_sqref(&a.value)?,
+                            _ => (),
+                        }
+                    }
+                    let mut inner_buf = Vec::new();
+                    loop {
+                        inner_buf.clear();
+                        match xml.read_event_into(&mut inner_buf)? {
+                            Event::Start(ref e) => match e.local_name().as_ref() {
+                                b"formula1" => dv.formula1 = Some(read_text(&mut xml, e.name())?),
+                                b"formula2" => dv.formula2 = Some(read_text(&mut xml, e.name())?),
+                                b"sqref" => {
+                                    dv.ranges =
+                                        get_sqref(read_text(&mut xml, e.name())?.as_bytes())?
+                                }
+                                _ => (),
+                            },
+                            Event::End(ref e) if e.local_name().as_ref() == b"dataValidation" => {
+                                break
+                            }
+                            Event::Eof => return Err(XlsxError::XmlEof("dataValidation")),
+                            _ => (),
+                        }
+                    }
+                    validations.push(dv);
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(XlsxError::Xml(e)),
+                _ => (),
+            }
+        }
+        Ok(validations)
+    }
+
+    fn worksheet_formula(&mut self, name: &str) -> Result<Range<String>, XlsxError> {
+        if self.metadata.is_chart_sheet(name) {
+            return Ok(Range::empty());
+        }
+        let mut cell_reader = self.worksheet_cells_reader(name)?;
+        let len = cell_reader.dimensions().len();
+        let mut cells = Vec::new();
+        if len < 100_000 {
+            cells.reserve(len as usize);
+        }
+        while let Some(cell) = cell_reader.next_formula()? {
+            if !cell.val.is_empty() {
+                cells.push(cell);
+            }
+        }
+        Ok(Range::from_sparse(cells))
+    }
+
+    fn worksheets(&mut self) -> Vec<(String, Range<Data>)> {
+        let names = self
+            .sheets
+            .iter()
+            .map(|(n, _)| n.clone())
+            .collect::<Vec<_>>();
+        names
+            .into_iter()
+            .filter_map(|n| {
+                let rge = self.worksheet_range(&n).ok()?;
+                Some((n, rge))
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "picture")]
+    fn pictures(&self) -> Option<Vec<(String, Vec<u8>)>> {
+        self.pictures.to_owned()
+    }
+
+    #[cfg(feature = "picture")]
+    fn sheet_pictures(&self) -> Option<Vec<Picture>> {
+        self.sheet_pictures.to_owned()
+    }
+}
+
+fn xml_reader<'a, RS: Read + Seek>(
+    zip: &'a mut ZipArchive<RS>,
+    path: &str,
+) -> Option<Result<XlReader<'a>, XlsxError>> {
+    match zip.by_name(path) {
+        Ok(f) => {
+            let mut r = XmlReader::from_reader(BufReader::new(f));
+            r.check_end_names(false)
+                .trim_text(false)
+                .check_comments(false)
+                .expand_empty_elements(true);
+            Some(Ok(r))
+        }
+        Err(ZipError::FileNotFound) => None,
+        Err(e) => Some(Err(e.into())),
+    }
+}
+
+/// Reads a `xsd:boolean` attribute value, `1` or `true` (`0` or `false` otherwise)
+pub(crate) fn is_true(value: &[u8]) -> bool {
+    value == b"1" || value == b"true"
+}
+
+/// search through an Element's attributes for the named one
+pub(crate) fn get_attribute<'a>(
+    atts: Attributes<'a>,
+    n: QName,
+) -> Result<Option<&'a [u8]>, XlsxError> {
+    for a in atts {
+        match a {
+            Ok(Attribute {
+                key,
+                value: Cow::Borrowed(value),
             }) if key == n => return Ok(Some(value)),
             Err(e) => return Err(XlsxError::XmlAttr(e)),
             _ => {} // ignore other attributes
@@ -887,6 +2488,15 @@ pub(crate) fn get_attribute<'a>(
     Ok(None)
 }
 
+/// converts a space separated list of dimensions (e.g. "A1:B3 D4") into `Dimensions`
+fn get_sqref(sqref: &[u8]) -> Result<Vec<Dimensions>, XlsxError> {
+    sqref
+        .split(|c| *c == b' ')
+        .filter(|r| !r.is_empty())
+        .map(get_dimension)
+        .collect()
+}
+
 /// converts a text representation (e.g. "A6:G67") of a dimension into integers
 /// - top left (row, column),
 /// - bottom right (row, column)
@@ -983,6 +2593,21 @@ fn get_row_and_optional_column(range: &[u8]) -> Result<(u32, Option<u32>), XlsxE
     Ok((row, col.checked_sub(1)))
 }
 
+/// reads the text content of an element up to its closing tag
+fn read_text(xml: &mut XlReader<'_>, closing: QName) -> Result<String, XlsxError> {
+    let mut buf = Vec::new();
+    let mut value = String::new();
+    loop {
+        match xml.read_event_into(&mut buf)? {
+            Event::Text(t) => value.push_str(&t.unescape()?),
+            Event::End(end) if end.name() == closing => return Ok(value),
+            Event::Eof => return Err(XlsxError::XmlEof("text")),
+            _ => (),
+        }
+        buf.clear();
+    }
+}
+
 /// attempts to read either a simple or richtext string
 pub(crate) fn read_string(
     xml: &mut XlReader<'_>,
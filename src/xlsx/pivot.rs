@@ -0,0 +1,281 @@
+//! Pivot table definitions (`xl/pivotTables`) and pivot caches (`xl/pivotCache`)
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::QName;
+
+use super::{get_attribute, get_dimension, is_true, XlReader, XlsxError};
+use crate::{
+    CellErrorType, Data, Dimensions, PivotAxisField, PivotDataField, PivotFunction, PivotPageField,
+    PivotSource, PivotTable,
+};
+
+/// A field of a pivot cache
+pub(crate) struct CacheField {
+    pub name: String,
+    /// Values referenced by index (`<x v="..."/>`) in the records
+    pub shared_items: Vec<Data>,
+    /// Is the field stored in the records, i.e. is not a calculated field
+    pub database: bool,
+}
+
+/// Read a pivot table definition, without the source and fields of its cache
+pub(crate) fn read_pivot_table(
+    xml: &mut XlReader<'_>,
+    sheet_name: &str,
+) -> Result<PivotTable, XlsxError> {
+    let mut table = PivotTable {
+        name: String::new(),
+        sheet_name: sheet_name.to_string(),
+        location: Dimensions::default(),
+        cache_id: 0,
+        source: PivotSource::Other(String::new()),
+        fields: Vec::new(),
+        row_fields: Vec::new(),
+        column_fields: Vec::new(),
+        page_fields: Vec::new(),
+        data_fields: Vec::new(),
+    };
+    let mut in_row_fields = false;
+    let mut in_column_fields = false;
+    let mut buf = Vec::with_capacity(1024);
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => match e.local_name().as_ref() {
+                b"pivotTableDefinition" => {
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"name") => {
+                                table.name = a.decode_and_unescape_value(xml)?.into_owned()
+                            }
+                            QName(b"cacheId") => {
+                                table.cache_id = xml.decoder().decode(&a.value)?.parse()?
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                b"location" => {
+                    if let Some(r) = get_attribute(e.attributes(), QName(b"ref"))? {
+                        table.location = get_dimension(r)?;
+                    }
+                }
+                b"rowFields" => in_row_fields = true,
+                b"colFields" => in_column_fields = true,
+                b"field" if in_row_fields || in_column_fields => {
+                    let x: i32 = match get_attribute(e.attributes(), QName(b"x"))? {
+                        Some(x) => xml.decoder().decode(x)?.parse()?,
+                        None => continue,
+                    };
+                    // -2 is the virtual field of the data fields
+                    let field = match usize::try_from(x) {
+                        Ok(x) => PivotAxisField::Field(x),
+                        Err(_) => PivotAxisField::Values,
+                    };
+                    if in_row_fields {
+                        table.row_fields.push(field);
+                    } else {
+                        table.column_fields.push(field);
+                    }
+                }
+                b"pageField" => {
+                    let mut field = PivotPageField {
+                        field: 0,
+                        item: None,
+                    };
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"fld") => {
+                                field.field = xml.decoder().decode(&a.value)?.parse()?
+                            }
+                            QName(b"item") => {
+                                field.item = Some(xml.decoder().decode(&a.value)?.parse()?)
+                            }
+                            _ => (),
+                        }
+                    }
+                    table.page_fields.push(field);
+                }
+                b"dataField" => {
+                    let mut field = PivotDataField {
+                        name: None,
+                        field: 0,
+                        function: PivotFunction::Sum,
+                    };
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"name") => {
+                                field.name = Some(a.decode_and_unescape_value(xml)?.into_owned())
+                            }
+                            QName(b"fld") => {
+                                field.field = xml.decoder().decode(&a.value)?.parse()?
+                            }
+                            QName(b"subtotal") => {
+                                field.function = PivotFunction::from_name(&a.value)
+                            }
+                            _ => (),
+                        }
+                    }
+                    table.data_fields.push(field);
+                }
+                _ => (),
+            },
+            Ok(Event::End(ref e)) => match e.local_name().as_ref() {
+                b"rowFields" => in_row_fields = false,
+                b"colFields" => in_column_fields = false,
+                b"pivotTableDefinition" => break,
+                _ => (),
+            },
+            Ok(Event::Eof) => return Err(XlsxError::XmlEof("pivotTableDefinition")),
+            Err(e) => return Err(XlsxError::Xml(e)),
+            _ => (),
+        }
+    }
+    Ok(table)
+}
+
+/// Read a pivot cache definition: the source of the cache and its fields
+pub(crate) fn read_cache_definition(
+    xml: &mut XlReader<'_>,
+) -> Result<(PivotSource, Vec<CacheField>), XlsxError> {
+    let mut source = PivotSource::Other(String::new());
+    let mut fields: Vec<CacheField> = Vec::new();
+    let mut in_shared_items = false;
+    let mut buf = Vec::with_capacity(1024);
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => match e.local_name().as_ref() {
+                b"cacheSource" => {
+                    if let Some(t) = get_attribute(e.attributes(), QName(b"type"))? {
+                        source = PivotSource::Other(xml.decoder().decode(t)?.into_owned());
+                    }
+                }
+                b"worksheetSource" => {
+                    let mut sheet = None;
+                    let mut range = None;
+                    let mut name = None;
+                    let mut external = false;
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"sheet") => {
+                                sheet = Some(a.decode_and_unescape_value(xml)?.into_owned())
+                            }
+                            QName(b"ref") => range = Some(get_dimension(&a.value)?),
+                            QName(b"name") => {
+                                name = Some(a.decode_and_unescape_value(xml)?.into_owned())
+                            }
+                            // source in another workbook
+                            QName(b"r:id") => external = true,
+                            _ => (),
+                        }
+                    }
+                    source = match (name, sheet, range) {
+                        _ if external => PivotSource::Other("external".to_string()),
+                        (Some(name), _, _) => PivotSource::Name(name),
+                        (None, Some(sheet), Some(range)) => PivotSource::Range { sheet, range },
+                        _ => source,
+                    };
+                }
+                b"cacheField" => {
+                    let mut field = CacheField {
+                        name: String::new(),
+                        shared_items: Vec::new(),
+                        database: true,
+                    };
+                    for a in e.attributes() {
+                        let a = a.map_err(XlsxError::XmlAttr)?;
+                        match a.key {
+                            QName(b"name") => {
+                                field.name = a.decode_and_unescape_value(xml)?.into_owned()
+                            }
+                            QName(b"databaseField") => field.database = is_true(&a.value),
+                            _ => (),
+                        }
+                    }
+                    fields.push(field);
+                }
+                b"sharedItems" => in_shared_items = true,
+                _ if in_shared_items => {
+                    if let (Some(field), Some(value)) = (fields.last_mut(), read_value(xml, e)?) {
+                        field.shared_items.push(value);
+                    }
+                }
+                _ => (),
+            },
+            Ok(Event::End(ref e)) => match e.local_name().as_ref() {
+                b"sharedItems" => in_shared_items = false,
+                b"pivotCacheDefinition" => break,
+                _ => (),
+            },
+            Ok(Event::Eof) => return Err(XlsxError::XmlEof("pivotCacheDefinition")),
+            Err(e) => return Err(XlsxError::Xml(e)),
+            _ => (),
+        }
+    }
+    Ok((source, fields))
+}
+
+/// Read the records of a pivot cache, with the values of the database fields
+pub(crate) fn read_cache_records(
+    xml: &mut XlReader<'_>,
+    fields: &[CacheField],
+) -> Result<Vec<Vec<Data>>, XlsxError> {
+    let database_fields: Vec<&CacheField> = fields.iter().filter(|f| f.database).collect();
+    let mut records = Vec::new();
+    let mut record = Vec::with_capacity(database_fields.len());
+    let mut buf = Vec::with_capacity(1024);
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"r" => record.clear(),
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"x" => {
+                let index: usize = match get_attribute(e.attributes(), QName(b"v"))? {
+                    Some(v) => xml.decoder().decode(v)?.parse()?,
+                    None => 0,
+                };
+                let value = database_fields
+                    .get(record.len())
+                    .and_then(|f| f.shared_items.get(index))
+                    .cloned()
+                    .unwrap_or(Data::Empty);
+                record.push(value);
+            }
+            Ok(Event::Start(ref e)) => {
+                if let Some(value) = read_value(xml, e)? {
+                    record.push(value);
+                }
+            }
+            Ok(Event::End(ref e)) if e.local_name().as_ref() == b"r" => {
+                records.push(std::mem::take(&mut record));
+            }
+            Ok(Event::End(ref e)) if e.local_name().as_ref() == b"pivotCacheRecords" => break,
+            Ok(Event::Eof) => return Err(XlsxError::XmlEof("pivotCacheRecords")),
+            Err(e) => return Err(XlsxError::Xml(e)),
+            _ => (),
+        }
+    }
+    Ok(records)
+}
+
+/// Read a value of shared items or records (`<n v="1"/>`, `<s v="a"/>`, `<m/>`...)
+fn read_value(xml: &XlReader<'_>, e: &BytesStart<'_>) -> Result<Option<Data>, XlsxError> {
+    let v = match e.try_get_attribute("v")? {
+        Some(a) => a.decode_and_unescape_value(xml)?.into_owned(),
+        None => String::new(),
+    };
+    let data = match e.local_name().as_ref() {
+        b"m" => Data::Empty,
+        b"n" => Data::Float(v.parse()?),
+        b"s" => Data::String(v),
+        b"b" => Data::Bool(is_true(v.as_bytes())),
+        b"e" => Data::Error(v.parse::<CellErrorType>()?),
+        b"d" => Data::DateTimeIso(v),
+        _ => return Ok(None),
+    };
+    Ok(Some(data))
+}
@@ -0,0 +1,404 @@
+//! Resolution of table structured references (e.g. `Sales[[#This Row],[Amount]]`)
+//! into plain A1 references
+
+use super::{TableMetadata, XlsxError};
+use crate::reference::write_sheet;
+use crate::utils::push_column;
+
+/// Special item specifiers of a structured reference
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Item {
+    All,
+    Data,
+    Headers,
+    Totals,
+    ThisRow,
+}
+
+/// Rewrites every structured reference of `formula` into an A1 reference
+///
+/// `sheet_name` and `cell` (row, column) locate the formula, they are used for
+/// `[#This Row]` items and for unqualified references (`[@Amount]`) within a table.
+pub(crate) fn resolve(
+    formula: &str,
+    tables: &[TableMetadata],
+    sheet_name: &str,
+    cell: (u32, u32),
+) -> Result<String, XlsxError> {
+    let chars: Vec<char> = formula.chars().collect();
+    let mut out = String::with_capacity(formula.len());
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            // string literals and quoted sheet names are copied verbatim
+            q @ ('"' | '\'') => {
+                let end = quoted_end(&chars, i, q);
+                out.extend(&chars[i..end]);
+                i = end;
+            }
+            c if is_name_char(c) => {
+                let start = i;
+                while i < chars.len() && is_name_char(chars[i]) {
+                    i += 1;
+                }
+                let ident: String = chars[start..i].iter().collect();
+                let table = tables.iter().find(|t| t.name.eq_ignore_ascii_case(&ident));
+                match (table, chars.get(i)) {
+                    (Some(table), Some('[')) => {
+                        let end = bracket_end(&chars, i)?;
+                        let inner: String = chars[i + 1..end - 1].iter().collect();
+                        push_reference(&mut out, table, &inner, sheet_name, cell)?;
+                        i = end;
+                    }
+                    (Some(_), Some('(' | '!')) | (None, _) => out.push_str(&ident),
+                    // a bare table name refers to its data
+                    (Some(table), _) => push_reference(&mut out, table, "", sheet_name, cell)?,
+                }
+            }
+            '[' => {
+                let end = bracket_end(&chars, i)?;
+                let inner: String = chars[i + 1..end - 1].iter().collect();
+                // external workbook references (`[1]Sheet1!A1`) are not structured references
+                let table = tables.iter().find(|t| {
+                    t.sheet_name == sheet_name
+                        && t.ref_dims.start.0 <= cell.0
+                        && cell.0 <= t.ref_dims.end.0
+                        && t.ref_dims.start.1 <= cell.1
+                        && cell.1 <= t.ref_dims.end.1
+                });
+                match table {
+                    Some(table) if !inner.chars().all(|c| c.is_ascii_digit()) => {
+                        push_reference(&mut out, table, &inner, sheet_name, cell)?
+                    }
+                    _ => out.extend(&chars[i..end]),
+                }
+                i = end;
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '\\')
+}
+
+/// Index after the closing quote of a quoted section starting at `start`
+fn quoted_end(chars: &[char], start: usize, quote: char) -> usize {
+    let mut i = start + 1;
+    while i < chars.len() {
+        if chars[i] == quote {
+            // doubled quotes are escaped quotes
+            if chars.get(i + 1) == Some(&quote) {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    chars.len()
+}
+
+/// Index after the `]` matching the `[` at `start`
+///
+/// Within structured references `'` escapes the next character.
+fn bracket_end(chars: &[char], start: usize) -> Result<usize, XlsxError> {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\'' => i += 1,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i + 1);
+                }
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    Err(XlsxError::StructuredReference(
+        chars[start..].iter().collect(),
+    ))
+}
+
+/// Splits `s` at top level occurrences of `sep` (i.e. outside of brackets)
+fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\'' => escaped = true,
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            c if c == sep && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => (),
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Removes the enclosing brackets, if any, and the `'` escapes of a specifier
+fn unescape(s: &str) -> String {
+    let s = s.trim();
+    let s = s
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .unwrap_or(s);
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Resolves the specifiers between the outer brackets of a structured reference
+/// and pushes the A1 reference
+fn push_reference(
+    out: &mut String,
+    table: &TableMetadata,
+    inner: &str,
+    sheet_name: &str,
+    cell: (u32, u32),
+) -> Result<(), XlsxError> {
+    let mut items = Vec::new();
+    let mut columns = Vec::new();
+    let inner = inner.trim();
+    let specifiers = if inner.starts_with('[') {
+        split_top_level(inner, ',')
+    } else if inner.is_empty() {
+        Vec::new()
+    } else {
+        // a single specifier, brackets are optional
+        vec![inner]
+    };
+    for spec in specifiers {
+        let spec = spec.trim();
+        let bare = spec
+            .strip_prefix('[')
+            .and_then(|s| s.strip_suffix(']'))
+            .filter(|s| s.starts_with('#'))
+            .unwrap_or(spec);
+        if let Some(column) = bare.strip_prefix('@') {
+            items.push(Item::ThisRow);
+            if !column.trim().is_empty() {
+                columns.push(column);
+            }
+            continue;
+        }
+        let item = match bare.to_ascii_lowercase().as_str() {
+            "#all" => Item::All,
+            "#data" => Item::Data,
+            "#headers" => Item::Headers,
+            "#totals" => Item::Totals,
+            "#this row" => Item::ThisRow,
+            _ => {
+                columns.push(spec);
+                continue;
+            }
+        };
+        items.push(item);
+    }
+
+    let rows = match row_span(table, &items, cell) {
+        Some(rows) => rows,
+        None => {
+            out.push_str("#REF!");
+            return Ok(());
+        }
+    };
+
+    let (first_col, last_col) = if columns.is_empty() {
+        (table.ref_dims.start.1, table.ref_dims.end.1)
+    } else {
+        let mut first = u32::MAX;
+        let mut last = 0;
+        for column in columns {
+            for name in split_top_level(column, ':') {
+                let name = unescape(name);
+                let idx = table
+                    .columns
+                    .iter()
+                    .position(|c| c.name.to_lowercase() == name.to_lowercase())
+                    .ok_or_else(|| {
+                        XlsxError::StructuredReference(format!("{}[{}]", table.name, name))
+                    })? as u32;
+                let col = table.ref_dims.start.1 + idx;
+                first = first.min(col);
+                last = last.max(col);
+            }
+        }
+        (first, last)
+    };
+
+    if table.sheet_name != sheet_name {
+        // writing to a `String` cannot fail
+        let _ = write_sheet(out, &table.sheet_name);
+    }
+    push_cell(out, (rows.0, first_col));
+    if rows.0 != rows.1 || first_col != last_col {
+        out.push(':');
+        push_cell(out, (rows.1, last_col));
+    }
+    Ok(())
+}
+
+/// First and last rows selected by the special items, `None` if they do not exist
+fn row_span(table: &TableMetadata, items: &[Item], cell: (u32, u32)) -> Option<(u32, u32)> {
+    let full = (table.ref_dims.start.0, table.ref_dims.end.0);
+    let data = (table.data_dims.start.0, table.data_dims.end.0);
+    let items = if items.is_empty() {
+        &[Item::Data][..]
+    } else {
+        items
+    };
+    let mut span: Option<(u32, u32)> = None;
+    for item in items {
+        let rows = match item {
+            Item::All => Some(full),
+            Item::Data => Some(data),
+            Item::Headers if table.header_row_count > 0 => {
+                Some((full.0, full.0 + table.header_row_count - 1))
+            }
+            Item::Totals if table.totals_row_count > 0 => {
+                Some((full.1 + 1 - table.totals_row_count, full.1))
+            }
+            Item::ThisRow if data.0 <= cell.0 && cell.0 <= data.1 => Some((cell.0, cell.0)),
+            // a row outside of the table data is an invalid reference
+            Item::ThisRow => return None,
+            Item::Headers | Item::Totals => None,
+        };
+        if let Some((first, last)) = rows {
+            span = Some(match span {
+                Some((f, l)) => (f.min(first), l.max(last)),
+                None => (first, last),
+            });
+        }
+    }
+    span
+}
+
+fn push_cell(out: &mut String, (row, col): (u32, u32)) {
+    push_column(col, out);
+    out.push_str(&(row + 1).to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dimensions, TableColumn};
+
+    fn sales() -> Vec<TableMetadata> {
+        let columns = ["Region", "Quantity", "Unit Price", "Amount"]
+            .iter()
+            .map(|n| TableColumn {
+                name: n.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        // A1:D5, header on row 1 and totals on row 5
+        vec![TableMetadata {
+            id: 1,
+            name: "Sales".to_string(),
+            sheet_name: "Sheet1".to_string(),
+            columns,
+            ref_dims: Dimensions {
+                start: (0, 0),
+                end: (4, 3),
+            },
+            data_dims: Dimensions {
+                start: (1, 0),
+                end: (3, 3),
+            },
+            header_row_count: 1,
+            totals_row_count: 1,
+            style: None,
+            auto_filter: None,
+        }]
+    }
+
+    fn check(formula: &str, cell: (u32, u32), expected: &str) {
+        let resolved = resolve(formula, &sales(), "Sheet1", cell).unwrap();
+        assert_eq!(resolved, expected, "resolving {formula}");
+    }
+
+    #[test]
+    fn resolve_specials() {
+        check("SUM(Sales)", (6, 0), "SUM(A2:D4)");
+        check("SUM(Sales[])", (6, 0), "SUM(A2:D4)");
+        check("ROWS(Sales[#All])", (6, 0), "ROWS(A1:D5)");
+        check("Sales[#Headers]", (6, 0), "A1:D1");
+        check("Sales[#Totals]", (6, 0), "A5:D5");
+        check("Sales[[#Headers],[#Data]]", (6, 0), "A1:D4");
+    }
+
+    #[test]
+    fn resolve_columns() {
+        check("SUM(Sales[Amount])", (6, 0), "SUM(D2:D4)");
+        check("SUM(sales[amount])", (6, 0), "SUM(D2:D4)");
+        check("SUM(Sales[[Quantity]:[Amount]])", (6, 0), "SUM(B2:D4)");
+        check("Sales[[#Totals],[Amount]]", (6, 0), "D5");
+        check("Sales[[#All],[Unit Price]]", (6, 0), "C1:C5");
+    }
+
+    #[test]
+    fn resolve_this_row() {
+        check("Sales[[#This Row],[Amount]]", (2, 5), "D3");
+        check("[@Quantity]*[@[Unit Price]]", (2, 3), "B3*C3");
+        check("[[#This Row],Quantity]*2", (1, 3), "B2*2");
+        check("SUBTOTAL(109,[Amount])", (4, 3), "SUBTOTAL(109,D2:D4)");
+        // outside of the data rows
+        check("Sales[@Amount]", (0, 5), "#REF!");
+    }
+
+    #[test]
+    fn resolve_verbatim() {
+        check("\"Sales[Amount]\"&A1", (6, 0), "\"Sales[Amount]\"&A1");
+        check("[1]Sheet1!A1+SalesTax", (2, 3), "[1]Sheet1!A1+SalesTax");
+        check("SUM('My Sheet'!A1:B2)", (6, 0), "SUM('My Sheet'!A1:B2)");
+        let other = resolve("SUM(Sales[Amount])", &sales(), "Other Sheet", (0, 0)).unwrap();
+        assert_eq!(other, "SUM(Sheet1!D2:D4)");
+    }
+
+    #[test]
+    fn resolve_quoted_sheet_names() {
+        for (sheet_name, expected) in [
+            ("My Sheet", "SUM('My Sheet'!D2:D4)"),
+            ("O'Brien", "SUM('O''Brien'!D2:D4)"),
+            ("2020", "SUM('2020'!D2:D4)"),
+            ("A1", "SUM('A1'!D2:D4)"),
+            ("R1C1", "SUM('R1C1'!D2:D4)"),
+        ] {
+            let mut tables = sales();
+            tables[0].sheet_name = sheet_name.to_string();
+            let resolved = resolve("SUM(Sales[Amount])", &tables, "Other", (0, 0)).unwrap();
+            assert_eq!(resolved, expected);
+        }
+    }
+
+    #[test]
+    fn resolve_unknown_column() {
+        assert!(resolve("Sales[Nope]", &sales(), "Sheet1", (6, 0)).is_err());
+    }
+}
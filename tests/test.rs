@@ -1,9 +1,9 @@
 use calamine::Data::{Bool, DateTime, DateTimeIso, DurationIso, Empty, Error, Float, String};
+use calamine::{formula, CellErrorType::*, Data};
 use calamine::{
     open_workbook, open_workbook_auto, DataType, ExcelDateTime, ExcelDateTimeType, Ods, Reader,
-    Sheet, SheetType, SheetVisible, Xls, Xlsb, Xlsx,
+    Sheet, SheetType, SheetVisible, TotalsRowFunction, Xls, Xlsb, Xlsx,
 };
-use calamine::{CellErrorType::*, Data};
 use std::io::Cursor;
 use std::sync::Once;
 
@@ -739,6 +739,197 @@ fn table() {
     xls.worksheet_range_at(0).unwrap().unwrap();
 }
 
+#[test]
+fn table_metadata() {
+    setup();
+    let path = format!("{}/tests/table-totals.xlsx", env!("CARGO_MANIFEST_DIR"));
+    let mut xls: Xlsx<_> = open_workbook(&path).unwrap();
+    xls.load_tables().unwrap();
+    let table = xls.table_by_name("SalesTable").unwrap();
+    assert_eq!(table.id(), 1);
+    assert_eq!(table.header_row_count(), 1);
+    assert!(table.has_autofilter());
+    assert!(table.has_totals_row());
+    // the totals row is not part of the data
+    range_eq!(
+        table.data(),
+        [
+            [
+                String("North".to_string()),
+                Float(10.),
+                Float(2.),
+                Float(20.)
+            ],
+            [
+                String("South".to_string()),
+                Float(20.),
+                Float(3.),
+                Float(60.)
+            ],
+            [
+                String("East".to_string()),
+                Float(30.),
+                Float(4.),
+                Float(120.)
+            ],
+        ]
+    );
+    assert_eq!(
+        table.totals().unwrap(),
+        [
+            String("Total".to_string()),
+            Float(60.),
+            Float(4.),
+            Float(200.)
+        ]
+    );
+
+    let columns = table.table_columns();
+    assert_eq!(columns.len(), 4);
+    assert_eq!(columns[0].name(), "Region");
+    assert_eq!(columns[0].totals_row_label(), Some("Total"));
+    assert_eq!(columns[0].totals_row_function(), &TotalsRowFunction::None);
+    assert_eq!(columns[1].id(), 2);
+    assert_eq!(columns[1].totals_row_function(), &TotalsRowFunction::Sum);
+    assert_eq!(
+        columns[2].totals_row_function(),
+        &TotalsRowFunction::Custom("MAX([Price])".to_string())
+    );
+    assert_eq!(columns[2].calculated_formula(), None);
+    assert_eq!(
+        columns[3].calculated_formula(),
+        Some("[[#This Row],Quantity]*[[#This Row],Price]")
+    );
+
+    let style = table.style().unwrap();
+    assert_eq!(style.name, "TableStyleMedium9");
+    assert!(style.show_first_column);
+    assert!(!style.show_last_column);
+    assert!(style.show_row_stripes);
+    assert!(!style.show_column_stripes);
+}
+
+#[test]
+fn table_structured_references() {
+    setup();
+    let path = format!("{}/tests/table-totals.xlsx", env!("CARGO_MANIFEST_DIR"));
+    let mut xls: Xlsx<_> = open_workbook(&path).unwrap();
+    xls.load_tables().unwrap();
+    let formulas = xls.worksheet_formula("Sales").unwrap();
+    let resolved: Vec<_> = formulas
+        .cells()
+        .map(|(row, col, f)| {
+            let pos = formulas.start().unwrap();
+            let cell = (pos.0 + row as u32, pos.1 + col as u32);
+            xls.resolve_structured_references(f, "Sales", cell).unwrap()
+        })
+        .filter(|f| !f.is_empty())
+        .collect();
+    assert_eq!(
+        resolved,
+        [
+            "B2*C2",
+            "B3*C3",
+            "B4*C4",
+            "SUBTOTAL(109,B2:B4)",
+            "MAX(C2:C4)",
+            "SUBTOTAL(109,D2:D4)"
+        ]
+    );
+}
+
+#[test]
+fn table_xlsb() {
+    setup();
+    let path = format!(
+        "{}/tests/temperature-table.xlsb",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let mut xlsb: Xlsb<_> = open_workbook(&path).unwrap();
+    xlsb.load_tables().unwrap();
+    let table_names = xlsb.table_names();
+    assert_eq!(table_names[0], "Temperature");
+    assert_eq!(table_names[1], "OtherTable");
+    assert_eq!(xlsb.table_names_in_sheet("Sheet2"), vec!["OtherTable"]);
+    let table = xlsb
+        .table_by_name("Temperature")
+        .expect("Parsing table's sheet should not error");
+    assert_eq!(table.name(), "Temperature");
+    assert_eq!(table.sheet_name(), "Sheet1");
+    assert_eq!(table.columns(), ["label", "value"]);
+    assert_eq!(table.header_row_count(), 1);
+    let data = table.data();
+    assert_eq!(data.get((0, 0)), Some(&String("celsius".to_owned())));
+    assert_eq!(data.get((1, 0)), Some(&String("fahrenheit".to_owned())));
+    assert_eq!(data.get((0, 1)), Some(&Float(22.2222)));
+    assert_eq!(data.get((1, 1)), Some(&Float(72.0)));
+    let table = xlsb
+        .table_by_name("OtherTable")
+        .expect("Parsing table's sheet should not error");
+    assert_eq!(table.columns(), ["label2", "value2"]);
+    let data = table.data();
+    assert_eq!(data.get((0, 0)), Some(&String("something".to_owned())));
+    assert_eq!(data.get((1, 1)), Some(&Float(64.0)));
+    assert!(xlsb.table_by_name("Missing").is_err());
+}
+
+#[test]
+fn table_xlsb_truncated() {
+    setup();
+    let path = format!("{}/tests/table-truncated.xlsb", env!("CARGO_MANIFEST_DIR"));
+    let mut xlsb: Xlsb<_> = open_workbook(&path).unwrap();
+    assert!(matches!(
+        xlsb.load_tables(),
+        Err(calamine::XlsbError::Len {
+            typ: "BrtBeginList",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn table_ods() {
+    setup();
+    let path = format!("{}/tests/database-ranges.ods", env!("CARGO_MANIFEST_DIR"));
+    let ods: Ods<_> = open_workbook(&path).unwrap();
+    // ranges ending before they start are skipped
+    assert_eq!(
+        ods.table_names(),
+        vec!["Temperature", "NoHeader", "HeaderOnly"]
+    );
+    assert_eq!(ods.table_names_in_sheet("Other Sheet"), vec!["NoHeader"]);
+    let table = ods
+        .table_by_name("Temperature")
+        .expect("Parsing table's sheet should not error");
+    assert_eq!(table.sheet_name(), "Sheet1");
+    assert_eq!(table.columns(), ["label", "value"]);
+    range_eq!(
+        table.data(),
+        [
+            [String("celsius".to_owned()), Float(22.2222)],
+            [String("fahrenheit".to_owned()), Float(72.0)]
+        ]
+    );
+    let table = ods
+        .table_by_name("NoHeader")
+        .expect("Parsing table's sheet should not error");
+    assert_eq!(table.sheet_name(), "Other Sheet");
+    assert_eq!(table.columns(), ["Column1", "Column2"]);
+    assert_eq!(table.header_row_count(), 0);
+    range_eq!(
+        table.data(),
+        [
+            [String("something".to_owned()), Float(12.5)],
+            [String("else".to_owned()), Float(64.0)]
+        ]
+    );
+    let table = ods.table_by_name("HeaderOnly").unwrap();
+    assert_eq!(table.columns(), ["label", "value"]);
+    assert_eq!(table.header_row_count(), 1);
+    assert!(table.data().is_empty());
+    assert!(ods.table_by_name("__Anonymous_Sheet_DB__0").is_err());
+}
+
 #[test]
 fn date_xls() {
     setup();
@@ -1258,6 +1449,76 @@ fn pictures() -> Result<(), calamine::Error> {
     Ok(())
 }
 
+#[test]
+#[cfg(feature = "picture")]
+fn sheet_pictures() {
+    use calamine::{AnchorMarker, PictureAnchor};
+
+    setup();
+    let jpg = std::fs::read(format!("{}/tests/picture.jpg", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let png = std::fs::read(format!("{}/tests/picture.png", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let from = AnchorMarker::default();
+    for ext in ["xlsx", "xlsb", "xls"] {
+        let path = format!("{}/tests/picture.{ext}", env!("CARGO_MANIFEST_DIR"));
+        let wb = open_workbook_auto(&path).unwrap();
+        let pics = wb.sheet_pictures().unwrap();
+        assert_eq!(pics.len(), 2, "{ext}");
+        assert_eq!(pics[0].sheet_name, "Sheet1");
+        assert_eq!(pics[0].name, "图片 2");
+        assert_eq!(pics[0].description, None);
+        assert_eq!(pics[0].extension, "jpg");
+        assert_eq!(pics[0].data, jpg);
+        assert_eq!(pics[1].sheet_name, "Sheet2");
+        assert_eq!(pics[1].extension, "png");
+        assert_eq!(pics[1].data, png);
+        let to = match pics[0].anchor {
+            PictureAnchor::TwoCell { from: f, to } if f == from => to,
+            ref a => panic!("unexpected anchor {a:?}"),
+        };
+        assert_eq!((to.row, to.col), (29, 7), "{ext}");
+    }
+
+    let xlsx: Xlsx<_> =
+        open_workbook(format!("{}/tests/picture.xlsx", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let pics = xlsx.sheet_pictures().unwrap();
+    assert_eq!(pics[0].size, Some((5201920, 5201920)));
+    assert_eq!(
+        pics[0].anchor,
+        PictureAnchor::TwoCell {
+            from,
+            to: AnchorMarker {
+                row: 29,
+                col: 7,
+                row_offset: 45720,
+                col_offset: 579120,
+            }
+        }
+    );
+
+    let ods: Ods<_> =
+        open_workbook(format!("{}/tests/picture.ods", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let pics = ods.sheet_pictures().unwrap();
+    assert_eq!(pics.len(), 2);
+    assert_eq!(pics[0].sheet_name, "工作表1");
+    assert_eq!(pics[0].name, "图形 1");
+    assert_eq!(pics[0].data, jpg);
+    // 14.272cm x 13.601cm
+    assert_eq!(pics[0].size, Some((5137920, 4896360)));
+    assert_eq!(
+        pics[0].anchor,
+        PictureAnchor::TwoCell {
+            from,
+            to: AnchorMarker {
+                row: 30,
+                col: 6,
+                row_offset: 19080,
+                col_offset: 260640,
+            }
+        }
+    );
+    assert_eq!(pics[1].data, png);
+}
+
 #[test]
 fn ods_merged_cells() {
     setup();
@@ -1442,22 +1703,34 @@ fn any_sheets_xlsx() {
             Sheet {
                 name: "Visible".to_string(),
                 typ: SheetType::WorkSheet,
-                visible: SheetVisible::Visible
+                visible: SheetVisible::Visible,
+                tab_color: None,
+                sheet_id: Some(1),
+                path: Some("xl/worksheets/sheet1.xml".to_string()),
             },
             Sheet {
                 name: "Hidden".to_string(),
                 typ: SheetType::WorkSheet,
-                visible: SheetVisible::Hidden
+                visible: SheetVisible::Hidden,
+                tab_color: None,
+                sheet_id: Some(2),
+                path: Some("xl/worksheets/sheet2.xml".to_string()),
             },
             Sheet {
                 name: "VeryHidden".to_string(),
                 typ: SheetType::WorkSheet,
-                visible: SheetVisible::VeryHidden
+                visible: SheetVisible::VeryHidden,
+                tab_color: None,
+                sheet_id: Some(3),
+                path: Some("xl/worksheets/sheet3.xml".to_string()),
             },
             Sheet {
                 name: "Chart".to_string(),
                 typ: SheetType::ChartSheet,
-                visible: SheetVisible::Visible
+                visible: SheetVisible::Visible,
+                tab_color: None,
+                sheet_id: Some(4),
+                path: Some("xl/chartsheets/sheet1.xml".to_string()),
             },
         ]
     );
@@ -1476,22 +1749,34 @@ fn any_sheets_xlsb() {
             Sheet {
                 name: "Visible".to_string(),
                 typ: SheetType::WorkSheet,
-                visible: SheetVisible::Visible
+                visible: SheetVisible::Visible,
+                tab_color: None,
+                sheet_id: Some(1),
+                path: Some("xl/worksheets/sheet1.bin".to_string()),
             },
             Sheet {
                 name: "Hidden".to_string(),
                 typ: SheetType::WorkSheet,
-                visible: SheetVisible::Hidden
+                visible: SheetVisible::Hidden,
+                tab_color: None,
+                sheet_id: Some(2),
+                path: Some("xl/worksheets/sheet2.bin".to_string()),
             },
             Sheet {
                 name: "VeryHidden".to_string(),
                 typ: SheetType::WorkSheet,
-                visible: SheetVisible::VeryHidden
+                visible: SheetVisible::VeryHidden,
+                tab_color: None,
+                sheet_id: Some(3),
+                path: Some("xl/worksheets/sheet3.bin".to_string()),
             },
             Sheet {
                 name: "Chart".to_string(),
                 typ: SheetType::ChartSheet,
-                visible: SheetVisible::Visible
+                visible: SheetVisible::Visible,
+                tab_color: None,
+                sheet_id: Some(4),
+                path: Some("xl/chartsheets/sheet1.bin".to_string()),
             },
         ]
     );
@@ -1510,22 +1795,34 @@ fn any_sheets_xls() {
             Sheet {
                 name: "Visible".to_string(),
                 typ: SheetType::WorkSheet,
-                visible: SheetVisible::Visible
+                visible: SheetVisible::Visible,
+                tab_color: None,
+                sheet_id: None,
+                path: None,
             },
             Sheet {
                 name: "Hidden".to_string(),
                 typ: SheetType::WorkSheet,
-                visible: SheetVisible::Hidden
+                visible: SheetVisible::Hidden,
+                tab_color: None,
+                sheet_id: None,
+                path: None,
             },
             Sheet {
                 name: "VeryHidden".to_string(),
                 typ: SheetType::WorkSheet,
-                visible: SheetVisible::VeryHidden
+                visible: SheetVisible::VeryHidden,
+                tab_color: None,
+                sheet_id: None,
+                path: None,
             },
             Sheet {
                 name: "Chart".to_string(),
                 typ: SheetType::ChartSheet,
-                visible: SheetVisible::Visible
+                visible: SheetVisible::Visible,
+                tab_color: None,
+                sheet_id: None,
+                path: None,
             },
         ]
     );
@@ -1544,24 +1841,36 @@ fn any_sheets_ods() {
             Sheet {
                 name: "Visible".to_string(),
                 typ: SheetType::WorkSheet,
-                visible: SheetVisible::Visible
+                visible: SheetVisible::Visible,
+                tab_color: None,
+                sheet_id: None,
+                path: None,
             },
             Sheet {
                 name: "Hidden".to_string(),
                 typ: SheetType::WorkSheet,
-                visible: SheetVisible::Hidden
+                visible: SheetVisible::Hidden,
+                tab_color: None,
+                sheet_id: None,
+                path: None,
             },
             // ODS doesn't support Very Hidden
             Sheet {
                 name: "VeryHidden".to_string(),
                 typ: SheetType::WorkSheet,
-                visible: SheetVisible::Hidden
+                visible: SheetVisible::Hidden,
+                tab_color: None,
+                sheet_id: None,
+                path: None,
             },
             // ODS doesn't support chartsheet
             Sheet {
                 name: "Chart".to_string(),
                 typ: SheetType::WorkSheet,
-                visible: SheetVisible::Visible
+                visible: SheetVisible::Visible,
+                tab_color: None,
+                sheet_id: None,
+                path: None,
             },
         ]
     );
@@ -1674,3 +1983,1336 @@ fn issue_384_multiple_formula() {
     ];
     assert_eq!(formula, expected)
 }
+
+#[test]
+fn worksheet_layout() {
+    setup();
+
+    for ext in ["xlsx", "xlsb", "xls", "ods"] {
+        let path = format!("{}/tests/layout.{}", env!("CARGO_MANIFEST_DIR"), ext);
+        let mut wb = open_workbook_auto(&path).unwrap();
+        let layout = wb.worksheet_layout("Sheet1").unwrap();
+
+        // the xlsx rows and columns have `hidden="false"` attributes, as written by LibreOffice
+        assert!(!layout.is_row_hidden(0), "{ext}");
+        assert!(layout.is_row_hidden(1), "{ext}");
+        assert!(!layout.is_row_hidden(2), "{ext}");
+        assert!(!layout.is_column_hidden(0), "{ext}");
+        assert!(layout.is_column_hidden(1), "{ext}");
+        assert!(!layout.is_column_hidden(3), "{ext}");
+        assert!(!layout.column(2).map_or(false, |c| c.collapsed), "{ext}");
+        assert_eq!(layout.row(3).map(|r| r.outline_level), Some(1), "{ext}");
+        assert_eq!(layout.column(2).map(|c| c.outline_level), Some(1), "{ext}");
+        assert!(layout.row(0).is_none(), "{ext}");
+
+        if ext != "ods" {
+            assert_eq!(layout.default_row_height, Some(15.), "{ext}");
+            assert_eq!(layout.default_column_width, Some(10.), "{ext}");
+            assert_eq!(layout.row(2).and_then(|r| r.size), Some(30.), "{ext}");
+            assert_eq!(layout.column(1).and_then(|c| c.size), Some(12.), "{ext}");
+            assert_eq!(layout.column(3).and_then(|c| c.size), Some(20.), "{ext}");
+        }
+
+        let range = wb.worksheet_range_visible("Sheet1").unwrap();
+        let s = |v: &str| String(v.to_string());
+        range_eq!(
+            range,
+            [
+                [s("A1"), s("C1"), s("D1")],
+                [s("A3"), s("C3"), s("D3")],
+                [s("A4"), s("C4"), s("D4")]
+            ]
+        );
+    }
+}
+
+#[test]
+fn data_validations() {
+    use calamine::{
+        DataValidation, Dimensions, ValidationErrorStyle, ValidationOperator, ValidationType,
+    };
+
+    setup();
+
+    let dims = |start, end| Dimensions { start, end };
+    for ext in ["xlsx", "xlsb", "xls", "ods"] {
+        let path = format!(
+            "{}/tests/data_validation.{}",
+            env!("CARGO_MANIFEST_DIR"),
+            ext
+        );
+        let mut wb = open_workbook_auto(&path).unwrap();
+        // xlsb and xls files have a fifth rule with an unsupported formula, which is skipped
+        let validations = wb.worksheet_data_validations("Sheet1").unwrap();
+        let expected = [
+            DataValidation {
+                ranges: vec![dims((0, 0), (4, 0))],
+                validation_type: ValidationType::List,
+                formula1: Some("\"Yes,No\"".to_string()),
+                allow_blank: true,
+                show_dropdown: true,
+                show_input_message: true,
+                show_error_message: true,
+                error_style: ValidationErrorStyle::Warning,
+                prompt_title: Some("Choose".to_string()),
+                prompt: Some("Pick one".to_string()),
+                error_title: Some("Oops".to_string()),
+                error: Some("Yes or No & nothing else".to_string()),
+                ..Default::default()
+            },
+            DataValidation {
+                ranges: vec![dims((0, 1), (4, 1)), dims((6, 2), (6, 2))],
+                validation_type: ValidationType::Whole,
+                operator: ValidationOperator::Between,
+                formula1: Some("1".to_string()),
+                formula2: Some("10".to_string()),
+                allow_blank: true,
+                show_dropdown: true,
+                show_error_message: true,
+                ..Default::default()
+            },
+            DataValidation {
+                ranges: vec![dims((0, 2), (0, 2))],
+                validation_type: ValidationType::TextLength,
+                operator: ValidationOperator::LessThanOrEqual,
+                formula1: Some("5".to_string()),
+                ..Default::default()
+            },
+            DataValidation {
+                ranges: vec![dims((0, 3), (1, 3))],
+                validation_type: ValidationType::List,
+                formula1: Some(if ext == "ods" {
+                    "[.$F$1:.$F$3]".to_string()
+                } else {
+                    "$F$1:$F$3".to_string()
+                }),
+                allow_blank: true,
+                show_dropdown: true,
+                ..Default::default()
+            },
+        ];
+        assert_eq!(validations, expected, "{ext}");
+        assert_eq!(
+            validations[0].list_values(),
+            Some(vec!["Yes".to_string(), "No".to_string()])
+        );
+        assert!(validations[1].applies_to((6, 2)));
+        assert!(!validations[1].applies_to((5, 1)));
+        assert_eq!(validations[3].list_values(), None);
+    }
+}
+
+#[test]
+fn conditional_formatting() {
+    use calamine::{
+        Color, ConditionalFormatType, ConditionalValueType, DifferentialFormat, Dimensions,
+        ValidationOperator,
+    };
+
+    setup();
+
+    let path = format!(
+        "{}/tests/conditional_format.xlsx",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let mut xlsx: Xlsx<_> = open_workbook(&path).unwrap();
+    let cfs = xlsx.worksheet_conditional_formatting("Sheet1").unwrap();
+    assert_eq!(cfs.len(), 3);
+    assert_eq!(
+        cfs[0].ranges,
+        vec![Dimensions {
+            start: (0, 0),
+            end: (4, 0)
+        }]
+    );
+    let rules = &cfs[0].rules;
+    assert_eq!(rules.len(), 4);
+    assert_eq!(
+        rules[0].rule_type,
+        ConditionalFormatType::CellIs(ValidationOperator::Between)
+    );
+    assert_eq!(rules[0].formulas, vec!["20", "40"]);
+    let red_bold = DifferentialFormat {
+        bold: Some(true),
+        font_color: Some(Color::Argb(0xFF9C0006)),
+        fill_background: Some(Color::Argb(0xFFFFC7CE)),
+        ..Default::default()
+    };
+    assert_eq!(rules[0].format, Some(red_bold.clone()));
+    assert_eq!(
+        rules[1].rule_type,
+        ConditionalFormatType::Top10 {
+            rank: 2,
+            percent: false,
+            bottom: true
+        }
+    );
+    assert!(rules[1].stop_if_true);
+    assert_eq!(
+        rules[1].format.as_ref().unwrap().number_format.as_deref(),
+        Some("0.00%")
+    );
+    match &rules[2].rule_type {
+        ConditionalFormatType::ColorScale(scale) => {
+            assert_eq!(scale.values.len(), 3);
+            assert_eq!(scale.values[1].value_type, ConditionalValueType::Percentile);
+            assert_eq!(scale.values[1].value.as_deref(), Some("50"));
+            assert_eq!(scale.colors[2], Color::Argb(0xFF63BE7B));
+        }
+        t => panic!("unexpected rule {t:?}"),
+    }
+    match &rules[3].rule_type {
+        ConditionalFormatType::DataBar(bar) => {
+            assert_eq!(bar.max.value_type, ConditionalValueType::Max);
+            assert_eq!(bar.color, Some(Color::Argb(0xFF638EC6)));
+        }
+        t => panic!("unexpected rule {t:?}"),
+    }
+    assert_eq!(
+        cfs[1].rules[0].rule_type,
+        ConditionalFormatType::ContainsText("an".to_string())
+    );
+    assert_eq!(
+        cfs[1].rules[1].rule_type,
+        ConditionalFormatType::DuplicateValues
+    );
+    assert_eq!(cfs[2].rules[0].rule_type, ConditionalFormatType::Expression);
+    assert_eq!(cfs[2].rules[0].formulas, vec!["$A1>30"]);
+    assert_eq!(cfs[2].rules[0].priority, 7);
+
+    let path = format!(
+        "{}/tests/conditional_format.xlsb",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let mut xlsb: Xlsb<_> = open_workbook(&path).unwrap();
+    let cfs = xlsb.worksheet_conditional_formatting("Sheet1").unwrap();
+    assert_eq!(cfs.len(), 2);
+    let rules = &cfs[0].rules;
+    assert_eq!(
+        rules[0].rule_type,
+        ConditionalFormatType::CellIs(ValidationOperator::Between)
+    );
+    assert_eq!(rules[0].formulas, vec!["20", "40"]);
+    assert_eq!(
+        rules[0].format,
+        Some(DifferentialFormat {
+            fill_pattern: Some("solid".to_string()),
+            fill_foreground: Some(Color::Theme {
+                index: 4,
+                tint: 13106. / 32767.
+            }),
+            fill_background: None,
+            ..red_bold
+        })
+    );
+    assert_eq!(
+        rules[1].rule_type,
+        ConditionalFormatType::Top10 {
+            rank: 2,
+            percent: false,
+            bottom: true
+        }
+    );
+    assert!(rules[1].stop_if_true);
+    assert_eq!(
+        rules[1].format,
+        Some(DifferentialFormat {
+            italic: Some(true),
+            number_format: Some("0.00%".to_string()),
+            ..Default::default()
+        })
+    );
+    match &rules[2].rule_type {
+        ConditionalFormatType::ColorScale(scale) => {
+            assert_eq!(scale.values.len(), 3);
+            assert_eq!(scale.values[0].value_type, ConditionalValueType::Min);
+            assert_eq!(scale.values[0].value, None);
+            assert_eq!(scale.values[1].value_type, ConditionalValueType::Percentile);
+            assert_eq!(scale.values[1].value.as_deref(), Some("50"));
+            assert_eq!(scale.colors.len(), 3);
+            assert_eq!(scale.colors[2], Color::Argb(0xFF63BE7B));
+        }
+        t => panic!("unexpected rule {t:?}"),
+    }
+    assert_eq!(rules[2].format, None);
+    match &rules[3].rule_type {
+        ConditionalFormatType::DataBar(bar) => {
+            assert_eq!(bar.min.value_type, ConditionalValueType::Min);
+            assert_eq!(bar.max.value_type, ConditionalValueType::Formula);
+            assert_eq!(bar.max.value.as_deref(), Some("40"));
+            assert_eq!(bar.color, Some(Color::Argb(0xFF638EC6)));
+            assert!(!bar.show_value);
+        }
+        t => panic!("unexpected rule {t:?}"),
+    }
+    match &rules[4].rule_type {
+        ConditionalFormatType::IconSet(icons) => {
+            assert_eq!(icons.icon_set, "3Flags");
+            assert_eq!(icons.values.len(), 3);
+            assert_eq!(icons.values[2].value_type, ConditionalValueType::Percent);
+            assert_eq!(icons.values[2].value.as_deref(), Some("67"));
+            assert!(icons.show_value);
+            assert!(icons.reverse);
+        }
+        t => panic!("unexpected rule {t:?}"),
+    }
+    let rules = &cfs[1].rules;
+    assert_eq!(cfs[1].ranges.len(), 2);
+    assert_eq!(
+        rules[0].rule_type,
+        ConditionalFormatType::ContainsText("an".to_string())
+    );
+    assert_eq!(rules[0].formulas, vec!["\"an\""]);
+    assert_eq!(rules[1].rule_type, ConditionalFormatType::DuplicateValues);
+    assert_eq!(
+        rules[2].rule_type,
+        ConditionalFormatType::AboveAverage {
+            above: false,
+            equal: true,
+            std_dev: None
+        }
+    );
+}
+
+#[test]
+fn worksheet_view() {
+    use calamine::{Dimensions, Pane, PanePosition, PaneState, SheetView};
+
+    setup();
+
+    for ext in ["xlsx", "xlsb", "xls", "ods"] {
+        let path = format!("{}/tests/sheet_view.{}", env!("CARGO_MANIFEST_DIR"), ext);
+        let mut wb = open_workbook_auto(&path).unwrap();
+        let view = wb.worksheet_view("Sheet1").unwrap();
+        let active_cell = (3, 2);
+        assert_eq!(
+            view,
+            SheetView {
+                show_grid_lines: false,
+                right_to_left: ext != "ods",
+                zoom: 85,
+                top_left_cell: (0, 0),
+                pane: Some(Pane {
+                    state: PaneState::Frozen,
+                    x_split: 1.,
+                    y_split: 2.,
+                    top_left_cell: (2, 1),
+                    active_pane: PanePosition::BottomRight,
+                }),
+                active_cell,
+                selection: vec![Dimensions {
+                    start: active_cell,
+                    end: if ext == "ods" { active_cell } else { (4, 3) },
+                }],
+            },
+            "{ext}"
+        );
+
+        let view = wb.worksheet_view("Sheet2").unwrap();
+        assert_eq!(
+            view,
+            SheetView {
+                selection: view.selection.clone(),
+                ..Default::default()
+            },
+            "{ext}"
+        );
+        assert!(wb.worksheet_view("Missing").is_err());
+    }
+}
+
+#[test]
+fn protection() {
+    use calamine::{PasswordHash, SheetProtection};
+
+    setup();
+
+    for ext in ["xlsx", "xlsb", "xls", "ods"] {
+        let path = format!("{}/tests/protection.{}", env!("CARGO_MANIFEST_DIR"), ext);
+        let mut wb = open_workbook_auto(&path).unwrap();
+
+        let ods = ext == "ods";
+        // ods passwords are SHA hashes
+        let supported = !ods || cfg!(feature = "password");
+        let protection = wb.worksheet_protection("Legacy").unwrap().unwrap();
+        let password = protection.password.as_ref().unwrap();
+        assert_eq!(
+            password.verify("calamine"),
+            supported.then_some(true),
+            "{ext}"
+        );
+        assert_eq!(
+            password.verify("Calamine"),
+            supported.then_some(false),
+            "{ext}"
+        );
+        assert_eq!(
+            protection,
+            SheetProtection {
+                password: protection.password.clone(),
+                select_locked_cells: false,
+                format_cells: !ods,
+                insert_rows: true,
+                sort: !ods,
+                ..Default::default()
+            },
+            "{ext}"
+        );
+
+        assert_eq!(wb.worksheet_protection("Open").unwrap(), None, "{ext}");
+
+        let protection = wb.worksheet_protection("Sha512").unwrap().unwrap();
+        if ext == "xlsx" {
+            let password = protection.password.as_ref().unwrap();
+            assert!(matches!(
+                password,
+                PasswordHash::Iterated {
+                    spin_count: 1000,
+                    ..
+                }
+            ));
+            #[cfg(feature = "password")]
+            {
+                assert_eq!(password.verify("sha512pass"), Some(true));
+                assert_eq!(password.verify("calamine"), Some(false));
+            }
+            #[cfg(not(feature = "password"))]
+            assert_eq!(password.verify("sha512pass"), None);
+        } else {
+            assert_eq!(protection, SheetProtection::default(), "{ext}");
+        }
+        assert!(wb.worksheet_protection("Missing").is_err());
+
+        let protection = wb.workbook_protection().unwrap().unwrap();
+        assert!(protection.lock_structure, "{ext}");
+        assert!(!protection.lock_windows, "{ext}");
+        // xlsx and ods workbooks are protected with SHA hashes
+        let sha = ext == "xlsx" || ods;
+        let password = if sha { "bookpass" } else { "calamine" };
+        assert_eq!(
+            protection.password.unwrap().verify(password),
+            (!sha || cfg!(feature = "password")).then_some(true),
+            "{ext}"
+        );
+    }
+}
+
+#[test]
+fn page_setup() {
+    use calamine::{Dimensions, HeaderFooterText, Orientation, PageMargins, PageOrder, PageSetup};
+
+    setup();
+
+    let path = format!("{}/tests/page_setup.xlsx", env!("CARGO_MANIFEST_DIR"));
+    let mut xlsx: Xlsx<_> = open_workbook(&path).unwrap();
+    let path = format!("{}/tests/page_setup.xlsb", env!("CARGO_MANIFEST_DIR"));
+    let mut xlsb: Xlsb<_> = open_workbook(&path).unwrap();
+
+    let setup = xlsx.worksheet_page_setup("Print Me").unwrap();
+    assert_eq!(setup, xlsb.worksheet_page_setup("Print Me").unwrap());
+    assert_eq!(setup.orientation, Orientation::Landscape);
+    assert_eq!(setup.paper_size, 9);
+    assert_eq!(setup.scale, 100);
+    assert_eq!(setup.fit_to_pages, Some((1, 0)));
+    assert_eq!(setup.first_page_number, Some(3));
+    assert_eq!(setup.page_order, PageOrder::OverThenDown);
+    assert_eq!(
+        setup.margins,
+        PageMargins {
+            left: 0.5,
+            right: 0.6,
+            top: 1.,
+            bottom: 1.1,
+            header: 0.2,
+            footer: 0.25,
+        }
+    );
+    assert!(setup.center_horizontally && !setup.center_vertically);
+    assert!(setup.print_grid_lines && setup.print_headings);
+    assert_eq!(
+        setup.header_footer.odd_header,
+        Some(HeaderFooterText {
+            left: "Q&&A".to_string(),
+            center: "&\"Arial,Bold\"Sales".to_string(),
+            right: "Page &P of &N".to_string(),
+        })
+    );
+    assert_eq!(
+        setup.header_footer.odd_footer,
+        Some(HeaderFooterText {
+            center: "&F".to_string(),
+            ..Default::default()
+        })
+    );
+    assert_eq!(setup.header_footer.even_header, None);
+    assert_eq!(setup.row_breaks, vec![20, 30]);
+    assert_eq!(setup.column_breaks, vec![4]);
+    assert_eq!(
+        setup.print_area,
+        vec![Dimensions {
+            start: (0, 0),
+            end: (40, 5)
+        }]
+    );
+    assert_eq!(setup.print_title_rows, Some((0, 1)));
+    assert_eq!(setup.print_title_columns, Some((0, 0)));
+
+    let default = PageSetup::default();
+    assert_eq!(xlsx.worksheet_page_setup("Default").unwrap(), default);
+    assert_eq!(xlsb.worksheet_page_setup("Default").unwrap(), default);
+    assert!(xlsx.worksheet_page_setup("Missing").is_err());
+}
+
+#[test]
+fn auto_filter() {
+    use calamine::{
+        CustomFilter, Dimensions, Filter, FilterColumn, FilterOperator, SortBy, SortCondition,
+    };
+
+    setup();
+
+    let path = format!("{}/tests/auto_filter.xlsx", env!("CARGO_MANIFEST_DIR"));
+    let mut xlsx: Xlsx<_> = open_workbook(&path).unwrap();
+    let dims = |start, end| Dimensions { start, end };
+
+    let filter = xlsx.worksheet_auto_filter("Sheet1").unwrap().unwrap();
+    assert_eq!(filter.range, dims((0, 0), (5, 2)));
+    assert_eq!(
+        filter.columns,
+        vec![
+            FilterColumn {
+                column: 0,
+                filter: Filter::Values {
+                    values: vec!["North".into(), "South".into(), "West".into()],
+                    blank: false,
+                },
+            },
+            FilterColumn {
+                column: 1,
+                filter: Filter::Custom {
+                    and: true,
+                    conditions: vec![
+                        CustomFilter {
+                            operator: FilterOperator::GreaterThan,
+                            value: "8".into(),
+                        },
+                        CustomFilter {
+                            operator: FilterOperator::LessThan,
+                            value: "35".into(),
+                        },
+                    ],
+                },
+            },
+        ]
+    );
+    let sort_state = filter.sort_state.as_ref().unwrap();
+    assert_eq!(sort_state.range, dims((1, 0), (5, 2)));
+    assert_eq!(
+        sort_state.conditions,
+        vec![
+            SortCondition {
+                range: dims((1, 1), (5, 1)),
+                descending: true,
+                ..Default::default()
+            },
+            SortCondition {
+                range: dims((1, 0), (5, 0)),
+                custom_list: Some("North,South,East,West".into()),
+                ..Default::default()
+            },
+        ]
+    );
+    // the same rows as those hidden by Excel
+    let range = xlsx.worksheet_range("Sheet1").unwrap();
+    assert_eq!(filter.visible_rows(&range), vec![1, 2]);
+    let layout = xlsx.worksheet_layout("Sheet1").unwrap();
+    assert!((1..=5).all(|r| layout.is_row_hidden(r) != [1, 2].contains(&r)));
+
+    assert_eq!(xlsx.worksheet_auto_filter("Sheet2").unwrap(), None);
+    xlsx.load_tables().unwrap();
+    let table = xlsx.table_by_name("Scores").unwrap();
+    assert!(table.has_autofilter());
+    let filter = table.auto_filter().unwrap();
+    assert_eq!(
+        filter.columns[0].filter,
+        Filter::Top10 {
+            top: true,
+            percent: false,
+            value: 2.,
+            filter_value: Some(7.)
+        }
+    );
+    assert_eq!(
+        filter.sort_state.as_ref().unwrap().conditions[0].sort_by,
+        SortBy::CellColor
+    );
+    let range = xlsx.worksheet_range("Sheet2").unwrap();
+    assert_eq!(filter.visible_rows(&range), vec![2, 3]);
+}
+
+#[test]
+fn sheet_metadata() {
+    use calamine::Color;
+
+    setup();
+
+    for ext in ["xlsx", "xlsb", "xls", "ods"] {
+        let path = format!(
+            "{}/tests/sheet_metadata.{}",
+            env!("CARGO_MANIFEST_DIR"),
+            ext
+        );
+        let wb = open_workbook_auto(&path).unwrap();
+        let sheets = wb.sheets_metadata();
+
+        let tab_colors: Vec<_> = sheets.iter().map(|s| s.tab_color).collect();
+        let expected = match ext {
+            "xls" => [Some(Color::Indexed(10)), Some(Color::Indexed(12)), None],
+            "ods" => [
+                Some(Color::Argb(0xFFFF0000)),
+                Some(Color::Argb(0xFF0070C0)),
+                None,
+            ],
+            _ => [
+                Some(Color::Argb(0xFFFF0000)),
+                Some(Color::Theme { index: 4, tint: 0. }),
+                None,
+            ],
+        };
+        assert_eq!(tab_colors, expected, "{ext}");
+
+        if ext == "xlsx" || ext == "xlsb" {
+            let ids: Vec<_> = sheets.iter().map(|s| s.sheet_id).collect();
+            assert_eq!(ids, [Some(1), Some(2), Some(3)], "{ext}");
+            let part = if ext == "xlsx" { "xml" } else { "bin" };
+            assert_eq!(
+                sheets[1].path,
+                Some(format!("xl/worksheets/sheet2.{part}")),
+                "{ext}"
+            );
+        } else {
+            assert!(sheets
+                .iter()
+                .all(|s| s.sheet_id.is_none() && s.path.is_none()));
+        }
+
+        // only the first workbook view is used, and ods files always open on the first sheet
+        let (active, first) = if ext == "ods" { (0, 0) } else { (2, 1) };
+        assert_eq!(wb.active_sheet(), active, "{ext}");
+        assert_eq!(wb.first_visible_sheet(), first, "{ext}");
+    }
+}
+
+#[test]
+fn theme_colors() {
+    setup();
+
+    for ext in ["xlsx", "xlsb"] {
+        let path = format!("{}/tests/theme.{}", env!("CARGO_MANIFEST_DIR"), ext);
+        let wb = open_workbook_auto(&path).unwrap();
+        let resolver = wb.color_resolver();
+        assert_eq!(resolver.theme.name, "Calamine", "{ext}");
+        assert_eq!(resolver.theme.major_font, "Calibri Light", "{ext}");
+        assert_eq!(resolver.theme.minor_font, "Calibri", "{ext}");
+        assert_eq!(resolver.theme.colors[2], 0xFF44546A, "{ext}");
+        assert_eq!(resolver.theme.color(0), Some(0xFFFFFFFF), "{ext}");
+
+        let colors: Vec<_> = wb
+            .sheets_metadata()
+            .iter()
+            .map(|s| resolver.resolve(s.tab_color.as_ref().unwrap()))
+            .collect();
+        // custom indexed colors are not read from xlsb files
+        let indexed = if ext == "xlsx" {
+            0xFF00B050
+        } else {
+            0xFFFF0000
+        };
+        assert_eq!(colors, [Some(indexed), Some(0xFF2E75B6)], "{ext}");
+    }
+
+    let path = format!("{}/tests/theme.xls", env!("CARGO_MANIFEST_DIR"));
+    let xls: Xls<_> = open_workbook(&path).unwrap();
+    let resolver = xls.color_resolver();
+    assert_eq!(resolver.theme, calamine::Theme::default());
+    let colors: Vec<_> = xls
+        .sheets_metadata()
+        .iter()
+        .map(|s| resolver.resolve(s.tab_color.as_ref().unwrap()))
+        .collect();
+    assert_eq!(colors, [Some(0xFF00B050), Some(0xFF0000FF)]);
+}
+
+#[test]
+fn pivot_tables() {
+    use calamine::{
+        Dimensions, PivotAxisField, PivotDataField, PivotFunction, PivotPageField, PivotSource,
+    };
+
+    setup();
+
+    let path = format!("{}/tests/pivot.xlsx", env!("CARGO_MANIFEST_DIR"));
+    let mut xlsx: Xlsx<_> = open_workbook(&path).unwrap();
+
+    let tables = xlsx.pivot_tables().unwrap();
+    assert_eq!(tables.len(), 2);
+    let sales = &tables[0];
+    assert_eq!(sales.name, "Sales by region");
+    assert_eq!(sales.sheet_name, "Pivot");
+    assert_eq!(
+        sales.location,
+        Dimensions {
+            start: (2, 0),
+            end: (5, 2)
+        }
+    );
+    assert_eq!(sales.cache_id, 3);
+    assert_eq!(
+        sales.source,
+        PivotSource::Range {
+            sheet: "Data".to_string(),
+            range: Dimensions {
+                start: (0, 0),
+                end: (4, 2)
+            }
+        }
+    );
+    assert_eq!(sales.fields, ["Region", "Product", "Amount", "Double"]);
+    assert_eq!(sales.row_fields, [PivotAxisField::Field(0)]);
+    assert_eq!(sales.column_fields, [PivotAxisField::Values]);
+    assert_eq!(
+        sales.page_fields,
+        [PivotPageField {
+            field: 1,
+            item: Some(1)
+        }]
+    );
+    assert_eq!(
+        sales.data_fields,
+        [
+            PivotDataField {
+                name: Some("Sum of Amount".to_string()),
+                field: 2,
+                function: PivotFunction::Sum,
+            },
+            PivotDataField {
+                name: Some("Average of Double".to_string()),
+                field: 3,
+                function: PivotFunction::Average,
+            },
+        ]
+    );
+
+    let records = xlsx.pivot_cache_records(sales.cache_id).unwrap();
+    range_eq!(
+        records,
+        [
+            [
+                String("Region".to_string()),
+                String("Product".to_string()),
+                String("Amount".to_string())
+            ],
+            [
+                String("North".to_string()),
+                String("Apple".to_string()),
+                Float(10.)
+            ],
+            [
+                String("South".to_string()),
+                String("Apple".to_string()),
+                Float(20.)
+            ],
+            [
+                String("North".to_string()),
+                String("Pear".to_string()),
+                Float(5.)
+            ],
+            [
+                String("South".to_string()),
+                String("Pear".to_string()),
+                Empty
+            ],
+        ]
+    );
+
+    // the records are still available once the source sheet is deleted
+    let archive = &tables[1];
+    assert_eq!(archive.column_fields, [PivotAxisField::Field(1)]);
+    assert_eq!(archive.data_fields[0].function, PivotFunction::Count);
+    assert!(xlsx.worksheet_range("Old").is_err());
+    let records = xlsx.pivot_cache_records(archive.cache_id).unwrap();
+    range_eq!(
+        records,
+        [
+            [
+                String("Done".to_string()),
+                String("Day".to_string()),
+                String("Check".to_string()),
+                String("Note".to_string())
+            ],
+            [
+                Bool(true),
+                DateTimeIso("2024-01-31T00:00:00".to_string()),
+                Error(NA),
+                String("a & b".to_string())
+            ],
+            [
+                Bool(false),
+                DateTimeIso("2024-02-01T00:00:00".to_string()),
+                Float(1.5),
+                Empty
+            ],
+        ]
+    );
+
+    assert!(xlsx.pivot_cache_records(1).is_err());
+}
+
+#[test]
+fn charts_xlsx() {
+    use calamine::{ChartAxisKind, ChartAxisPosition, ChartType};
+
+    setup();
+
+    let path = format!("{}/tests/chart.xlsx", env!("CARGO_MANIFEST_DIR"));
+    let mut xlsx: Xlsx<_> = open_workbook(&path).unwrap();
+
+    let charts = xlsx.charts().unwrap();
+    assert_eq!(charts.len(), 2);
+    let combined = &charts[0];
+    assert_eq!(combined.sheet_name, "Data");
+    assert_eq!(combined.name.as_deref(), Some("Sales chart"));
+    assert_eq!(combined.chart_type, Some(ChartType::Column));
+    assert_eq!(combined.title.as_deref(), Some("Sales & costs"));
+    let axes: Vec<_> = combined
+        .axes
+        .iter()
+        .map(|a| (a.kind, a.position, a.title.as_deref()))
+        .collect();
+    assert_eq!(
+        axes,
+        vec![
+            (
+                ChartAxisKind::Category,
+                ChartAxisPosition::Bottom,
+                Some("Month")
+            ),
+            (
+                ChartAxisKind::Value,
+                ChartAxisPosition::Left,
+                Some("Amount")
+            ),
+        ]
+    );
+
+    assert_eq!(combined.series.len(), 2);
+    let sales = &combined.series[0];
+    assert_eq!(sales.chart_type, ChartType::Column);
+    assert_eq!(sales.name.as_deref(), Some("Sales"));
+    assert_eq!(sales.name_formula.as_deref(), Some("Data!$B$1"));
+    let categories = sales.categories.as_ref().unwrap();
+    assert_eq!(categories.formula.as_deref(), Some("Data!$A$2:$A$5"));
+    assert_eq!(
+        categories.values,
+        ["Jan", "Feb", "Mar", "Apr"].map(|m| Data::String(m.to_string()))
+    );
+    let values = sales.values.as_ref().unwrap();
+    assert_eq!(values.formula.as_deref(), Some("Data!$B$2:$B$5"));
+    assert_eq!(values.values, [10., 12.5, 9., 14.].map(Data::Float));
+    let costs = &combined.series[1];
+    assert_eq!(costs.chart_type, ChartType::Line);
+    assert_eq!(costs.name.as_deref(), Some("Costs"));
+    assert_eq!(costs.name_formula, None);
+    assert_eq!(
+        costs.values.as_ref().unwrap().values,
+        [7., 8., 6.5, 9.5].map(Data::Float)
+    );
+
+    let scatter = &charts[1];
+    assert_eq!(scatter.sheet_name, "Chart");
+    assert_eq!(scatter.chart_type, Some(ChartType::Scatter));
+    assert_eq!(scatter.title, None);
+    let series = &scatter.series[0];
+    assert_eq!(
+        series.categories.as_ref().unwrap().formula.as_deref(),
+        Some("Data!$B$2:$B$5")
+    );
+    assert_eq!(
+        series.values.as_ref().unwrap().formula.as_deref(),
+        Some("Data!$C$2:$C$5")
+    );
+
+    // chartsheets have no cells
+    assert!(xlsx.worksheet_range("Chart").unwrap().is_empty());
+    assert!(xlsx.worksheet_formula("Chart").unwrap().is_empty());
+}
+
+#[test]
+fn charts_xlsb() {
+    use calamine::ChartType;
+
+    setup();
+
+    let path = format!("{}/tests/any_sheets.xlsb", env!("CARGO_MANIFEST_DIR"));
+    let mut xlsb: Xlsb<_> = open_workbook(&path).unwrap();
+
+    let charts = xlsb.charts().unwrap();
+    assert_eq!(charts.len(), 1);
+    let chart = &charts[0];
+    assert_eq!(chart.sheet_name, "Chart");
+    assert_eq!(chart.chart_type, Some(ChartType::Column));
+    assert_eq!(chart.axes.len(), 2);
+    let formulas: Vec<_> = chart
+        .series
+        .iter()
+        .map(|s| s.values.as_ref().unwrap().formula.as_deref())
+        .collect();
+    assert_eq!(
+        formulas,
+        vec![Some("Visible!$A$1:$A$3"), Some("Visible!$B$1:$B$3")]
+    );
+    assert_eq!(
+        chart.series[1].values.as_ref().unwrap().values,
+        [2., 4., 6.].map(Data::Float)
+    );
+
+    assert!(xlsb.worksheet_range("Chart").unwrap().is_empty());
+}
+
+#[test]
+fn external_links_xlsx() {
+    setup();
+
+    let path = format!("{}/tests/external_links.xlsx", env!("CARGO_MANIFEST_DIR"));
+    let mut xlsx: Xlsx<_> = open_workbook(&path).unwrap();
+
+    let links = xlsx.external_links();
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[0].target, "rates.xlsx");
+    assert_eq!(links[0].sheets.len(), 1);
+    assert_eq!(links[0].sheets[0].name, "Rates");
+    range_eq!(
+        links[0].sheets[0].cells,
+        [
+            [Float(1.5), String("EUR".to_string())],
+            [Bool(true), Error(NA)]
+        ]
+    );
+    assert_eq!(links[1].target, "file:///C:\\Reports\\Q1 Budget.xlsx");
+    assert_eq!(links[1].file_name(), "Q1 Budget.xlsx");
+    assert_eq!(links[1].names, vec!["Total".to_string()]);
+    let sheets: Vec<_> = links[1].sheets.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(sheets, vec!["Summary", "Q1 Data"]);
+    assert!(links[1].sheets[0].cells.is_empty());
+    assert_eq!(links[1].sheets[1].cells.start(), Some((0, 1)));
+    range_eq!(links[1].sheets[1].cells, [[Float(10.)], [Float(20.)]]);
+
+    // indexes are replaced by the file names
+    assert_eq!(
+        xlsx.defined_names(),
+        [("Budget".to_string(), "'[Q1 Budget.xlsx]'!Total".to_string())]
+    );
+    let formulas = xlsx.worksheet_formula("Sheet1").unwrap();
+    range_eq!(
+        formulas,
+        [
+            ["[rates.xlsx]Rates!A1*2".to_string()],
+            ["SUM('[Q1 Budget.xlsx]Q1 Data'!B1:B2)".to_string()],
+            ["[rates.xlsx]Rates!B1&\"[1]\"".to_string()]
+        ]
+    );
+}
+
+#[test]
+fn external_links_xls() {
+    setup();
+
+    let path = format!("{}/tests/external_links.xls", env!("CARGO_MANIFEST_DIR"));
+    let mut xls: Xls<_> = open_workbook(&path).unwrap();
+
+    // the SupBook of the workbook itself is not an external link
+    let links = xls.external_links();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].target, "C:\\Reports\\Budget.xls");
+    assert_eq!(links[0].names, vec!["Total".to_string()]);
+    assert_eq!(links[0].sheets[1].name, "Q1 Data");
+    range_eq!(
+        links[0].sheets[1].cells,
+        [
+            [Empty, Float(10.), Empty],
+            [String("x".to_string()), Bool(true), Error(NA)]
+        ]
+    );
+
+    let formulas = xls.worksheet_formula("Sheet1").unwrap();
+    range_eq!(
+        formulas,
+        [
+            ["'[Budget.xls]Q1 Data'!B$1".to_string()],
+            ["[Budget.xls]!Total".to_string()]
+        ]
+    );
+}
+
+#[test]
+fn formula_ast() {
+    setup();
+
+    let expected = [
+        "SUM($A$1:B2)*-2^2",
+        "IF(A$1>=10,\"big\",'My Data'!$C3)",
+        "SUM(A:A,$2:$2)",
+    ];
+    let path = format!("{}/tests/formula_ast.xls", env!("CARGO_MANIFEST_DIR"));
+    let mut xls: Xls<_> = open_workbook(&path).unwrap();
+    let formulas = xls.worksheet_formula("Sheet1").unwrap();
+    let exprs = xls.worksheet_formula_ast("Sheet1").unwrap();
+    let path = format!("{}/tests/formula_ast.xlsb", env!("CARGO_MANIFEST_DIR"));
+    let mut xlsb: Xlsb<_> = open_workbook(&path).unwrap();
+    let xlsb_formulas = xlsb.worksheet_formula("Sheet1").unwrap();
+    let xlsb_exprs = xlsb.worksheet_formula_ast("Sheet1").unwrap();
+
+    for (i, formula) in expected.iter().enumerate() {
+        // decoded expressions are the ones parsed from the text
+        let expr = formula::parse(formula).unwrap();
+        assert_eq!(formulas.get((i, 0)).unwrap(), formula);
+        assert_eq!(exprs.get((i, 0)), Some(&expr));
+        assert_eq!(xlsb_formulas.get((i, 0)).unwrap(), formula);
+        assert_eq!(xlsb_exprs.get((i, 0)), Some(&expr));
+    }
+
+    // formulas which cannot be parsed are skipped
+    let path = format!("{}/tests/formula_ast.xlsx", env!("CARGO_MANIFEST_DIR"));
+    let mut xlsx: Xlsx<_> = open_workbook(&path).unwrap();
+    let exprs = xlsx.worksheet_formula_ast("Sheet1").unwrap();
+    assert!(matches!(
+        exprs.get((0, 0)),
+        Some(formula::Expr::Call { .. })
+    ));
+    assert_eq!(
+        exprs.get((0, 0)).unwrap().to_string(),
+        "_xlfn.LAMBDA(_xlpm.x,_xlpm.x*2)(3)"
+    );
+    assert_eq!(exprs.get((1, 0)), Some(&formula::Expr::Missing));
+    assert!(matches!(exprs.get((2, 0)), Some(formula::Expr::Binary(..))));
+}
+
+#[test]
+fn evaluate_formulas() {
+    setup();
+
+    let path = format!("{}/tests/evaluate.xlsx", env!("CARGO_MANIFEST_DIR"));
+    let mut excel: Xlsx<_> = open_workbook(&path).unwrap();
+    // the generator wrote 0 for all the results
+    let cached = excel.worksheet_range("Sheet1").unwrap();
+    assert_eq!(cached.get_value((1, 2)), Some(&Float(0.)));
+
+    let mut evaluator = formula::Evaluator::new(&mut excel).unwrap();
+    let range = evaluator.worksheet_range("Sheet1").unwrap();
+    let expected = [
+        Float(10.),
+        Float(16.),
+        Float(2.),
+        String("cherry".to_string()),
+        Float(3.),
+        String("big!".to_string()),
+        Float(45322.),
+        Float(45351.),
+        Float(2.),
+        Error(Div0),
+        Float(-1.),
+        Float(5.),
+        Float(1.),
+        String("apple-banana-cherry".to_string()),
+    ];
+    for (row, value) in expected.iter().enumerate() {
+        assert_eq!(range.get_value((row as u32, 2)), Some(value), "row {row}");
+    }
+    assert_eq!(range.get_value((0, 0)), cached.get_value((0, 0)));
+
+    assert_eq!(
+        evaluator.evaluate("Sheet1", "AVERAGE(C1:C3)&\"\"").unwrap(),
+        String("9.33333333333333".to_string())
+    );
+    // areas are read up to the last used cell, but keep their size
+    assert_eq!(
+        evaluator.evaluate("Sheet1", "SUM(A1:XFD1048576)").unwrap(),
+        evaluator.evaluate("Sheet1", "SUM(1:1048576)").unwrap()
+    );
+    assert_eq!(
+        evaluator.evaluate("Sheet1", "ROWS(A1:XFD1048576)").unwrap(),
+        Float(1048576.)
+    );
+    assert_eq!(
+        evaluator.evaluate("Sheet1", "REPT(\"a\",1e12)").unwrap(),
+        Error(Value)
+    );
+    assert!(matches!(
+        evaluator.evaluate_cell("Loop", (0, 0)),
+        Err(formula::EvalError::CircularReference { row: 0, col: 0, .. })
+    ));
+    assert!(matches!(
+        evaluator.evaluate("Missing", "1"),
+        Err(formula::EvalError::SheetNotFound(_))
+    ));
+}
+
+#[test]
+fn formula_dependencies() {
+    setup();
+
+    let path = format!("{}/tests/dependencies.xlsx", env!("CARGO_MANIFEST_DIR"));
+    let mut excel: Xlsx<_> = open_workbook(&path).unwrap();
+    let graph = formula::DependencyGraph::new(&mut excel).unwrap();
+    let to_strings = |cells: Vec<formula::CellAddress>| -> Vec<std::string::String> {
+        cells.iter().map(|c| c.to_string()).collect()
+    };
+
+    // B1: =A3*Rate, with Rate defined as Sheet2!$B$1
+    let precedents: Vec<_> = graph
+        .precedents("Sheet1", (0, 1))
+        .unwrap()
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+    assert_eq!(precedents, ["Sheet1!A3", "Sheet2!B1"]);
+    assert_eq!(
+        graph.precedents("Sheet1", (0, 2)).unwrap()[0].to_string(),
+        "Sheet1!A1:A1048576"
+    );
+    // B2: =SUM(Sheet2:Sheet3!A1), Sheet3!A1 being =Sheet1!A1+1
+    assert_eq!(
+        to_strings(graph.all_precedents("Sheet1", (1, 1)).unwrap()),
+        ["Sheet1!A1", "Sheet2!A1", "Sheet3!A1"]
+    );
+    assert_eq!(
+        to_strings(graph.dependents("Sheet1", (0, 0)).unwrap()),
+        ["Sheet1!C1", "Sheet1!A3", "Sheet3!A1"]
+    );
+    assert_eq!(
+        to_strings(graph.all_dependents("Sheet1", (0, 0)).unwrap()),
+        [
+            "Sheet1!B1",
+            "Sheet1!C1",
+            "Sheet1!B2",
+            "Sheet1!A3",
+            "Sheet3!A1"
+        ]
+    );
+
+    let order = to_strings(graph.evaluation_order());
+    assert_eq!(order.len(), 7);
+    let position = |cell: &str| order.iter().position(|c| c == cell).unwrap();
+    assert!(position("Sheet1!A3") < position("Sheet1!B1"));
+    assert!(position("Sheet1!A3") < position("Sheet1!C1"));
+    assert!(position("Sheet3!A1") < position("Sheet1!B2"));
+
+    let circular: Vec<_> = graph
+        .circular_references()
+        .into_iter()
+        .map(to_strings)
+        .collect();
+    assert_eq!(circular, [["Sheet1!C2", "Sheet1!C3"]]);
+    assert!(graph.dependents("Missing", (0, 0)).is_err());
+}
+
+#[test]
+fn shared_formulas_xlsx() {
+    setup();
+
+    let path = format!("{}/tests/shared_formula.xlsx", env!("CARGO_MANIFEST_DIR"));
+    let mut excel: Xlsx<_> = open_workbook(&path).unwrap();
+    let formulas = excel.worksheet_formula("Sheet1").unwrap();
+    let cell = |pos| formulas.get_value(pos).unwrap().as_str();
+    // the other cells of a shared formula are moved from its first cell
+    assert_eq!(cell((0, 1)), "A1*2+$A$1");
+    assert_eq!(cell((1, 1)), "A2*2+$A$1");
+    assert_eq!(cell((2, 1)), "A3*2+$A$1");
+    assert_eq!(cell((0, 2)), "SUM($A1:A$3)");
+    assert_eq!(cell((0, 3)), "SUM($A1:B$3)");
+    assert_eq!(cell((0, 4)), "SUM($A1:C$3)");
+}
+
+#[test]
+fn reference_notations() {
+    use calamine::reference::{a1_to_r1c1, column_name, r1c1_to_a1, Reference};
+
+    let reference: Reference = "'My Sheet'!$A$1:B2".parse().unwrap();
+    assert_eq!(reference.to_r1c1((2, 2)), "'My Sheet'!R1C1:R[-1]C[-1]");
+    assert_eq!(
+        Reference::from_r1c1("Sheet1:Sheet3!C[1]", (0, 0))
+            .unwrap()
+            .to_string(),
+        "Sheet1:Sheet3!B:B"
+    );
+    assert_eq!(column_name(16383), "XFD");
+
+    // copies of a formula have the same R1C1 formula
+    let first = a1_to_r1c1("=VLOOKUP(A2,Data!$A:$C,3,FALSE)", (1, 3)).unwrap();
+    let copy = a1_to_r1c1("=VLOOKUP(A3,Data!$A:$C,3,FALSE)", (2, 3)).unwrap();
+    assert_eq!(first, "=VLOOKUP(RC[-3],Data!C1:C3,3,FALSE)");
+    assert_eq!(first, copy);
+    assert_eq!(
+        r1c1_to_a1(&first, (9, 3)).unwrap(),
+        "=VLOOKUP(A10,Data!$A:$C,3,FALSE)"
+    );
+}
+
+#[test]
+fn future_functions() {
+    setup();
+
+    let path = format!("{}/tests/future_functions.xlsx", env!("CARGO_MANIFEST_DIR"));
+    let mut xlsx: Xlsx<_> = open_workbook(&path).unwrap();
+    let raw = xlsx.worksheet_formula("Sheet1").unwrap();
+    assert_eq!(
+        raw.get_value((0, 4)).unwrap(),
+        "_xlfn.XLOOKUP(A1,B1:B3,C1:C3)"
+    );
+    let displayed = xlsx.worksheet_formula_display("Sheet1").unwrap();
+    let displayed: Vec<_> = displayed.used_cells().map(|c| c.2.as_str()).collect();
+    assert_eq!(
+        displayed,
+        [
+            "XLOOKUP(A1,B1:B3,C1:C3)",
+            "SUM(FILTER(B1:B3,C1:C3>1))",
+            "LET(x,A1,x*2)",
+            "@B1:B3+@(B1:B3*2)",
+            "SUM(D2#)&\"_xlfn.\"",
+        ]
+    );
+
+    // add-in functions of xls files are called with their name
+    let path = format!("{}/tests/future_functions.xls", env!("CARGO_MANIFEST_DIR"));
+    let mut xls: Xls<_> = open_workbook(&path).unwrap();
+    let raw = xls.worksheet_formula("Sheet1").unwrap();
+    assert_eq!(
+        raw.get_value((0, 4)).unwrap(),
+        "_xlfn.XLOOKUP(A1,B1:B3,C1:C3)"
+    );
+    assert_eq!(raw.get_value((1, 4)).unwrap(), "_xlfn.CONCAT(B1:B3)");
+    let displayed = xls.worksheet_formula_display("Sheet1").unwrap();
+    assert_eq!(
+        displayed.get_value((0, 4)).unwrap(),
+        "XLOOKUP(A1,B1:B3,C1:C3)"
+    );
+    assert_eq!(displayed.get_value((1, 4)).unwrap(), "CONCAT(B1:B3)");
+}
+
+#[test]
+fn localized_formulas() {
+    use calamine::formula::Locale;
+
+    setup();
+
+    let path = format!("{}/tests/future_functions.xlsx", env!("CARGO_MANIFEST_DIR"));
+    let mut xlsx: Xlsx<_> = open_workbook(&path).unwrap();
+    let formulas = xlsx
+        .worksheet_formula_localized("Sheet1", Locale::German)
+        .unwrap();
+    assert_eq!(
+        formulas.get_value((0, 4)).unwrap(),
+        "XVERWEIS(A1;B1:B3;C1:C3)"
+    );
+    assert_eq!(
+        formulas.get_value((1, 4)).unwrap(),
+        "SUMME(FILTER(B1:B3;C1:C3>1))"
+    );
+
+    let path = format!("{}/tests/future_functions.xls", env!("CARGO_MANIFEST_DIR"));
+    let mut xls: Xls<_> = open_workbook(&path).unwrap();
+    let formulas = xls
+        .worksheet_formula_localized("Sheet1", Locale::from_code("fr-FR").unwrap())
+        .unwrap();
+    assert_eq!(
+        formulas.get_value((0, 4)).unwrap(),
+        "RECHERCHEX(A1;B1:B3;C1:C3)"
+    );
+    assert_eq!(formulas.get_value((1, 4)).unwrap(), "CONCAT(B1:B3)");
+}
+
+#[test]
+fn xlsb_formulas_as_xlsx() {
+    setup();
+
+    // the formulas of each xlsb workbook are the ones of the same workbook saved as xlsx; the
+    // formula_tokens pair is generated, not saved by Excel, for the tokens missing from the others
+    for name in [
+        "formula_tokens",
+        "issues",
+        "any_sheets",
+        "temperature-table",
+    ] {
+        let path = format!("{}/tests/{name}.xlsb", env!("CARGO_MANIFEST_DIR"));
+        let mut xlsb: Xlsb<_> = open_workbook(&path).unwrap();
+        let path = format!("{}/tests/{name}.xlsx", env!("CARGO_MANIFEST_DIR"));
+        let mut xlsx: Xlsx<_> = open_workbook(&path).unwrap();
+        assert_eq!(xlsb.defined_names(), xlsx.defined_names(), "{name}");
+        for sheet in xlsx.sheet_names() {
+            let expected = xlsx.worksheet_formula(&sheet).unwrap();
+            let formulas = xlsb.worksheet_formula(&sheet).unwrap();
+            let cells = |r: &calamine::Range<std::string::String>| {
+                let start = r.start().unwrap_or_default();
+                r.used_cells()
+                    .map(|(row, col, f)| ((start.0 + row as u32, start.1 + col as u32), f.clone()))
+                    .filter(|(_, f)| !f.is_empty())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(cells(&formulas), cells(&expected), "{name} {sheet}");
+        }
+    }
+
+    let path = format!("{}/tests/formula_tokens.xlsb", env!("CARGO_MANIFEST_DIR"));
+    let mut xlsb: Xlsb<_> = open_workbook(&path).unwrap();
+    // structured references need the tables, which cell readers do not load
+    let mut cells_reader = xlsb.worksheet_cells_reader("Sheet1").unwrap();
+    assert!(matches!(
+        std::iter::from_fn(|| cells_reader.next_formula().transpose()).find_map(Result::err),
+        Some(calamine::XlsbError::TablesNotLoaded)
+    ));
+    drop(cells_reader);
+    let formulas = xlsb.worksheet_formula("Sheet1").unwrap();
+    assert_eq!(formulas.get_value((2, 1)).unwrap(), "A3*2+$A$1");
+    assert_eq!(formulas.get_value((0, 4)).unwrap(), "SUM($A1:C$3)");
+    assert_eq!(formulas.get_value((0, 6)).unwrap(), "A1:A3*2");
+    assert!(formulas.get_value((1, 6)).unwrap().is_empty());
+    assert_eq!(
+        formulas.get_value((4, 2)).unwrap(),
+        "ROWS(Sales[])+ROWS(Sales[#All])+COUNTA(Sales[[#Headers],[Region]:[Amount]])\
+         +SUM(Sales[Q'#1])+SUM(Sales[[#Data],[#Totals],[Amount]])"
+    );
+}
+
+#[test]
+fn defined_names_formulas_xls() {
+    setup();
+
+    let path = format!("{}/tests/defined_names.xls", env!("CARGO_MANIFEST_DIR"));
+    let mut xls: Xls<_> = open_workbook(&path).unwrap();
+    let names: Vec<(&str, &str)> = xls
+        .defined_names()
+        .iter()
+        .map(|(name, formula)| (name.as_str(), formula.as_str()))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ("DataRange", "OFFSET(Data!$A$1,0,0,COUNTA(Data!$A:$A),1)"),
+            ("Double", "Rate*2"),
+            ("Rate", "0.05"),
+            ("Months", "{\"Jan\",\"Feb\";1.5,TRUE}"),
+            ("Joined", "_xlfn.CONCAT(Data!$A$1:$A$3)"),
+            ("Shifted", "Rate+1"),
+            ("Both", "(Data!$A$1,Data!$B$1)"),
+            ("Totals", "SUM((Data!$A$1:$A$2,Data!$B$1:$B$2))+SUM({5,6})"),
+            ("Broken", "Data!#REF!"),
+            ("Überblick", "Data!A1"),
+            ("Unknown", "#NAME?"),
+            ("_xlnm.Print_Area", "Sheet1!$A$1:$B$2"),
+        ]
+    );
+
+    // array constants of cell formulas are read too
+    let formulas = xls.worksheet_formula("Sheet1").unwrap();
+    assert_eq!(formulas.get_value((0, 0)).unwrap(), "SUM({1,2,3})");
+}